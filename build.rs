@@ -0,0 +1,260 @@
+//! Generates the raw syscall stubs from the syscall table (`syscalls.toml`),
+//! see the header of that file for the format.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct Syscall {
+    module: String,
+    name: String,
+    num: String,
+    args: Vec<String>,
+    returns: Option<String>,
+    doc: Option<String>,
+}
+
+/// Finds the syscall table to generate from, see the header of `syscalls.toml`
+fn table_path() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=SAFA_API_SYSCALL_TABLE");
+    println!("cargo:rerun-if-env-changed=DEP_SAFA_ABI_SYSCALL_TABLE");
+
+    env::var_os("SAFA_API_SYSCALL_TABLE")
+        .or_else(|| env::var_os("DEP_SAFA_ABI_SYSCALL_TABLE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("syscalls.toml")
+        })
+}
+
+fn parse_str(value: &str, line: usize) -> (String, &str) {
+    let value = value
+        .strip_prefix('"')
+        .unwrap_or_else(|| panic!("syscall table:{line}: expected a string"));
+
+    let mut results = String::new();
+    let mut chars = value.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (results, &value[i + 1..]),
+            '\\' => match chars.next() {
+                Some((_, '"')) => results.push('"'),
+                Some((_, '\\')) => results.push('\\'),
+                Some((_, 'n')) => results.push('\n'),
+                Some((_, 't')) => results.push('\t'),
+                other => panic!("syscall table:{line}: unsupported escape {other:?}"),
+            },
+            c => results.push(c),
+        }
+    }
+
+    panic!("syscall table:{line}: unterminated string")
+}
+
+fn parse_array(value: &str, line: usize) -> Vec<String> {
+    let mut rest = value
+        .strip_prefix('[')
+        .unwrap_or_else(|| panic!("syscall table:{line}: expected an array"))
+        .trim_start();
+
+    let mut results = Vec::new();
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            assert!(
+                after.trim().is_empty(),
+                "syscall table:{line}: trailing characters after array"
+            );
+            return results;
+        }
+
+        let (item, after) = parse_str(rest, line);
+        results.push(item);
+
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+}
+
+fn parse_table(table: &str) -> Vec<Syscall> {
+    let mut entries: Vec<BTreeMap<&str, (usize, String)>> = Vec::new();
+    let mut lines = table.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    while let Some((line, raw)) = lines.next() {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if trimmed == "[[syscall]]" {
+            entries.push(BTreeMap::new());
+            continue;
+        }
+
+        let (key, value) = trimmed
+            .split_once('=')
+            .unwrap_or_else(|| panic!("syscall table:{line}: expected `key = value`"));
+        let (key, value) = (key.trim(), value.trim());
+
+        let entry = entries
+            .last_mut()
+            .unwrap_or_else(|| panic!("syscall table:{line}: `{key}` outside of a [[syscall]]"));
+
+        let value = if value == "\"\"\"" {
+            let mut doc = String::new();
+            loop {
+                let (_, raw) = lines
+                    .next()
+                    .unwrap_or_else(|| panic!("syscall table:{line}: unterminated `\"\"\"`"));
+                if raw.trim_end() == "\"\"\"" {
+                    break;
+                }
+                doc.push_str(&raw.replace("\\\\", "\\"));
+                doc.push('\n');
+            }
+            doc
+        } else if value.starts_with('[') {
+            // stored joined and split again below, so that every value is a string
+            parse_array(value, line).join("\n")
+        } else {
+            let (value, rest) = parse_str(value, line);
+            assert!(
+                rest.trim().is_empty() || rest.trim().starts_with('#'),
+                "syscall table:{line}: trailing characters after string"
+            );
+            value
+        };
+
+        if entry.insert(key, (line, value)).is_some() {
+            panic!("syscall table:{line}: duplicate key `{key}`");
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            let mut take = |key: &str| entry.remove(key).map(|(_, v)| v);
+            let required = |v: Option<String>, key: &str| {
+                v.unwrap_or_else(|| panic!("syscall table: a [[syscall]] is missing `{key}`"))
+            };
+
+            let syscall = Syscall {
+                module: required(take("module"), "module"),
+                name: required(take("name"), "name"),
+                num: required(take("num"), "num"),
+                args: take("args")
+                    .map(|args| {
+                        args.lines()
+                            .filter(|a| !a.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                returns: take("returns"),
+                doc: take("doc"),
+            };
+
+            if let Some((key, (line, _))) = entry.into_iter().next() {
+                panic!("syscall table:{line}: unknown key `{key}`");
+            }
+            syscall
+        })
+        .collect()
+}
+
+fn generate(syscall: &Syscall, out: &mut String) {
+    writeln!(out, "    SyscallNum::{} => {{", syscall.num).unwrap();
+
+    for line in syscall.doc.iter().flat_map(|d| d.lines()) {
+        if line.is_empty() {
+            out.push_str("        ///\n");
+        } else {
+            writeln!(out, "        /// {line}").unwrap();
+        }
+    }
+
+    let returns = match syscall.returns.as_deref() {
+        None => String::new(),
+        Some("!") => String::from(" unreachable"),
+        Some(ty) => format!(" {ty}"),
+    };
+
+    writeln!(
+        out,
+        "        {}({}){returns}",
+        syscall.name,
+        syscall.args.join(", ")
+    )
+    .unwrap();
+    out.push_str("    },\n");
+}
+
+/// Enables the `outlined_syscalls` cfg when the `small-syscalls` feature is enabled
+/// and the syscall convention of the target passes the number in a register, see `src/syscalls/syscall_backend.rs`
+fn emit_outlined_cfg() {
+    println!("cargo::rustc-check-cfg=cfg(outlined_syscalls)");
+
+    let small = env::var_os("CARGO_FEATURE_SMALL_SYSCALLS").is_some();
+    let svc_register = env::var_os("CARGO_FEATURE_AARCH64_SVC_REGISTER").is_some();
+    let number_in_register = match env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+        Ok("x86_64") => true,
+        Ok("aarch64") => svc_register,
+        _ => false,
+    };
+
+    if small && number_in_register {
+        println!("cargo::rustc-cfg=outlined_syscalls");
+    }
+}
+
+fn main() {
+    emit_outlined_cfg();
+
+    let table_path = table_path();
+    println!("cargo:rerun-if-changed={}", table_path.display());
+
+    let table = fs::read_to_string(&table_path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read the syscall table at {}: {e}",
+            table_path.display()
+        )
+    });
+
+    let mut modules: BTreeMap<&str, String> = BTreeMap::new();
+    // every module that includes generated stubs must get a file, even if the table has no entries for it
+    for module in [
+        "clock",
+        "fs",
+        "futex",
+        "io",
+        "mem",
+        "misc",
+        "process",
+        "process_misc",
+        "resources",
+        "sockets",
+        "thread",
+    ] {
+        modules.insert(module, String::new());
+    }
+
+    let syscalls = parse_table(&table);
+    for syscall in &syscalls {
+        let out = modules
+            .get_mut(syscall.module.as_str())
+            .unwrap_or_else(|| panic!("syscall table: unknown module `{}`", syscall.module));
+        generate(syscall, out);
+    }
+
+    let out_dir = Path::new(&env::var_os("OUT_DIR").unwrap()).join("syscalls");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    for (module, stubs) in modules {
+        let contents = format!(
+            "// @generated by build.rs from {}, do not edit\n\ndefine_syscall! {{\n{stubs}}}\n",
+            table_path.display()
+        );
+        fs::write(out_dir.join(format!("{module}.rs")), contents).unwrap();
+    }
+}
@@ -4,7 +4,7 @@
 
 set -euo pipefail
 
-libsafa_api=$(RUSTC_BOOTSTRAP=1 cargo rustc --crate-type=staticlib --release --no-default-features --features linkonce --message-format=json-render-diagnostics -- -C force-unwind-tables | jq -r 'select(.reason == "compiler-artifact" and (.target.kind | index("staticlib"))) | .filenames[] | select(endswith(".a"))')
+libsafa_api=$(RUSTC_BOOTSTRAP=1 cargo rustc --crate-type=staticlib --release --no-default-features --features c-exports,linkonce,alloc --message-format=json-render-diagnostics -- -C force-unwind-tables | jq -r 'select(.reason == "compiler-artifact" and (.target.kind | index("staticlib"))) | .filenames[] | select(endswith(".a"))')
 
 mkdir -p out
 cp $libsafa_api out/libsafa_api.a
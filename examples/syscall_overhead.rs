@@ -0,0 +1,58 @@
+//! Measures the overhead of the syscall wrappers, used to compare the default build with the `small-syscalls` feature.
+//!
+//! Build it once with and once without the feature for a SafaOS target, then compare the reported per-call times
+//! and the sizes of the two binaries (e.g. with `size`):
+//! ```sh
+//! cargo build --release --example syscall_overhead --features std
+//! cargo build --release --example syscall_overhead --features std,small-syscalls
+//! ```
+//!
+//! Each case is timed over [`ITERATIONS`] calls after [`WARMUP`] untimed ones,
+//! the cheapest syscalls are used so that the wrappers make up as much of the time as possible.
+
+use std::hint::black_box;
+
+use safa_api::{abi::clock::Clock, syscalls, time::Instant};
+
+/// The amount of timed calls per case
+const ITERATIONS: u32 = 100_000;
+/// The amount of untimed calls made before timing each case
+const WARMUP: u32 = 1_000;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    for _ in 0..WARMUP {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{name:<24} {:>8} ns/call ({ITERATIONS} calls in {elapsed:?})",
+        elapsed.as_nanos() / ITERATIONS as u128
+    );
+}
+
+fn main() {
+    println!(
+        "small-syscalls: {}",
+        if cfg!(feature = "small-syscalls") {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    // the success path, no error decoding
+    bench("clock_gettime", || {
+        black_box(syscalls::clock::clock_gettime(black_box(Clock::Monotonic)));
+    });
+
+    // the error path, the kernel rejects the resource so the error code is decoded
+    bench("kind (invalid resource)", || {
+        _ = black_box(syscalls::resources::kind(black_box(u32::MAX)));
+    });
+}
@@ -0,0 +1,77 @@
+//! Self-checks of the ABI structures filled by the kernel, catching drift between `safa-abi` and the kernel early
+//!
+//! The kernel doesn't report the sizes or layouts of its structures, so there is no handshake to compare against,
+//! instead the values the kernel writes are checked for invariants that a mismatched layout breaks
+//! (for example a field holding a well-known size ending up with an unrelated value).
+//!
+//! The checks only run with debug assertions enabled, a failing check panics with a message naming the structure,
+//! except for the validation of the structures passed at initialization which always runs and returns an error instead.
+
+use core::fmt::Arguments;
+
+#[cfg(not(feature = "std"))]
+use safa_abi::process::AbiStructures;
+use safa_abi::{consts::MAX_NAME_LENGTH, fs::DirEntry, poll::PollEntry};
+
+/// The size of an ELF program header of the current target, what the kernel puts in [`AbiStructures::at_phent`].
+#[cfg(not(feature = "std"))]
+const PROGRAM_HEADER_SIZE: usize = if size_of::<usize>() == 8 { 56 } else { 32 };
+
+#[cold]
+#[inline(never)]
+fn mismatch(structure: &str, details: Arguments) -> ! {
+    panic!(
+        "ABI mismatch between safa-api {} and the kernel in {structure}: {details}, rebuild against the kernel's safa-abi",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Validates the [`AbiStructures`] passed to [`crate::process::try_api_init`], returns the broken invariant if any.
+///
+/// Unlike the other checks, this always runs, as initialization can report the error instead of panicking.
+#[cfg(not(feature = "std"))]
+pub(crate) fn validate_abi_structures(structures: &AbiStructures) -> Result<(), &'static str> {
+    if structures.at_entry == 0 {
+        return Err("the entry point is null");
+    }
+
+    if structures.at_phdr != 0 && structures.at_phent != PROGRAM_HEADER_SIZE {
+        return Err("the program header size doesn't match the target's");
+    }
+    Ok(())
+}
+
+/// Checks a [`DirEntry`] returned by the kernel.
+pub(crate) fn check_dir_entry(entry: &DirEntry) {
+    if cfg!(debug_assertions) && entry.name_length > MAX_NAME_LENGTH {
+        mismatch(
+            "DirEntry",
+            format_args!(
+                "the name is {} bytes long, the maximum is {MAX_NAME_LENGTH}",
+                entry.name_length
+            ),
+        );
+    }
+}
+
+/// A checksum of the resources of `entries`, which the kernel must not change when polling.
+#[inline]
+pub(crate) fn poll_entries_checksum(entries: &[PollEntry]) -> u64 {
+    if !cfg!(debug_assertions) {
+        return 0;
+    }
+
+    entries.iter().fold(0u64, |sum, entry| {
+        sum.rotate_left(5) ^ entry.resource() as u64
+    })
+}
+
+/// Checks the [`PollEntry`]s written back by the kernel against the checksum taken before polling.
+pub(crate) fn check_poll_entries(entries: &[PollEntry], checksum: u64) {
+    if cfg!(debug_assertions) && poll_entries_checksum(entries) != checksum {
+        mismatch(
+            "PollEntry",
+            format_args!("the kernel overwrote the polled resources while returning the events"),
+        );
+    }
+}
@@ -2,14 +2,18 @@
 //! which internally uses the [`crate::syscalls::syssbrk`] syscall
 //! to allocate memory
 
-#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+#[cfg(all(feature = "c-exports", not(feature = "std")))]
 use safa_abi::ffi::{option::OptZero, slice::Slice};
 use safa_abi::mem::MemMapFlags;
 
-use crate::sync::locks::Mutex;
+use crate::{debug::debug_log, limits::page_size, sync::locks::Mutex};
 
 use super::syscalls;
-use core::{alloc::GlobalAlloc, ptr::NonNull};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::NonNull,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
 
 #[derive(Debug, Default)]
 struct Block {
@@ -20,17 +24,25 @@ struct Block {
 }
 
 fn sys_allocate(size_hint: usize) -> Option<(*mut u8, usize)> {
-    let page_count = size_hint.next_multiple_of(4096) / 4096;
-    let (_, s) = syscalls::mem::map(
+    let page_count = size_hint.div_ceil(page_size());
+    let results = syscalls::mem::map(
         core::ptr::null(),
         page_count,
         0,
         None,
         None,
         MemMapFlags::WRITE,
-    )
-    .ok()?;
+    );
 
+    let (_, s) = match results {
+        Ok(results) => results,
+        Err(err) => {
+            debug_log!(ALLOC, "mapping {page_count} pages failed: {}", err.as_str());
+            return None;
+        }
+    };
+
+    debug_log!(ALLOC, "mapped {page_count} pages at {:p}", s.as_ptr());
     Some((s.as_ptr() as *mut u8, s.len()))
 }
 
@@ -91,7 +103,7 @@ impl Block {
         unsafe {
             let length = (*ptr).data_len;
             let ptr_to_data = ptr.offset(1) as *const u8 as *mut u8;
-            NonNull::new_unchecked(core::slice::from_raw_parts_mut(ptr_to_data, length) as *mut [u8])
+            NonNull::new_unchecked(core::ptr::slice_from_raw_parts_mut(ptr_to_data, length))
         }
     }
 }
@@ -155,8 +167,8 @@ impl SystemAllocator {
     #[inline]
     fn find_block(&mut self, data_len: usize, alignment: usize) -> Option<NonNull<Block>> {
         assert!(
-            alignment <= 4096,
-            "Max allowed alignment is Page size which is 4096 bytes"
+            alignment <= page_size(),
+            "Max allowed alignment is the page size"
         );
 
         let data_len = data_len.next_multiple_of(size_of::<Block>());
@@ -250,9 +262,132 @@ impl SystemAllocator {
     }
 }
 
+/// Statistics of the allocator, see [`GlobalSystemAllocator::stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// The amount of blocks, both free and used
+    pub blocks: usize,
+    /// The amount of bytes in use by allocations, including the padding added for alignment
+    pub used_bytes: usize,
+    /// The amount of bytes mapped by the allocator that are free for new allocations
+    pub free_bytes: usize,
+}
+
+impl SystemAllocator {
+    fn stats(&self) -> AllocatorStats {
+        let mut stats = AllocatorStats::default();
+        let mut current = self.head;
+
+        while let Some(block_ptr) = current {
+            let block = unsafe { &*block_ptr.as_ptr() };
+            stats.blocks += 1;
+            if block.free {
+                stats.free_bytes += block.data_len;
+            } else {
+                stats.used_bytes += block.data_len;
+            }
+            current = block.next;
+        }
+
+        stats
+    }
+}
+
 unsafe impl Send for SystemAllocator {}
 unsafe impl Sync for SystemAllocator {}
 
+/// The error returned when an allocation fails, see [`allocate_fallible`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl core::fmt::Display for AllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+/// The hook called when an allocation fails, null if there is none, see [`set_oom_hook`]
+static OOM_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+/// The address of the frame of [`call_oom_hook`] on the thread running the OOM hook, 0 if it isn't running,
+/// so that allocations failing inside of it don't call it again
+static OOM_HOOK_FRAME: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the hook called when an allocation fails, returning the previous hook.
+///
+/// The hook is called with the layout of the failed allocation before giving up, and the allocation is retried once after it returns,
+/// which allows the hook to purge caches or record diagnostics,
+/// the allocator isn't locked while the hook runs, so it can free (and allocate) memory.
+///
+/// Allocations failing while the hook is running don't call the hook again, on any thread:
+/// the hook runs on one thread at a time, and allocations failing on other threads meanwhile fail without calling it.
+///
+/// The hook may panic, it is then called again by the next failing allocation,
+/// unless the panic takes the whole process down.
+pub fn set_oom_hook(hook: fn(Layout)) -> Option<fn(Layout)> {
+    let previous = OOM_HOOK.swap(hook as *mut (), Ordering::AcqRel);
+    unsafe { hook_from_ptr(previous) }
+}
+
+/// Removes the hook set by [`set_oom_hook`], returning it.
+pub fn take_oom_hook() -> Option<fn(Layout)> {
+    let previous = OOM_HOOK.swap(core::ptr::null_mut(), Ordering::AcqRel);
+    unsafe { hook_from_ptr(previous) }
+}
+
+/// # Safety
+/// `ptr` must be null or a pointer stored in [`OOM_HOOK`] by [`set_oom_hook`]
+unsafe fn hook_from_ptr(ptr: *mut ()) -> Option<fn(Layout)> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { core::mem::transmute::<*mut (), fn(Layout)>(ptr) })
+    }
+}
+
+/// Calls the OOM hook if there is one and it isn't already running, returns whether or not it was called.
+fn call_oom_hook(size: usize, alignment: usize) -> bool {
+    let Some(hook) = (unsafe { hook_from_ptr(OOM_HOOK.load(Ordering::Acquire)) }) else {
+        return false;
+    };
+    let Ok(layout) = Layout::from_size_align(size, alignment) else {
+        return false;
+    };
+
+    let marker = 0u8;
+    let frame = core::hint::black_box(&marker) as *const u8 as usize;
+    if OOM_HOOK_FRAME
+        .compare_exchange(0, frame, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return false;
+    }
+
+    /// Marks the hook as no longer running on drop, including when the hook panics and unwinds
+    struct Running;
+    impl Drop for Running {
+        fn drop(&mut self) {
+            OOM_HOOK_FRAME.store(0, Ordering::Release);
+        }
+    }
+
+    let _running = Running;
+    hook(layout);
+    true
+}
+
+/// Marks the OOM hook as no longer running if it was running on the thread whose stack is `stack`,
+/// called when recovering from a panic without unwinding, which skips the drop guard in [`call_oom_hook`].
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+pub(crate) fn release_oom_hook(stack: &core::ops::Range<usize>) {
+    let frame = OOM_HOOK_FRAME.load(Ordering::Acquire);
+    if frame != 0 && stack.contains(&frame) {
+        _ = OOM_HOOK_FRAME.compare_exchange(frame, 0, Ordering::Release, Ordering::Relaxed);
+    }
+}
+
 pub struct GlobalSystemAllocator {
     inner: Mutex<SystemAllocator>,
 }
@@ -264,16 +399,56 @@ impl GlobalSystemAllocator {
         }
     }
 
+    /// Allocates `size` bytes aligned to `alignment`, returns [`None`] on failure.
+    ///
+    /// The OOM hook is called before failing, see [`set_oom_hook`].
     #[inline]
     pub fn allocate(&self, size: usize, alignment: usize) -> Option<NonNull<[u8]>> {
-        self.inner.lock().allocate(size, alignment)
+        if let Some(results) = self.inner.lock().allocate(size, alignment) {
+            return Some(results);
+        }
+
+        if !call_oom_hook(size, alignment) {
+            debug_log!(
+                ALLOC,
+                "failed to allocate {size} bytes aligned to {alignment}"
+            );
+            return None;
+        }
+
+        let results = self.inner.lock().allocate(size, alignment);
+        if results.is_none() {
+            debug_log!(ALLOC, "failed to allocate {size} bytes aligned to {alignment}, even after calling the OOM hook");
+        }
+        results
     }
 
+    /// Same as [`GlobalSystemAllocator::allocate`] but takes a [`Layout`] and returns an error on failure,
+    /// for use with `try_reserve`-like APIs.
+    #[inline]
+    pub fn allocate_fallible(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate(layout.size(), layout.align())
+            .ok_or(AllocError)
+    }
+
+    /// # Safety
+    /// `ptr` must be a pointer allocated by this allocator that wasn't freed yet
     #[inline]
     pub unsafe fn deallocate(&self, ptr: NonNull<u8>) {
         self.inner.lock().deallocate(ptr)
     }
 
+    /// Returns statistics about the allocator, walks all the blocks so it is slow.
+    pub fn stats(&self) -> AllocatorStats {
+        self.inner.lock().stats()
+    }
+
+    /// Same as [`GlobalSystemAllocator::stats`] but returns [`None`] instead of blocking if the allocator is locked,
+    /// for use in contexts that might have interrupted an allocation such as panic handlers.
+    pub fn try_stats(&self) -> Option<AllocatorStats> {
+        self.inner.try_lock().map(|inner| inner.stats())
+    }
+
     // TODO: implement grow and shrink
 }
 
@@ -282,7 +457,7 @@ unsafe impl Send for GlobalSystemAllocator {}
 
 unsafe impl GlobalAlloc for GlobalSystemAllocator {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        self.allocate(layout.size(), layout.align())
+        self.allocate_fallible(layout)
             .map(|x| x.as_ptr() as *mut u8)
             .unwrap_or(core::ptr::null_mut())
     }
@@ -300,8 +475,26 @@ unsafe impl GlobalAlloc for GlobalSystemAllocator {
 /// (rust wrapper)
 pub static GLOBAL_SYSTEM_ALLOCATOR: GlobalSystemAllocator = GlobalSystemAllocator::new();
 
-#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+/// Allocates memory for `layout` using [`GLOBAL_SYSTEM_ALLOCATOR`], returning an error instead of aborting on failure.
+///
+/// The OOM hook is called before failing, see [`set_oom_hook`],
+/// the memory must be freed using [`deallocate`].
+#[inline]
+pub fn allocate_fallible(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+    GLOBAL_SYSTEM_ALLOCATOR.allocate_fallible(layout)
+}
+
+/// Frees memory allocated by [`allocate_fallible`].
+/// # Safety
+/// `ptr` must be a pointer returned by [`allocate_fallible`] that wasn't freed yet
+#[inline]
+pub unsafe fn deallocate(ptr: NonNull<u8>) {
+    unsafe { GLOBAL_SYSTEM_ALLOCATOR.deallocate(ptr) }
+}
+
+#[cfg(all(feature = "c-exports", not(feature = "std")))]
 #[unsafe(no_mangle)]
+#[cfg_attr(feature = "linkonce", linkage = "weak")]
 /// Allocates an object sized `object_size` using [`GLOBAL_SYSTEM_ALLOCATOR`]
 pub extern "C" fn syscreate(object_size: usize, object_align: usize) -> OptZero<Slice<u8>> {
     GLOBAL_SYSTEM_ALLOCATOR
@@ -313,8 +506,9 @@ pub extern "C" fn syscreate(object_size: usize, object_align: usize) -> OptZero<
         .into()
 }
 
-#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+#[cfg(all(feature = "c-exports", not(feature = "std")))]
 #[unsafe(no_mangle)]
+#[cfg_attr(feature = "linkonce", linkage = "weak")]
 /// Deallocates an object sized `object_size` using [`GLOBAL_SYSTEM_ALLOCATOR`]
 /// # Safety
 /// `object_ptr` must be a pointer to a valid object allocated by [`GLOBAL_SYSTEM_ALLOCATOR`]
@@ -1,3 +1,9 @@
+//! Stack traces of the current thread, see [`StackTrace`]
+//!
+//! By default stack traces are captured by walking the frame pointers, which requires the code to be compiled with frame pointers,
+//! with the `shadow-stack` feature they are captured from a per-thread shadow stack instead,
+//! which works regardless of codegen flags but only contains the functions instrumented using [`crate::shadow_frame`].
+
 #[cfg(target_arch = "aarch64")]
 mod inner {
     #[derive(Debug, Clone, Copy)]
@@ -14,6 +20,8 @@ mod inner {
     impl StackFrame {
         #[inline(always)]
         /// Gets the current Frame Pointer from the fp register
+        /// # Safety
+        /// The code must be compiled with frame pointers, otherwise the returned frame is garbage
         pub unsafe fn get_current<'a>() -> &'a Self {
             unsafe {
                 let fp: *mut Self;
@@ -28,6 +36,8 @@ mod inner {
         }
 
         /// Gets the previous Frame Pointer from this one
+        /// # Safety
+        /// The frame must have been obtained from a valid chain of frame pointers, see [`StackFrame::get_current`]
         pub unsafe fn prev(&self) -> Option<&Self> {
             let prev = self.prev.0;
 
@@ -51,6 +61,8 @@ mod inner {
     impl StackFrame {
         #[inline(always)]
         /// Gets the current Frame Pointer from the fp register
+        /// # Safety
+        /// The code must be compiled with frame pointers, otherwise the returned frame is garbage
         pub unsafe fn get_current<'a>() -> &'a Self {
             unsafe {
                 let fp: *mut Self;
@@ -65,6 +77,8 @@ mod inner {
         }
 
         /// Gets the previous Frame Pointer from this one
+        /// # Safety
+        /// The frame must have been obtained from a valid chain of frame pointers, see [`StackFrame::get_current`]
         pub unsafe fn prev(&self) -> Option<&Self> {
             let prev = self.prev;
 
@@ -76,46 +90,289 @@ mod inner {
     }
 }
 
+/// A per-thread shadow stack of the functions instrumented using [`crate::shadow_frame`]
+#[cfg(feature = "shadow-stack")]
+pub mod shadow {
+    use core::{cell::UnsafeCell, marker::PhantomData};
+
+    /// The maximum amount of frames recorded in the shadow stack,
+    /// frames deeper than this are counted but not recorded.
+    pub const MAX_DEPTH: usize = 64;
+
+    struct ShadowStack {
+        frames: [*const u8; MAX_DEPTH],
+        depth: usize,
+    }
+
+    #[thread_local]
+    static SHADOW_STACK: UnsafeCell<ShadowStack> = UnsafeCell::new(ShadowStack {
+        frames: [core::ptr::null(); MAX_DEPTH],
+        depth: 0,
+    });
+
+    /// Pops the frame pushed by [`enter`] when dropped.
+    #[must_use = "the frame is popped as soon as the guard is dropped"]
+    pub struct ShadowFrameGuard {
+        // the frame belongs to the current thread
+        _marker: PhantomData<*const ()>,
+    }
+
+    impl Drop for ShadowFrameGuard {
+        #[inline(always)]
+        fn drop(&mut self) {
+            unsafe {
+                let stack = &mut *SHADOW_STACK.get();
+                stack.depth = stack.depth.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Gets an address inside of the function this is inlined into.
+    #[inline(always)]
+    pub fn current_pc() -> *const u8 {
+        let pc: *const u8;
+        unsafe {
+            #[cfg(target_arch = "x86_64")]
+            core::arch::asm!("lea {}, [rip]", out(reg) pc, options(nomem, nostack, preserves_flags));
+            #[cfg(target_arch = "aarch64")]
+            core::arch::asm!("adr {}, .", out(reg) pc, options(nomem, nostack, preserves_flags));
+        }
+        pc
+    }
+
+    /// Pushes a frame with the address `addr` to the shadow stack of the current thread,
+    /// the frame is popped when the returned guard is dropped, use [`crate::shadow_frame`] instead.
+    #[inline(always)]
+    pub fn enter(addr: *const u8) -> ShadowFrameGuard {
+        unsafe {
+            let stack = &mut *SHADOW_STACK.get();
+            if let Some(frame) = stack.frames.get_mut(stack.depth) {
+                *frame = addr;
+            }
+            stack.depth += 1;
+        }
+
+        ShadowFrameGuard {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the depth of the shadow stack of the current thread, including the frames that weren't recorded.
+    #[inline]
+    pub fn depth() -> usize {
+        unsafe { (*SHADOW_STACK.get()).depth }
+    }
+
+    /// Returns the address of the recorded frame at `index` in the shadow stack of the current thread,
+    /// 0 being the innermost frame, [`None`] if there is no such frame or it wasn't recorded.
+    #[inline]
+    pub fn frame_at(index: usize) -> Option<*const u8> {
+        unsafe {
+            let stack = &*SHADOW_STACK.get();
+            let recorded = stack.depth.min(MAX_DEPTH);
+            let index = recorded.checked_sub(index + 1)?;
+            Some(stack.frames[index])
+        }
+    }
+
+    /// Calls `f` with the address of each recorded frame in the shadow stack of the current thread, starting from the innermost one.
+    pub fn for_each(mut f: impl FnMut(*const u8)) {
+        unsafe {
+            let stack = &*SHADOW_STACK.get();
+            let recorded = stack.depth.min(MAX_DEPTH);
+            for frame in stack.frames[..recorded].iter().rev() {
+                f(*frame);
+            }
+        }
+    }
+}
+
+/// Records the current function in the shadow stack of the current thread until the end of the enclosing scope,
+/// place it at the start of a function to make it show up in stack traces captured using the shadow stack.
+///
+/// Does nothing unless the `shadow-stack` feature is enabled, so it can be used unconditionally.
+#[cfg(feature = "shadow-stack")]
+#[macro_export]
+macro_rules! shadow_frame {
+    () => {
+        let _shadow_frame_guard =
+            $crate::backtrace::shadow::enter($crate::backtrace::shadow::current_pc());
+    };
+}
+
+/// Records the current function in the shadow stack of the current thread until the end of the enclosing scope,
+/// place it at the start of a function to make it show up in stack traces captured using the shadow stack.
+///
+/// Does nothing unless the `shadow-stack` feature is enabled, so it can be used unconditionally.
+#[cfg(not(feature = "shadow-stack"))]
+#[macro_export]
+macro_rules! shadow_frame {
+    () => {};
+}
+
 use core::fmt::Display;
 pub use inner::StackFrame;
 
+/// The default maximum amount of frames in a [`StackTrace`], see [`StackTrace::max_depth`]
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// A frame of a [`StackTrace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    return_address: *const u8,
+    frame_pointer: *const StackFrame,
+}
+
+impl Frame {
+    /// The address the frame returns to,
+    /// or an address inside of the instrumented function if captured from the shadow stack.
+    #[inline]
+    pub const fn return_address(&self) -> *const u8 {
+        self.return_address
+    }
+
+    /// The frame pointer of the frame, null if captured from the shadow stack.
+    #[inline]
+    pub const fn frame_pointer(&self) -> *const StackFrame {
+        self.frame_pointer
+    }
+}
+
+/// A stack trace of the current thread, printed using [`Display`] or walked using [`StackTrace::frames`]
 #[derive(Clone, Copy)]
-pub struct StackTrace<'a>(&'a StackFrame);
+pub struct StackTrace<'a> {
+    #[cfg_attr(feature = "shadow-stack", allow(dead_code))]
+    frame: &'a StackFrame,
+    skip: usize,
+    max_depth: usize,
+}
 
 impl<'a> StackTrace<'a> {
     /// Gets the current Stack Trace, unsafe because the StackTrace may be corrupted
+    /// # Safety
+    /// Unless the `shadow-stack` feature is enabled, the code must be compiled with frame pointers
     #[inline(always)]
     pub unsafe fn current() -> Self {
-        Self(unsafe { StackFrame::get_current() })
+        Self {
+            frame: unsafe { StackFrame::get_current() },
+            skip: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Skips the `n` innermost frames, for example the frames of an error-reporting library that captured the trace.
+    #[inline]
+    pub const fn skip(self, n: usize) -> Self {
+        Self {
+            skip: self.skip + n,
+            ..self
+        }
+    }
+
+    /// Sets the maximum amount of frames in the trace (after skipping), defaults to [`DEFAULT_MAX_DEPTH`].
+    #[inline]
+    pub const fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// Returns an iterator over the frames of the trace, starting from the innermost one.
+    pub fn frames(&self) -> Frames<'a> {
+        let mut frames = Frames {
+            #[cfg(not(feature = "shadow-stack"))]
+            next: Some(self.frame),
+            #[cfg(feature = "shadow-stack")]
+            next: 0,
+            remaining: usize::MAX,
+            #[cfg(feature = "shadow-stack")]
+            _marker: core::marker::PhantomData,
+        };
+
+        for _ in frames.by_ref().take(self.skip) {}
+        frames.remaining = self.max_depth;
+        frames
     }
 }
 
-impl<'a> Display for StackTrace<'a> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        const MAX_FRAMES: usize = 16;
-        unsafe {
-            let mut fp = self.0;
-            writeln!(f, "\x1B[34mStack trace:")?;
+/// An iterator over the frames of a [`StackTrace`], see [`StackTrace::frames`]
+#[derive(Debug, Clone)]
+pub struct Frames<'a> {
+    #[cfg(not(feature = "shadow-stack"))]
+    next: Option<&'a StackFrame>,
+    /// the index of the next frame in the shadow stack, starting from the innermost
+    #[cfg(feature = "shadow-stack")]
+    next: usize,
+    remaining: usize,
+    #[cfg(feature = "shadow-stack")]
+    _marker: core::marker::PhantomData<&'a StackFrame>,
+}
 
-            let mut omitted = false;
-            for i in 0..MAX_FRAMES {
-                let return_address = fp.return_ptr();
+impl<'a> Frames<'a> {
+    /// Returns whether or not there are more frames left that weren't returned because of the maximum depth,
+    /// see [`StackTrace::max_depth`].
+    pub fn is_truncated(&self) -> bool {
+        if self.remaining != 0 {
+            return false;
+        }
 
-                writeln!(f, "  {:?} ", return_address)?;
+        #[cfg(not(feature = "shadow-stack"))]
+        return self.next.is_some();
+        #[cfg(feature = "shadow-stack")]
+        return self.next < shadow::depth();
+    }
+}
 
-                let Some(frame) = fp.prev() else {
-                    break;
-                };
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame;
 
-                fp = frame;
-                omitted = i == MAX_FRAMES - 1;
-            }
+    #[cfg(not(feature = "shadow-stack"))]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
 
-            if omitted {
-                writeln!(f, "  ...<frames omitted>")?;
-            }
-            write!(f, "\x1B[0m")?;
+        let frame = self.next?;
+        self.next = unsafe { frame.prev() };
+        self.remaining -= 1;
+
+        Some(Frame {
+            return_address: frame.return_ptr(),
+            frame_pointer: frame,
+        })
+    }
+
+    #[cfg(feature = "shadow-stack")]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let return_address = shadow::frame_at(self.next)?;
+        self.next += 1;
+        self.remaining -= 1;
+
+        Some(Frame {
+            return_address,
+            frame_pointer: core::ptr::null(),
+        })
+    }
+}
+
+impl<'a> Display for StackTrace<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if cfg!(feature = "shadow-stack") {
+            writeln!(f, "\x1B[34mStack trace (shadow stack):")?;
+        } else {
+            writeln!(f, "\x1B[34mStack trace:")?;
+        }
+
+        let mut frames = self.frames();
+        for frame in frames.by_ref() {
+            writeln!(f, "  {:?} ", frame.return_address())?;
+        }
+
+        if frames.is_truncated() {
+            writeln!(f, "  ...<frames omitted>")?;
         }
-        Ok(())
+        write!(f, "\x1B[0m")
     }
 }
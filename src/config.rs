@@ -0,0 +1,249 @@
+//! A parser for simple INI-like configuration files, see [`Config`]
+//!
+//! The format is a subset of INI/TOML:
+//! ```ini
+//! # comments start with `#` or `;`
+//! key = value outside of any section
+//!
+//! [section]
+//! name = "quoted values can contain \"escapes\" and # characters"
+//! port = 53
+//! enabled = true
+//! ```
+
+use core::fmt::Display;
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use safa_abi::{errors::ErrorStatus, fs::OpenOptions};
+
+use crate::{resource::Resource, syscalls};
+
+/// An error that occurred while parsing a configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line the error occurred at, starting from 1
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A `[section` header without a closing `]`
+    UnterminatedSection,
+    /// A quoted value without a closing `"`
+    UnterminatedString,
+    /// An unknown escape sequence in a quoted value
+    InvalidEscape,
+    /// A line that is neither a section header, a `key = value` pair or a comment
+    ExpectedKeyValue,
+    /// A `key = value` pair with an empty key
+    EmptyKey,
+    /// Characters after a quoted value
+    TrailingCharacters,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self.kind {
+            ParseErrorKind::UnterminatedSection => "unterminated section header",
+            ParseErrorKind::UnterminatedString => "unterminated string",
+            ParseErrorKind::InvalidEscape => "invalid escape sequence",
+            ParseErrorKind::ExpectedKeyValue => "expected `key = value`",
+            ParseErrorKind::EmptyKey => "empty key",
+            ParseErrorKind::TrailingCharacters => "trailing characters after value",
+        };
+        write!(f, "line {}: {msg}", self.line)
+    }
+}
+
+/// An error that occurred while loading a configuration file, see [`Config::load`]
+#[derive(Debug, Clone, Copy)]
+pub enum LoadError {
+    Parse(ParseError),
+    InvalidUtf8,
+    System(ErrorStatus),
+}
+
+impl From<ErrorStatus> for LoadError {
+    fn from(value: ErrorStatus) -> Self {
+        Self::System(value)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+/// An error that occurred while looking up a typed value, see [`Config::get_u64`] and [`Config::get_bool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupError {
+    /// There is no such key in the given section
+    Missing,
+    /// The value couldn't be parsed as the requested type
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    section: String,
+    key: String,
+    value: String,
+}
+
+/// A parsed configuration file.
+///
+/// Keys outside of any section belong to the section `""`,
+/// if a key is repeated in the same section the last value wins.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    entries: Vec<Entry>,
+}
+
+fn parse_value(value: &str, line: usize) -> Result<String, ParseError> {
+    let err = |kind| ParseError { line, kind };
+
+    let Some(quoted) = value.strip_prefix('"') else {
+        // unquoted values end at the first comment
+        let value = value
+            .split_once(['#', ';'])
+            .map(|(value, _)| value)
+            .unwrap_or(value);
+        return Ok(String::from(value.trim_end()));
+    };
+
+    let mut results = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let rest = quoted[i + 1..].trim_start();
+                if !(rest.is_empty() || rest.starts_with(['#', ';'])) {
+                    return Err(err(ParseErrorKind::TrailingCharacters));
+                }
+                return Ok(results);
+            }
+            '\\' => match chars.next() {
+                Some((_, '"')) => results.push('"'),
+                Some((_, '\\')) => results.push('\\'),
+                Some((_, 'n')) => results.push('\n'),
+                Some((_, 't')) => results.push('\t'),
+                _ => return Err(err(ParseErrorKind::InvalidEscape)),
+            },
+            c => results.push(c),
+        }
+    }
+
+    Err(err(ParseErrorKind::UnterminatedString))
+}
+
+impl Config {
+    /// Parses a configuration file from its contents `src`.
+    pub fn parse(src: &str) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        let mut section = String::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let line_num = i + 1;
+            let err = |kind| ParseError {
+                line: line_num,
+                kind,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let (name, _) = header
+                    .split_once(']')
+                    .ok_or(err(ParseErrorKind::UnterminatedSection))?;
+                section = String::from(name.trim());
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(err(ParseErrorKind::ExpectedKeyValue))?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(err(ParseErrorKind::EmptyKey));
+            }
+
+            entries.push(Entry {
+                section: section.clone(),
+                key: String::from(key),
+                value: parse_value(value.trim_start(), line_num)?,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Reads and parses the configuration file at `path`.
+    pub fn load(path: &str) -> Result<Self, LoadError> {
+        let resource = Resource::open(path, OpenOptions::READ)?;
+        let size = syscalls::io::fsize(resource.ri())?;
+
+        let mut buf = alloc::vec![0u8; size];
+        let mut read = 0;
+        while read < size {
+            let amount = unsafe { resource.read(read as isize, &mut buf[read..])? };
+            if amount == 0 {
+                break;
+            }
+            read += amount;
+        }
+        buf.truncate(read);
+
+        let src = core::str::from_utf8(&buf).map_err(|_| LoadError::InvalidUtf8)?;
+        Ok(Self::parse(src)?)
+    }
+
+    /// Returns the value of `key` in `section` as is, use `""` as the section for keys outside of any section.
+    pub fn get_str(&self, section: &str, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.section == section && e.key == key)
+            .map(|e| e.value.as_str())
+    }
+
+    /// Returns the value of `key` in `section` as a [`u64`], the value can be decimal or hexadecimal (prefixed with `0x`).
+    pub fn get_u64(&self, section: &str, key: &str) -> Result<u64, LookupError> {
+        let value = self.get_str(section, key).ok_or(LookupError::Missing)?;
+        let results = match value.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => value.parse(),
+        };
+
+        results.map_err(|_| LookupError::Invalid)
+    }
+
+    /// Returns the value of `key` in `section` as a [`bool`],
+    /// `true`, `yes`, `on` and `1` are true, `false`, `no`, `off` and `0` are false.
+    pub fn get_bool(&self, section: &str, key: &str) -> Result<bool, LookupError> {
+        match self.get_str(section, key).ok_or(LookupError::Missing)? {
+            "true" | "yes" | "on" | "1" => Ok(true),
+            "false" | "no" | "off" | "0" => Ok(false),
+            _ => Err(LookupError::Invalid),
+        }
+    }
+
+    /// Returns an iterator over the `(key, value)` pairs in `section`, in the order they appear in.
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.entries
+            .iter()
+            .filter(move |e| e.section == section)
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+    }
+}
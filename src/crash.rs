@@ -0,0 +1,209 @@
+//! Crash reports written to disk on panic, see [`install`]
+//!
+//! A report is a plain text file made of sections, each starting with a `[section]` header:
+//! ```text
+//! [message]
+//! panicked at src/main.rs:4:5: oops
+//! [stack trace]
+//!   0x1234
+//! [args]
+//!   0: sys:/bin/app
+//! [env]
+//!   PATH=sys:/bin
+//! [resources]
+//!   0: File, 120 bytes
+//! [memory]
+//!   blocks: 12, used: 4.0 KiB, free: 60.0 KiB
+//! ```
+//!
+//! Without the `std` feature reports are written by the crate's panic handler once installed,
+//! with it call [`write_report`] from a panic hook.
+
+use core::{
+    fmt::{self, Display, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use safa_abi::{errors::ErrorStatus, fs::OpenOptions};
+
+use crate::{
+    alloc::GLOBAL_SYSTEM_ALLOCATOR, backtrace::StackTrace, fmt_util::format_size,
+    process::args::ArgsIter, resource::Resource, sync::locks::Mutex, syscalls,
+};
+
+/// The configuration of crash reports, see [`install`]
+#[derive(Debug, Clone)]
+pub struct CrashConfig {
+    path: String,
+    env_keys: Vec<String>,
+}
+
+impl CrashConfig {
+    /// Constructs a new configuration writing reports to `path`, overwriting any previous report.
+    ///
+    /// By default no environment variables are included, as they might contain secrets.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: String::from(path),
+            env_keys: Vec::new(),
+        }
+    }
+
+    /// Includes the environment variable `key` in reports.
+    pub fn env_key(&mut self, key: &str) -> &mut Self {
+        self.env_keys.push(String::from(key));
+        self
+    }
+}
+
+static CRASH_CONFIG: Mutex<Option<CrashConfig>> = Mutex::new(None);
+/// Whether or not a report is being written, so that panicking while writing one doesn't write another
+static WRITING_REPORT: AtomicBool = AtomicBool::new(false);
+
+/// Installs `config`, enabling crash reports, returns the previously installed configuration.
+pub fn install(config: CrashConfig) -> Option<CrashConfig> {
+    CRASH_CONFIG.lock().replace(config)
+}
+
+/// Disables crash reports, returning the installed configuration.
+pub fn uninstall() -> Option<CrashConfig> {
+    CRASH_CONFIG.lock().take()
+}
+
+/// Writes to a resource at an increasing offset.
+struct ReportWriter {
+    resource: Resource,
+    offset: isize,
+    error: Option<ErrorStatus>,
+}
+
+impl Write for ReportWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            match unsafe { self.resource.write(self.offset, bytes) } {
+                Ok(0) => {
+                    self.error = Some(ErrorStatus::Generic);
+                    return Err(fmt::Error);
+                }
+                Ok(written) => {
+                    self.offset += written as isize;
+                    bytes = &bytes[written..];
+                }
+                Err(err) => {
+                    self.error = Some(err);
+                    return Err(fmt::Error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_sections(
+    w: &mut ReportWriter,
+    config: &CrashConfig,
+    message: &dyn Display,
+) -> fmt::Result {
+    writeln!(w, "[message]\n{message}")?;
+
+    writeln!(w, "[stack trace]")?;
+    // skips the frames of the crash module
+    let trace = unsafe { StackTrace::current() }.skip(2);
+    for frame in trace.frames() {
+        writeln!(w, "  {:?}", frame.return_address())?;
+    }
+
+    writeln!(w, "[args]")?;
+    let mut args = ArgsIter::get();
+    let mut i = 0;
+    while let Some(arg) = args.next() {
+        writeln!(w, "  {i}: {arg}")?;
+        i += 1;
+    }
+
+    writeln!(w, "[env]")?;
+    for key in &config.env_keys {
+        let results = crate::process::env::try_env_with(key.as_bytes(), |value| match value {
+            Some(value) => writeln!(w, "  {key}={}", value.escape_ascii()),
+            None => writeln!(w, "  {key} is unset"),
+        });
+
+        match results {
+            Some(results) => results?,
+            None => writeln!(w, "  <environment is locked>")?,
+        }
+    }
+
+    writeln!(w, "[resources]")?;
+    let mut results = Ok(());
+    syscalls::resources::list_with(|info| {
+        if results.is_ok() && info.ri != w.resource.ri() {
+            results = writeln!(w, "  {}: {:?}, {} bytes", info.ri, info.kind, info.size);
+        }
+    });
+    results?;
+
+    writeln!(w, "[memory]")?;
+    match GLOBAL_SYSTEM_ALLOCATOR.try_stats() {
+        Some(stats) => writeln!(
+            w,
+            "  blocks: {}, used: {}, free: {}",
+            stats.blocks,
+            format_size(stats.used_bytes as u64),
+            format_size(stats.free_bytes as u64)
+        ),
+        None => writeln!(w, "  <allocator is locked>"),
+    }
+}
+
+/// Writes a crash report with the message `message` to the path given in the installed [`CrashConfig`],
+/// does nothing if there is no installed configuration.
+///
+/// Called by the crate's panic handler, call it from a panic hook if the `std` feature is enabled.
+/// Calls made while a report is being written (for example by panicking while writing one) do nothing.
+pub fn write_report(message: &dyn Display) -> Result<(), ErrorStatus> {
+    if WRITING_REPORT.swap(true, Ordering::Acquire) {
+        return Ok(());
+    }
+
+    let results = (|| {
+        // the panicking thread might be holding the lock
+        let Some(config) = CRASH_CONFIG.try_lock() else {
+            return Err(ErrorStatus::Busy);
+        };
+        let Some(config) = config.as_ref() else {
+            return Ok(());
+        };
+
+        match syscalls::fs::create(&config.path) {
+            Ok(()) | Err(ErrorStatus::AlreadyExists) => {}
+            Err(err) => return Err(err),
+        }
+
+        let resource = Resource::open(&config.path, OpenOptions::WRITE)?;
+        syscalls::io::truncate(resource.ri(), 0)?;
+
+        let mut writer = ReportWriter {
+            resource,
+            offset: 0,
+            error: None,
+        };
+
+        if write_sections(&mut writer, config, message).is_err() {
+            return Err(writer.error.unwrap_or(ErrorStatus::Generic));
+        }
+        syscalls::io::sync(writer.resource.ri())
+    })();
+
+    WRITING_REPORT.store(false, Ordering::Release);
+    results
+}
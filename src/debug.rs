@@ -0,0 +1,124 @@
+//! Diagnostics of the crate itself, enabled at runtime using the [`DEBUG_VAR`] environment variable
+//!
+//! For example `SAFA_API_DEBUG=alloc,dns,spawn` (or `SAFA_API_DEBUG=all`) makes the allocator, the DNS resolver
+//! and spawning processes log what they do to stderr, without having to rebuild anything.
+//!
+//! The variable is read once by [`crate::process::sysapi_init`], with the `std` feature there is no such initialization,
+//! call [`init_from_env`] at the start of `main` instead, the topics can also be changed at any time using [`set_topics`].
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// The environment variable listing the enabled [`Topics`], separated by commas.
+pub const DEBUG_VAR: &str = "SAFA_API_DEBUG";
+
+/// A set of parts of the crate that log diagnostics, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Topics(u8);
+
+impl Topics {
+    pub const NONE: Self = Self(0);
+    /// Mapping memory from the kernel and allocation failures, `alloc`
+    pub const ALLOC: Self = Self(1 << 0);
+    /// DNS queries and their responses, `dns`
+    pub const DNS: Self = Self(1 << 1);
+    /// Spawning processes, `spawn`
+    pub const SPAWN: Self = Self(1 << 2);
+    /// All of the topics, `all`
+    pub const ALL: Self = Self(Self::ALLOC.0 | Self::DNS.0 | Self::SPAWN.0);
+
+    const NAMES: [(&'static str, Self); 4] = [
+        ("alloc", Self::ALLOC),
+        ("dns", Self::DNS),
+        ("spawn", Self::SPAWN),
+        ("all", Self::ALL),
+    ];
+
+    /// Parses a comma separated list of topic names such as `alloc,dns`, unknown names are ignored.
+    pub fn parse(value: &[u8]) -> Self {
+        let mut topics = Self::NONE;
+        for name in value.split(|b| *b == b',') {
+            let name = name.trim_ascii();
+            if let Some((_, topic)) = Self::NAMES.iter().find(|(n, _)| n.as_bytes() == name) {
+                topics = topics.union(*topic);
+            }
+        }
+        topics
+    }
+
+    /// Whether or not all of the topics in `other` are in `self`.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The topics in either `self` or `other`.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The name of the topic as used in [`DEBUG_VAR`], [`None`] if `self` isn't a single topic.
+    pub fn name(self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(_, topic)| *topic == self)
+            .map(|(name, _)| *name)
+    }
+}
+
+impl core::ops::BitOr for Topics {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+static TOPICS: AtomicU8 = AtomicU8::new(0);
+
+/// Returns the enabled topics.
+#[inline]
+pub fn topics() -> Topics {
+    Topics(TOPICS.load(Ordering::Relaxed))
+}
+
+/// Enables exactly `topics`, replacing the topics read from [`DEBUG_VAR`].
+#[inline]
+pub fn set_topics(topics: Topics) {
+    TOPICS.store(topics.0, Ordering::Relaxed);
+}
+
+/// Whether or not `topic` is enabled.
+#[inline]
+pub fn enabled(topic: Topics) -> bool {
+    topics().contains(topic)
+}
+
+/// Enables the topics listed in `value`, the value of [`DEBUG_VAR`].
+pub(crate) fn init(value: &[u8]) {
+    set_topics(Topics::parse(value));
+}
+
+/// Enables the topics listed in [`DEBUG_VAR`], for programs using the `std` feature, see the [module documentation](self).
+#[cfg(feature = "std")]
+pub fn init_from_env() {
+    if let Some(value) = std::env::var_os(DEBUG_VAR) {
+        init(value.as_encoded_bytes());
+    }
+}
+
+/// Logs to stderr if the topic `$topic` (the name of a [`Topics`] constant) is enabled,
+/// doesn't allocate so that it can be used inside the allocator.
+macro_rules! debug_log {
+    ($topic: ident, $($arg: tt)*) => {
+        if $crate::debug::enabled($crate::debug::Topics::$topic) {
+            $crate::printerrln!(
+                "safa-api[{}]: {}",
+                $crate::debug::Topics::$topic.name().unwrap_or_default(),
+                format_args!($($arg)*)
+            );
+        }
+    };
+}
+
+pub(crate) use debug_log;
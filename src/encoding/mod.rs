@@ -0,0 +1,3 @@
+//! Encoding and decoding helpers for binary formats, see [`wire`]
+
+pub mod wire;
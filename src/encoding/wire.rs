@@ -0,0 +1,316 @@
+//! Byte-order aware reading and writing of integers for wire formats such as network protocols
+//!
+//! The free functions ([`get_u16_be`], [`put_u32_le`], ...) read from or write to the start of a slice,
+//! while [`Cursor`] and [`CursorMut`] keep track of the position for sequential parsing and encoding,
+//! none of them allocate or panic on short buffers, they fail with a [`WireError`] instead.
+
+use core::fmt;
+
+/// An error returned when a buffer is too short for a read or a write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    /// The input ended before the value could be read.
+    UnexpectedEnd {
+        /// The amount of bytes needed by the read.
+        needed: usize,
+        /// The amount of bytes left in the input.
+        available: usize,
+    },
+    /// The output doesn't have enough room left for the value.
+    BufferFull {
+        /// The amount of bytes needed by the write.
+        needed: usize,
+        /// The amount of bytes left in the output.
+        available: usize,
+    },
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd { needed, available } => write!(
+                f,
+                "unexpected end of input, needed {needed} bytes but only {available} are left"
+            ),
+            Self::BufferFull { needed, available } => write!(
+                f,
+                "buffer full, needed {needed} bytes but only {available} are left"
+            ),
+        }
+    }
+}
+
+fn take<const N: usize>(buf: &[u8]) -> Result<[u8; N], WireError> {
+    match buf.first_chunk::<N>() {
+        Some(bytes) => Ok(*bytes),
+        None => Err(WireError::UnexpectedEnd {
+            needed: N,
+            available: buf.len(),
+        }),
+    }
+}
+
+fn give<const N: usize>(buf: &mut [u8], bytes: [u8; N]) -> Result<(), WireError> {
+    let available = buf.len();
+    match buf.first_chunk_mut::<N>() {
+        Some(dest) => {
+            *dest = bytes;
+            Ok(())
+        }
+        None => Err(WireError::BufferFull {
+            needed: N,
+            available,
+        }),
+    }
+}
+
+macro_rules! int_functions {
+    ($($ty:ty => $get_be:ident, $get_le:ident, $put_be:ident, $put_le:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a big-endian (network order) `", stringify!($ty), "` from the start of `buf`.")]
+            #[inline]
+            pub fn $get_be(buf: &[u8]) -> Result<$ty, WireError> {
+                take(buf).map(<$ty>::from_be_bytes)
+            }
+
+            #[doc = concat!("Reads a little-endian `", stringify!($ty), "` from the start of `buf`.")]
+            #[inline]
+            pub fn $get_le(buf: &[u8]) -> Result<$ty, WireError> {
+                take(buf).map(<$ty>::from_le_bytes)
+            }
+
+            #[doc = concat!("Writes `value` as a big-endian (network order) `", stringify!($ty), "` to the start of `buf`.")]
+            #[inline]
+            pub fn $put_be(buf: &mut [u8], value: $ty) -> Result<(), WireError> {
+                give(buf, value.to_be_bytes())
+            }
+
+            #[doc = concat!("Writes `value` as a little-endian `", stringify!($ty), "` to the start of `buf`.")]
+            #[inline]
+            pub fn $put_le(buf: &mut [u8], value: $ty) -> Result<(), WireError> {
+                give(buf, value.to_le_bytes())
+            }
+        )*
+    };
+}
+
+int_functions! {
+    u16 => get_u16_be, get_u16_le, put_u16_be, put_u16_le;
+    u32 => get_u32_be, get_u32_le, put_u32_be, put_u32_le;
+    u64 => get_u64_be, get_u64_le, put_u64_be, put_u64_le;
+}
+
+/// Reads values sequentially from a byte slice
+///
+/// A failed read doesn't advance the cursor.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+macro_rules! cursor_reads {
+    ($($ty:ty => $read:ident, $get:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a `", stringify!($ty), "` and advances past it, see [`", stringify!($get), "`].")]
+            #[inline]
+            pub fn $read(&mut self) -> Result<$ty, WireError> {
+                let value = $get(self.remaining())?;
+                self.pos += size_of::<$ty>();
+                Ok(value)
+            }
+        )*
+    };
+}
+
+impl<'a> Cursor<'a> {
+    /// Creates a cursor at the start of `buf`.
+    #[inline]
+    pub const fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// The offset of the next read from the start of the input.
+    #[inline]
+    pub const fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the cursor to `pos`, used to follow offsets such as DNS name compression pointers.
+    pub fn set_position(&mut self, pos: usize) -> Result<(), WireError> {
+        if pos > self.buf.len() {
+            return Err(WireError::UnexpectedEnd {
+                needed: pos,
+                available: self.buf.len(),
+            });
+        }
+
+        self.pos = pos;
+        Ok(())
+    }
+
+    /// The whole input, including the already read part.
+    #[inline]
+    pub const fn get_ref(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// The input that wasn't read yet.
+    #[inline]
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Returns true if the whole input was read.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    /// Reads `len` bytes and advances past them.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        let remaining = self.remaining();
+        if len > remaining.len() {
+            return Err(WireError::UnexpectedEnd {
+                needed: len,
+                available: remaining.len(),
+            });
+        }
+
+        self.pos += len;
+        Ok(&remaining[..len])
+    }
+
+    /// Reads `N` bytes into an array and advances past them.
+    #[inline]
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], WireError> {
+        let bytes = take(self.remaining())?;
+        self.pos += N;
+        Ok(bytes)
+    }
+
+    /// Advances past `len` bytes without reading them.
+    #[inline]
+    pub fn skip(&mut self, len: usize) -> Result<(), WireError> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    /// Returns the next byte without advancing past it.
+    #[inline]
+    pub fn peek_u8(&self) -> Result<u8, WireError> {
+        take::<1>(self.remaining()).map(|[byte]| byte)
+    }
+
+    /// Reads a byte and advances past it.
+    #[inline]
+    pub fn read_u8(&mut self) -> Result<u8, WireError> {
+        self.read_array::<1>().map(|[byte]| byte)
+    }
+
+    cursor_reads! {
+        u16 => read_u16_be, get_u16_be;
+        u16 => read_u16_le, get_u16_le;
+        u32 => read_u32_be, get_u32_be;
+        u32 => read_u32_le, get_u32_le;
+        u64 => read_u64_be, get_u64_be;
+        u64 => read_u64_le, get_u64_le;
+    }
+}
+
+/// Writes values sequentially into a byte slice
+///
+/// A failed write doesn't advance the cursor nor modify the output.
+#[derive(Debug)]
+pub struct CursorMut<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+macro_rules! cursor_writes {
+    ($($ty:ty => $write:ident, $put:ident;)*) => {
+        $(
+            #[doc = concat!("Writes `value` and advances past it, see [`", stringify!($put), "`].")]
+            #[inline]
+            pub fn $write(&mut self, value: $ty) -> Result<(), WireError> {
+                $put(&mut self.buf[self.pos..], value)?;
+                self.pos += size_of::<$ty>();
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'a> CursorMut<'a> {
+    /// Creates a cursor at the start of `buf`.
+    #[inline]
+    pub const fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// The offset of the next write from the start of the output, which is also the amount of bytes written so far.
+    #[inline]
+    pub const fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The amount of bytes that can still be written.
+    #[inline]
+    pub const fn remaining_len(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// The part of the output written so far.
+    #[inline]
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+
+    /// Consumes the cursor returning the part of the output written so far.
+    #[inline]
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.buf[..self.pos]
+    }
+
+    /// Writes `bytes` and advances past them.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WireError> {
+        let available = self.remaining_len();
+        if bytes.len() > available {
+            return Err(WireError::BufferFull {
+                needed: bytes.len(),
+                available,
+            });
+        }
+
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+        Ok(())
+    }
+
+    /// Writes a byte and advances past it.
+    #[inline]
+    pub fn write_u8(&mut self, value: u8) -> Result<(), WireError> {
+        self.write_bytes(&[value])
+    }
+
+    /// Writes a big-endian `u16` at `pos` which must be before the current position, without moving the cursor,
+    /// used to fill in lengths and counts once the data they describe was written.
+    pub fn patch_u16_be(&mut self, pos: usize, value: u16) -> Result<(), WireError> {
+        let written = &mut self.buf[..self.pos];
+        match written.get_mut(pos..) {
+            Some(dest) => put_u16_be(dest, value),
+            None => Err(WireError::BufferFull {
+                needed: size_of::<u16>(),
+                available: 0,
+            }),
+        }
+    }
+
+    cursor_writes! {
+        u16 => write_u16_be, put_u16_be;
+        u16 => write_u16_le, put_u16_le;
+        u32 => write_u32_be, put_u32_be;
+        u32 => write_u32_le, put_u32_le;
+        u64 => write_u64_be, put_u64_be;
+        u64 => write_u64_le, put_u64_le;
+    }
+}
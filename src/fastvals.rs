@@ -0,0 +1,106 @@
+//! Frequently read kernel values served without a syscall per read, see [`uptime`]
+//!
+//! SafaOS doesn't map a kernel-shared page with these values (yet), so they are derived from a cached snapshot instead:
+//! the uptime is extrapolated from the hardware counter (see [`syscalls::clock::getcnt`]), which is read without trapping,
+//! and the snapshot is refreshed from the kernel once it is older than [`MAX_STALENESS`], bounding the drift between the two clocks.
+//!
+//! The values returned here are consistent with each other but may differ slightly from [`crate::time::uptime`],
+//! so they shouldn't be mixed with it.
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use safa_abi::clock::Clock;
+
+use crate::{sync::SeqLock, syscalls};
+
+/// How long a snapshot of the kernel's values is used before it is refreshed.
+pub const MAX_STALENESS: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    /// The monotonic uptime reported by the kernel
+    uptime: Duration,
+    /// The value of the hardware counter when `uptime` was taken
+    cnt: u64,
+    /// The frequency of the hardware counter, 0 until the first refresh and if the counter is unusable
+    freq: u64,
+}
+
+static SNAPSHOT: SeqLock<Snapshot> = SeqLock::new(Snapshot {
+    uptime: Duration::ZERO,
+    cnt: 0,
+    freq: 0,
+});
+
+/// The highest uptime returned so far in nanoseconds, so that refreshing the snapshot never makes the uptime go backwards.
+static LAST_UPTIME: AtomicU64 = AtomicU64::new(0);
+
+/// Converts `cnt` ticks of a counter running at `freq` hz to a duration.
+fn ticks_to_duration(cnt: u64, freq: u64) -> Duration {
+    let nanos = (cnt as u128 * 1_000_000_000) / freq as u128;
+    Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+}
+
+/// Takes a new snapshot from the kernel, returns it.
+fn refresh() -> Snapshot {
+    let freq = match SNAPSHOT.read().freq {
+        0 => syscalls::clock::getcntfreq(),
+        freq => freq,
+    };
+
+    let snapshot = Snapshot {
+        uptime: syscalls::clock::clock_gettime(Clock::Monotonic),
+        cnt: syscalls::clock::getcnt(),
+        freq,
+    };
+
+    SNAPSHOT.write(snapshot);
+    snapshot
+}
+
+/// Forces the next read to take a new snapshot from the kernel, for example after the system was suspended.
+pub fn invalidate() {
+    SNAPSHOT.write(Snapshot {
+        uptime: Duration::ZERO,
+        cnt: 0,
+        freq: 0,
+    });
+}
+
+/// Returns the time passed since boot (the [`Clock::Monotonic`] clock) without a syscall,
+/// except when the snapshot is refreshed, see the [module documentation](self).
+///
+/// Falls back to syscalls on every call if the hardware counter is unusable (the kernel reports its frequency as 0).
+pub fn uptime() -> Duration {
+    let mut snapshot = SNAPSHOT.read();
+    let mut now = syscalls::clock::getcnt();
+
+    // a counter going backwards means the snapshot was taken on another CPU whose counter is ahead
+    let stale = snapshot.freq == 0
+        || now < snapshot.cnt
+        || ticks_to_duration(now - snapshot.cnt, snapshot.freq) > MAX_STALENESS;
+
+    if stale {
+        snapshot = refresh();
+        now = snapshot.cnt;
+    }
+
+    let uptime = if snapshot.freq == 0 {
+        snapshot.uptime
+    } else {
+        snapshot.uptime + ticks_to_duration(now - snapshot.cnt, snapshot.freq)
+    };
+
+    let nanos = uptime.as_nanos().min(u64::MAX as u128) as u64;
+    let last = LAST_UPTIME.fetch_max(nanos, Ordering::Relaxed);
+    Duration::from_nanos(nanos.max(last))
+}
+
+/// Returns the time passed since boot in milliseconds, see [`uptime`].
+#[inline]
+pub fn uptime_ms() -> u64 {
+    uptime().as_millis() as u64
+}
@@ -0,0 +1,89 @@
+//! Validation of strings received from the kernel (or any other FFI boundary),
+//! so that corrupted data can't end up as an invalid [`str`].
+
+use core::fmt::Display;
+
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use safa_abi::{errors::ErrorStatus, ffi::str::Str};
+
+/// The bytes given to [`str_from_kernel`] aren't valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidStr {
+    valid_up_to: usize,
+}
+
+impl InvalidStr {
+    /// The length of the longest valid UTF-8 prefix of the given bytes
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl Display for InvalidStr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid utf-8 after byte {}", self.valid_up_to)
+    }
+}
+
+impl From<InvalidStr> for ErrorStatus {
+    #[inline]
+    fn from(_: InvalidStr) -> Self {
+        ErrorStatus::InvalidStr
+    }
+}
+
+/// Validates that `bytes` received from the kernel are valid UTF-8 and returns them as a [`str`].
+#[inline]
+pub const fn str_from_kernel(bytes: &[u8]) -> Result<&str, InvalidStr> {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(InvalidStr {
+            valid_up_to: e.valid_up_to(),
+        }),
+    }
+}
+
+/// Same as [`str_from_kernel`] but takes an FFI [`Str`].
+///
+/// # Safety
+/// `raw` must point to `raw.len()` readable bytes that live for `'a`, they don't have to be valid UTF-8.
+#[inline]
+pub unsafe fn str_from_kernel_raw<'a>(raw: Str) -> Result<&'a str, InvalidStr> {
+    if raw.len() == 0 {
+        return Ok("");
+    }
+
+    let bytes = unsafe { core::slice::from_raw_parts(raw.as_ptr(), raw.len()) };
+    str_from_kernel(bytes)
+}
+
+/// Returns the longest valid UTF-8 prefix of `bytes` received from the kernel, doesn't allocate.
+#[inline]
+pub const fn str_from_kernel_truncated(bytes: &[u8]) -> &str {
+    match str_from_kernel(bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            let (valid, _) = bytes.split_at(e.valid_up_to);
+            // Safety: validated above
+            unsafe { core::str::from_utf8_unchecked(valid) }
+        }
+    }
+}
+
+/// Converts `bytes` received from the kernel to a [`String`], replacing invalid UTF-8 sequences with `U+FFFD`.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn string_from_kernel_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
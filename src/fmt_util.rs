@@ -0,0 +1,101 @@
+//! Human readable formatting of sizes, durations and rates, for CLI output
+//!
+//! The functions return values implementing [`Display`] instead of allocating strings.
+
+use core::fmt::{self, Display};
+use core::time::Duration;
+
+const SIZE_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+fn write_size(f: &mut fmt::Formatter<'_>, bytes: u64) -> fmt::Result {
+    let mut unit = 0;
+    let mut divisor: u64 = 1;
+    while unit + 1 < SIZE_UNITS.len() && bytes / divisor >= 1024 {
+        divisor *= 1024;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        return write!(f, "{bytes} B");
+    }
+
+    // rounded to a single decimal place
+    let mut tenths = ((bytes as u128 * 10 + divisor as u128 / 2) / divisor as u128) as u64;
+    // rounding up might reach the next unit, `1024.0 KiB` should be `1.0 MiB`
+    if tenths >= 10240 && unit + 1 < SIZE_UNITS.len() {
+        unit += 1;
+        tenths = (tenths + 512) / 1024;
+    }
+    write!(f, "{}.{} {}", tenths / 10, tenths % 10, SIZE_UNITS[unit])
+}
+
+/// A size in bytes, see [`format_size`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size(pub u64);
+
+impl Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_size(f, self.0)
+    }
+}
+
+/// Formats `bytes` using binary units, for example `512 B`, `1.5 KiB` or `3.2 GiB`.
+pub const fn format_size(bytes: u64) -> Size {
+    Size(bytes)
+}
+
+/// A duration, see [`format_duration`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        if secs == 0 {
+            return write!(f, "{}ms", self.0.subsec_millis());
+        }
+
+        let (days, hours, mins, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+        if days != 0 {
+            write!(f, "{days}d {hours}h {mins}m {secs}s")
+        } else if hours != 0 {
+            write!(f, "{hours}h {mins}m {secs}s")
+        } else if mins != 0 {
+            write!(f, "{mins}m {secs}s")
+        } else {
+            write!(f, "{secs}.{:03}s", self.0.subsec_millis())
+        }
+    }
+}
+
+/// Formats a duration of `ms` milliseconds, for example `250ms`, `1.500s`, `2m 5s` or `1d 3h 0m 12s`.
+pub const fn format_duration(ms: u64) -> HumanDuration {
+    HumanDuration(Duration::from_millis(ms))
+}
+
+/// A transfer rate, see [`format_rate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.elapsed.as_nanos();
+        if nanos == 0 {
+            return f.write_str("-/s");
+        }
+
+        let per_sec = (self.bytes as u128 * 1_000_000_000 / nanos).min(u64::MAX as u128) as u64;
+        write_size(f, per_sec)?;
+        f.write_str("/s")
+    }
+}
+
+/// Formats the rate of transferring `bytes` in `elapsed`, for example `12.4 MiB/s`,
+/// `-/s` if `elapsed` is zero.
+pub const fn format_rate(bytes: u64, elapsed: Duration) -> Rate {
+    Rate { bytes, elapsed }
+}
@@ -0,0 +1,368 @@
+use safa_abi::{errors::ErrorStatus, fs::OpenOptions};
+
+use crate::{resource::Resource, syscalls, syscalls::types::Ri};
+
+use super::Metadata;
+
+/// The size of the zeroed chunks written when growing a file
+const ZERO_CHUNK_SIZE: usize = 512;
+
+/// The offset the kernel treats as the end of the file
+const END_OFFSET: isize = -1;
+
+/// A position in a file to seek to, see [`File::seek`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// An offset from the start of the file
+    Start(u64),
+    /// An offset from the end of the file
+    End(i64),
+    /// An offset from the current position
+    Current(i64),
+}
+
+/// Options for opening a [`File`], a higher-level builder over [`OpenOptions`], see [`File::options`]
+///
+/// By default nothing is enabled, opening fails unless at least one of [`FileOptions::read`] and [`FileOptions::write`] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl FileOptions {
+    /// Constructs new options with nothing enabled.
+    pub const fn new() -> Self {
+        Self {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+        }
+    }
+
+    /// Opens the file for reading.
+    pub const fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Opens the file for writing.
+    pub const fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Makes every write append to the end of the file regardless of the position, implies [`FileOptions::write`].
+    pub const fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Truncates the file to 0 bytes when opening it, requires [`FileOptions::write`].
+    pub const fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Creates the file if it doesn't exist.
+    pub const fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Creates the file, failing with [`ErrorStatus::AlreadyExists`] if it already exists,
+    /// [`FileOptions::create`] and [`FileOptions::truncate`] are ignored when set.
+    pub const fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// The raw options passed to the kernel, [`None`] if neither reading nor writing is enabled.
+    fn open_options(&self) -> Option<OpenOptions> {
+        let write = self.write || self.append;
+        let flags = [
+            (self.read, OpenOptions::READ),
+            (write, OpenOptions::WRITE),
+            (self.append, OpenOptions::APPEND),
+            (
+                self.truncate && write && !self.create_new,
+                OpenOptions::WRITE_TRUNCATE,
+            ),
+            (self.create && !self.create_new, OpenOptions::CREATE_FILE),
+        ];
+
+        if !(self.read || write) {
+            return None;
+        }
+
+        flags
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, flag)| flag)
+            .reduce(|options, flag| options | flag)
+    }
+
+    /// Opens the file at `path` with these options.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if neither reading nor writing is enabled
+    pub fn open(&self, path: &str) -> Result<File, ErrorStatus> {
+        let options = self.open_options().ok_or(ErrorStatus::InvalidArgument)?;
+        if self.create_new {
+            syscalls::fs::create(path)?;
+        }
+
+        let mut file = File::open(path, options)?;
+        file.append = self.append;
+        Ok(file)
+    }
+}
+
+/// An opened file, destroyed on drop.
+///
+/// [`File::read`] and [`File::write`] operate at the file's position, which is tracked by the [`File`] itself,
+/// [`File::read_at`] and [`File::write_at`] operate at a given offset instead.
+#[derive(Debug)]
+pub struct File {
+    resource: Resource,
+    pos: u64,
+    append: bool,
+}
+
+impl File {
+    /// Opens the file at `path` with the given `options`.
+    pub fn open(path: &str, options: OpenOptions) -> Result<Self, ErrorStatus> {
+        Resource::open(path, options).map(Self::from_resource)
+    }
+
+    /// Opens the file at `path` for writing, creating it if it doesn't exist and truncating it if it does.
+    pub fn create(path: &str) -> Result<Self, ErrorStatus> {
+        Self::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+    }
+
+    /// Returns new [`FileOptions`] for opening a file.
+    #[inline]
+    pub const fn options() -> FileOptions {
+        FileOptions::new()
+    }
+
+    /// Wraps an already opened file resource, the position starts at 0.
+    #[inline]
+    pub const fn from_resource(resource: Resource) -> Self {
+        Self {
+            resource,
+            pos: 0,
+            append: false,
+        }
+    }
+
+    /// Returns the underlying resource, the position is lost.
+    #[inline]
+    pub fn into_resource(self) -> Resource {
+        self.resource
+    }
+
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.resource.ri()
+    }
+
+    #[inline]
+    pub const fn resource(&self) -> &Resource {
+        &self.resource
+    }
+
+    /// The current position in the file.
+    #[inline]
+    pub const fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Reads into `buf` at `offset` without changing the position, returns the amount of bytes read, 0 meaning the end of the file.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        let offset = isize::try_from(offset).map_err(|_| ErrorStatus::InvalidOffset)?;
+        syscalls::io::read(self.ri(), offset, buf)
+    }
+
+    /// Writes `buf` at `offset` without changing the position, returns the amount of bytes written.
+    pub fn write_at(&self, offset: u64, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        let offset = isize::try_from(offset).map_err(|_| ErrorStatus::InvalidOffset)?;
+        syscalls::io::write(self.ri(), offset, buf)
+    }
+
+    /// Reads into `buf` at the position, advancing it, returns the amount of bytes read, 0 meaning the end of the file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        let read = self.read_at(self.pos, buf)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+
+    /// Writes `buf` at the position (or at the end if opened with [`FileOptions::append`]), advancing it,
+    /// returns the amount of bytes written.
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        if self.append {
+            let written = syscalls::io::write(self.ri(), END_OFFSET, buf)?;
+            self.pos = self.size()?;
+            return Ok(written);
+        }
+
+        let written = self.write_at(self.pos, buf)?;
+        self.pos += written as u64;
+        Ok(written)
+    }
+
+    /// Writes all of `buf`, see [`File::write`].
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<(), ErrorStatus> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(ErrorStatus::Generic),
+                written => buf = &buf[written..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the position, returns the new position from the start of the file,
+    /// seeking past the end is allowed, writing there grows the file.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidOffset`] if the position would be before the start of the file
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorStatus> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (self.size()?, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+
+        self.pos = base
+            .checked_add_signed(offset)
+            .ok_or(ErrorStatus::InvalidOffset)?;
+        Ok(self.pos)
+    }
+
+    /// Flushes the written data to the underlying storage.
+    #[inline]
+    pub fn sync(&self) -> Result<(), ErrorStatus> {
+        syscalls::io::sync(self.ri())
+    }
+
+    /// Returns the metadata of the file.
+    pub fn metadata(&self) -> Result<Metadata, ErrorStatus> {
+        syscalls::io::fattrs(self.ri()).map(Metadata::from_attrs)
+    }
+
+    /// Returns the size of the file in bytes.
+    #[inline]
+    pub fn size(&self) -> Result<u64, ErrorStatus> {
+        syscalls::io::fsize(self.ri()).map(|size| size as u64)
+    }
+
+    /// Writes zeros to the range `from..to` of the file.
+    ///
+    /// Fails with [`ErrorStatus::InvalidArgument`] without writing anything if `to` doesn't fit in an offset.
+    fn write_zeros(&self, from: u64, to: u64) -> Result<(), ErrorStatus> {
+        isize::try_from(to).map_err(|_| ErrorStatus::InvalidArgument)?;
+
+        let zeros = [0u8; ZERO_CHUNK_SIZE];
+        let mut offset = from;
+
+        while offset < to {
+            let amount = (to - offset).min(ZERO_CHUNK_SIZE as u64) as usize;
+            let position = isize::try_from(offset).map_err(|_| ErrorStatus::InvalidArgument)?;
+            let written = syscalls::io::write(self.ri(), position, &zeros[..amount])?;
+            if written == 0 {
+                return Err(ErrorStatus::Generic);
+            }
+            offset += written as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the size of the file to exactly `len` bytes,
+    /// shrinking it discards the data past `len`, and growing it fills the new bytes with zeros.
+    pub fn set_len(&self, len: u64) -> Result<(), ErrorStatus> {
+        let size = usize::try_from(len).map_err(|_| ErrorStatus::InvalidSize)?;
+        let current = self.size()?;
+        if current == len {
+            return Ok(());
+        }
+
+        syscalls::io::truncate(self.ri(), size)?;
+
+        // the kernel might not grow files on truncate, in which case they are grown manually
+        if len > current {
+            let grown = self.size()?;
+            if grown < len {
+                self.write_zeros(grown, len)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure space is reserved for at least `len` bytes of the file, growing it with zeros if it is smaller,
+    /// so that later writes within `len` can't fail due to lack of space.
+    ///
+    /// Unlike [`File::set_len`] the zeros are always written, so that the space is allocated even if the file can be sparse,
+    /// and the file is never shrunk.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `len` is too large to be an offset in the file
+    pub fn allocate(&self, len: u64) -> Result<(), ErrorStatus> {
+        let current = self.size()?;
+        if len > current {
+            self.write_zeros(current, len)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+mod _std {
+    use std::io;
+
+    use super::{File, SeekFrom};
+
+    impl io::Read for File {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            File::read(self, buf).map_err(crate::errors::into_io_error)
+        }
+    }
+
+    impl io::Write for File {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            File::write(self, buf).map_err(crate::errors::into_io_error)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl io::Seek for File {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            let pos = match pos {
+                io::SeekFrom::Start(offset) => SeekFrom::Start(offset),
+                io::SeekFrom::End(offset) => SeekFrom::End(offset),
+                io::SeekFrom::Current(offset) => SeekFrom::Current(offset),
+            };
+            File::seek(self, pos).map_err(crate::errors::into_io_error)
+        }
+    }
+}
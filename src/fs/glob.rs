@@ -0,0 +1,217 @@
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use safa_abi::{errors::ErrorStatus, fs::FSObjectType};
+
+use crate::process::cwd;
+
+use super::split_scheme;
+
+/// Matches a character class (the part after `[`) against `c`,
+/// returns whether or not it matched and the rest of the pattern after the class.
+fn match_class(class: &str, c: char) -> Option<(bool, &str)> {
+    let (negated, class) = match class.strip_prefix(['!', '^']) {
+        Some(class) => (true, class),
+        None => (false, class),
+    };
+
+    let mut chars = class.char_indices().peekable();
+    let mut matched = false;
+    let mut first = true;
+
+    while let Some((i, start)) = chars.next() {
+        // a `]` right after the `[` is a literal
+        if start == ']' && !first {
+            return Some((matched != negated, &class[i + 1..]));
+        }
+        first = false;
+
+        let mut lookahead = chars.clone();
+        match (lookahead.next(), lookahead.next()) {
+            (Some((_, '-')), Some((_, end))) if end != ']' => {
+                matched |= (start..=end).contains(&c);
+                chars = lookahead;
+            }
+            _ => matched |= start == c,
+        }
+    }
+
+    // unterminated class
+    None
+}
+
+/// Matches a single path component `name` against the pattern `pattern`,
+/// which can contain `*`, `?` and character classes such as `[abc]`, `[a-z]` and `[!abc]`.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+    let Some(p) = chars.next() else {
+        return name.is_empty();
+    };
+    let rest = chars.as_str();
+
+    match p {
+        '*' => {
+            let mut name = name;
+            loop {
+                if matches(rest, name) {
+                    return true;
+                }
+
+                let mut name_chars = name.chars();
+                if name_chars.next().is_none() {
+                    return false;
+                }
+                name = name_chars.as_str();
+            }
+        }
+        '?' => {
+            let mut name_chars = name.chars();
+            name_chars.next().is_some() && matches(rest, name_chars.as_str())
+        }
+        '[' => {
+            let mut name_chars = name.chars();
+            let Some(c) = name_chars.next() else {
+                return false;
+            };
+
+            match match_class(rest, c) {
+                Some((matched, rest)) => matched && matches(rest, name_chars.as_str()),
+                // an unterminated class is a literal `[`
+                None => c == '[' && matches(rest, name_chars.as_str()),
+            }
+        }
+        p => {
+            let mut name_chars = name.chars();
+            name_chars.next() == Some(p) && matches(rest, name_chars.as_str())
+        }
+    }
+}
+
+fn is_pattern(component: &str) -> bool {
+    component.contains(['*', '?', '['])
+}
+
+fn join(path: &str, name: &str) -> String {
+    let mut results = String::with_capacity(path.len() + 1 + name.len());
+    results.push_str(path);
+    if !path.is_empty() && !path.ends_with('/') {
+        results.push('/');
+    }
+    results.push_str(name);
+    results
+}
+
+/// Lists the names of the entries of the directory at `path` and whether or not each is a directory.
+fn list_dir(path: &str) -> Result<Vec<(String, bool)>, ErrorStatus> {
+    let cwd;
+    let path = if path.is_empty() {
+        cwd = cwd::cwd_ref()?;
+        &*cwd
+    } else {
+        path
+    };
+
+    let mut results = Vec::new();
+    for entry in super::read_dir(path)? {
+        let entry = entry?;
+        let name = crate::ffi_util::str_from_kernel(&entry.name[..entry.name_length])?;
+        let is_dir = matches!(entry.attrs.kind, FSObjectType::Directory);
+        results.push((String::from(name), is_dir));
+    }
+
+    Ok(results)
+}
+
+/// An iterator over the paths matching a glob pattern, see [`glob`]
+#[derive(Debug)]
+pub struct Glob {
+    components: Vec<String>,
+    /// Paths that matched the first `n` components, yet to be matched against the rest
+    stack: Vec<(String, usize)>,
+}
+
+impl Iterator for Glob {
+    type Item = Result<String, ErrorStatus>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, index)) = self.stack.pop() {
+            let Some(component) = self.components.get(index) else {
+                if path.is_empty() {
+                    continue;
+                }
+                return Some(Ok(path));
+            };
+
+            if component == "**" {
+                let entries = match list_dir(&path) {
+                    Ok(entries) => entries,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                for (name, _) in entries.into_iter().rev().filter(|(_, is_dir)| *is_dir) {
+                    self.stack.push((join(&path, &name), index));
+                }
+                // `**` also matches zero directories
+                self.stack.push((path, index + 1));
+            } else if !is_pattern(component) {
+                let joined = join(&path, component);
+                match super::attrs_of(&joined) {
+                    Ok(_) => self.stack.push((joined, index + 1)),
+                    Err(ErrorStatus::NoSuchAFileOrDirectory) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+            } else {
+                let entries = match list_dir(&path) {
+                    Ok(entries) => entries,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                let is_last = index + 1 == self.components.len();
+                for (name, is_dir) in entries.into_iter().rev() {
+                    if (is_last || is_dir) && matches(component, &name) {
+                        self.stack.push((join(&path, &name), index + 1));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Returns an iterator over the paths matching the glob pattern `pattern`.
+///
+/// Each path component of the pattern can contain `*`, `?` and character classes (see [`matches`]),
+/// and a component that is exactly `**` matches zero or more directories.
+///
+/// Relative patterns yield paths relative to the current working directory,
+/// directories that can't be read yield an error and are skipped.
+pub fn glob(pattern: &str) -> Glob {
+    let (scheme, rest) = split_scheme(pattern);
+
+    let root = match scheme {
+        Some(scheme) => {
+            let mut root = String::from(scheme);
+            root.push_str(":/");
+            root
+        }
+        None if rest.starts_with('/') => String::from("/"),
+        None => String::new(),
+    };
+
+    let components = rest
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .map(String::from)
+        .collect();
+
+    Glob {
+        components,
+        stack: alloc::vec![(root, 0)],
+    }
+}
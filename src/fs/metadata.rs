@@ -0,0 +1,61 @@
+use safa_abi::fs::{FSObjectType, FileAttr};
+
+/// Whether or not a file can be sparse, that is grow without allocating space for the zeroed parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SparseSupport {
+    Supported,
+    Unsupported,
+    /// The kernel doesn't report it, this is always the case for now
+    Unknown,
+}
+
+/// Metadata about a filesystem object, see [`super::File::metadata`] and [`super::metadata`]
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    attrs: FileAttr,
+}
+
+impl Metadata {
+    #[inline]
+    pub(crate) const fn from_attrs(attrs: FileAttr) -> Self {
+        Self { attrs }
+    }
+
+    /// The raw attributes as reported by the kernel
+    #[inline]
+    pub const fn attrs(&self) -> &FileAttr {
+        &self.attrs
+    }
+
+    /// The size in bytes
+    #[inline]
+    pub const fn len(&self) -> u64 {
+        self.attrs.size as u64
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub const fn is_file(&self) -> bool {
+        matches!(self.attrs.kind, FSObjectType::File)
+    }
+
+    #[inline]
+    pub const fn is_dir(&self) -> bool {
+        matches!(self.attrs.kind, FSObjectType::Directory)
+    }
+
+    #[inline]
+    pub const fn is_device(&self) -> bool {
+        matches!(self.attrs.kind, FSObjectType::Device)
+    }
+
+    /// Whether or not the file can be sparse, see [`SparseSupport`]
+    #[inline]
+    pub const fn sparse_support(&self) -> SparseSupport {
+        SparseSupport::Unknown
+    }
+}
@@ -0,0 +1,111 @@
+//! Higher-level filesystem operations, see [`crate::syscalls::fs`] for the raw syscalls
+//!
+//! Paths on SafaOS are scheme-qualified, for example `sys:/bin/safa-shell`, paths without a scheme are relative to the
+//! current working directory, or to its scheme's root if they start with `/`.
+
+mod file;
+#[cfg(feature = "alloc")]
+mod glob;
+mod metadata;
+mod path;
+mod read_dir;
+
+pub use file::{File, FileOptions, SeekFrom};
+#[cfg(feature = "alloc")]
+pub use glob::{glob, matches, Glob};
+pub use metadata::{Metadata, SparseSupport};
+#[cfg(feature = "alloc")]
+pub use path::canonicalize;
+pub use path::{canonicalize_into, split_scheme};
+pub use read_dir::{read_dir, ReadDir};
+
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+use safa_abi::{errors::ErrorStatus, fs::FileAttr};
+
+use crate::{process::umask, syscalls};
+
+/// The highest mode accepted by [`create_file`] and [`create_dir`], the permission bits plus the setuid, setgid and sticky bits.
+pub const MAX_MODE: u32 = 0o7777;
+
+/// How many names [`create_temp_file`] tries before giving up.
+#[cfg(feature = "alloc")]
+const TEMP_FILE_ATTEMPTS: usize = 8;
+
+/// Creates the file `path` with the permissions `mode` minus the process's [`umask`](crate::process::umask()),
+/// returns the mode the file should have.
+///
+/// The kernel doesn't support permissions yet, so the file is created with the kernel's default permissions regardless of the mode.
+///
+/// # Returns
+/// - [`ErrorStatus::InvalidArgument`] if `mode` is larger than [`MAX_MODE`]
+pub fn create_file(path: &str, mode: u32) -> Result<u32, ErrorStatus> {
+    if mode > MAX_MODE {
+        return Err(ErrorStatus::InvalidArgument);
+    }
+
+    let mode = umask::apply_umask(mode);
+    syscalls::fs::create(path)?;
+    Ok(mode)
+}
+
+/// Creates the directory `path` with the permissions `mode` minus the process's [`umask`](crate::process::umask()),
+/// returns the mode the directory should have.
+///
+/// The kernel doesn't support permissions yet, so the directory is created with the kernel's default permissions regardless of the mode.
+///
+/// # Returns
+/// - [`ErrorStatus::InvalidArgument`] if `mode` is larger than [`MAX_MODE`]
+pub fn create_dir(path: &str, mode: u32) -> Result<u32, ErrorStatus> {
+    if mode > MAX_MODE {
+        return Err(ErrorStatus::InvalidArgument);
+    }
+
+    let mode = umask::apply_umask(mode);
+    syscalls::fs::createdir(path)?;
+    Ok(mode)
+}
+
+/// Returns the attributes of the object at `path` with a single syscall, without opening it.
+///
+/// Prefer this over opening the object and asking for its attributes when only the attributes are needed, such as when scanning trees.
+#[inline]
+pub fn attrs_of(path: &str) -> Result<FileAttr, ErrorStatus> {
+    syscalls::fs::getdirentry(path).map(|entry| entry.attrs)
+}
+
+/// Returns the metadata of the object at `path` without opening it, see [`attrs_of`].
+#[inline]
+pub fn metadata(path: &str) -> Result<Metadata, ErrorStatus> {
+    attrs_of(path).map(Metadata::from_attrs)
+}
+
+/// Creates a new empty file in the directory `dir`, named `prefix` followed by a [`unique_id`](crate::util::unique_id()),
+/// with the permissions `0o600` minus the process's [`umask`](crate::process::umask()), returns the path of the created file.
+///
+/// The file isn't removed automatically.
+#[cfg(feature = "alloc")]
+pub fn create_temp_file(dir: &str, prefix: &str) -> Result<String, ErrorStatus> {
+    let dir = dir.strip_suffix('/').unwrap_or(dir);
+
+    // identifiers never repeat within a process, but another process could still have created the same name
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        let path = format!("{dir}/{prefix}{}", crate::util::unique_id());
+        match create_file(&path, 0o600) {
+            Ok(_) => return Ok(path),
+            Err(ErrorStatus::AlreadyExists) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(ErrorStatus::AlreadyExists)
+}
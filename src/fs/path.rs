@@ -0,0 +1,167 @@
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use safa_abi::{consts::MAX_PATH_LENGTH, errors::ErrorStatus, fs::FSObjectType};
+
+#[cfg(feature = "alloc")]
+use crate::process::cwd;
+use crate::syscalls;
+
+/// Splits `path` into its scheme (without the `:`) and the rest of the path,
+/// the scheme is [`None`] if the path isn't scheme-qualified.
+///
+/// For example `sys:/bin` is split into `(Some("sys"), "/bin")`.
+pub fn split_scheme(path: &str) -> (Option<&str>, &str) {
+    match path.split_once(':') {
+        Some((scheme, rest)) if !scheme.contains('/') => (Some(scheme), rest),
+        _ => (None, path),
+    }
+}
+
+/// Returns the canonical, absolute and scheme-qualified form of `path`,
+/// with `.` and `..` segments resolved and repeated separators collapsed.
+///
+/// Relative paths are resolved against the current working directory, see [`cwd::cwd_ref`].
+///
+/// Every component of the path must exist, and every component except the last one must be a directory.
+/// SafaOS has no symbolic links (yet), so there are no links to resolve.
+///
+/// # Returns
+/// - [`ErrorStatus::NoSuchAFileOrDirectory`] if a component doesn't exist
+/// - [`ErrorStatus::NotADirectory`] if a component other than the last one isn't a directory
+#[cfg(feature = "alloc")]
+pub fn canonicalize(path: &str) -> Result<String, ErrorStatus> {
+    let cwd;
+    let (scheme, rest) = split_scheme(path);
+
+    let (scheme, base, rest) = match scheme {
+        Some(scheme) => (scheme, "", rest),
+        None => {
+            cwd = cwd::cwd_ref()?;
+            let (scheme, cwd_rest) = split_scheme(&cwd);
+            let scheme = scheme.ok_or(ErrorStatus::InvalidPath)?;
+
+            if rest.starts_with('/') {
+                (scheme, "", rest)
+            } else {
+                (scheme, cwd_rest, rest)
+            }
+        }
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in base.split('/').chain(rest.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => _ = components.pop(),
+            component => components.push(component),
+        }
+    }
+
+    let mut results = String::with_capacity(scheme.len() + 2 + rest.len() + base.len());
+    results.push_str(scheme);
+    results.push_str(":/");
+
+    for (i, component) in components.iter().enumerate() {
+        if i != 0 {
+            results.push('/');
+        }
+        results.push_str(component);
+
+        let attrs = super::attrs_of(&results)?;
+        let is_last = i + 1 == components.len();
+        if !is_last && !matches!(attrs.kind, FSObjectType::Directory) {
+            return Err(ErrorStatus::NotADirectory);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Appends `bytes` to the first `len` bytes of `buf`.
+fn push(buf: &mut [u8], len: &mut usize, bytes: &[u8]) -> Result<(), ErrorStatus> {
+    let dest = buf
+        .get_mut(*len..*len + bytes.len())
+        .ok_or(ErrorStatus::StrTooLong)?;
+    dest.copy_from_slice(bytes);
+    *len += bytes.len();
+    Ok(())
+}
+
+/// Same as [`canonicalize`] but writes the result into `buf` instead of allocating, returns the part of `buf` it was written to.
+///
+/// Relative paths are resolved against the current working directory as returned by the kernel, bypassing the cache of [`crate::process::cwd::cwd_ref`].
+///
+/// # Returns
+/// - [`ErrorStatus::StrTooLong`] if the result doesn't fit in `buf`
+/// - the same errors as [`canonicalize`]
+pub fn canonicalize_into<'a>(path: &str, buf: &'a mut [u8]) -> Result<&'a str, ErrorStatus> {
+    let mut cwd_buf = [0u8; MAX_PATH_LENGTH];
+    let (scheme, rest) = split_scheme(path);
+
+    let (scheme, base, rest) = match scheme {
+        Some(scheme) => (scheme, "", rest),
+        None => {
+            let cwd = syscalls::process_misc::getcwd_into(&mut cwd_buf)?;
+            let (scheme, cwd_rest) = split_scheme(cwd);
+            let scheme = scheme.ok_or(ErrorStatus::InvalidPath)?;
+
+            if rest.starts_with('/') {
+                (scheme, "", rest)
+            } else {
+                (scheme, cwd_rest, rest)
+            }
+        }
+    };
+
+    let mut len = 0;
+    push(buf, &mut len, scheme.as_bytes())?;
+    push(buf, &mut len, b":/")?;
+    let root_len = len;
+
+    // resolves the components first, so that components removed by `..` don't have to exist
+    for component in base.split('/').chain(rest.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                let parent = buf[root_len..len].iter().rposition(|b| *b == b'/');
+                len = parent.map_or(root_len, |i| root_len + i);
+            }
+            component => {
+                if len != root_len {
+                    push(buf, &mut len, b"/")?;
+                }
+                push(buf, &mut len, component.as_bytes())?;
+            }
+        }
+    }
+
+    // only made of `&str`s
+    let results = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+    if len == root_len {
+        return Ok(results);
+    }
+
+    let ends = results[root_len..]
+        .match_indices('/')
+        .map(|(i, _)| root_len + i)
+        .chain(core::iter::once(len));
+
+    for end in ends {
+        let attrs = super::attrs_of(&results[..end])?;
+        let is_last = end == len;
+        if !is_last && !matches!(attrs.kind, FSObjectType::Directory) {
+            return Err(ErrorStatus::NotADirectory);
+        }
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,131 @@
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+use safa_abi::{errors::ErrorStatus, fs::DirEntry};
+
+use crate::{resource::Resource, syscalls};
+
+/// How many entries [`ReadDir`] fetches at once, see [`syscalls::io::diriter_next_many`]
+const BATCH_SIZE: usize = 8;
+
+/// An iterator over the entries of a directory, see [`read_dir`]
+///
+/// Entries are fetched in batches of a few entries.
+/// Both the directory and the directory iterator resources are destroyed on drop.
+pub struct ReadDir {
+    // dropped before the directory it iterates over
+    iter: Resource,
+    dir: Resource,
+    done: bool,
+    batch: [DirEntry; BATCH_SIZE],
+    /// the index of the next entry of the batch to yield
+    pos: usize,
+    /// the amount of entries in the batch
+    len: usize,
+}
+
+impl ReadDir {
+    /// Iterates over the entries of the opened directory `dir`.
+    pub fn new(dir: Resource) -> Result<Self, ErrorStatus> {
+        let iter = unsafe { Resource::from_raw(syscalls::io::diriter_open(dir.ri())?) };
+        Ok(Self {
+            iter,
+            dir,
+            done: false,
+            batch: unsafe { core::mem::zeroed() },
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// The directory being iterated over.
+    #[inline]
+    pub const fn dir(&self) -> &Resource {
+        &self.dir
+    }
+
+    /// Restarts the iteration from the first entry of the directory, reflecting any changes made to it since.
+    ///
+    /// The kernel can't rewind a directory iterator, so a new one is opened over the same directory,
+    /// which is still cheaper than reopening the directory itself.
+    pub fn rewind(&mut self) -> Result<(), ErrorStatus> {
+        let iter = unsafe { Resource::from_raw(syscalls::io::diriter_open(self.dir.ri())?) };
+        self.iter = iter;
+        self.done = false;
+        self.pos = 0;
+        self.len = 0;
+        Ok(())
+    }
+
+    /// Rewinds (see [`Self::rewind`]) and replaces the contents of `entries` with all the entries of the directory,
+    /// reusing its allocation, returns the amount of entries.
+    ///
+    /// Meant for rescanning the same directory repeatedly, on error `entries` holds the entries read so far.
+    #[cfg(feature = "alloc")]
+    pub fn list_into(&mut self, entries: &mut Vec<DirEntry>) -> Result<usize, ErrorStatus> {
+        self.rewind()?;
+        entries.clear();
+        for entry in self {
+            entries.push(entry?);
+        }
+        Ok(entries.len())
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry, ErrorStatus>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.len {
+            if self.done {
+                return None;
+            }
+
+            self.pos = 0;
+            self.len = 0;
+            match syscalls::io::diriter_next_many(self.iter.ri(), &mut self.batch) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(len) => self.len = len,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        let entry = self.batch[self.pos];
+        self.pos += 1;
+        Some(Ok(entry))
+    }
+}
+
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadDir")
+            .field("iter", &self.iter)
+            .field("dir", &self.dir)
+            .field("done", &self.done)
+            .field("buffered", &(self.len - self.pos))
+            .finish()
+    }
+}
+
+impl core::iter::FusedIterator for ReadDir {}
+
+/// Opens the directory at `path` and returns an iterator over its entries.
+pub fn read_dir(path: &str) -> Result<ReadDir, ErrorStatus> {
+    let dir = unsafe { Resource::from_raw(syscalls::fs::open_all(path)?) };
+    ReadDir::new(dir)
+}
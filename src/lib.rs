@@ -7,8 +7,10 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(feature = "linkonce", feature(linkage))]
+#![cfg_attr(feature = "shadow-stack", feature(thread_local))]
 
-mod backtrace;
+mod abi_check;
+pub mod backtrace;
 
 use core::fmt::{Arguments, Write};
 
@@ -17,14 +19,240 @@ use crate::process::stdio::sysget_stderr;
 pub mod errors {
     pub use safa_abi::errors::{ErrorStatus, SysResult};
 
+    /// An error code returned by the kernel that doesn't map to any [`ErrorStatus`] known to this crate,
+    /// typically produced by a newer kernel.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct UnknownError(pub u16);
+
+    impl core::fmt::Display for UnknownError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "unknown error code {}", self.0)
+        }
+    }
+
+    /// An operation that gave up waiting, carrying how long it waited and on what,
+    /// see [`WaitError`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TimeoutError {
+        waited: core::time::Duration,
+        operation: &'static str,
+    }
+
+    impl TimeoutError {
+        /// Constructs a new error for `operation` (for example `"recv"`) that waited for `waited`.
+        #[inline]
+        pub const fn new(operation: &'static str, waited: core::time::Duration) -> Self {
+            Self { waited, operation }
+        }
+
+        /// Constructs a new error for `operation` that started waiting at `start`.
+        #[inline]
+        pub fn since(operation: &'static str, start: crate::time::Instant) -> Self {
+            Self::new(operation, start.elapsed())
+        }
+
+        /// How long the operation waited before giving up.
+        #[inline]
+        pub const fn waited(&self) -> core::time::Duration {
+            self.waited
+        }
+
+        /// The name of the operation that gave up, such as `"recv"` or `"futex wait"`.
+        #[inline]
+        pub const fn operation(&self) -> &'static str {
+            self.operation
+        }
+    }
+
+    impl core::fmt::Display for TimeoutError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(
+                f,
+                "{} timed out after {}",
+                self.operation,
+                crate::fmt_util::format_duration(self.waited.as_millis() as u64)
+            )
+        }
+    }
+
+    impl From<TimeoutError> for ErrorStatus {
+        #[inline]
+        fn from(_: TimeoutError) -> Self {
+            ErrorStatus::Timeout
+        }
+    }
+
+    /// An error returned by operations that wait with a deadline or a timeout,
+    /// the timeouts carry context that a bare [`ErrorStatus::Timeout`] can't
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum WaitError {
+        Timeout(TimeoutError),
+        /// The operation was cancelled using a [`crate::sync::CancellationToken`]
+        Cancelled,
+        System(ErrorStatus),
+    }
+
+    impl WaitError {
+        /// Returns the [`TimeoutError`] if the operation timed out.
+        #[inline]
+        pub const fn timeout(&self) -> Option<TimeoutError> {
+            match self {
+                Self::Timeout(timeout) => Some(*timeout),
+                Self::Cancelled | Self::System(_) => None,
+            }
+        }
+
+        /// Returns the error as an [`ErrorStatus`], dropping the context of timeouts,
+        /// cancellations become [`crate::sync::cancel::CANCELLED`].
+        #[inline]
+        pub const fn status(&self) -> ErrorStatus {
+            match self {
+                Self::Timeout(_) => ErrorStatus::Timeout,
+                Self::Cancelled => crate::sync::cancel::CANCELLED,
+                Self::System(err) => *err,
+            }
+        }
+
+        /// Converts a bare [`ErrorStatus::Timeout`] returned by the kernel for `operation` into a [`WaitError::Timeout`],
+        /// other errors are kept as is.
+        pub fn from_status(
+            err: ErrorStatus,
+            operation: &'static str,
+            start: crate::time::Instant,
+        ) -> Self {
+            match err {
+                ErrorStatus::Timeout => Self::Timeout(TimeoutError::since(operation, start)),
+                err => Self::System(err),
+            }
+        }
+    }
+
+    impl core::fmt::Display for WaitError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::Timeout(timeout) => timeout.fmt(f),
+                Self::Cancelled => f.write_str("cancelled"),
+                Self::System(err) => f.write_str(err.as_str()),
+            }
+        }
+    }
+
+    impl From<ErrorStatus> for WaitError {
+        #[inline]
+        fn from(value: ErrorStatus) -> Self {
+            Self::System(value)
+        }
+    }
+
+    impl From<TimeoutError> for WaitError {
+        #[inline]
+        fn from(value: TimeoutError) -> Self {
+            Self::Timeout(value)
+        }
+    }
+
+    impl From<WaitError> for ErrorStatus {
+        #[inline]
+        fn from(value: WaitError) -> Self {
+            value.status()
+        }
+    }
+
+    /// Every [`ErrorStatus`] this crate knows how to decode.
+    ///
+    /// Decoding compares against the discriminants of this table instead of transmuting,
+    /// so codes outside of it are rejected rather than producing an invalid [`ErrorStatus`].
+    const KNOWN_ERRORS: [ErrorStatus; 45] = {
+        use ErrorStatus::*;
+        [
+            Generic,
+            OperationNotSupported,
+            NotSupported,
+            Corrupted,
+            InvalidSyscall,
+            UnknownResource,
+            InvalidPid,
+            InvalidOffset,
+            InvalidPtr,
+            InvalidStr,
+            StrTooLong,
+            InvalidPath,
+            NoSuchAFileOrDirectory,
+            NotAFile,
+            NotADirectory,
+            AlreadyExists,
+            NotExecutable,
+            DirectoryNotEmpty,
+            MissingPermissions,
+            MMapError,
+            Busy,
+            NotEnoughArguments,
+            OutOfMemory,
+            InvalidTid,
+            Timeout,
+            InvalidCommand,
+            InvalidArgument,
+            Unknown,
+            Panic,
+            NotADevice,
+            UnsupportedResource,
+            ResourceCloneFailed,
+            TypeMismatch,
+            TooShort,
+            InvalidSize,
+            ForceTerminated,
+            AddressAlreadyInUse,
+            NotBound,
+            ConnectionClosed,
+            ConnectionRefused,
+            AddressNotFound,
+            WouldBlock,
+            ProtocolNotSupported,
+            NetworkUnreachable,
+            HostUnreachable,
+        ]
+    };
+
+    /// Safely decodes a raw error code returned by the kernel into an [`ErrorStatus`].
+    ///
+    /// This is total: any `u16` is accepted, codes that this crate doesn't know about
+    /// are returned as an [`UnknownError`] instead of causing undefined behavior.
+    pub const fn from_raw(raw: u16) -> Result<ErrorStatus, UnknownError> {
+        let mut i = 0;
+        while i < KNOWN_ERRORS.len() {
+            if KNOWN_ERRORS[i] as u16 == raw {
+                return Ok(KNOWN_ERRORS[i]);
+            }
+            i += 1;
+        }
+        Err(UnknownError(raw))
+    }
+
+    /// Extension trait exposing [`from_raw`] as `ErrorStatus::from_raw`.
+    pub trait ErrorStatusExt: Sized {
+        /// See [`from_raw`].
+        fn from_raw(raw: u16) -> Result<Self, UnknownError>;
+    }
+
+    impl ErrorStatusExt for ErrorStatus {
+        #[inline]
+        fn from_raw(raw: u16) -> Result<Self, UnknownError> {
+            from_raw(raw)
+        }
+    }
+
+    /// Maps an `io::ErrorKind`-like enum of type `$io_err_ty` to an [`ErrorStatus`].
+    ///
+    /// Expands to an expression, which makes it usable in `const` contexts.
+    #[doc(hidden)]
     #[cfg(any(feature = "rustc-dep-of-std", feature = "std"))]
-    #[cfg_attr(feature = "rustc-dep-of-std", macro_export)]
-    macro_rules! err_from_io_error_kind {
-        ($io_err_ty: path, $io_err: ident) => {
+    #[macro_export]
+    macro_rules! __map_io_error_kind {
+        ($io_err_ty: path, $io_err: expr) => {{
             use $crate::errors::ErrorStatus::*;
             use $io_err_ty as IoErrorKind;
 
-            return match $io_err {
+            match $io_err {
                 IoErrorKind::NotFound => NoSuchAFileOrDirectory,
                 IoErrorKind::AlreadyExists => AlreadyExists,
                 IoErrorKind::PermissionDenied => MissingPermissions,
@@ -44,33 +272,23 @@ pub mod errors {
                 IoErrorKind::ConnectionReset => ConnectionClosed,
 
                 _ => Generic,
-            };
-        };
-    }
-
-    #[cfg(feature = "std")]
-    pub fn err_from_io_error_kind(io_err: std::io::ErrorKind) -> ErrorStatus {
-        err_from_io_error_kind!(std::io::ErrorKind, io_err);
+            }
+        }};
     }
 
+    /// Maps an [`ErrorStatus`] to an `io::ErrorKind`-like enum of type `$io_err_ty`,
+    /// errors that has no matching kind are mapped to `$unknown`.
+    ///
+    /// Expands to an expression, which makes it usable in `const` contexts.
+    #[doc(hidden)]
     #[cfg(any(feature = "rustc-dep-of-std", feature = "std"))]
-    #[cfg_attr(feature = "rustc-dep-of-std", macro_export)]
-    macro_rules! err_into_io_error_kind {
-        ($err: ident, $io_err_ty: path) => {
+    #[macro_export]
+    macro_rules! __map_err_to_io_error_kind {
+        ($err: expr, $io_err_ty: path, $unknown: expr) => {{
             use $crate::errors::ErrorStatus::*;
             use $io_err_ty as IoErrorKind;
 
-            #[cfg(feature = "std")]
-            const fn unknown_err() -> IoErrorKind {
-                IoErrorKind::Other
-            }
-
-            #[cfg(feature = "rustc-dep-of-std")]
-            const fn unknown_err() -> IoErrorKind {
-                IoErrorKind::Uncategorized
-            }
-
-            return match $err {
+            match $err {
                 NoSuchAFileOrDirectory => IoErrorKind::NotFound,
                 AlreadyExists => IoErrorKind::AlreadyExists,
                 MissingPermissions => IoErrorKind::PermissionDenied,
@@ -89,7 +307,7 @@ pub mod errors {
                     IoErrorKind::Unsupported
                 }
                 NotEnoughArguments | Generic | MMapError | Panic | Unknown
-                | ResourceCloneFailed | NotBound => unknown_err(),
+                | ResourceCloneFailed | NotBound => $unknown,
                 InvalidArgument | InvalidCommand => IoErrorKind::InvalidInput,
                 Timeout => IoErrorKind::TimedOut,
                 ConnectionClosed => IoErrorKind::ConnectionReset,
@@ -100,25 +318,90 @@ pub mod errors {
                 AddressAlreadyInUse => IoErrorKind::AddrInUse,
                 NetworkUnreachable => IoErrorKind::NetworkUnreachable,
                 HostUnreachable => IoErrorKind::HostUnreachable,
-            };
+            }
+        }};
+    }
+
+    /// Shim over `__map_io_error_kind` kept for the standard library,
+    /// returns the mapped [`ErrorStatus`] from the calling function.
+    #[cfg(feature = "rustc-dep-of-std")]
+    #[macro_export]
+    macro_rules! err_from_io_error_kind {
+        ($io_err_ty: path, $io_err: ident) => {
+            return $crate::__map_io_error_kind!($io_err_ty, $io_err);
+        };
+    }
+
+    /// Shim over `__map_err_to_io_error_kind` kept for the standard library,
+    /// returns the mapped error kind from the calling function.
+    #[cfg(feature = "rustc-dep-of-std")]
+    #[macro_export]
+    macro_rules! err_into_io_error_kind {
+        ($err: ident, $io_err_ty: path) => {
+            use $io_err_ty as IoErrorKind;
+            return $crate::__map_err_to_io_error_kind!(
+                $err,
+                $io_err_ty,
+                IoErrorKind::Uncategorized
+            );
         };
     }
 
+    /// Converts a [`std::io::ErrorKind`] into the closest matching [`ErrorStatus`].
     #[cfg(feature = "std")]
-    pub fn err_into_io_error_kind(err: ErrorStatus) -> std::io::ErrorKind {
-        err_into_io_error_kind!(err, std::io::ErrorKind);
+    pub const fn err_from_io_error_kind(io_err: std::io::ErrorKind) -> ErrorStatus {
+        crate::__map_io_error_kind!(std::io::ErrorKind, io_err)
     }
 
+    /// Converts an [`ErrorStatus`] into the closest matching [`std::io::ErrorKind`].
+    #[cfg(feature = "std")]
+    pub const fn err_into_io_error_kind(err: ErrorStatus) -> std::io::ErrorKind {
+        crate::__map_err_to_io_error_kind!(err, std::io::ErrorKind, std::io::ErrorKind::Other)
+    }
+
+    /// Converts an [`ErrorStatus`] into a [`std::io::Error`] carrying the error's description.
     #[cfg(feature = "std")]
     pub fn into_io_error(err: ErrorStatus) -> std::io::Error {
         let kind = err_into_io_error_kind(err);
         std::io::Error::new(kind, err.as_str())
     }
+
+    /// Converts a [`WaitError`] into a [`std::io::Error`], timeouts keep their context in the error's message.
+    #[cfg(feature = "std")]
+    pub fn wait_into_io_error(err: WaitError) -> std::io::Error {
+        match err {
+            WaitError::Timeout(timeout) => std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                std::string::ToString::to_string(&timeout),
+            ),
+            WaitError::Cancelled => {
+                std::io::Error::new(std::io::ErrorKind::Interrupted, "cancelled")
+            }
+            WaitError::System(err) => into_io_error(err),
+        }
+    }
 }
 
 pub mod alloc;
+#[cfg(feature = "alloc")]
+pub mod config;
+#[cfg(feature = "alloc")]
+pub mod crash;
+pub mod debug;
+pub mod encoding;
+pub mod fastvals;
+pub mod ffi_util;
+pub mod fmt_util;
+pub mod fs;
+pub mod limits;
 pub mod mem;
+pub mod metrics;
+#[cfg(feature = "alloc")]
+pub mod misc;
+#[cfg(feature = "alloc")]
 pub mod net;
+#[cfg(feature = "alloc")]
+pub mod poll;
 pub mod process;
 pub mod resource;
 pub mod shm;
@@ -126,6 +409,11 @@ pub mod shm;
 pub mod sockets;
 pub mod sync;
 pub mod syscalls;
+pub mod thread;
+pub mod time;
+#[cfg(feature = "alloc")]
+pub mod tty;
+pub mod util;
 pub mod vtty;
 pub use safa_abi as abi;
 pub use safa_abi::ffi;
@@ -134,11 +422,9 @@ pub use safa_abi::ffi;
 macro_rules! exported_func {
     {$($meta: meta)? $($inner: tt)*} => {
         $($meta)?
-        #[cfg_attr(
-            not(any(feature = "std", feature = "rustc-dep-of-std")),
-            unsafe(no_mangle)
-        )]
-        #[cfg_attr(any(feature = "std", feature = "rustc-dep-of-std"), inline(always))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std"), feature = "linkonce"), linkage = "weak")]
+        #[cfg_attr(not(any(all(feature = "c-exports", not(feature = "std")), feature = "small-syscalls")), inline(always))]
         $($inner)*
     };
 }
@@ -182,13 +468,43 @@ macro_rules! printerrln {
     };
 }
 
+/// Prints to the buffered stdout, see [`process::stdio::BufWriter`].
+#[macro_export]
+#[allow(unused)]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::process::stdio::_print(format_args!($($arg)*));
+    };
+}
+
+/// Prints to the buffered stdout followed by a newline, see [`process::stdio::BufWriter`].
+#[macro_export]
+#[allow(unused)]
+macro_rules! println {
+    () => {
+        $crate::print!("\n");
+    };
+    ($($arg:tt)*) => {
+        $crate::print!("{}\n", format_args!($($arg)*));
+    };
+}
+
 #[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
 #[panic_handler]
 fn _panic(info: &core::panic::PanicInfo) -> ! {
     use crate::backtrace::StackTrace;
 
+    // the output printed before the panic comes first
+    process::stdio::try_flush_stdout();
     printerrln!("Safa-API panicked: {}", info);
     printerrln!("{}", unsafe { StackTrace::current() });
+    // isolated threads exit here, without crashing the process
+    #[cfg(feature = "alloc")]
+    thread::recovery::recover(info);
+    #[cfg(feature = "alloc")]
+    if let Err(err) = crash::write_report(info) {
+        printerrln!("failed to write the crash report: {}", err.as_str());
+    }
 
     syscalls::process::exit(1);
 }
@@ -0,0 +1,50 @@
+//! Fixed limits of the ABI and of this API, in one place so that code doesn't hardcode them
+//!
+//! Every limit is a constant usable in const contexts (such as array lengths),
+//! except for the page size which should be queried with [`page_size`] where it doesn't have to be constant.
+//!
+//! See [`crate::process::Limits`] for the per-process resource limits instead.
+
+use crate::syscalls::SyscallNum;
+
+/// The maximum length of a path in bytes, a buffer of this size can hold any path returned by the kernel
+pub const MAX_PATH_LENGTH: usize = safa_abi::consts::MAX_PATH_LENGTH;
+/// The maximum length of a name in bytes, such as a file name, a process name or a local socket name
+pub const MAX_NAME_LENGTH: usize = safa_abi::consts::MAX_NAME_LENGTH;
+
+// The spawn caps below aren't limits of the ABI or of the kernel, which doesn't publish any,
+// they are soft caps chosen by this API to catch runaway arguments and environments early,
+// enforced by [`crate::process::command::Command`] unless disabled with `Command::soft_limits`.
+
+/// The soft cap on the amount of arguments (including the path) passed to a process, chosen by this API.
+pub const MAX_ARG_COUNT: usize = 4096;
+/// The soft cap on the length of a single argument in bytes, chosen by this API.
+pub const MAX_ARG_LENGTH: usize = 32 * 1024;
+/// The soft cap on the total size of the arguments in bytes, chosen by this API.
+pub const MAX_ARGS_SIZE: usize = 256 * 1024;
+/// The soft cap on the total size of the environment in bytes, counting each variable as `key=value\0`, chosen by this API.
+pub const MAX_ENV_SIZE: usize = 256 * 1024;
+
+/// The maximum amount of entries a single [`poll_resources`](crate::syscalls::io::poll_resources) call accepts.
+///
+/// The kernel doesn't publish a limit, so this API enforces its own to keep each poll bounded.
+pub const MAX_POLL_ENTRIES: usize = 1024;
+
+/// The size of a page in bytes, mappings are made of whole pages
+///
+/// The kernel can't be asked for its page size yet, every supported configuration uses 4 KiB pages.
+pub const PAGE_SIZE: usize = 4096;
+
+/// The amount of syscalls known to this version of the API, valid syscall numbers are `0..SYSCALL_COUNT`.
+///
+/// The kernel may support more syscalls than this API knows about.
+pub const SYSCALL_COUNT: usize = SyscallNum::SysACtrl as usize + 1;
+
+/// Returns the size of a page in bytes.
+///
+/// Always [`PAGE_SIZE`] for now, prefer this over the constant where the value doesn't have to be constant,
+/// as the page size may be queried from the kernel in the future.
+#[inline]
+pub fn page_size() -> usize {
+    PAGE_SIZE
+}
@@ -1,8 +1,16 @@
-use core::ptr::NonNull;
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
 
 use safa_abi::{errors::ErrorStatus, mem::MemMapFlags};
 
-use crate::{resource::Resource, syscalls};
+use crate::{
+    limits::page_size,
+    resource::Resource,
+    syscalls::{self, types::Ri},
+};
 
 /// A cleaner interface over [`syscalls::mem::map`].
 ///
@@ -73,3 +81,392 @@ impl MemoryMapper {
         .map(|(ri, data)| unsafe { (Resource::from_raw(ri), data) })
     }
 }
+
+/// Marks a [`MemMap`] as read-only, it can only be dereferenced as `&[u8]`
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnly;
+
+/// Marks a [`MemMap`] as writable, it can be dereferenced as `&[u8]` and `&mut [u8]`
+#[derive(Debug, Clone, Copy)]
+pub struct ReadWrite;
+
+/// No [`MemMapFlags`] at all, that is a read-only mapping, the flags are a `u8` bitfield (see [`syscalls::mem`]).
+const NO_FLAGS: MemMapFlags = unsafe { core::mem::transmute::<u8, MemMapFlags>(0) };
+
+/// Options for mapping memory, see [`MemMap`].
+///
+/// The access mode `M` ([`ReadWrite`] by default) is carried over to the produced [`MemMap`],
+/// so that read-only mappings can't be written to.
+#[derive(Debug, Clone, Copy)]
+pub struct MapOptions<M = ReadWrite> {
+    hint: *const (),
+    guard_pages: usize,
+    copy_on_write: bool,
+    /// the requested alignment, the page size is used instead if smaller
+    align: usize,
+    huge_pages: bool,
+    _mode: PhantomData<M>,
+}
+
+impl MapOptions<ReadWrite> {
+    /// Constructs new options for a writable mapping, without hints or guard pages, aligned to a page.
+    pub const fn new() -> Self {
+        Self {
+            hint: core::ptr::null(),
+            guard_pages: 0,
+            copy_on_write: false,
+            align: 1,
+            huge_pages: false,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Makes the mapping read-only.
+    pub const fn read_only(self) -> MapOptions<ReadOnly> {
+        MapOptions {
+            hint: self.hint,
+            guard_pages: self.guard_pages,
+            copy_on_write: self.copy_on_write,
+            align: self.align,
+            huge_pages: self.huge_pages,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Makes writes to a mapping of a resource private to the mapping, so that they are never written back to the resource.
+    ///
+    /// The kernel has no copy-on-write mappings yet, so the resource is copied into a private mapping eagerly instead.
+    pub const fn copy_on_write(self) -> Self {
+        Self {
+            copy_on_write: true,
+            ..self
+        }
+    }
+}
+
+impl Default for MapOptions<ReadWrite> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> MapOptions<M> {
+    /// Sets the number of guard pages placed around the mapping.
+    pub const fn guard(self, pages: usize) -> Self {
+        Self {
+            guard_pages: pages,
+            ..self
+        }
+    }
+
+    /// Sets the address hint of the mapping.
+    pub const fn hint(self, hint: *const ()) -> Self {
+        Self { hint, ..self }
+    }
+
+    /// Sets the alignment of the start of the mapping, for example 2 MiB for huge page sized mappings,
+    /// alignments smaller than a page are rounded up to a page.
+    ///
+    /// The kernel can't align mappings, so anonymous mappings are over-mapped and the start is moved forward,
+    /// mappings of resources can't be moved, and are left unaligned if the kernel happens to map them unaligned,
+    /// see [`MemMap::fallback`].
+    ///
+    /// # Panics
+    /// if `align` isn't a power of two
+    pub const fn align(self, align: usize) -> Self {
+        assert!(
+            align.is_power_of_two(),
+            "mapping alignment must be a power of two"
+        );
+        Self { align, ..self }
+    }
+
+    /// Hints that the mapping should be backed by huge pages,
+    /// the kernel doesn't support them yet so normal pages are always used, see [`MemMap::fallback`].
+    pub const fn huge_pages(self) -> Self {
+        Self {
+            huge_pages: true,
+            ..self
+        }
+    }
+}
+
+/// Mapping memory with a given access mode, implemented by [`ReadOnly`] and [`ReadWrite`]
+pub trait MapMode: private::Sealed {
+    #[doc(hidden)]
+    const FLAGS: MemMapFlags;
+}
+
+impl MapMode for ReadOnly {
+    const FLAGS: MemMapFlags = NO_FLAGS;
+}
+
+impl MapMode for ReadWrite {
+    const FLAGS: MemMapFlags = MemMapFlags::WRITE;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::ReadOnly {}
+    impl Sealed for super::ReadWrite {}
+}
+
+impl<M: MapMode> MapOptions<M> {
+    /// Maps `len` bytes, honoring the alignment as much as possible.
+    fn map_aligned<N>(
+        &self,
+        len: usize,
+        resource: Option<(Ri, isize)>,
+        flags: MemMapFlags,
+    ) -> Result<MemMap<N>, ErrorStatus> {
+        let page_size = page_size();
+        let align = self.align.max(page_size);
+        let pages = len.div_ceil(page_size);
+        let mut fallback = MapFallback {
+            no_huge_pages: self.huge_pages,
+            ..MapFallback::default()
+        };
+
+        let (ri, data) = match resource {
+            Some((ri, offset)) => syscalls::mem::map(
+                self.hint,
+                pages,
+                self.guard_pages,
+                Some(ri),
+                Some(offset),
+                flags,
+            )?,
+            None => syscalls::mem::map(
+                self.hint,
+                pages + (align / page_size - 1),
+                self.guard_pages,
+                None,
+                None,
+                flags,
+            )?,
+        };
+
+        let start = data.as_ptr() as *mut u8 as usize;
+        let aligned = start.next_multiple_of(align);
+
+        let data = if resource.is_none() {
+            fallback.padding = aligned - start;
+            unsafe { NonNull::new_unchecked(aligned as *mut u8) }
+        } else {
+            fallback.unaligned = aligned != start;
+            data.cast()
+        };
+
+        Ok(MemMap::new(ri, data, len, fallback))
+    }
+
+    /// Maps `len` bytes of anonymous zeroed memory.
+    pub fn map_anonymous(&self, len: usize) -> Result<MemMap<M>, ErrorStatus> {
+        self.map_aligned(len, None, M::FLAGS)
+    }
+
+    /// Maps `len` bytes of `resource` starting at `offset`.
+    pub fn map_resource(
+        &self,
+        resource: &Resource,
+        offset: isize,
+        len: usize,
+    ) -> Result<MemMap<M>, ErrorStatus> {
+        if !self.copy_on_write {
+            return self.map_aligned(len, Some((resource.ri(), offset)), M::FLAGS);
+        }
+
+        // copy-on-write, has to be writable while copying
+        let map = self.map_aligned::<M>(len, None, MemMapFlags::WRITE)?;
+
+        let buf = unsafe { core::slice::from_raw_parts_mut(map.data.as_ptr(), len) };
+        let mut read = 0;
+        while read < len {
+            let amount = unsafe { resource.read(offset + read as isize, &mut buf[read..])? };
+            if amount == 0 {
+                break;
+            }
+            read += amount;
+        }
+
+        Ok(map)
+    }
+}
+
+/// How a [`MemMap`] differs from what was requested in its [`MapOptions`], see [`MemMap::fallback`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MapFallback {
+    /// Huge pages were requested but normal pages were used, which is always the case for now
+    pub no_huge_pages: bool,
+    /// The requested alignment couldn't be honored, can only happen when mapping resources
+    pub unaligned: bool,
+    /// The amount of bytes mapped before the start of the mapping to honor the requested alignment
+    pub padding: usize,
+}
+
+/// A mapping of memory, that is unmapped on drop, see [`MapOptions`].
+///
+/// Dereferences to the mapped bytes, mutably only if `M` is [`ReadWrite`].
+#[derive(Debug)]
+pub struct MemMap<M = ReadWrite> {
+    resource: Resource,
+    data: NonNull<u8>,
+    len: usize,
+    fallback: MapFallback,
+    _mode: PhantomData<M>,
+}
+
+impl<M> MemMap<M> {
+    fn new(ri: Ri, data: NonNull<u8>, len: usize, fallback: MapFallback) -> Self {
+        Self {
+            resource: unsafe { Resource::from_raw(ri) },
+            data,
+            len,
+            fallback,
+            _mode: PhantomData,
+        }
+    }
+
+    /// The resource tracking the mapping
+    #[inline]
+    pub const fn resource(&self) -> &Resource {
+        &self.resource
+    }
+
+    /// A pointer to the start of the mapping
+    #[inline]
+    pub const fn as_ptr(&self) -> *const u8 {
+        self.data.as_ptr()
+    }
+
+    /// How the mapping differs from what was requested, see [`MapFallback`]
+    #[inline]
+    pub const fn fallback(&self) -> &MapFallback {
+        &self.fallback
+    }
+}
+
+impl<M> Deref for MemMap<M> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { core::slice::from_raw_parts(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for MemMap<ReadWrite> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { core::slice::from_raw_parts_mut(self.data.as_ptr(), self.len) }
+    }
+}
+
+unsafe impl<M> Send for MemMap<M> {}
+unsafe impl<M> Sync for MemMap<M> {}
+
+/// Maps `pages` pages of zeroed memory that is readable, writable and executable, for generating code at runtime.
+///
+/// After writing code to the mapping and before executing it, [`flush_icache`] must be called on the written range.
+///
+/// # Returns
+/// - [`ErrorStatus::InvalidArgument`] if the size of `pages` pages overflows
+pub fn map_executable(pages: usize) -> Result<MemMap<ReadWrite>, ErrorStatus> {
+    // mappings are executable unless `MemMapFlags::DISABLE_EXEC` is given
+    let len = pages
+        .checked_mul(page_size())
+        .ok_or(ErrorStatus::InvalidArgument)?;
+    MapOptions::new().map_anonymous(len)
+}
+
+/// Makes sure that code written to `code` is visible to instruction fetches, must be called after generating code
+/// and before executing it.
+///
+/// This is a no-op on x86_64 which has coherent instruction caches,
+/// on aarch64 the data cache is cleaned and the instruction cache is invalidated for the range.
+#[inline]
+pub fn flush_icache(code: &[u8]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        _ = code;
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        use core::arch::asm;
+
+        let start = code.as_ptr() as usize;
+        let end = start + code.len();
+
+        let ctr: u64;
+        asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack, preserves_flags));
+        // the smallest cache line sizes in words, as log2
+        let dcache_line = 4usize << ((ctr >> 16) & 0xF);
+        let icache_line = 4usize << (ctr & 0xF);
+
+        let mut addr = start & !(dcache_line - 1);
+        while addr < end {
+            asm!("dc cvau, {}", in(reg) addr, options(nostack, preserves_flags));
+            addr += dcache_line;
+        }
+        asm!("dsb ish", options(nostack, preserves_flags));
+
+        let mut addr = start & !(icache_line - 1);
+        while addr < end {
+            asm!("ic ivau, {}", in(reg) addr, options(nostack, preserves_flags));
+            addr += icache_line;
+        }
+        asm!("dsb ish", "isb", options(nostack, preserves_flags));
+    }
+}
+
+/// Advice given to [`advise`] about how a range of memory is going to be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /// The range isn't needed for now, the next access reads zeros
+    DontNeed,
+    /// The range is going to be accessed soon
+    WillNeed,
+    /// The contents of the range are no longer needed, the next access reads either the old contents or zeros
+    Free,
+}
+
+/// Advises the kernel about how the memory in `range` is going to be used, so that it can release or prefetch physical pages.
+///
+/// The kernel has no memory advice yet, so for now:
+/// - [`Advice::DontNeed`] fails with [`ErrorStatus::NotSupported`], as the kernel can't release the pages of a mapping,
+///   callers that need the range zeroed have to zero it themselves
+/// - [`Advice::WillNeed`] touches each page of the range, faulting them in
+/// - [`Advice::Free`] does nothing, which it is allowed to do
+///
+/// # Safety
+/// `range` must be valid for reads, nothing else may be accessing it during the call.
+pub unsafe fn advise(range: NonNull<[u8]>, advice: Advice) -> Result<(), ErrorStatus> {
+    let start = range.cast::<u8>().as_ptr();
+    let len = range.len();
+
+    match advice {
+        // until the kernel can decommit pages, nothing is released
+        Advice::DontNeed => return Err(ErrorStatus::NotSupported),
+        Advice::WillNeed => {
+            let page_size = page_size();
+            // the first byte of the range, then the first byte of each page after it,
+            // so that a range starting mid-page reaches the last page it covers
+            let mut offset = 0;
+            while offset < len {
+                unsafe { _ = start.add(offset).read_volatile() };
+                let address = start as usize + offset;
+                offset += page_size - address % page_size;
+            }
+        }
+        Advice::Free => {}
+    }
+
+    Ok(())
+}
+
+impl MemMap<ReadWrite> {
+    /// Advises the kernel about how the mapping is going to be used, see [`advise`].
+    pub fn advise(&mut self, advice: Advice) -> Result<(), ErrorStatus> {
+        unsafe { advise(NonNull::from(&mut **self), advice) }
+    }
+}
@@ -0,0 +1,340 @@
+//! Lightweight metrics for daemons to expose their internals, see [`Registry`]
+//!
+//! Metrics are atomic-backed and don't allocate, so they are meant to be declared as statics
+//! and grouped in a [`Registry`] that dumps them in the Prometheus text exposition format:
+//!
+//! ```ignore
+//! static REQUESTS: Counter = Counter::new("requests_total", "The amount of requests handled");
+//! static CONNECTIONS: Gauge = Gauge::new("connections", "The amount of open connections");
+//! static LATENCY: Histogram<3> = Histogram::new("latency_us", "Request latency in microseconds", [100, 1000, 10000]);
+//!
+//! static METRICS: Registry = Registry::new(&[&REQUESTS, &CONNECTIONS, &LATENCY]);
+//! ```
+//!
+//! Metric names should follow the Prometheus naming rules (`[a-zA-Z_:][a-zA-Z0-9_:]*`), they aren't validated.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+};
+
+/// The kind of a [`Metric`], written as its `# TYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric that can be dumped by a [`Registry`]
+pub trait Metric: Sync {
+    fn name(&self) -> &str;
+    fn help(&self) -> &str;
+    fn kind(&self) -> MetricKind;
+    /// Writes the samples of the metric, one `name value` line each, without the `# HELP` and `# TYPE` lines.
+    fn write_samples(&self, w: &mut dyn fmt::Write) -> fmt::Result;
+}
+
+/// A value that only goes up, such as the amount of handled requests
+#[derive(Debug)]
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    #[inline]
+    pub fn add(&self, amount: u64) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Counter {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Counter
+    }
+
+    fn write_samples(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(w, "{} {}", self.name, self.get())
+    }
+}
+
+/// A value that can go up and down, such as the amount of open connections
+#[derive(Debug)]
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    #[inline]
+    pub fn dec(&self) {
+        self.sub(1);
+    }
+
+    #[inline]
+    pub fn add(&self, amount: i64) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn sub(&self, amount: i64) {
+        self.value.fetch_sub(amount, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Gauge
+    }
+
+    fn write_samples(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(w, "{} {}", self.name, self.get())
+    }
+}
+
+/// Counts observed values (such as latencies) into `N` buckets, each holding the values up to (and including) its bound,
+/// plus an implicit last bucket for the values above all the bounds
+#[derive(Debug)]
+pub struct Histogram<const N: usize> {
+    name: &'static str,
+    help: &'static str,
+    /// the upper bounds of the buckets, in increasing order
+    bounds: [u64; N],
+    /// the amount of values in each bucket, not cumulative unlike the exposition format
+    buckets: [AtomicU64; N],
+    count: AtomicU64,
+    sum: AtomicU64,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// Creates a new histogram with buckets bounded by `bounds`, which must be in increasing order.
+    pub const fn new(name: &'static str, help: &'static str, bounds: [u64; N]) -> Self {
+        Self {
+            name,
+            help,
+            bounds,
+            buckets: [const { AtomicU64::new(0) }; N],
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the value `value`.
+    pub fn observe(&self, value: u64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// The amount of values recorded.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// The sum of the values recorded, wrapping on overflow.
+    #[inline]
+    pub fn sum(&self) -> u64 {
+        self.sum.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Metric for Histogram<N> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Histogram
+    }
+
+    fn write_samples(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        // the count is read first so that the `+Inf` bucket is never smaller than the others
+        let count = self.count();
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            writeln!(w, "{}_bucket{{le=\"{bound}\"}} {cumulative}", self.name)?;
+        }
+
+        writeln!(
+            w,
+            "{}_bucket{{le=\"+Inf\"}} {}",
+            self.name,
+            count.max(cumulative)
+        )?;
+        writeln!(w, "{}_sum {}", self.name, self.sum())?;
+        writeln!(w, "{}_count {}", self.name, count.max(cumulative))
+    }
+}
+
+/// A group of metrics dumped together, see the [module documentation](self)
+#[derive(Clone, Copy)]
+pub struct Registry<'a> {
+    metrics: &'a [&'a dyn Metric],
+}
+
+impl<'a> Registry<'a> {
+    pub const fn new(metrics: &'a [&'a dyn Metric]) -> Self {
+        Self { metrics }
+    }
+
+    #[inline]
+    pub const fn metrics(&self) -> &'a [&'a dyn Metric] {
+        self.metrics
+    }
+
+    /// Writes all the metrics to `w` in the Prometheus text exposition format.
+    pub fn write_to(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        for metric in self.metrics {
+            writeln!(w, "# HELP {} {}", metric.name(), metric.help())?;
+            writeln!(w, "# TYPE {} {}", metric.name(), metric.kind().as_str())?;
+            metric.write_samples(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Registry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+
+impl fmt::Debug for Registry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.metrics.iter().map(|metric| metric.name()))
+            .finish()
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod serve {
+    use core::fmt;
+
+    use safa_abi::errors::ErrorStatus;
+
+    use super::Registry;
+    use crate::sockets::{
+        buf::{BufStream, Connection},
+        UnixListener,
+    };
+
+    /// Forwards formatted text to a [`BufStream`], keeping the error that [`fmt::Write`] can't carry
+    struct StreamWriter<'a, C: Connection> {
+        stream: &'a mut BufStream<C>,
+        error: Option<ErrorStatus>,
+    }
+
+    impl<C: Connection> fmt::Write for StreamWriter<'_, C> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.stream.write_all(s.as_bytes()).map_err(|err| {
+                self.error = Some(err);
+                fmt::Error
+            })
+        }
+    }
+
+    impl Registry<'_> {
+        /// Writes all the metrics to `conn`, see [`Registry::write_to`].
+        pub fn write_to_connection<C: Connection>(&self, conn: C) -> Result<(), ErrorStatus> {
+            let mut stream = BufStream::new(conn);
+            let mut writer = StreamWriter {
+                stream: &mut stream,
+                error: None,
+            };
+
+            if self.write_to(&mut writer).is_err() {
+                return Err(writer.error.unwrap_or(ErrorStatus::Generic));
+            }
+            stream.flush()
+        }
+
+        /// Accepts a single connection from `listener` and writes all the metrics to it before closing it,
+        /// scrapers connect to the listener's address and read until the connection is closed.
+        ///
+        /// Daemons call this whenever the listener has a pending connection, for example after polling it.
+        pub fn serve_one(&self, listener: &UnixListener) -> Result<(), ErrorStatus> {
+            let mut conn = listener.accept()?;
+            self.write_to_connection(&mut conn)
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Access to the kernel log (the equivalent of `dmesg`), see [`read_kernel_log`] and [`KernelLog`]
+//!
+//! The kernel doesn't expose its log at a known path yet, neither the ABI nor the kernel define one, so:
+//! - [`read_kernel_log`] and [`KernelLog::open`] fail with [`ErrorStatus::NotSupported`]
+//! - [`KernelLog::open_at`] reads a log from a path given by the caller, such as a device provided by a driver
+
+use core::time::Duration;
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::vec::Vec;
+use safa_abi::{
+    errors::ErrorStatus,
+    fs::OpenOptions,
+    poll::{PollEntry, PollEvents},
+};
+
+use crate::{resource::Resource, syscalls};
+
+/// How long [`KernelLog::follow`] sleeps between checks for new messages, if the kernel log can't be polled.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the chunks the kernel log is read in
+const CHUNK_SIZE: usize = 4096;
+
+/// Reads the whole kernel log, appending it to `dest`.
+///
+/// Returns the amount of bytes read, or [`ErrorStatus::NotSupported`] as the kernel log has no known path yet,
+/// see the [module documentation](self).
+pub fn read_kernel_log(dest: &mut Vec<u8>) -> Result<usize, ErrorStatus> {
+    KernelLog::open()?.read_new(dest)
+}
+
+/// An opened kernel log, that keeps track of how much of it was read so far,
+/// so that only new messages are returned by subsequent reads.
+#[derive(Debug)]
+pub struct KernelLog {
+    resource: Resource,
+    offset: usize,
+}
+
+impl KernelLog {
+    /// Opens the kernel log, reading starts from the oldest message.
+    ///
+    /// Fails with [`ErrorStatus::NotSupported`] as the kernel log has no known path yet,
+    /// see [`KernelLog::open_at`] to open it from a path given by the caller.
+    pub fn open() -> Result<Self, ErrorStatus> {
+        Err(ErrorStatus::NotSupported)
+    }
+
+    /// Opens the kernel log at `path`, reading starts from the oldest message.
+    pub fn open_at(path: &str) -> Result<Self, ErrorStatus> {
+        let resource = Resource::open(path, OpenOptions::READ)?;
+        Ok(Self {
+            resource,
+            offset: 0,
+        })
+    }
+
+    /// Reads the messages that were logged since the last read (or since opening), appending them to `dest`,
+    /// doesn't block.
+    ///
+    /// Returns the amount of bytes read, 0 if there are no new messages.
+    pub fn read_new(&mut self, dest: &mut Vec<u8>) -> Result<usize, ErrorStatus> {
+        let start = dest.len();
+
+        loop {
+            let len = dest.len();
+            dest.resize(len + CHUNK_SIZE, 0);
+
+            let read = unsafe { self.resource.read(self.offset as isize, &mut dest[len..]) };
+            let read = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    dest.truncate(len);
+                    return Err(e);
+                }
+            };
+
+            dest.truncate(len + read);
+            self.offset += read;
+
+            if read == 0 {
+                return Ok(dest.len() - start);
+            }
+        }
+    }
+
+    /// Waits up to `timeout` (forever if [`None`]) for new messages and then reads them, appending them to `dest`.
+    ///
+    /// Polls the kernel log if it supports polling, otherwise checks for new messages periodically.
+    ///
+    /// Returns the amount of bytes read, 0 if the timeout was reached.
+    pub fn follow(
+        &mut self,
+        dest: &mut Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, ErrorStatus> {
+        let mut entries = [PollEntry::new(
+            self.resource.ri(),
+            PollEvents::DATA_AVAILABLE,
+        )];
+
+        match syscalls::io::poll_resources(&mut entries, timeout) {
+            Ok(()) if entries[0].returned_events() == PollEvents::NONE => return Ok(0),
+            Ok(()) => return self.read_new(dest),
+            Err(ErrorStatus::OperationNotSupported | ErrorStatus::NotSupported) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut waited = Duration::ZERO;
+        loop {
+            let read = self.read_new(dest)?;
+            if read != 0 || timeout.is_some_and(|timeout| waited >= timeout) {
+                return Ok(read);
+            }
+
+            let interval = match timeout {
+                Some(timeout) => FOLLOW_INTERVAL.min(timeout - waited),
+                None => FOLLOW_INTERVAL,
+            };
+            _ = syscalls::thread::sleep(interval);
+            waited += interval;
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! Higher-level wrappers over system-wide operations that don't fall into a specific category,
+//! see [`crate::syscalls::misc`] for the raw syscalls.
+
+pub mod klog;
+pub mod power;
@@ -0,0 +1,129 @@
+//! Power management, see [`PowerAction`] for what the system can be asked to do
+//!
+//! Only shutting down and rebooting are supported by the kernel for now,
+//! suspending and hibernating have no entry points until the kernel grows syscalls for them,
+//! use [`is_supported`] to check which actions can be offered to the user.
+
+use core::time::Duration;
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::vec::Vec;
+
+use crate::{sockets::unix::UnixSockConnectionBuilder, sync::locks::Mutex, syscalls};
+
+/// An action that changes the power state of the system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerAction {
+    Shutdown,
+    Reboot,
+    /// Suspends the system to memory
+    Suspend,
+    /// Suspends the system to disk
+    Hibernate,
+}
+
+/// Whether or not the kernel supports the power action `action`.
+pub const fn is_supported(action: PowerAction) -> bool {
+    match action {
+        PowerAction::Shutdown | PowerAction::Reboot => true,
+        PowerAction::Suspend | PowerAction::Hibernate => false,
+    }
+}
+
+/// Immediately shuts down the system, see [`syscalls::misc::shutdown`].
+#[inline]
+pub fn shutdown() -> ! {
+    syscalls::misc::shutdown()
+}
+
+/// Immediately reboots the system, see [`syscalls::misc::reboot`].
+#[inline]
+pub fn reboot() -> ! {
+    syscalls::misc::reboot()
+}
+
+/// How the system is stopped by [`shutdown_system`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShutdownKind {
+    Shutdown,
+    Reboot,
+}
+
+/// Options for [`shutdown_system`]
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownOptions<'a> {
+    kind: ShutdownKind,
+    delay: Duration,
+    service_path: Option<&'a str>,
+}
+
+impl<'a> ShutdownOptions<'a> {
+    /// Creates new options, that stop the system as `kind` immediately, without notifying any service.
+    pub const fn new(kind: ShutdownKind) -> Self {
+        Self {
+            kind,
+            delay: Duration::ZERO,
+            service_path: None,
+        }
+    }
+
+    /// Sets the time to wait after notifying the hooks and the service, before stopping the system.
+    pub const fn set_delay(&mut self, delay: Duration) -> &mut Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the abstract path of a local socket of a system service to notify of the shutdown,
+    /// it receives `shutdown\n` or `reboot\n` depending on the [`ShutdownKind`].
+    pub const fn set_service_path(&mut self, path: &'a str) -> &mut Self {
+        self.service_path = Some(path);
+        self
+    }
+}
+
+static SHUTDOWN_HOOKS: Mutex<Vec<fn(ShutdownKind)>> = Mutex::new(Vec::new());
+
+/// Registers `hook` to be called by [`shutdown_system`] before the system is stopped,
+/// hooks are called in reverse order of registration.
+pub fn register_shutdown_hook(hook: fn(ShutdownKind)) {
+    SHUTDOWN_HOOKS.lock().push(hook);
+}
+
+/// Gracefully stops the system:
+/// 1. calls the hooks registered with [`register_shutdown_hook`]
+/// 2. notifies the system service at [`ShutdownOptions::set_service_path`] if any, failing to notify it is ignored
+/// 3. waits for [`ShutdownOptions::set_delay`]
+/// 4. shuts down or reboots the system
+pub fn shutdown_system(options: &ShutdownOptions) -> ! {
+    let hooks = core::mem::take(&mut *SHUTDOWN_HOOKS.lock());
+    for hook in hooks.into_iter().rev() {
+        hook(options.kind);
+    }
+
+    if let Some(path) = options.service_path {
+        let message: &[u8] = match options.kind {
+            ShutdownKind::Shutdown => b"shutdown\n",
+            ShutdownKind::Reboot => b"reboot\n",
+        };
+
+        if let Ok(builder) = UnixSockConnectionBuilder::from_abstract_path(path) {
+            if let Ok(mut connection) = builder.connect() {
+                _ = connection.write(message);
+            }
+        }
+    }
+
+    if !options.delay.is_zero() {
+        _ = syscalls::thread::sleep(options.delay);
+    }
+
+    match options.kind {
+        ShutdownKind::Shutdown => shutdown(),
+        ShutdownKind::Reboot => reboot(),
+    }
+}
@@ -1,69 +1,355 @@
 #[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
 extern crate alloc;
 
-use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::{
+    net::{IpAddr, Ipv4Addr, SocketAddrV4},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
 #[cfg(feature = "std")]
 use std as alloc;
 
-use alloc::string::String;
-use safa_abi::{errors::ErrorStatus, sockets::SockMsgFlags};
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use safa_abi::{errors::ErrorStatus, poll::PollEvents};
 use simpldns::message::{
-    DnsClass, DnsMessage, DnsMessageFlags, DnsMessageHeader, DnsOpCode, DnsQuestion, DnsRCode,
-    DnsType, RRData,
+    DnsClass, DnsLabel, DnsMessage, DnsMessageFlags, DnsMessageHeader, DnsOpCode, DnsQuestion,
+    DnsRCode, DnsType, RRData,
 };
 
 use crate::{
-    sockets::{socket::SocketOpt, Socket, SocketDomain, SocketKind},
+    debug::debug_log,
+    encoding::wire,
+    sockets::UDPSocket,
+    sync::{locks::Mutex, CancellationToken},
     syscalls,
+    time::Instant,
+    util::{self, RetryPolicy},
 };
 
+/// The nameserver used when [`ResolveOptions`] doesn't specify any
+pub const DEFAULT_NAMESERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(1, 1, 1, 1), 53);
+
+/// The longest an answer is kept in the DNS cache, regardless of its TTL
+pub const MAX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// The amount of domain names the DNS cache holds before evicting the ones expiring first
+pub const MAX_CACHE_ENTRIES: usize = 256;
+
+/// How a DNS query is distributed across the nameservers of [`ResolveOptions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameserverStrategy {
+    /// Queries the nameservers one after the other in order, moving on to the next one when a nameserver doesn't respond in time.
+    #[default]
+    Sequential,
+    /// Like [`NameserverStrategy::Sequential`] but each lookup starts from the nameserver after the one the previous lookup started from,
+    /// spreading the load across the nameservers.
+    Rotate,
+    /// Queries all the nameservers at once, using the first response.
+    Parallel,
+}
+
+/// Options controlling how DNS lookups are performed, see [`super::lookup_addr_info_with`]
+#[derive(Debug, Clone)]
+pub struct ResolveOptions {
+    nameservers: Vec<SocketAddrV4>,
+    timeout: Duration,
+    attempts: usize,
+    strategy: NameserverStrategy,
+    use_cache: bool,
+}
+
+impl ResolveOptions {
+    /// Constructs new options querying [`DEFAULT_NAMESERVER`], waiting 300ms for each response,
+    /// making 4 attempts using the [`NameserverStrategy::Sequential`] strategy, using the DNS cache.
+    pub const fn new() -> Self {
+        Self {
+            nameservers: Vec::new(),
+            timeout: Duration::from_millis(300),
+            attempts: 4,
+            strategy: NameserverStrategy::Sequential,
+            use_cache: true,
+        }
+    }
+
+    /// Adds `nameserver` to the nameservers queried, once a nameserver is added [`DEFAULT_NAMESERVER`] is no longer used.
+    pub fn add_nameserver(&mut self, nameserver: SocketAddrV4) -> &mut Self {
+        self.nameservers.push(nameserver);
+        self
+    }
+
+    /// Sets how long to wait for a response from a nameserver before moving on to the next nameserver or attempt.
+    pub const fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times the nameservers are queried before giving up, 0 is treated as 1.
+    pub const fn set_attempts(&mut self, attempts: usize) -> &mut Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Sets how queries are distributed across the nameservers.
+    pub const fn set_strategy(&mut self, strategy: NameserverStrategy) -> &mut Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets whether lookups are answered from and stored in the process-wide DNS cache, see [`flush_dns_cache`].
+    ///
+    /// The cache is shared by all the options, so answers from other nameservers may be returned.
+    pub const fn set_use_cache(&mut self, use_cache: bool) -> &mut Self {
+        self.use_cache = use_cache;
+        self
+    }
+
+    /// The nameservers queried.
+    pub fn nameservers(&self) -> &[SocketAddrV4] {
+        if self.nameservers.is_empty() {
+            core::slice::from_ref(&DEFAULT_NAMESERVER)
+        } else {
+            &self.nameservers
+        }
+    }
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The kind of address records a query asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RecordType {
+    /// Ipv4 addresses
+    A,
+    /// Ipv6 addresses
+    Aaaa,
+}
+
+impl RecordType {
+    const fn dns_type(self) -> DnsType {
+        match self {
+            Self::A => DnsType::A,
+            Self::Aaaa => DnsType::AAAA,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    addrs: Vec<IpAddr>,
+    canon_name: Option<String>,
+    expires: Instant,
+}
+
+/// Answers keyed by lowercase domain names without a trailing dot and by the type of records they hold
+static CACHE: Mutex<BTreeMap<(String, RecordType), CacheEntry>> = Mutex::new(BTreeMap::new());
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+fn cache_key(domain: &str, record: RecordType) -> (String, RecordType) {
+    let domain = domain
+        .strip_suffix('.')
+        .unwrap_or(domain)
+        .to_ascii_lowercase();
+    (domain, record)
+}
+
+/// Removes every answer from the DNS cache, for example after the network changed.
+pub fn flush_dns_cache() {
+    CACHE.lock().clear();
+}
+
+/// Enables or disables the DNS cache for the whole process, disabling it also flushes it.
+///
+/// The cache is enabled by default, see [`ResolveOptions::set_use_cache`] to bypass it for some lookups only.
+pub fn set_dns_cache_enabled(enabled: bool) {
+    CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        flush_dns_cache();
+    }
+}
+
 #[inline]
-fn get_nameserver() -> SocketAddrV4 {
-    // TODO: actually read nameserver
-    SocketAddrV4::new(Ipv4Addr::new(1, 1, 1, 1), 53)
+pub fn dns_cache_enabled() -> bool {
+    CACHE_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Calls `with_result` with each cached address of `domain` and returns its canonical name,
+/// returns [`None`] without calling it if there is no unexpired answer.
+fn cache_lookup<F>(domain: &str, record: RecordType, with_result: &mut F) -> Option<Option<String>>
+where
+    F: FnMut(IpAddr),
+{
+    let key = cache_key(domain, record);
+    let mut cache = CACHE.lock();
+    let entry = cache.get(&key)?;
+
+    if entry.expires <= Instant::now() {
+        cache.remove(&key);
+        return None;
+    }
+
+    for addr in &entry.addrs {
+        with_result(*addr);
+    }
+    Some(entry.canon_name.clone())
+}
+
+fn cache_insert(
+    domain: &str,
+    record: RecordType,
+    addrs: Vec<IpAddr>,
+    canon_name: Option<String>,
+    ttl: Duration,
+) {
+    let now = Instant::now();
+    let Some(expires) = now.checked_add(ttl.min(MAX_CACHE_TTL)) else {
+        return;
+    };
+
+    let mut cache = CACHE.lock();
+    if cache.len() >= MAX_CACHE_ENTRIES {
+        cache.retain(|_, entry| entry.expires > now);
+    }
+
+    if cache.len() >= MAX_CACHE_ENTRIES {
+        let soonest = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.expires)
+            .map(|(key, _)| key.clone());
+        if let Some(soonest) = soonest {
+            cache.remove(&soonest);
+        }
+    }
+
+    cache.insert(
+        cache_key(domain, record),
+        CacheEntry {
+            addrs,
+            canon_name,
+            expires,
+        },
+    );
+}
+
+/// The nameserver the next [`NameserverStrategy::Rotate`] lookup starts from
+static ROTATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Receives a response to the query with the transaction ID `trans_id` from any of `nameservers` into `buf`,
+/// giving up with [`ErrorStatus::Timeout`] once `deadline` passes, or once `cancel` is cancelled.
+fn recv_from_any(
+    socket: &UDPSocket,
+    buf: &mut [u8],
+    nameservers: &[SocketAddrV4],
+    trans_id: u16,
+    deadline: Instant,
+    cancel: Option<&CancellationToken>,
+) -> Result<usize, ErrorStatus> {
+    let start = Instant::now();
+    loop {
+        // unrelated datagrams don't extend the wait
+        socket.raw_socket().wait_until(
+            PollEvents::DATA_AVAILABLE,
+            deadline,
+            "recv",
+            start,
+            cancel,
+        )?;
+        let (recv, addr) = match socket.recv_from(buf) {
+            // another handle might have taken the datagram in the meantime
+            Err(ErrorStatus::WouldBlock) => continue,
+            results => results?,
+        };
+        // recv again without counting this as a retry
+        if nameservers.contains(&addr) && wire::get_u16_be(&buf[..recv]) == Ok(trans_id) {
+            debug_log!(
+                DNS,
+                "received {recv} bytes answering {trans_id:#06x} from {addr}"
+            );
+            return Ok(recv);
+        }
+        debug_log!(
+            DNS,
+            "ignored {recv} bytes from {addr} while waiting for {trans_id:#06x}"
+        );
+    }
 }
 
 fn send_and_recv<'a>(
     send: &[u8],
     encode_to: &'a mut [u8],
-    mut retries: usize,
-    timeout_ms: u64,
+    options: &ResolveOptions,
+    cancel: Option<&CancellationToken>,
 ) -> Result<&'a [u8], ErrorStatus> {
-    let send_to = get_nameserver();
+    let nameservers = options.nameservers();
+    // the transaction ID is the first field of the header
+    let trans_id = wire::get_u16_be(send).map_err(|_| ErrorStatus::InvalidArgument)?;
 
-    let bind_to = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+    let socket = UDPSocket::bind_any()?;
+    let deadline = || {
+        let now = Instant::now();
+        now.checked_add(options.timeout).unwrap_or(now)
+    };
 
-    let socket = Socket::builder(SocketDomain::Ipv4, SocketKind::Datagram, 0).build()?;
-    socket.set_sock_opt(SocketOpt::ReadTimeout, timeout_ms)?;
-    socket.bind_to_addr(bind_to)?;
+    let first = match options.strategy {
+        NameserverStrategy::Rotate => ROTATION.fetch_add(1, Ordering::Relaxed) % nameservers.len(),
+        NameserverStrategy::Sequential | NameserverStrategy::Parallel => 0,
+    };
 
-    let send_me = || socket.send_to_addr(send, SockMsgFlags::NONE, SocketAddr::V4(send_to));
-    send_me()?;
+    // the deadlines already space out the attempts
+    let policy = *RetryPolicy::new()
+        .set_max_attempts(options.attempts)
+        .set_initial_backoff(Duration::ZERO)
+        .set_retry_on(|err| err == ErrorStatus::Timeout);
 
-    loop {
-        let results = socket.recv_from_addr(encode_to, SockMsgFlags::NONE);
-        break match results {
-            Ok((recv, addr)) => {
-                if addr != send_to {
-                    // recv again without counting this as a retry
-                    continue;
-                }
-
-                Ok(&encode_to[..recv])
+    // buf didn't change, so retries resend the same query
+    let attempt = |_| match options.strategy {
+        NameserverStrategy::Parallel => {
+            for nameserver in nameservers {
+                debug_log!(DNS, "sending query {trans_id:#06x} to {nameserver}");
+                socket.send_to(send, *nameserver)?;
             }
-            Err(e @ ErrorStatus::Timeout) => {
-                if retries == 0 {
-                    break Err(e);
-                }
+            recv_from_any(
+                &socket,
+                encode_to,
+                nameservers,
+                trans_id,
+                deadline(),
+                cancel,
+            )
+        }
+        NameserverStrategy::Sequential | NameserverStrategy::Rotate => {
+            for i in 0..nameservers.len() {
+                let nameserver = nameservers[(first + i) % nameservers.len()];
+                debug_log!(DNS, "sending query {trans_id:#06x} to {nameserver}");
+                socket.send_to(send, nameserver)?;
 
-                // buf didn't change
-                send_me()?;
-                retries -= 1;
-                continue;
+                // a late response from a previous nameserver answers the same query
+                match recv_from_any(
+                    &socket,
+                    encode_to,
+                    nameservers,
+                    trans_id,
+                    deadline(),
+                    cancel,
+                ) {
+                    Err(ErrorStatus::Timeout) => continue,
+                    results => return results,
+                }
             }
-            Err(e) => Err(e),
-        };
-    }
+            Err(ErrorStatus::Timeout)
+        }
+    };
+
+    let results = match cancel {
+        Some(cancel) => util::retry_cancellable(&policy, cancel, attempt),
+        None => util::retry(&policy, attempt),
+    };
+    let recv = results
+        .inspect_err(|err| debug_log!(DNS, "query {trans_id:#06x} failed: {}", err.as_str()))?;
+
+    Ok(&encode_to[..recv])
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -72,6 +358,7 @@ pub enum DnsResolutionError {
     NoSuchName,
     Refused,
     InvalidDomainName,
+    Cancelled,
     System(ErrorStatus),
 }
 
@@ -84,15 +371,93 @@ impl From<ErrorStatus> for DnsResolutionError {
     }
 }
 
-pub fn lookup_dns<F>(domain: &str, mut with_result: F) -> Result<Option<String>, DnsResolutionError>
+/// Resolves `domain`, calling `with_result` with each of its addresses and returning its canonical name if it differs from `domain`.
+///
+/// Only Ipv4 addresses (A records) are looked up unless `ipv6` is true,
+/// in which case the Ipv6 addresses (AAAA records) are looked up afterwards,
+/// failing to look them up isn't an error if any Ipv4 address was found.
+///
+/// Answers are taken from the DNS cache when possible, and stored in it for as long as the smallest TTL of the answer records
+/// (up to [`MAX_CACHE_TTL`]), see [`ResolveOptions::set_use_cache`].
+pub fn lookup_dns<F>(
+    domain: &str,
+    options: &ResolveOptions,
+    cancel: Option<&CancellationToken>,
+    ipv6: bool,
+    mut with_result: F,
+) -> Result<Option<String>, DnsResolutionError>
 where
-    F: FnMut(Ipv4Addr),
+    F: FnMut(IpAddr),
+{
+    let mut found = 0;
+    let mut counted = |ip| {
+        found += 1;
+        with_result(ip)
+    };
+
+    let canon_name = lookup_records(domain, RecordType::A, options, cancel, &mut counted)?;
+    if !ipv6 {
+        return Ok(canon_name);
+    }
+
+    match lookup_records(domain, RecordType::Aaaa, options, cancel, &mut counted) {
+        Ok(canon_name6) => Ok(canon_name.or(canon_name6)),
+        Err(err) if found != 0 && !matches!(err, DnsResolutionError::Cancelled) => {
+            debug_log!(DNS, "{domain}: ignoring the failed Ipv6 lookup");
+            Ok(canon_name)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Looks up the `record` records of `domain` using the cache if possible, see [`lookup_dns`].
+fn lookup_records<F>(
+    domain: &str,
+    record: RecordType,
+    options: &ResolveOptions,
+    cancel: Option<&CancellationToken>,
+    with_result: &mut F,
+) -> Result<Option<String>, DnsResolutionError>
+where
+    F: FnMut(IpAddr),
+{
+    let use_cache = options.use_cache && dns_cache_enabled();
+    if use_cache {
+        if let Some(canon_name) = cache_lookup(domain, record, with_result) {
+            debug_log!(DNS, "{domain}: answered from the cache");
+            return Ok(canon_name);
+        }
+    }
+
+    let mut addrs = Vec::new();
+    let (canon_name, ttl) = query_dns(domain, record, options, cancel, |ip| addrs.push(ip))?;
+    for addr in &addrs {
+        with_result(*addr);
+    }
+
+    if use_cache && !addrs.is_empty() && !ttl.is_zero() {
+        cache_insert(domain, record, addrs, canon_name.clone(), ttl);
+    }
+    Ok(canon_name)
+}
+
+/// Queries the nameservers for the `record` records of `domain`, see [`lookup_dns`],
+/// also returns the smallest TTL of the answer records.
+fn query_dns<F>(
+    domain: &str,
+    record: RecordType,
+    options: &ResolveOptions,
+    cancel: Option<&CancellationToken>,
+    with_result: F,
+) -> Result<(Option<String>, Duration), DnsResolutionError>
+where
+    F: FnMut(IpAddr),
 {
     // TODO: random numbers
     let trans_id =
         syscalls::clock::clock_gettime(safa_abi::clock::Clock::Monotonic).as_nanos() as u16;
     let questions = [
-        DnsQuestion::try_new(domain, DnsType::A /* TODO: Ipv6? */, DnsClass::IN)
+        DnsQuestion::try_new(domain, record.dns_type(), DnsClass::IN)
             .map_err(|_| DnsResolutionError::InvalidDomainName)?,
     ];
 
@@ -109,43 +474,202 @@ where
         .expect("Encoding the message shall not fail");
 
     let mut resp_buf = [0u8; 512];
-    let response_msg = send_and_recv(&encode_buf, &mut resp_buf, 3, 300)?;
+    let response_msg =
+        send_and_recv(&encode_buf, &mut resp_buf, options, cancel).map_err(|err| {
+            // the token tells cancellations apart from the process being terminated
+            match cancel {
+                Some(cancel) if cancel.is_cancelled() => DnsResolutionError::Cancelled,
+                _ => err.into(),
+            }
+        })?;
     let message =
         DnsMessage::parse(response_msg).expect("DNS nameserver returned an invalid message");
 
+    debug_log!(
+        DNS,
+        "{domain}: response code {:?}",
+        message.header().rcode()
+    );
     match message.header().rcode() {
         DnsRCode::FormatError => unreachable!("We encoded a bad DNS message"),
-        DnsRCode::NameError => return Err(DnsResolutionError::NoSuchName),
+        DnsRCode::NameError => {
+            debug_log!(DNS, "{domain}: no such name");
+            return Err(DnsResolutionError::NoSuchName);
+        }
         DnsRCode::Refused | DnsRCode::NotImplemented | DnsRCode::ServerFailure => {
-            return Err(DnsResolutionError::Refused)
+            debug_log!(DNS, "{domain}: the nameserver refused the query");
+            return Err(DnsResolutionError::Refused);
         }
         DnsRCode::NoError => {}
     }
 
-    let mut name_buf = [0u8; 512];
-    let answers = message.answers();
-
-    let mut cname = None;
+    let ttl = message.answers().map(|ans| ans.ttl()).min().unwrap_or(0);
+    let canon_name = collect_answers(message.answers().map(|ans| ans.rdata()), with_result);
+    Ok((
+        canon_name.filter(|canon_name| !same_name(canon_name, domain)),
+        Duration::from_secs(ttl as u64),
+    ))
+}
 
-    for ans in answers {
-        match ans.rdata() {
-            RRData::A(a) => with_result(*a),
-            RRData::CName(canon_name) => {
-                let mut cursor = 0;
-                for n in *canon_name {
-                    let len = n.len() as usize;
-                    name_buf[cursor..cursor + len].copy_from_slice(n.as_bytes());
+/// Joins `labels` into a domain name without a trailing dot.
+fn join_labels(labels: &[DnsLabel]) -> String {
+    let mut name = String::new();
+    for label in labels {
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&String::from_utf8_lossy(label.as_bytes()));
+    }
+    name
+}
 
-                    cursor += len;
-                    name_buf[cursor] = b'.';
-                    cursor += 1;
-                }
+/// Compares two domain names, ignoring case and a trailing dot.
+fn same_name(a: &str, b: &str) -> bool {
+    let a = a.strip_suffix('.').unwrap_or(a);
+    let b = b.strip_suffix('.').unwrap_or(b);
+    a.eq_ignore_ascii_case(b)
+}
 
-                let name_str = core::str::from_utf8(&name_buf[..cursor]).unwrap();
-                cname = Some(name_str);
-            }
+/// Calls `with_result` with each address in `answers` and returns the canonical name,
+/// which is the target of the last CNAME record as nameservers answer with the CNAME chain in order.
+fn collect_answers<'a, F>(
+    answers: impl Iterator<Item = &'a RRData<'a>>,
+    mut with_result: F,
+) -> Option<String>
+where
+    F: FnMut(IpAddr),
+{
+    let mut canon_name = None;
+    for rdata in answers {
+        match rdata {
+            RRData::A(a) => with_result(IpAddr::V4(*a)),
+            RRData::AAAA(aaaa) => with_result(IpAddr::V6(*aaaa)),
+            RRData::CName(labels) => canon_name = Some(join_labels(labels)),
             _ => {}
         }
     }
-    Ok(cname.filter(|c| *c != domain).map(|c| String::from(c)))
+    canon_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::Ipv6Addr;
+
+    const TYPE_A: u16 = 1;
+    const TYPE_CNAME: u16 = 5;
+    const TYPE_AAAA: u16 = 28;
+    const CLASS_IN: u16 = 1;
+
+    /// Appends `name` as uncompressed labels
+    fn push_name(msg: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            msg.push(label.len() as u8);
+            msg.extend_from_slice(label.as_bytes());
+        }
+        msg.push(0);
+    }
+
+    fn name_rdata(name: &str) -> Vec<u8> {
+        let mut rdata = Vec::new();
+        push_name(&mut rdata, name);
+        rdata
+    }
+
+    /// Builds the response to a query of `domain` with the answer records `answers`, given as (owner, type, rdata)
+    fn response(domain: &str, qtype: u16, answers: &[(&str, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&0x1234u16.to_be_bytes());
+        // a response to a standard query, recursion desired and available, no error
+        msg.extend_from_slice(&0x8180u16.to_be_bytes());
+        msg.extend_from_slice(&1u16.to_be_bytes());
+        msg.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&[0; 4]);
+
+        push_name(&mut msg, domain);
+        msg.extend_from_slice(&qtype.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        for (owner, rtype, rdata) in answers {
+            push_name(&mut msg, owner);
+            msg.extend_from_slice(&rtype.to_be_bytes());
+            msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+            msg.extend_from_slice(&300u32.to_be_bytes());
+            msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            msg.extend_from_slice(rdata);
+        }
+        msg
+    }
+
+    /// Parses `msg` and collects its answers like [`query_dns`] does
+    fn collect(msg: &[u8]) -> (Vec<IpAddr>, Option<String>) {
+        let message = DnsMessage::parse(msg).expect("the test response is invalid");
+        let mut addrs = Vec::new();
+        let canon_name = collect_answers(message.answers().map(|ans| ans.rdata()), |ip| {
+            addrs.push(ip)
+        });
+        (addrs, canon_name)
+    }
+
+    #[test]
+    fn cname_chain_gives_last_target() {
+        let msg = response(
+            "www.example.com",
+            TYPE_A,
+            &[
+                (
+                    "www.example.com",
+                    TYPE_CNAME,
+                    name_rdata("edge.example.net"),
+                ),
+                (
+                    "edge.example.net",
+                    TYPE_CNAME,
+                    name_rdata("node1.cdn.example.org"),
+                ),
+                ("node1.cdn.example.org", TYPE_A, [192, 0, 2, 10].to_vec()),
+            ],
+        );
+
+        let (addrs, canon_name) = collect(&msg);
+        assert_eq!(addrs, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10))]);
+        assert_eq!(canon_name.as_deref(), Some("node1.cdn.example.org"));
+    }
+
+    #[test]
+    fn no_cname_gives_no_canonical_name() {
+        let msg = response(
+            "example.com",
+            TYPE_A,
+            &[("example.com", TYPE_A, [192, 0, 2, 1].to_vec())],
+        );
+
+        let (addrs, canon_name) = collect(&msg);
+        assert_eq!(addrs, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+        assert_eq!(canon_name, None);
+    }
+
+    #[test]
+    fn addresses_keep_answer_order() {
+        let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let msg = response(
+            "example.com",
+            TYPE_A,
+            &[
+                ("example.com", TYPE_A, [192, 0, 2, 1].to_vec()),
+                ("example.com", TYPE_A, [192, 0, 2, 2].to_vec()),
+                ("example.com", TYPE_AAAA, v6.octets().to_vec()),
+            ],
+        );
+
+        let (addrs, _) = collect(&msg);
+        assert_eq!(
+            addrs,
+            [
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+                IpAddr::V6(v6),
+            ]
+        );
+    }
 }
@@ -9,6 +9,7 @@ use std as alloc;
 
 use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use safa_abi::errors::ErrorStatus;
 use safa_abi::sockets::InetV4SocketAddr;
@@ -17,8 +18,16 @@ use safa_abi::sockets::SockDomain as AbiSocketDomain;
 use safa_abi::sockets::SocketAddr;
 
 mod dns;
+pub mod pool;
+pub mod resolver;
 use crate::net::dns::DnsResolutionError;
-use crate::sockets::{SocketDomain, SocketKind};
+use crate::sockets::{Socket, SocketDomain, SocketKind};
+use crate::sync::CancellationToken;
+use crate::util::{self, RetryPolicy};
+pub use dns::{
+    dns_cache_enabled, flush_dns_cache, set_dns_cache_enabled, NameserverStrategy, ResolveOptions,
+    DEFAULT_NAMESERVER, MAX_CACHE_ENTRIES, MAX_CACHE_TTL,
+};
 
 const fn fam_to_raw(fam: Option<SocketDomain>) -> AbiSocketDomain {
     match fam {
@@ -34,6 +43,52 @@ const fn kind_to_raw(kind: Option<SocketKind>) -> AbiSocketKind {
     }
 }
 
+/// Flags given to [`lookup_addr_info`] as part of [`AddrHints`], see [`AddrHints::with_flags`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct AddrHintFlags(u32);
+
+impl AddrHintFlags {
+    pub const NONE: Self = Self(0);
+    /// The node must be a numeric address, no DNS lookup is performed,
+    /// fails with [`LookupError::NoSuchNode`] otherwise (like `AI_NUMERICHOST`).
+    pub const NUMERIC_HOST: Self = Self(1 << 0);
+    /// The service must be a numeric port, no service lookup is performed,
+    /// fails with [`LookupError::NoSuchService`] otherwise (like `AI_NUMERICSERV`).
+    pub const NUMERIC_SERV: Self = Self(1 << 1);
+    /// Also resolve Ipv6 addresses (numeric Ipv6 nodes and AAAA records), returned after the Ipv4 addresses.
+    ///
+    /// The ABI has no Ipv6 sockets yet, so the returned Ipv6 addresses can't be connected to by this crate,
+    /// see [`AddrInfo::is_ipv6`].
+    pub const IPV6: Self = Self(1 << 2);
+
+    /// Returns the raw bits of the flags.
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Constructs flags from raw bits, unknown bits are kept but ignored.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns true if all the flags in `other` are set in `self`.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for AddrHintFlags {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
 /// Address hints given to [`lookup_addr_info`]
 ///
 /// TODO: Docs
@@ -43,7 +98,8 @@ pub struct AddrHints {
     __0: u8,
     kind: AbiSocketKind,
     protocol: u32,
-    __1: u64,
+    flags: AddrHintFlags,
+    __1: u32,
 }
 
 impl AddrHints {
@@ -60,10 +116,24 @@ impl AddrHints {
             __0: 0,
             kind,
             protocol,
+            flags: AddrHintFlags::NONE,
             __1: 0,
         }
     }
 
+    /// Returns the hints with the flags `flags`, see [`AddrHintFlags`].
+    #[inline]
+    pub const fn with_flags(mut self, flags: AddrHintFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Returns the flags of this hint.
+    #[inline]
+    pub const fn flags(&self) -> AddrHintFlags {
+        self.flags
+    }
+
     /// Returns the kind of the socket that this hint accepts
     pub const fn kind(&self) -> Option<SocketKind> {
         match SocketKind::from_raw(self.kind) {
@@ -94,22 +164,32 @@ pub struct AddrInfo {
     protocol: u32,
     __1: u64,
     next: Option<Box<Self>>,
+    /// empty for Ipv6 addresses, which the ABI can't represent yet
     socket_addr_raw: Box<[u8]>,
     canon_name: Option<Box<str>>,
+    addr: core::net::SocketAddr,
 }
 
 impl AddrInfo {
     fn new(
-        fam: Option<SocketDomain>,
         kind: Option<SocketKind>,
         protocol: u32,
-        addr: core::net::SocketAddrV4,
+        addr: core::net::SocketAddr,
         canon_name: Option<String>,
     ) -> Self {
-        let family = fam_to_raw(fam);
         let kind = kind_to_raw(kind);
-        let addr_raw = InetV4SocketAddr::new(addr.port(), *addr.ip());
-        let addr_bytes = addr_raw.as_bytes();
+        let (family, socket_addr_raw) = match addr {
+            core::net::SocketAddr::V4(v4) => {
+                let addr_raw = InetV4SocketAddr::new(v4.port(), *v4.ip());
+                let addr_bytes = addr_raw.as_bytes();
+                (
+                    fam_to_raw(Some(SocketDomain::Ipv4)),
+                    addr_bytes.to_vec().into_boxed_slice(),
+                )
+            }
+            core::net::SocketAddr::V6(_) => (fam_to_raw(None), Box::default()),
+        };
+
         Self {
             family,
             __0: 0,
@@ -117,8 +197,9 @@ impl AddrInfo {
             protocol,
             __1: 0,
             next: None,
-            socket_addr_raw: addr_bytes.to_vec().into_boxed_slice(),
+            socket_addr_raw,
             canon_name: canon_name.map(|s| s.into_boxed_str()),
+            addr,
         }
     }
 
@@ -126,10 +207,6 @@ impl AddrInfo {
         self.next = n;
     }
 
-    fn set_canon(&mut self, canon_name: Option<String>) {
-        self.canon_name = canon_name.map(|s| s.into_boxed_str());
-    }
-
     /// Returns the next [`AddrInfo`] in this linked list
     pub fn next(&self) -> Option<&AddrInfo> {
         self.next.as_ref().map(|n| n.as_ref())
@@ -150,11 +227,24 @@ impl AddrInfo {
         self.next.take().map(|n| *n)
     }
 
-    /// Returns the domain(family) of the socket that uses this address.
+    /// Returns the domain(family) of the socket that uses this address,
+    /// [`None`] for Ipv6 addresses as there is no Ipv6 [`SocketDomain`] yet.
     pub const fn domain(&self) -> Option<SocketDomain> {
         SocketDomain::from_raw(self.family)
     }
 
+    /// Returns the canonical name of the looked up node, the target of its CNAME records if it had any, the same for every node of the list.
+    #[inline]
+    pub fn canon_name(&self) -> Option<&str> {
+        self.canon_name.as_deref()
+    }
+
+    /// Returns true if this is an Ipv6 address, see [`AddrHintFlags::IPV6`].
+    #[inline]
+    pub const fn is_ipv6(&self) -> bool {
+        self.addr.is_ipv6()
+    }
+
     /// Returns the kind of the socket that uses this address
     pub const fn kind(&self) -> Option<SocketKind> {
         match SocketKind::from_raw(self.kind) {
@@ -174,10 +264,16 @@ impl AddrInfo {
         !self.kind.contains(AbiSocketKind::SOCK_NON_BLOCKING)
     }
 
+    /// Returns the address in the ABI's format,
+    /// [`None`] if this is an Ipv6 address, as the ABI has no Ipv6 socket addresses yet, see [`Self::is_ipv6`].
     #[inline]
-    pub const fn socket_addr(&self) -> &SocketAddr {
-        unsafe { &*self.socket_addr_raw.as_ptr().cast::<SocketAddr>() }
+    pub const fn socket_addr(&self) -> Option<&SocketAddr> {
+        if self.socket_addr_raw.is_empty() {
+            return None;
+        }
+        Some(unsafe { &*self.socket_addr_raw.as_ptr().cast::<SocketAddr>() })
     }
+    /// Returns the size of [`Self::socket_addr`], 0 for Ipv6 addresses.
     #[inline]
     pub const fn socket_addr_size(&self) -> usize {
         self.socket_addr_raw.len()
@@ -185,15 +281,96 @@ impl AddrInfo {
 
     #[inline]
     /// Returns the socket addr inside of self as an [`core::net::SocketAddr`] which is possible because we only accept IpV4 and IpV6 family.
-    pub fn ip_socket_addr(&self) -> core::net::SocketAddr {
-        let addr = self.socket_addr();
+    pub const fn ip_socket_addr(&self) -> core::net::SocketAddr {
+        self.addr
+    }
+
+    /// Returns an iterator over the addresses of this node and of the nodes after it, see [`Self::ip_socket_addr`].
+    #[inline]
+    pub fn iter(&self) -> AddrInfoIter<'_> {
+        AddrInfoIter {
+            current: Some(self),
+        }
+    }
+
+    /// Collects the addresses of this node and of the nodes after it in order, see [`Self::iter`].
+    pub fn into_vec(self) -> Vec<core::net::SocketAddr> {
+        self.into_iter().collect()
+    }
+}
+
+/// An iterator over the addresses of a list of [`AddrInfo`], see [`AddrInfo::iter`]
+#[derive(Clone)]
+pub struct AddrInfoIter<'a> {
+    current: Option<&'a AddrInfo>,
+}
+
+impl<'a> Iterator for AddrInfoIter<'a> {
+    type Item = core::net::SocketAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let info = self.current?;
+        self.current = info.next();
+        Some(info.ip_socket_addr())
+    }
+}
+
+impl core::iter::FusedIterator for AddrInfoIter<'_> {}
 
-        addr.as_known::<InetV4SocketAddr>()
-            .map(|k| core::net::SocketAddr::new(IpAddr::V4(k.ip()), k.port()))
-            .expect("AddrInfo family isn't IpV4 or IpV6")
+impl<'a> IntoIterator for &'a AddrInfo {
+    type Item = core::net::SocketAddr;
+    type IntoIter = AddrInfoIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator taking ownership of a list of [`AddrInfo`] and returning its addresses, see [`AddrInfo::into_iter`]
+pub struct AddrInfoIntoIter {
+    current: Option<AddrInfo>,
+}
+
+impl Iterator for AddrInfoIntoIter {
+    type Item = core::net::SocketAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut info = self.current.take()?;
+        // unlinking each node as we go also frees the list without recursing through it
+        self.current = info.take_next();
+        Some(info.ip_socket_addr())
     }
 }
 
+impl core::iter::FusedIterator for AddrInfoIntoIter {}
+
+impl IntoIterator for AddrInfo {
+    type Item = core::net::SocketAddr;
+    type IntoIter = AddrInfoIntoIter;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        AddrInfoIntoIter {
+            current: Some(self),
+        }
+    }
+}
+
+/// Links `infos` into a list in the same order, giving each node the canonical name `canon_name`,
+/// returns the head of the list or [`None`] if `infos` is empty.
+fn link_addr_infos(infos: Vec<AddrInfo>, canon_name: Option<String>) -> Option<AddrInfo> {
+    let canon_name = canon_name.map(String::into_boxed_str);
+
+    let mut head: Option<Box<AddrInfo>> = None;
+    for mut info in infos.into_iter().rev() {
+        info.canon_name = canon_name.clone();
+        info.set_next(head);
+        head = Some(Box::new(info));
+    }
+    head.map(|head| *head)
+}
+
 /// An error during node and service lookup operation
 ///
 /// see [`lookup_addr_info`].
@@ -217,6 +394,8 @@ pub enum LookupError {
     ///
     /// Eg. A DNS Query resolving the node as a domain name didn't return any address nodes.
     NoData,
+    /// The lookup was cancelled using a [`CancellationToken`].
+    Cancelled,
     /// A System Error has occurred.
     System(ErrorStatus),
 }
@@ -228,6 +407,7 @@ impl From<DnsResolutionError> for LookupError {
             DnsResolutionError::NoResponse => Self::TemporaryFailure,
             DnsResolutionError::NoSuchName => Self::NoSuchNode,
             DnsResolutionError::Refused => Self::ServerRefused,
+            DnsResolutionError::Cancelled => Self::Cancelled,
             DnsResolutionError::System(sys) => Self::System(sys),
         }
     }
@@ -238,24 +418,47 @@ impl From<DnsResolutionError> for LookupError {
 /// `node` can be a string indicating a domain name in this case a DNS Resolution would be performed or None for only service lookup or an Ip Address respecting the family.
 /// `service` can be a port number or a string specifying the service (it will be converted to a port number) not really implemented currently.
 ///
-/// `hint` is information and hints about what addresses we should accept see [`AddrHints`], it is currently necessary to figure out the returned protocol and kind.
+/// `hint` is information and hints about what addresses we should accept see [`AddrHints`], it is currently necessary to figure out the returned protocol and kind,
+/// its flags can forbid lookups, see [`AddrHintFlags`].
+///
+/// Numeric nodes and services take a fast path that never touches the network, see [`lookup_numeric`].
 ///
 /// Returns a linked list of [`AddrInfo`] or a [`LookupError`].
+///
+/// DNS lookups use the system resolver configured by [`resolver::RESOLV_CONF_PATH`], see [`resolver::default_resolver`].
 pub fn lookup_addr_info(
     node: Option<&str>,
     service: Option<&str>,
     hint: Option<&AddrHints>,
+) -> Result<AddrInfo, LookupError> {
+    resolver::default_resolver().lookup_addr_info(node, service, hint, None)
+}
+
+/// Like [`lookup_addr_info`] but gives up with [`LookupError::Cancelled`] once `cancel` is cancelled,
+/// which is checked between retries and while waiting for the nameservers.
+pub fn lookup_addr_info_cancellable(
+    node: Option<&str>,
+    service: Option<&str>,
+    hint: Option<&AddrHints>,
+    cancel: &CancellationToken,
+) -> Result<AddrInfo, LookupError> {
+    resolver::default_resolver().lookup_addr_info(node, service, hint, Some(cancel))
+}
+
+/// Like [`lookup_addr_info`] but performs DNS lookups using `options`, without the search domains of a [`resolver::Resolver`],
+/// giving up once `cancel` is cancelled if given, see [`lookup_addr_info_cancellable`].
+pub fn lookup_addr_info_with(
+    node: Option<&str>,
+    service: Option<&str>,
+    hint: Option<&AddrHints>,
+    options: &ResolveOptions,
+    cancel: Option<&CancellationToken>,
 ) -> Result<AddrInfo, LookupError> {
     if node.is_none() && service.is_none() {
         return Err(LookupError::NoSuchNode);
     }
 
-    // TODO: Implement services lookup
-    let service = service
-        .map(|s| s.parse::<u16>())
-        .unwrap_or(Ok(0))
-        .map_err(|_| LookupError::NoSuchService)?;
-
+    let flags = hint.map(|h| h.flags()).unwrap_or_default();
     let protocol = hint.map(|h| h.protocol()).unwrap_or(0);
     let family = hint
         .map(|h| h.domain())
@@ -263,84 +466,181 @@ pub fn lookup_addr_info(
         .unwrap_or(SocketDomain::Ipv4);
 
     let kind = hint.map(|h| h.kind()).flatten();
+    let ipv6 = flags.contains(AddrHintFlags::IPV6);
 
     match family {
         SocketDomain::Ipv4 => {}
-        // TODO: Ipv6
         _ => return Err(LookupError::InvalidFamily),
     }
 
-    match node {
-        None => {
-            // STUB
-            // TODO: service lookup
+    // fast path, numeric hosts are never looked up
+    match lookup_numeric_ip(node, service) {
+        Ok(addr) if addr.is_ipv6() && !ipv6 => return Err(LookupError::InvalidFamily),
+        Ok(addr) => return Ok(AddrInfo::new(kind, protocol, addr, None)),
+        Err(LookupError::NoSuchNode) if !flags.contains(AddrHintFlags::NUMERIC_HOST) => {}
+        Err(err) => return Err(err),
+    }
 
-            Ok(AddrInfo::new(
-                Some(family),
-                kind,
-                protocol,
-                core::net::SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, service),
-                None,
-            ))
-        }
+    let service = parse_service(service)?;
+    let domain = node.expect("lookup_numeric handles lookups without a node");
 
-        Some(domain) => {
-            if let Ok(ip) = domain.parse::<Ipv4Addr>() {
-                // STUB
-                // TODO: service lookup
-                return Ok(AddrInfo::new(
-                    Some(family),
-                    kind,
-                    protocol,
-                    core::net::SocketAddrV4::new(ip, service),
-                    None,
-                ));
-            }
+    let mut infos = Vec::new();
+    let canon = dns::lookup_dns(domain, options, cancel, ipv6, |ip| {
+        infos.push(AddrInfo::new(
+            kind,
+            protocol,
+            core::net::SocketAddr::new(ip, service),
+            None,
+        ));
+    })?;
+
+    link_addr_infos(infos, canon).ok_or(LookupError::NoData)
+}
+
+/// Parses `service` into a port number, [`None`] being port 0.
+fn parse_service(service: Option<&str>) -> Result<u16, LookupError> {
+    // TODO: Implement services lookup
+    service
+        .map(|s| s.parse::<u16>())
+        .unwrap_or(Ok(0))
+        .map_err(|_| LookupError::NoSuchService)
+}
+
+/// Resolves a numeric `node` and `service` without any lookups or allocations,
+/// this is the fast path taken by [`lookup_addr_info`] before attempting any lookup.
+///
+/// A `node` of [`None`] resolves to [`Ipv4Addr::UNSPECIFIED`] and a `service` of [`None`] resolves to port 0.
+///
+/// # Returns
+/// - [`LookupError::NoSuchNode`] if `node` isn't a numeric Ipv4 address, or if both `node` and `service` are [`None`]
+/// - [`LookupError::InvalidFamily`] if `node` is a numeric Ipv6 address, see [`lookup_numeric_ip`]
+/// - [`LookupError::NoSuchService`] if `service` isn't a numeric port
+pub fn lookup_numeric(
+    node: Option<&str>,
+    service: Option<&str>,
+) -> Result<SocketAddrV4, LookupError> {
+    match lookup_numeric_ip(node, service)? {
+        core::net::SocketAddr::V4(addr) => Ok(addr),
+        core::net::SocketAddr::V6(_) => Err(LookupError::InvalidFamily),
+    }
+}
+
+/// Same as [`lookup_numeric`] but also accepts numeric Ipv6 nodes.
+pub fn lookup_numeric_ip(
+    node: Option<&str>,
+    service: Option<&str>,
+) -> Result<core::net::SocketAddr, LookupError> {
+    if node.is_none() && service.is_none() {
+        return Err(LookupError::NoSuchNode);
+    }
+
+    let ip = match node {
+        None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        Some(node) => node
+            .parse::<IpAddr>()
+            .map_err(|_| LookupError::NoSuchNode)?,
+    };
+
+    let port = parse_service(service)?;
+    Ok(core::net::SocketAddr::new(ip, port))
+}
+
+/// Resolves `host` using [`lookup_addr_info`] and connects a stream socket to `port` on the first address that accepts the connection.
+///
+/// Each address is retried on transient errors using the default [`RetryPolicy`],
+/// returns the error of the last attempted address if none of them accept the connection.
+pub fn connect(host: &str, port: u16) -> Result<Socket, LookupError> {
+    connect_inner(host, port, None)
+}
 
-            let mut root = None;
-            let mut tail = None;
-            let canon = dns::lookup_dns(domain, |ip| {
-                let mut inner = AddrInfo::new(
-                    Some(family),
-                    kind,
-                    protocol,
-                    SocketAddrV4::new(ip, service),
-                    None,
-                );
-
-                if root.is_none() {
-                    root = Some(inner);
-                } else {
-                    match core::mem::take(&mut tail) {
-                        None => {}
-                        Some(o) => {
-                            inner.set_next(Some(Box::new(o)));
-                        }
-                    }
-
-                    tail = Some(inner);
-                }
-            })?;
-
-            match root {
-                Some(mut r) => {
-                    r.set_next(tail.map(|t| Box::new(t)));
-                    if let Some(canon) = canon {
-                        if r.next.is_none() {
-                            r.set_canon(Some(canon));
-                        } else {
-                            r.set_canon(Some(canon.clone()));
-
-                            while let Some(n) = r.next.as_mut() {
-                                n.set_canon(Some(canon.clone()));
-                            }
-                        }
-                    }
-
-                    Ok(r)
-                }
-                None => Err(LookupError::NoData),
+/// Like [`connect`] but gives up with [`LookupError::Cancelled`] once `cancel` is cancelled,
+/// which is checked during the lookup and between connection attempts,
+/// a connection attempt that already started isn't interrupted.
+pub fn connect_cancellable(
+    host: &str,
+    port: u16,
+    cancel: &CancellationToken,
+) -> Result<Socket, LookupError> {
+    connect_inner(host, port, Some(cancel))
+}
+
+fn connect_inner(
+    host: &str,
+    port: u16,
+    cancel: Option<&CancellationToken>,
+) -> Result<Socket, LookupError> {
+    let hints = AddrHints::new(Some(SocketKind::Stream), Some(SocketDomain::Ipv4), 0);
+    let info =
+        resolver::default_resolver().lookup_addr_info(Some(host), None, Some(&hints), cancel)?;
+
+    let mut last_err = LookupError::NoData;
+    for addr in &info {
+        let addr = match addr {
+            core::net::SocketAddr::V4(addr) => SocketAddrV4::new(*addr.ip(), port),
+            core::net::SocketAddr::V6(_) => unreachable!("only Ipv4 addresses were requested"),
+        };
+
+        // a failed connection attempt may leave the socket unusable, so each attempt uses a new one
+        let attempt = |_| {
+            let socket = Socket::builder(SocketDomain::Ipv4, SocketKind::Stream, 0).build()?;
+            socket.connect_to_addr(addr)?;
+            Ok(socket)
+        };
+        let results = match cancel {
+            Some(cancel) => util::retry_cancellable(&RetryPolicy::new(), cancel, attempt),
+            None => util::retry(&RetryPolicy::new(), attempt),
+        };
+
+        match results {
+            Ok(socket) => return Ok(socket),
+            Err(_) if cancel.is_some_and(CancellationToken::is_cancelled) => {
+                return Err(LookupError::Cancelled)
             }
+            Err(err) => last_err = LookupError::System(err),
+        }
+    }
+
+    Err(last_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::{Ipv6Addr, SocketAddr};
+
+    fn info(addr: SocketAddr) -> AddrInfo {
+        AddrInfo::new(Some(SocketKind::Stream), 0, addr, None)
+    }
+
+    #[test]
+    fn linked_list_keeps_order_and_canonical_name() {
+        // A records are looked up first, then AAAA records, see `dns::lookup_dns`
+        let addrs = [
+            SocketAddr::new(Ipv4Addr::new(192, 0, 2, 1).into(), 80),
+            SocketAddr::new(Ipv4Addr::new(192, 0, 2, 2).into(), 80),
+            SocketAddr::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(), 80),
+        ];
+
+        let head = link_addr_infos(
+            addrs.iter().copied().map(info).collect(),
+            Some(String::from("node1.cdn.example.org")),
+        )
+        .expect("the list isn't empty");
+
+        let mut nodes = 0;
+        let mut node = Some(&head);
+        while let Some(current) = node {
+            assert_eq!(current.ip_socket_addr(), addrs[nodes]);
+            assert_eq!(current.canon_name(), Some("node1.cdn.example.org"));
+            assert_eq!(current.socket_addr().is_none(), current.is_ipv6());
+            nodes += 1;
+            node = current.next();
         }
+        assert_eq!(nodes, addrs.len());
+    }
+
+    #[test]
+    fn linking_nothing_gives_no_list() {
+        assert!(link_addr_infos(Vec::new(), Some(String::from("example.com"))).is_none());
     }
 }
@@ -0,0 +1,223 @@
+//! Reusing client connections across requests, see [`ConnectionPool`]
+
+use core::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{string::String, vec::Vec};
+use safa_abi::poll::{PollEntry, PollEvents};
+
+use crate::{sockets::Socket, sync::locks::Mutex, syscalls, time::Instant};
+
+use super::LookupError;
+
+/// The default idle timeout of a [`ConnectionPool`]
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct IdleConnection {
+    host: String,
+    port: u16,
+    socket: Socket,
+    idle_since: Instant,
+}
+
+/// A pool of idle client stream connections keyed by `(host, port)`,
+/// so that repeated requests to the same service reuse a connection instead of resolving and connecting again.
+///
+/// Connections are taken from the pool using [`ConnectionPool::get`] and go back to it when the returned [`PooledConnection`] is dropped.
+/// Idle connections are dropped once they have been idle for longer than the idle timeout,
+/// and are checked using a non-blocking poll before being reused,
+/// connections that were closed by the peer or have unexpected data pending are dropped instead.
+#[derive(Debug)]
+pub struct ConnectionPool {
+    idle: Mutex<Vec<IdleConnection>>,
+    max_idle: usize,
+    idle_timeout: Duration,
+}
+
+impl ConnectionPool {
+    /// Constructs a new empty pool keeping at most `max_idle` idle connections (across all keys),
+    /// with an idle timeout of [`DEFAULT_IDLE_TIMEOUT`].
+    pub const fn new(max_idle: usize) -> Self {
+        Self::with_idle_timeout(max_idle, DEFAULT_IDLE_TIMEOUT)
+    }
+
+    /// Constructs a new empty pool keeping at most `max_idle` idle connections (across all keys),
+    /// each for at most `idle_timeout`.
+    pub const fn with_idle_timeout(max_idle: usize, idle_timeout: Duration) -> Self {
+        Self {
+            idle: Mutex::new(Vec::new()),
+            max_idle,
+            idle_timeout,
+        }
+    }
+
+    /// The maximum amount of idle connections kept by the pool.
+    #[inline]
+    pub const fn max_idle(&self) -> usize {
+        self.max_idle
+    }
+
+    /// How long a connection may stay idle in the pool before being dropped.
+    #[inline]
+    pub const fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Returns the amount of idle connections currently in the pool, including the expired ones that weren't dropped yet.
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().len()
+    }
+
+    /// Drops all the idle connections in the pool.
+    pub fn clear(&self) {
+        // the sockets are closed outside of the lock
+        let idle = core::mem::take(&mut *self.idle.lock());
+        drop(idle);
+    }
+
+    /// Drops the idle connections that have been idle for longer than the idle timeout.
+    pub fn evict_expired(&self) {
+        let mut expired = Vec::new();
+        {
+            let mut idle = self.idle.lock();
+            let mut i = 0;
+            while i < idle.len() {
+                if idle[i].idle_since.elapsed() > self.idle_timeout {
+                    expired.push(idle.swap_remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        drop(expired);
+    }
+
+    /// Takes the most recently used healthy idle connection to `host`:`port` from the pool.
+    fn take_idle(&self, host: &str, port: u16) -> Option<Socket> {
+        self.evict_expired();
+
+        loop {
+            let connection = {
+                let mut idle = self.idle.lock();
+                let index = idle
+                    .iter()
+                    .rposition(|conn| conn.port == port && conn.host == host)?;
+                idle.remove(index)
+            };
+
+            if is_healthy(&connection.socket) {
+                return Some(connection.socket);
+            }
+        }
+    }
+
+    /// Gets a connection to `host`:`port`, reusing an idle one from the pool if possible,
+    /// otherwise connecting a new one using [`super::connect`].
+    pub fn get(&self, host: &str, port: u16) -> Result<PooledConnection<'_>, LookupError> {
+        let socket = match self.take_idle(host, port) {
+            Some(socket) => socket,
+            None => super::connect(host, port)?,
+        };
+
+        Ok(PooledConnection {
+            pool: self,
+            host: String::from(host),
+            port,
+            socket: Some(socket),
+        })
+    }
+
+    fn put(&self, host: String, port: u16, socket: Socket) {
+        let mut idle = self.idle.lock();
+        if idle.len() >= self.max_idle {
+            drop(idle);
+            drop(socket);
+            return;
+        }
+
+        idle.push(IdleConnection {
+            host,
+            port,
+            socket,
+            idle_since: Instant::now(),
+        });
+    }
+}
+
+/// Checks whether an idle connection can be reused,
+/// an idle connection should have nothing to read, anything readable is either the peer closing it or stale data.
+fn is_healthy(socket: &Socket) -> bool {
+    let mut entries = [PollEntry::new(
+        socket.ri(),
+        PollEvents::DATA_AVAILABLE | PollEvents::DISCONNECTED,
+    )];
+
+    // the zero timeout running out returns no events, which is the idle and healthy case,
+    // only an actual error means that the connection is dead
+    match syscalls::io::poll_resources(&mut entries, Some(Duration::ZERO)) {
+        Ok(()) => entries[0].returned_events() == PollEvents::NONE,
+        Err(_) => false,
+    }
+}
+
+/// A connection taken from a [`ConnectionPool`], dereferences to the [`Socket`] of the connection.
+///
+/// The connection goes back to the pool when dropped, use [`PooledConnection::discard`] for connections
+/// that shouldn't be reused, for example after an error or when the protocol state is unknown.
+#[derive(Debug)]
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    host: String,
+    port: u16,
+    socket: Option<Socket>,
+}
+
+impl<'a> PooledConnection<'a> {
+    /// Drops the connection instead of returning it to the pool.
+    pub fn discard(mut self) {
+        self.socket = None;
+    }
+
+    /// Takes the connection out of the pool's management, it won't be returned to the pool.
+    pub fn detach(mut self) -> Socket {
+        self.socket
+            .take()
+            .expect("the socket is only taken on drop")
+    }
+}
+
+impl<'a> Deref for PooledConnection<'a> {
+    type Target = Socket;
+
+    fn deref(&self) -> &Self::Target {
+        self.socket
+            .as_ref()
+            .expect("the socket is only taken on drop")
+    }
+}
+
+impl<'a> DerefMut for PooledConnection<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.socket
+            .as_mut()
+            .expect("the socket is only taken on drop")
+    }
+}
+
+impl<'a> Drop for PooledConnection<'a> {
+    fn drop(&mut self) {
+        if let Some(socket) = self.socket.take() {
+            self.pool
+                .put(core::mem::take(&mut self.host), self.port, socket);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Configurable DNS resolution, see [`Resolver`]
+//!
+//! The system resolver is configured by [`RESOLV_CONF_PATH`], a [configuration file](crate::config) such as:
+//! ```ini
+//! # queried in order, the port defaults to 53
+//! nameserver = 10.0.0.1
+//! nameserver = 1.1.1.1:53
+//! # names without a dot are tried with each of these appended first
+//! search = home.lan corp.example
+//! timeout_ms = 300
+//! attempts = 4
+//! # sequential, rotate or parallel, see `NameserverStrategy`
+//! strategy = rotate
+//! ```
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+use core::{
+    net::{Ipv4Addr, SocketAddrV4},
+    time::Duration,
+};
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+
+use super::{AddrHints, AddrInfo, LookupError, NameserverStrategy, ResolveOptions};
+use crate::{
+    config::{self, Config},
+    debug::debug_log,
+    sync::{locks::Mutex, CancellationToken},
+};
+
+/// The configuration file the system resolver is loaded from, see [`default_resolver`]
+pub const RESOLV_CONF_PATH: &str = "sys:/etc/resolv.conf";
+/// The port nameservers listen on unless a configuration says otherwise
+pub const DNS_PORT: u16 = 53;
+
+/// An error that occurred while loading a resolver configuration, see [`Resolver::load`]
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigError {
+    Load(config::LoadError),
+    /// The value of the key isn't valid
+    Invalid(&'static str),
+}
+
+impl From<config::LoadError> for ConfigError {
+    fn from(value: config::LoadError) -> Self {
+        Self::Load(value)
+    }
+}
+
+/// Parses a nameserver address, the port is optional.
+fn parse_nameserver(value: &str) -> Option<SocketAddrV4> {
+    value
+        .parse::<SocketAddrV4>()
+        .ok()
+        .or_else(|| Some(SocketAddrV4::new(value.parse::<Ipv4Addr>().ok()?, DNS_PORT)))
+}
+
+/// Resolves names using a set of nameservers and search domains
+///
+/// Use [`default_resolver`] to get the system resolver, or build one with [`Resolver::new`] to override the system configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    options: ResolveOptions,
+    search: Vec<String>,
+}
+
+impl Resolver {
+    /// Constructs a resolver with the default [`ResolveOptions`] and no search domains.
+    pub const fn new() -> Self {
+        Self {
+            options: ResolveOptions::new(),
+            search: Vec::new(),
+        }
+    }
+
+    /// Constructs a resolver using `options` and no search domains.
+    pub const fn with_options(options: ResolveOptions) -> Self {
+        Self {
+            options,
+            search: Vec::new(),
+        }
+    }
+
+    /// Constructs a resolver from a parsed configuration, see the [module documentation](self) for the keys,
+    /// unknown keys are ignored.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut this = Self::new();
+        for (key, value) in config.section("") {
+            match key {
+                "nameserver" => {
+                    let nameserver =
+                        parse_nameserver(value).ok_or(ConfigError::Invalid("nameserver"))?;
+                    this.options.add_nameserver(nameserver);
+                }
+                "search" => {
+                    for domain in value.split([' ', '\t', ',']).filter(|d| !d.is_empty()) {
+                        this.add_search_domain(domain);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match config.get_u64("", "timeout_ms") {
+            Ok(ms) => _ = this.options.set_timeout(Duration::from_millis(ms)),
+            Err(config::LookupError::Missing) => {}
+            Err(config::LookupError::Invalid) => return Err(ConfigError::Invalid("timeout_ms")),
+        }
+
+        match config.get_u64("", "attempts") {
+            Ok(attempts) => _ = this.options.set_attempts(attempts as usize),
+            Err(config::LookupError::Missing) => {}
+            Err(config::LookupError::Invalid) => return Err(ConfigError::Invalid("attempts")),
+        }
+
+        if let Some(strategy) = config.get_str("", "strategy") {
+            let strategy = match strategy {
+                "sequential" => NameserverStrategy::Sequential,
+                "rotate" => NameserverStrategy::Rotate,
+                "parallel" => NameserverStrategy::Parallel,
+                _ => return Err(ConfigError::Invalid("strategy")),
+            };
+            this.options.set_strategy(strategy);
+        }
+
+        Ok(this)
+    }
+
+    /// Reads and parses the resolver configuration at `path`, see [`Self::from_config`].
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        Self::from_config(&Config::load(path)?)
+    }
+
+    /// Loads the system configuration from [`RESOLV_CONF_PATH`],
+    /// falling back to [`Self::new`] if it doesn't exist or is invalid.
+    pub fn system() -> Self {
+        Self::load(RESOLV_CONF_PATH)
+            .inspect_err(|_| debug_log!(DNS, "using the defaults, {RESOLV_CONF_PATH} is unusable"))
+            .unwrap_or_default()
+    }
+
+    /// The options used for each DNS lookup.
+    #[inline]
+    pub const fn options(&self) -> &ResolveOptions {
+        &self.options
+    }
+
+    /// The options used for each DNS lookup, for example to add nameservers.
+    #[inline]
+    pub const fn options_mut(&mut self) -> &mut ResolveOptions {
+        &mut self.options
+    }
+
+    /// The domains appended to names without a dot, in the order they are tried.
+    #[inline]
+    pub fn search_domains(&self) -> &[String] {
+        &self.search
+    }
+
+    /// Adds `domain` to the end of the search domains, see [`Self::search_domains`].
+    pub fn add_search_domain(&mut self, domain: &str) -> &mut Self {
+        let domain = domain.trim_matches('.');
+        if !domain.is_empty() {
+            self.search.push(String::from(domain));
+        }
+        self
+    }
+
+    /// The names tried when looking up `node` in order:
+    /// names ending with a dot are only tried as is, names with a dot are tried as is then with each search domain,
+    /// and names without a dot are tried with each search domain then as is.
+    fn candidates(&self, node: &str) -> Vec<String> {
+        if let Some(absolute) = node.strip_suffix('.') {
+            return alloc::vec![String::from(absolute)];
+        }
+
+        let searched = self.search.iter().map(|domain| format!("{node}.{domain}"));
+        if node.contains('.') {
+            core::iter::once(String::from(node))
+                .chain(searched)
+                .collect()
+        } else {
+            searched
+                .chain(core::iter::once(String::from(node)))
+                .collect()
+        }
+    }
+
+    /// Same as [`super::lookup_addr_info`] but uses the nameservers and search domains of this resolver.
+    ///
+    /// Each candidate name (see [`Self::search_domains`]) is looked up until one resolves,
+    /// names that don't exist or have no addresses move on to the next candidate, other errors are returned immediately.
+    ///
+    /// Gives up with [`LookupError::Cancelled`] once `cancel` is cancelled if given, see [`super::lookup_addr_info_cancellable`].
+    pub fn lookup_addr_info(
+        &self,
+        node: Option<&str>,
+        service: Option<&str>,
+        hint: Option<&AddrHints>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<AddrInfo, LookupError> {
+        let Some(node) = node else {
+            return super::lookup_addr_info_with(None, service, hint, &self.options, cancel);
+        };
+
+        // numeric hosts are never searched
+        if node.parse::<core::net::IpAddr>().is_ok() || self.search.is_empty() {
+            return super::lookup_addr_info_with(Some(node), service, hint, &self.options, cancel);
+        }
+
+        let mut last_err = LookupError::NoSuchNode;
+        for name in self.candidates(node) {
+            match super::lookup_addr_info_with(Some(&name), service, hint, &self.options, cancel) {
+                Err(err @ (LookupError::NoSuchNode | LookupError::NoData)) => {
+                    debug_log!(DNS, "{name}: not found, trying the next search domain");
+                    last_err = err;
+                }
+                results => return results,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+/// The system resolver, loaded on first use
+static DEFAULT: Mutex<Option<Arc<Resolver>>> = Mutex::new(None);
+
+/// Returns the resolver used by [`super::lookup_addr_info`],
+/// loaded from [`RESOLV_CONF_PATH`] on first use unless replaced with [`set_default_resolver`].
+pub fn default_resolver() -> Arc<Resolver> {
+    // the lock isn't held while loading, a racing thread at worst loads the same configuration twice
+    if let Some(resolver) = DEFAULT.lock().as_ref() {
+        return resolver.clone();
+    }
+
+    let resolver = Arc::new(Resolver::system());
+    DEFAULT.lock().get_or_insert(resolver).clone()
+}
+
+/// Replaces the resolver used by [`super::lookup_addr_info`] for the rest of the process, lookups in progress are unaffected.
+pub fn set_default_resolver(resolver: Resolver) {
+    *DEFAULT.lock() = Some(Arc::new(resolver));
+}
+
+/// Reloads the system resolver from [`RESOLV_CONF_PATH`], for example after the configuration changed,
+/// discarding any resolver set with [`set_default_resolver`].
+pub fn reload_default_resolver() {
+    set_default_resolver(Resolver::system());
+}
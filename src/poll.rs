@@ -0,0 +1,529 @@
+//! Waiting for multiple resources to become ready, see [`Poller`]
+
+use core::{ops::ControlFlow, time::Duration};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use safa_abi::{
+    errors::ErrorStatus,
+    poll::{PollEntry, PollEvents},
+};
+
+use crate::{
+    sockets::{Socket, UnixListenerBuilder, UnixSockConnectionBuilder},
+    syscalls::{self, types::Ri},
+};
+
+/// The token of the events returned when a [`Waker`] wakes up a [`Poller`], see [`Poller::waker`]
+pub const WAKE_TOKEN: Token = Token(usize::MAX);
+
+/// A caller-chosen value associated with a resource registered in a [`Poller`], returned with its events,
+/// for example an index into the caller's own table of connections, see [`Poller::register_with_token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(pub usize);
+
+/// A readiness event returned by a [`Poller`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    ri: Ri,
+    token: Token,
+    events: PollEvents,
+}
+
+impl Event {
+    /// The resource the event occurred on.
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.ri
+    }
+
+    /// The token the resource was registered with, see [`Poller::register_with_token`].
+    #[inline]
+    pub const fn token(&self) -> Token {
+        self.token
+    }
+
+    /// The events that occurred, only the registered ones and [`PollEvents::DISCONNECTED`] are reported.
+    #[inline]
+    pub const fn events(&self) -> PollEvents {
+        self.events
+    }
+
+    /// Whether or not data is available to read.
+    #[inline]
+    pub const fn is_readable(&self) -> bool {
+        self.events.contains(PollEvents::DATA_AVAILABLE)
+    }
+
+    /// Whether or not the resource can be written to.
+    #[inline]
+    pub const fn is_writable(&self) -> bool {
+        self.events.contains(PollEvents::CAN_WRITE)
+    }
+
+    /// Whether or not the other end of the resource disconnected.
+    #[inline]
+    pub const fn is_disconnected(&self) -> bool {
+        self.events.contains(PollEvents::DISCONNECTED)
+    }
+}
+
+/// How the events of a resource registered in a [`Poller`] are reported, see [`Poller::register_with_mode`]
+///
+/// The kernel only supports level-triggered polling, the other modes are emulated by not polling for the events
+/// that were already reported, until the caller rearms the resource using [`Poller::rearm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PollMode {
+    /// Events are reported on every wait for as long as the resource is ready
+    #[default]
+    Level,
+    /// Each kind of event is reported once, and not again until [`Poller::rearm`] is called,
+    /// which should be done once the resource was drained (for example after a read fails with [`ErrorStatus::WouldBlock`])
+    Edge,
+    /// The resource is disarmed after its first event, until [`Poller::rearm`] or [`Poller::modify`] is called
+    Oneshot,
+}
+
+/// The events that can be masked by [`PollMode::Edge`] and [`PollMode::Oneshot`]
+const MASKABLE_EVENTS: [PollEvents; 3] = [
+    PollEvents::DATA_AVAILABLE,
+    PollEvents::CAN_WRITE,
+    PollEvents::DISCONNECTED,
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Registration {
+    ri: Ri,
+    token: Token,
+    mode: PollMode,
+    events: PollEvents,
+    /// the reported events that aren't polled for until the resource is rearmed
+    masked: PollEvents,
+}
+
+impl Registration {
+    /// The events to poll the resource for, [`None`] if it shouldn't be polled at all.
+    fn armed_events(&self) -> Option<PollEvents> {
+        if self.masked == PollEvents::NONE {
+            return Some(self.events);
+        }
+        // the kernel reports disconnections regardless of the polled events
+        if self.masked.contains(PollEvents::DISCONNECTED) {
+            return None;
+        }
+
+        let armed = MASKABLE_EVENTS
+            .into_iter()
+            .filter(|event| self.events.contains(*event) && !self.masked.contains(*event))
+            .fold(PollEvents::NONE, |armed, event| armed | event);
+        (armed != PollEvents::NONE).then_some(armed)
+    }
+
+    /// Masks the reported events `returned` according to the mode.
+    fn report(&mut self, returned: PollEvents) {
+        match self.mode {
+            PollMode::Level => {}
+            PollMode::Edge => self.masked = self.masked | returned,
+            PollMode::Oneshot => self.masked = self.events | PollEvents::DISCONNECTED,
+        }
+    }
+}
+
+fn position(registrations: &[Registration], ri: Ri) -> Option<usize> {
+    registrations
+        .iter()
+        .position(|registration| registration.ri == ri)
+}
+
+/// Wakes up a thread blocked waiting on a [`Poller`] from any other thread, see [`Poller::waker`]
+#[derive(Debug, Clone)]
+pub struct Waker {
+    sender: Arc<Socket>,
+}
+
+impl Waker {
+    /// Wakes up the [`Poller`] this waker belongs to, making its current or next wait return an event with the token [`WAKE_TOKEN`].
+    ///
+    /// Multiple wakes before the Poller waits again are coalesced into a single event.
+    pub fn wake(&self) -> Result<(), ErrorStatus> {
+        match self.sender.write(&[1]) {
+            // the pending wakes are enough to wake up the poller
+            Ok(_) | Err(ErrorStatus::WouldBlock) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Creates a connected pair of non-blocking unix sockets, returned as `(receiver, sender)`.
+fn wake_pair() -> Result<(Socket, Socket), ErrorStatus> {
+    let listener = UnixListenerBuilder::autobind().bind()?;
+    let sender = UnixSockConnectionBuilder::from_abstract_path(listener.local_addr())
+        .map_err(|()| ErrorStatus::StrTooLong)?
+        .connect()?
+        .into_socket();
+    let receiver = listener.accept()?.into_socket();
+
+    sender.set_blocking(false)?;
+    receiver.set_blocking(false)?;
+    Ok((receiver, sender))
+}
+
+/// A set of resources registered for readiness events, a higher level wrapper around [`syscalls::io::poll_resources`].
+///
+/// Events are either waited on in batches using [`Poller::wait`], iterated over using [`Poller::events`],
+/// or dispatched to handlers using [`Poller::dispatch`].
+#[derive(Debug, Default)]
+pub struct Poller {
+    registrations: Vec<Registration>,
+    /// the entries given to the kernel, rebuilt from the armed registrations on each wait
+    entries: Vec<PollEntry>,
+    /// the index in `registrations` of each of `entries`
+    polled: Vec<usize>,
+    /// the receiving end of the waker and the waker, see [`Poller::waker`]
+    waker: Option<(Socket, Waker)>,
+}
+
+impl Poller {
+    /// Constructs a new Poller without any registered resources.
+    pub const fn new() -> Self {
+        Self {
+            registrations: Vec::new(),
+            entries: Vec::new(),
+            polled: Vec::new(),
+            waker: None,
+        }
+    }
+
+    /// Returns a [`Waker`] that wakes up this Poller from any thread, for example to make it pick up new registrations,
+    /// the returned events have the token [`WAKE_TOKEN`] and are otherwise handled internally.
+    ///
+    /// The kernel doesn't have event resources, so the first call creates a pair of connected unix sockets
+    /// and registers the receiving one, the following calls return the same waker.
+    pub fn waker(&mut self) -> Result<Waker, ErrorStatus> {
+        if let Some((_, waker)) = &self.waker {
+            return Ok(waker.clone());
+        }
+
+        let (receiver, sender) = wake_pair()?;
+        self.register_with_token(receiver.ri(), PollEvents::DATA_AVAILABLE, WAKE_TOKEN)?;
+
+        let waker = Waker {
+            sender: Arc::new(sender),
+        };
+        self.waker = Some((receiver, waker.clone()));
+        Ok(waker)
+    }
+
+    /// Consumes the pending wakes, so that the waker doesn't keep waking up the Poller.
+    fn drain_wakes(&self) {
+        if let Some((receiver, _)) = &self.waker {
+            let mut buf = [0u8; 64];
+            while let Ok(1..) = receiver.read(&mut buf) {}
+        }
+    }
+
+    fn position(&self, ri: Ri) -> Option<usize> {
+        position(&self.registrations, ri)
+    }
+
+    /// Registers the resource `ri` for the events `events`, with the resource ID as its token.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::AlreadyExists`] if `ri` is already registered, use [`Poller::modify`] instead
+    #[inline]
+    pub fn register(&mut self, ri: Ri, events: PollEvents) -> Result<(), ErrorStatus> {
+        self.register_with_token(ri, events, Token(ri as usize))
+    }
+
+    /// Registers the resource `ri` for the events `events`, its events are returned with `token`, see [`Event::token`].
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::AlreadyExists`] if `ri` is already registered, use [`Poller::modify`] instead
+    #[inline]
+    pub fn register_with_token(
+        &mut self,
+        ri: Ri,
+        events: PollEvents,
+        token: Token,
+    ) -> Result<(), ErrorStatus> {
+        self.register_with_mode(ri, events, token, PollMode::Level)
+    }
+
+    /// Registers the resource `ri` for the events `events` reported according to `mode`, with the token `token`.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::AlreadyExists`] if `ri` is already registered, use [`Poller::modify`] instead
+    pub fn register_with_mode(
+        &mut self,
+        ri: Ri,
+        events: PollEvents,
+        token: Token,
+        mode: PollMode,
+    ) -> Result<(), ErrorStatus> {
+        if self.position(ri).is_some() {
+            return Err(ErrorStatus::AlreadyExists);
+        }
+
+        self.registrations.push(Registration {
+            ri,
+            token,
+            mode,
+            events,
+            masked: PollEvents::NONE,
+        });
+        Ok(())
+    }
+
+    /// Returns the token the resource `ri` was registered with, [`None`] if it isn't registered.
+    pub fn token(&self, ri: Ri) -> Option<Token> {
+        self.position(ri)
+            .map(|index| self.registrations[index].token)
+    }
+
+    /// Changes the token of the resource `ri` to `token`.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `ri` isn't registered
+    pub fn set_token(&mut self, ri: Ri, token: Token) -> Result<(), ErrorStatus> {
+        let index = self.position(ri).ok_or(ErrorStatus::InvalidArgument)?;
+        self.registrations[index].token = token;
+        Ok(())
+    }
+
+    /// Changes the events the resource `ri` is registered for to `events`, keeping its token and mode, and rearms it.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `ri` isn't registered
+    pub fn modify(&mut self, ri: Ri, events: PollEvents) -> Result<(), ErrorStatus> {
+        let index = self.position(ri).ok_or(ErrorStatus::InvalidArgument)?;
+        let registration = &mut self.registrations[index];
+        registration.events = events;
+        registration.masked = PollEvents::NONE;
+        Ok(())
+    }
+
+    /// Polls the resource `ri` for all of its registered events again, see [`PollMode`].
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `ri` isn't registered
+    pub fn rearm(&mut self, ri: Ri) -> Result<(), ErrorStatus> {
+        let index = self.position(ri).ok_or(ErrorStatus::InvalidArgument)?;
+        self.registrations[index].masked = PollEvents::NONE;
+        Ok(())
+    }
+
+    /// Removes the resource `ri` from the Poller, returns whether or not it was registered.
+    pub fn deregister(&mut self, ri: Ri) -> bool {
+        match self.position(ri) {
+            Some(index) => {
+                self.registrations.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The amount of registered resources, including disarmed ones.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.registrations.len()
+    }
+
+    /// Whether or not there are no registered resources.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+
+    /// Blocks until any of the registered resources is ready or until `timeout` passes (forever if [`None`]),
+    /// replacing the contents of `events` with the events that occurred, returns the amount of events.
+    ///
+    /// Returns 0 if the timeout passed without any events, a Poller without any armed resources just sleeps.
+    pub fn wait(
+        &mut self,
+        events: &mut Vec<Event>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, ErrorStatus> {
+        events.clear();
+        self.entries.clear();
+        self.polled.clear();
+
+        for (index, registration) in self.registrations.iter().enumerate() {
+            if let Some(armed) = registration.armed_events() {
+                self.entries.push(PollEntry::new(registration.ri, armed));
+                self.polled.push(index);
+            }
+        }
+
+        syscalls::io::poll_resources(&mut self.entries, timeout)?;
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let returned = entry.returned_events();
+            if returned == PollEvents::NONE {
+                continue;
+            }
+
+            // falls back to searching in case the kernel didn't return the entries in the order they were given
+            let index = match self.polled.get(i) {
+                Some(&index) if self.registrations[index].ri == entry.resource() => index,
+                _ => match position(&self.registrations, entry.resource()) {
+                    Some(index) => index,
+                    None => continue,
+                },
+            };
+
+            let registration = &mut self.registrations[index];
+            registration.report(returned);
+            events.push(Event {
+                ri: registration.ri,
+                token: registration.token,
+                events: returned,
+            });
+        }
+
+        let waker_ri = self.waker.as_ref().map(|(receiver, _)| receiver.ri());
+        if events.iter().any(|event| Some(event.ri) == waker_ri) {
+            self.drain_wakes();
+        }
+        Ok(events.len())
+    }
+
+    /// Returns a blocking iterator over the events of the registered resources,
+    /// each call to [`Iterator::next`] returns a buffered event or blocks until more events occur.
+    ///
+    /// The iterator only ends when waiting fails, yielding the error, or if there are no registered resources.
+    pub fn events(&mut self) -> Events<'_> {
+        Events {
+            poller: self,
+            buffer: Vec::new(),
+            next: 0,
+            failed: false,
+        }
+    }
+
+    /// Blocks on the registered resources and calls the handler registered in `handlers` for the resource of each event,
+    /// until a handler returns [`ControlFlow::Break`], returning the value it broke with.
+    ///
+    /// Handlers are given the Poller and the handlers so that they can register and deregister resources and their handlers,
+    /// for example to handle accepted connections, events of resources without a handler are ignored.
+    pub fn dispatch<B>(&mut self, handlers: &mut Handlers<'_, B>) -> Result<B, ErrorStatus> {
+        let mut events = Vec::new();
+        loop {
+            self.wait(&mut events, None)?;
+
+            for event in &events {
+                if let ControlFlow::Break(value) = handlers.call(self, *event) {
+                    return Ok(value);
+                }
+            }
+        }
+    }
+}
+
+/// A blocking iterator over the events of a [`Poller`], see [`Poller::events`]
+#[derive(Debug)]
+pub struct Events<'a> {
+    poller: &'a mut Poller,
+    buffer: Vec<Event>,
+    next: usize,
+    failed: bool,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event, ErrorStatus>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.buffer.get(self.next) {
+                self.next += 1;
+                return Some(Ok(*event));
+            }
+
+            if self.failed || self.poller.is_empty() {
+                return None;
+            }
+
+            self.next = 0;
+            if let Err(err) = self.poller.wait(&mut self.buffer, None) {
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// A handler called by [`Poller::dispatch`]
+pub type Handler<'a, B> =
+    Box<dyn FnMut(&mut Poller, &mut Handlers<'a, B>, Event) -> ControlFlow<B> + 'a>;
+
+/// Maps resources to the handlers called by [`Poller::dispatch`] for their events
+pub struct Handlers<'a, B> {
+    /// the handler is taken out while it is being called
+    handlers: Vec<(Ri, Option<Handler<'a, B>>)>,
+}
+
+impl<'a, B> Handlers<'a, B> {
+    /// Constructs a new empty set of handlers.
+    pub const fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Sets the handler called for the events of the resource `ri`, replacing any previous handler.
+    pub fn on(
+        &mut self,
+        ri: Ri,
+        handler: impl FnMut(&mut Poller, &mut Handlers<'a, B>, Event) -> ControlFlow<B> + 'a,
+    ) -> &mut Self {
+        self.remove(ri);
+        self.handlers.push((ri, Some(Box::new(handler))));
+        self
+    }
+
+    /// Removes the handler of the resource `ri`, returns whether or not there was one.
+    pub fn remove(&mut self, ri: Ri) -> bool {
+        match self.position(ri) {
+            Some(index) => {
+                self.handlers.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn position(&self, ri: Ri) -> Option<usize> {
+        self.handlers.iter().position(|(r, _)| *r == ri)
+    }
+
+    fn call(&mut self, poller: &mut Poller, event: Event) -> ControlFlow<B> {
+        let Some(index) = self.position(event.ri) else {
+            return ControlFlow::Continue(());
+        };
+        let Some(mut handler) = self.handlers[index].1.take() else {
+            return ControlFlow::Continue(());
+        };
+
+        let results = handler(poller, self, event);
+
+        // the handler might have removed or replaced itself
+        if let Some(index) = self.position(event.ri) {
+            let slot = &mut self.handlers[index].1;
+            if slot.is_none() {
+                *slot = Some(handler);
+            }
+        }
+        results
+    }
+}
+
+impl<'a, B> Default for Handlers<'a, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
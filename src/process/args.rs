@@ -14,6 +14,7 @@ pub(super) struct RawArgs {
 }
 
 impl RawArgs {
+    #[cfg(not(feature = "std"))]
     pub const fn new(args: Option<NonNull<[&'static str]>>) -> Self {
         Self {
             args: match args {
@@ -44,6 +45,7 @@ impl RawArgsStatic {
         Self(UnsafeCell::new(MaybeUninit::uninit()))
     }
 
+    #[cfg(not(feature = "std"))]
     pub unsafe fn init(&self, args: RawArgs) {
         unsafe {
             self.0.get().write(MaybeUninit::new(args));
@@ -84,7 +86,7 @@ exported_func! {
 exported_func! {
     /// Get the argument at the given index.
     pub extern "C" fn sysget_arg(index: usize) -> OptZero<Str> {
-        unsafe { SAAPI_RAW_ARGS.get(index).map(|s| Str::from_str(s)).into() }
+        unsafe { SAAPI_RAW_ARGS.get(index).map(Str::from_str).into() }
     }
 }
 
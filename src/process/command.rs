@@ -0,0 +1,700 @@
+//! A builder for spawning child processes, see [`Command`]
+
+use core::{fmt::Display, num::NonZero, time::Duration};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use safa_abi::{
+    errors::ErrorStatus,
+    ffi::{num::ShouldNotBeZero, option::OptZero, slice::Slice, str::Str},
+    process::{ContextPriority, SpawnFlags},
+};
+
+use crate::{
+    debug::debug_log,
+    limits::{MAX_NAME_LENGTH, MAX_PATH_LENGTH},
+    process::{
+        env::{self, DuplicatedEnv},
+        pipe::{self, PipeReader, PipeWriter},
+        Limits,
+    },
+    resource::Resource,
+    syscalls::{
+        self,
+        types::{Pid, Ri},
+    },
+    tty::VttyPair,
+};
+
+pub use crate::limits::{MAX_ARGS_SIZE, MAX_ARG_COUNT, MAX_ARG_LENGTH, MAX_ENV_SIZE};
+
+/// An error that occurred while spawning a process, see [`Command::spawn`]
+///
+/// The limits are checked before asking the kernel to spawn the process, which would otherwise fail with an opaque error,
+/// the argument and environment limits are soft caps chosen by this API, see [`Command::soft_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnError {
+    /// The path is longer than [`MAX_PATH_LENGTH`]
+    PathTooLong {
+        len: usize,
+    },
+    /// The name is longer than [`MAX_NAME_LENGTH`]
+    NameTooLong {
+        len: usize,
+    },
+    /// There are more than [`MAX_ARG_COUNT`] arguments
+    TooManyArgs {
+        count: usize,
+    },
+    /// The argument at `index` (0 being the path) is longer than [`MAX_ARG_LENGTH`]
+    ArgTooLong {
+        index: usize,
+        len: usize,
+    },
+    /// The arguments are larger than [`MAX_ARGS_SIZE`] in total
+    ArgsTooLarge {
+        size: usize,
+    },
+    /// The environment is larger than [`MAX_ENV_SIZE`]
+    EnvTooLarge {
+        size: usize,
+    },
+    System(ErrorStatus),
+}
+
+impl From<ErrorStatus> for SpawnError {
+    fn from(value: ErrorStatus) -> Self {
+        Self::System(value)
+    }
+}
+
+impl From<SpawnError> for ErrorStatus {
+    fn from(value: SpawnError) -> Self {
+        match value {
+            SpawnError::PathTooLong { .. }
+            | SpawnError::NameTooLong { .. }
+            | SpawnError::ArgTooLong { .. } => ErrorStatus::StrTooLong,
+            SpawnError::TooManyArgs { .. }
+            | SpawnError::ArgsTooLarge { .. }
+            | SpawnError::EnvTooLarge { .. } => ErrorStatus::InvalidArgument,
+            SpawnError::System(err) => err,
+        }
+    }
+}
+
+impl Display for SpawnError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::PathTooLong { len } => write!(
+                f,
+                "path is {len} bytes long, the maximum is {MAX_PATH_LENGTH}"
+            ),
+            Self::NameTooLong { len } => write!(
+                f,
+                "process name is {len} bytes long, the maximum is {MAX_NAME_LENGTH}"
+            ),
+            Self::TooManyArgs { count } => write!(
+                f,
+                "{count} arguments were given, the maximum is {MAX_ARG_COUNT}"
+            ),
+            Self::ArgTooLong { index, len } => write!(
+                f,
+                "argument {index} is {len} bytes long, the maximum is {MAX_ARG_LENGTH}"
+            ),
+            Self::ArgsTooLarge { size } => write!(
+                f,
+                "arguments are {size} bytes in total, the maximum is {MAX_ARGS_SIZE}"
+            ),
+            Self::EnvTooLarge { size } => write!(
+                f,
+                "environment is {size} bytes in total, the maximum is {MAX_ENV_SIZE}"
+            ),
+            Self::System(err) => write!(f, "{}", err.as_str()),
+        }
+    }
+}
+
+/// Checks the arguments `args` (including the path as the first argument) against the argument soft caps.
+fn validate_args<'a>(args: impl Iterator<Item = &'a str>) -> Result<(), SpawnError> {
+    let mut size = 0;
+    let mut count = 0;
+    for (index, arg) in args.enumerate() {
+        if arg.len() > MAX_ARG_LENGTH {
+            return Err(SpawnError::ArgTooLong {
+                index,
+                len: arg.len(),
+            });
+        }
+
+        size += arg.len() + 1;
+        count += 1;
+    }
+
+    if count > MAX_ARG_COUNT {
+        return Err(SpawnError::TooManyArgs { count });
+    }
+    if size > MAX_ARGS_SIZE {
+        return Err(SpawnError::ArgsTooLarge { size });
+    }
+    Ok(())
+}
+
+/// The size of a duplicated environment in bytes, counting each variable as `key=value\0`.
+fn raw_env_size(env: &DuplicatedEnv) -> usize {
+    env.raw_slices().iter().map(|s| s.len() + 1).sum()
+}
+
+/// Checks an environment of `size` bytes against [`MAX_ENV_SIZE`].
+fn validate_env_size(size: usize) -> Result<(), SpawnError> {
+    if size > MAX_ENV_SIZE {
+        return Err(SpawnError::EnvTooLarge { size });
+    }
+    Ok(())
+}
+
+/// Logs the results of spawning `path` with `arg_count` arguments (not counting the path), see [`crate::debug`].
+fn log_spawn(path: &str, arg_count: usize, results: &Result<Pid, ErrorStatus>) {
+    match results {
+        Ok(pid) => debug_log!(SPAWN, "spawned {path} with {arg_count} arguments as {pid}"),
+        Err(err) => debug_log!(SPAWN, "spawning {path} failed: {}", err.as_str()),
+    }
+}
+
+/// What a spawned process uses as one of its stdin, stdout and stderr, see [`Command::stdin`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stdio {
+    /// The process inherits the parent's stream
+    #[default]
+    Inherit,
+    /// A new pipe is created for the process, the parent's end is taken with [`Child::take_stdin`],
+    /// [`Child::take_stdout`] or [`Child::take_stderr`], see [`pipe::pipe`]
+    Piped,
+    /// The process uses the given resource, which must stay open until the process is spawned
+    Resource(Ri),
+}
+
+impl Stdio {
+    /// See [`Stdio::Inherit`].
+    #[inline]
+    pub const fn inherit() -> Self {
+        Self::Inherit
+    }
+
+    /// See [`Stdio::Piped`].
+    ///
+    /// The process may block writing to a pipe nobody reads from, so the parent should keep reading it while waiting for the process.
+    #[inline]
+    pub const fn piped() -> Self {
+        Self::Piped
+    }
+}
+
+impl From<Ri> for Stdio {
+    #[inline]
+    fn from(ri: Ri) -> Self {
+        Self::Resource(ri)
+    }
+}
+
+/// The stdio of a process being spawned, see [`Command::open_stdio`]
+#[derive(Debug, Default)]
+struct SpawnStdio {
+    stdin: Option<Ri>,
+    stdout: Option<Ri>,
+    stderr: Option<Ri>,
+    /// the process's ends of the pipes, the parent's copies are destroyed once the process is spawned
+    child_ends: Vec<Resource>,
+    pipes: ChildPipes,
+}
+
+/// The parent's ends of the pipes of a [`Child`]
+#[derive(Debug, Default)]
+struct ChildPipes {
+    stdin: Option<PipeWriter>,
+    stdout: Option<PipeReader>,
+    stderr: Option<PipeReader>,
+}
+
+/// Returns the resource the process should use as its stdout or stderr,
+/// and the parent's end of the pipe created for it if `stdio` is [`Stdio::Piped`].
+fn open_output(
+    stdio: Stdio,
+    child_ends: &mut Vec<Resource>,
+) -> Result<(Option<Ri>, Option<PipeReader>), ErrorStatus> {
+    Ok(match stdio {
+        Stdio::Inherit => (None, None),
+        Stdio::Resource(ri) => (Some(ri), None),
+        Stdio::Piped => {
+            let (reader, writer) = pipe::pipe()?;
+            let ri = writer.ri();
+            child_ends.push(writer.into_resource());
+            (Some(ri), Some(reader))
+        }
+    })
+}
+
+/// The output of a process spawned with [`Command::output`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub exit_code: usize,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// How long [`Command::output`] waits for output before checking again whether the process exited
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Reads everything currently available from `reader` into `dest`.
+fn drain_pipe(reader: &PipeReader, dest: &mut Vec<u8>) -> Result<(), ErrorStatus> {
+    let mut buf = [0u8; 512];
+    while let Some(read) = reader.try_read(&mut buf)? {
+        if read == 0 {
+            break;
+        }
+        dest.extend_from_slice(&buf[..read]);
+    }
+    Ok(())
+}
+
+/// A process builder, a higher-level interface over [`syscalls::process::spawn`].
+///
+/// The spawned process gets the path as its first argument, followed by the arguments given using [`Command::arg`] and [`Command::args`].
+#[derive(Debug, Clone)]
+pub struct Command {
+    path: String,
+    name: Option<String>,
+    args: Vec<String>,
+    flags: SpawnFlags,
+    priority: Option<ContextPriority>,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+    stack_size: Option<NonZero<usize>>,
+    limits: Limits,
+    /// the parent resources passed to the process, as `(parent, child)` pairs
+    resources: Vec<(Ri, Ri)>,
+    /// whether the arguments and the environment are checked against the soft caps, see [`Command::soft_limits`]
+    soft_limits: bool,
+}
+
+impl Command {
+    /// Constructs a new [`Command`] that spawns the executable at `path`,
+    /// by default the stdio is inherited from the parent, and the flags are [`SpawnFlags::EMPTY`].
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: String::from(path),
+            name: None,
+            args: Vec::new(),
+            flags: SpawnFlags::EMPTY,
+            priority: None,
+            stdin: Stdio::Inherit,
+            stdout: Stdio::Inherit,
+            stderr: Stdio::Inherit,
+            stack_size: None,
+            limits: Limits::UNLIMITED,
+            resources: Vec::new(),
+            soft_limits: true,
+        }
+    }
+
+    /// Sets the name of the process, defaults to the path.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Adds an argument to pass to the process.
+    pub fn arg(&mut self, arg: &str) -> &mut Self {
+        self.args.push(String::from(arg));
+        self
+    }
+
+    /// Adds multiple arguments to pass to the process.
+    pub fn args<'a>(&mut self, args: impl IntoIterator<Item = &'a str>) -> &mut Self {
+        self.args.extend(args.into_iter().map(String::from));
+        self
+    }
+
+    /// Sets the [`SpawnFlags`] of the process.
+    pub fn flags(&mut self, flags: SpawnFlags) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the default priority of the process's threads.
+    pub fn priority(&mut self, priority: ContextPriority) -> &mut Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Sets the stack size of the process's root thread.
+    pub fn stack_size(&mut self, size: NonZero<usize>) -> &mut Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Sets the resource limits of the process, see [`Limits`].
+    ///
+    /// The kernel doesn't support limits yet, so spawning fails with [`ErrorStatus::NotSupported`] if any limit is set.
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets what the process uses as its stdin, either a resource or a [`Stdio`].
+    pub fn stdin(&mut self, stdio: impl Into<Stdio>) -> &mut Self {
+        self.stdin = stdio.into();
+        self
+    }
+
+    /// Sets what the process uses as its stdout, either a resource or a [`Stdio`].
+    pub fn stdout(&mut self, stdio: impl Into<Stdio>) -> &mut Self {
+        self.stdout = stdio.into();
+        self
+    }
+
+    /// Sets what the process uses as its stderr, either a resource or a [`Stdio`].
+    pub fn stderr(&mut self, stdio: impl Into<Stdio>) -> &mut Self {
+        self.stderr = stdio.into();
+        self
+    }
+
+    /// Sets whether the arguments and the environment are checked against the soft caps
+    /// [`MAX_ARG_COUNT`], [`MAX_ARG_LENGTH`], [`MAX_ARGS_SIZE`] and [`MAX_ENV_SIZE`] before spawning, enabled by default.
+    ///
+    /// The caps are chosen by this API and aren't limits of the kernel, which may accept larger spawns,
+    /// the path and name limits come from the ABI and are always checked.
+    pub fn soft_limits(&mut self, enforce: bool) -> &mut Self {
+        self.soft_limits = enforce;
+        self
+    }
+
+    /// Passes the parent's resource `parent` to the process as the resource `child`,
+    /// replacing any resource previously mapped to `child`, for example to hand a listening socket over to a re-executed worker.
+    ///
+    /// The kernel can only pass resources to a process by cloning all of the parent's resources at the same resource IDs
+    /// (see [`SpawnFlags::CLONE_RESOURCES`], which mapping any resource implies), so:
+    /// - spawning fails with [`ErrorStatus::NotSupported`] if `parent` and `child` differ, use [`Command::inherit_resource`]
+    /// - the process also receives every other resource of the parent, which it should destroy if it doesn't need them
+    ///
+    /// `parent` must stay open until the process is spawned.
+    pub fn map_resource(&mut self, parent: Ri, child: Ri) -> &mut Self {
+        self.resources.retain(|&(_, slot)| slot != child);
+        self.resources.push((parent, child));
+        self
+    }
+
+    /// Passes the parent's resource `ri` to the process at the same resource ID, see [`Command::map_resource`].
+    #[inline]
+    pub fn inherit_resource(&mut self, ri: Ri) -> &mut Self {
+        self.map_resource(ri, ri)
+    }
+
+    /// The flags the process is actually spawned with, see [`Command::map_resource`].
+    fn spawn_flags(&self) -> SpawnFlags {
+        if self.resources.is_empty() {
+            self.flags
+        } else {
+            self.flags | SpawnFlags::CLONE_RESOURCES
+        }
+    }
+
+    /// Resolves the stdio of the process, creating the pipes it needs.
+    fn open_stdio(&self) -> Result<SpawnStdio, ErrorStatus> {
+        let mut stdio = SpawnStdio::default();
+        stdio.stdin = match self.stdin {
+            Stdio::Inherit => None,
+            Stdio::Resource(ri) => Some(ri),
+            Stdio::Piped => {
+                let (reader, writer) = pipe::input_pipe()?;
+                let ri = reader.ri();
+                stdio.child_ends.push(reader.into_resource());
+                stdio.pipes.stdin = Some(writer);
+                Some(ri)
+            }
+        };
+
+        (stdio.stdout, stdio.pipes.stdout) = open_output(self.stdout, &mut stdio.child_ends)?;
+        (stdio.stderr, stdio.pipes.stderr) = open_output(self.stderr, &mut stdio.child_ends)?;
+        Ok(stdio)
+    }
+
+    /// Attaches the process to the terminal `tty`, using its slave side as the stdin, stdout and stderr of the process.
+    ///
+    /// `tty` must outlive the call to [`Command::spawn`].
+    pub fn terminal(&mut self, tty: &VttyPair) -> &mut Self {
+        let ri = tty.slave.ri();
+        self.stdin(ri).stdout(ri).stderr(ri)
+    }
+
+    /// Checks the path, name and arguments against the spawn limits, see [`SpawnError`],
+    /// and checks that the resource limits set with [`Command::limits`] and the resources mapped with [`Command::map_resource`] are supported.
+    ///
+    /// Called by [`Command::spawn`] and [`Command::prepare`].
+    pub fn validate(&self) -> Result<(), SpawnError> {
+        self.limits.check_supported()?;
+
+        for &(parent, child) in &self.resources {
+            if parent != child {
+                return Err(SpawnError::System(ErrorStatus::NotSupported));
+            }
+            // fails early if the resource was already closed, instead of spawning a process missing it
+            syscalls::resources::kind(parent)?;
+        }
+
+        if self.path.len() > MAX_PATH_LENGTH {
+            return Err(SpawnError::PathTooLong {
+                len: self.path.len(),
+            });
+        }
+
+        if let Some(name) = &self.name {
+            if name.len() > MAX_NAME_LENGTH {
+                return Err(SpawnError::NameTooLong { len: name.len() });
+            }
+        }
+
+        if !self.soft_limits {
+            return Ok(());
+        }
+
+        validate_args(
+            core::iter::once(self.path.as_str()).chain(self.args.iter().map(String::as_str)),
+        )
+    }
+
+    /// Checks the environment against [`MAX_ENV_SIZE`] unless the soft caps are disabled.
+    fn validate_env(&self, size: impl FnOnce() -> usize) -> Result<(), SpawnError> {
+        if self.soft_limits {
+            validate_env_size(size())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Spawns the process, returning a handle to it.
+    ///
+    /// The command and the environment are checked against the spawn limits first, see [`SpawnError`].
+    pub fn spawn(&self) -> Result<Child, SpawnError> {
+        self.validate()?;
+        // measured in place, the spawn is what duplicates the environment
+        self.validate_env(env::env_size)?;
+
+        let mut argv: Vec<&str> = Vec::with_capacity(self.args.len() + 1);
+        argv.push(&self.path);
+        argv.extend(self.args.iter().map(String::as_str));
+
+        let stdio = self.open_stdio()?;
+        let results = syscalls::process::spawn(
+            self.name.as_deref(),
+            &self.path,
+            argv,
+            self.spawn_flags(),
+            self.priority.into(),
+            stdio.stdin,
+            stdio.stdout,
+            stdio.stderr,
+            self.stack_size,
+        );
+
+        log_spawn(&self.path, self.args.len(), &results);
+        results
+            .map(|pid| Child::new(pid, stdio))
+            .map_err(SpawnError::System)
+    }
+
+    /// Spawns the process with its stdout and stderr piped, then waits for it to exit while collecting its output.
+    ///
+    /// The stdin of the process is the one set with [`Command::stdin`], inherited by default.
+    pub fn output(&self) -> Result<Output, SpawnError> {
+        let mut command = self.clone();
+        command.stdout(Stdio::Piped).stderr(Stdio::Piped);
+        let mut child = command.spawn()?;
+
+        let (stdout, stderr) = match (child.take_stdout(), child.take_stderr()) {
+            (Some(stdout), Some(stderr)) => (stdout, stderr),
+            _ => unreachable!("piped process spawned without its pipes"),
+        };
+
+        let mut output = Output {
+            exit_code: 0,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        };
+
+        // the pipes don't report when the process is gone, so the process is checked on between polls
+        loop {
+            // checked before draining, so that output written right before exiting is collected
+            let exit_code = child.try_wait()?;
+            drain_pipe(&stdout, &mut output.stdout)?;
+            drain_pipe(&stderr, &mut output.stderr)?;
+
+            if let Some(exit_code) = exit_code {
+                output.exit_code = exit_code;
+                return Ok(output);
+            }
+
+            // the interval running out without output isn't an error, the process is checked on again
+            let mut entries = [stdout.poll_entry(), stderr.poll_entry()];
+            syscalls::io::poll_resources(&mut entries, Some(OUTPUT_POLL_INTERVAL))?;
+        }
+    }
+
+    /// Prepares the command for being spawned repeatedly, see [`PreparedSpawn`].
+    ///
+    /// The command is checked against the spawn limits first, see [`SpawnError`].
+    pub fn prepare(&self) -> Result<PreparedSpawn, SpawnError> {
+        self.validate()?;
+        let mut args = Vec::with_capacity(self.args.len() + 1);
+        args.push(self.path.clone());
+        args.extend(self.args.iter().cloned());
+
+        let argv = args.iter().map(|arg| Str::from_str(arg)).collect();
+        let env = env::duplicate_env();
+        self.validate_env(|| raw_env_size(&env))?;
+
+        Ok(PreparedSpawn {
+            command: Self {
+                args,
+                ..self.clone()
+            },
+            argv,
+            env,
+        })
+    }
+}
+
+/// A [`Command`] prepared for being spawned many times, see [`Command::prepare`].
+///
+/// The arguments and a snapshot of the environment are serialized once, each spawn only appends the given argument tail,
+/// which is much cheaper than [`Command::spawn`] for programs spawning many children, such as shells.
+///
+/// Changes to the environment after preparing aren't seen by the spawned processes until [`PreparedSpawn::refresh_env`] is called.
+#[derive(Debug)]
+pub struct PreparedSpawn {
+    /// holds the path as the first argument, `argv` points into its arguments
+    command: Command,
+    argv: Vec<Str>,
+    env: DuplicatedEnv,
+}
+
+impl PreparedSpawn {
+    /// Takes a new snapshot of the current environment, to be used by the next spawns.
+    pub fn refresh_env(&mut self) -> Result<(), SpawnError> {
+        let env = env::duplicate_env();
+        self.command.validate_env(|| raw_env_size(&env))?;
+        self.env = env;
+        Ok(())
+    }
+
+    /// Spawns the process with the prepared arguments followed by `tail`.
+    ///
+    /// The arguments are checked against the argument limits first, see [`SpawnError`].
+    pub fn spawn(&mut self, tail: &[&str]) -> Result<Child, SpawnError> {
+        let command = &self.command;
+        let base_len = command.args.len();
+
+        if command.soft_limits {
+            validate_args(
+                command
+                    .args
+                    .iter()
+                    .map(String::as_str)
+                    .chain(tail.iter().copied()),
+            )?;
+        }
+
+        self.argv.truncate(base_len);
+        self.argv.extend(tail.iter().map(|arg| Str::from_str(arg)));
+
+        let stdio = command.open_stdio()?;
+        let env_slices = self.env.raw_slices_mut();
+        let results = unsafe {
+            let args = Slice::from_raw_parts(self.argv.as_mut_ptr(), self.argv.len());
+            let env = Slice::from_raw_parts(env_slices.as_mut_ptr(), env_slices.len());
+
+            syscalls::process::spawn_with_env(
+                command.name.as_deref().map(Str::from_str).into(),
+                Str::from_str(&command.path),
+                OptZero::some(args),
+                OptZero::some(env),
+                command.spawn_flags(),
+                command.priority.into(),
+                stdio.stdin.into(),
+                stdio.stdout.into(),
+                stdio.stderr.into(),
+                match command.stack_size {
+                    None => OptZero::none(),
+                    Some(size) => OptZero::some(ShouldNotBeZero::new_unchecked(size.get())),
+                },
+            )
+            .get()
+        };
+
+        // `tail` doesn't outlive this call
+        self.argv.truncate(base_len);
+        log_spawn(&command.path, base_len - 1 + tail.len(), &results);
+        results
+            .map(|pid| Child::new(pid, stdio))
+            .map_err(SpawnError::System)
+    }
+}
+
+/// A handle to a child process spawned using [`Command::spawn`].
+#[derive(Debug)]
+pub struct Child {
+    pid: Pid,
+    pipes: ChildPipes,
+}
+
+impl Child {
+    /// Takes the parent's ends of the pipes out of `stdio`, destroying the process's ends.
+    fn new(pid: Pid, stdio: SpawnStdio) -> Self {
+        Self {
+            pid,
+            pipes: stdio.pipes,
+        }
+    }
+
+    /// Takes the pipe writing to the stdin of the child, if it was set to [`Stdio::Piped`] and not taken yet.
+    #[inline]
+    pub fn take_stdin(&mut self) -> Option<PipeWriter> {
+        self.pipes.stdin.take()
+    }
+
+    /// Takes the pipe reading the stdout of the child, if it was set to [`Stdio::Piped`] and not taken yet.
+    #[inline]
+    pub fn take_stdout(&mut self) -> Option<PipeReader> {
+        self.pipes.stdout.take()
+    }
+
+    /// Takes the pipe reading the stderr of the child, if it was set to [`Stdio::Piped`] and not taken yet.
+    #[inline]
+    pub fn take_stderr(&mut self) -> Option<PipeReader> {
+        self.pipes.stderr.take()
+    }
+
+    /// The process ID of the child.
+    #[inline]
+    pub const fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Waits for the child to exit, returning its exit code.
+    #[inline]
+    pub fn wait(&self) -> Result<usize, ErrorStatus> {
+        syscalls::process::wait(self.pid)
+    }
+
+    /// Returns the exit code of the child if it has exited, or [`None`] if it is still running.
+    #[inline]
+    pub fn try_wait(&self) -> Result<Option<usize>, ErrorStatus> {
+        syscalls::process::try_cleanup(self.pid)
+    }
+}
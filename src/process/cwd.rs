@@ -0,0 +1,62 @@
+//! A process-local cache of the current working directory, see [`cwd_ref`] and [`ScopedChdir`]
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::sync::Arc;
+use safa_abi::errors::ErrorStatus;
+
+use crate::{sync::locks::Mutex, syscalls};
+
+static CWD_CACHE: Mutex<Option<Arc<str>>> = Mutex::new(None);
+
+/// Invalidates the cached current working directory, so that it is fetched again on the next [`cwd_ref`].
+///
+/// Called by [`syscalls::process_misc::chdir`], only needs to be called manually after using the raw [`syscalls::process_misc::syschdir`].
+#[inline]
+pub fn invalidate() {
+    *CWD_CACHE.lock() = None;
+}
+
+/// Returns the current working directory, only asks the kernel for it if it isn't cached.
+pub fn cwd_ref() -> Result<Arc<str>, ErrorStatus> {
+    let mut cache = CWD_CACHE.lock();
+    if let Some(cwd) = &*cache {
+        return Ok(cwd.clone());
+    }
+
+    let cwd: Arc<str> = Arc::from(syscalls::process_misc::getcwd()?);
+    *cache = Some(cwd.clone());
+    Ok(cwd)
+}
+
+/// Changes the current working directory to `path` for as long as the guard lives,
+/// on drop changes it back to the previous one.
+#[derive(Debug)]
+pub struct ScopedChdir {
+    previous: Arc<str>,
+}
+
+impl ScopedChdir {
+    /// Changes the current working directory to `path`, see [`ScopedChdir`].
+    pub fn new(path: &str) -> Result<Self, ErrorStatus> {
+        let previous = cwd_ref()?;
+        syscalls::process_misc::chdir(path)?;
+        Ok(Self { previous })
+    }
+
+    /// The working directory that is restored on drop
+    #[inline]
+    pub fn previous(&self) -> &str {
+        &self.previous
+    }
+}
+
+impl Drop for ScopedChdir {
+    fn drop(&mut self) {
+        _ = syscalls::process_misc::chdir(&self.previous);
+    }
+}
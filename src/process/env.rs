@@ -11,13 +11,13 @@ use std as alloc;
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
+use safa_abi::errors::ErrorStatus;
 use safa_abi::ffi::option::OptZero;
 use safa_abi::ffi::slice::Slice;
 
-use alloc::ffi::CString;
-
 use crate::sync::cell::LazyCell;
-use crate::sync::locks::Mutex;
+use crate::sync::locks::{RwLock, RwLockReadGuard};
+use crate::syscalls::types::{OkSyscallResult, SyscallResults};
 
 // Environment variables
 
@@ -44,28 +44,90 @@ impl DuplicatedEnv {
     }
 }
 
+/// An environment variable stored in [`EnvVars::buf`]
+#[derive(Debug, Clone, Copy)]
+struct EnvEntry {
+    offset: usize,
+    key_len: usize,
+    /// the length of the value without the null terminator
+    value_len: usize,
+}
+
+impl EnvEntry {
+    /// The length of the whole entry in bytes (`key=value\0`)
+    const fn raw_len(&self) -> usize {
+        self.key_len + 1 + self.value_len + 1
+    }
+}
+
 struct EnvVars {
-    env: Vec<(Box<[u8]>, Box<CStr>)>,
-    /// hints the size of the environment variables in bytes (key.length + value.length + 1 ('='))
-    /// which can then be used to duplicate the environment variables
-    raw_len: usize,
+    /// All the environment variables serialized as `key=value\0` one after the other,
+    /// which is the same format used when duplicating the environment.
+    ///
+    /// removing or replacing a variable leaves its old bytes behind, they are reclaimed once they make up most of the buffer.
+    buf: Vec<u8>,
+    entries: Vec<EnvEntry>,
+    /// the amount of bytes in `buf` that no longer belong to any entry
+    garbage: usize,
 }
 
 impl EnvVars {
     pub const fn new() -> Self {
         Self {
-            env: Vec::new(),
-            raw_len: 0,
+            buf: Vec::new(),
+            entries: Vec::new(),
+            garbage: 0,
         }
     }
 
+    const fn key_of<'a>(buf: &'a [u8], entry: &EnvEntry) -> &'a [u8] {
+        let (key, _) = buf.split_at(entry.offset).1.split_at(entry.key_len);
+        key
+    }
+
+    /// Returns the value of `entry` with the null terminator.
+    const fn value_with_nul_of<'a>(buf: &'a [u8], entry: &EnvEntry) -> &'a [u8] {
+        let (_, rest) = buf.split_at(entry.offset + entry.key_len + 1);
+        let (value, _) = rest.split_at(entry.value_len + 1);
+        value
+    }
+
+    fn find(&self, key: &[u8]) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| Self::key_of(&self.buf, e) == key)
+    }
+
     pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
-        for (k, v) in &self.env {
-            if &**k == key {
-                return Some(v.to_bytes());
-            }
-        }
-        None
+        self.find(key).map(|i| {
+            let value = Self::value_with_nul_of(&self.buf, &self.entries[i]);
+            &value[..value.len() - 1]
+        })
+    }
+
+    /// Same as [`EnvVars::get`] but compares the keys ignoring ASCII case.
+    pub fn get_ignore_case(&self, key: &[u8]) -> Option<&[u8]> {
+        self.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns all the environment variables as `(key, value)` pairs.
+    fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.entries.iter().map(|e| {
+            let value = Self::value_with_nul_of(&self.buf, e);
+            (Self::key_of(&self.buf, e), &value[..value.len() - 1])
+        })
+    }
+
+    /// Same as [`EnvVars::iter`] but returns the values as C strings,
+    /// which end at the first null byte of the value.
+    fn iter_c_str(&self) -> impl Iterator<Item = (&[u8], &CStr)> {
+        self.entries.iter().map(|e| {
+            let value = Self::value_with_nul_of(&self.buf, e);
+            let value = CStr::from_bytes_until_nul(value).expect("values are null terminated");
+            (Self::key_of(&self.buf, e), value)
+        })
     }
 
     /// # Safety
@@ -73,31 +135,47 @@ impl EnvVars {
     /// otherwise use [`EnvVars::set`]
     #[inline(always)]
     unsafe fn push(&mut self, key: &[u8], value: &[u8]) {
-        let cstr = CString::new(value)
-            .unwrap_or_else(|_| CStr::from_bytes_until_nul(value).unwrap().into())
-            .into_boxed_c_str();
+        let entry = EnvEntry {
+            offset: self.buf.len(),
+            key_len: key.len(),
+            value_len: value.len(),
+        };
 
-        let impact = Self::len_change(key, &cstr);
-        self.env.push((key.to_vec().into_boxed_slice(), cstr));
-        self.raw_len += impact;
+        self.buf.reserve(entry.raw_len());
+        self.buf.extend_from_slice(key);
+        self.buf.push(b'=');
+        self.buf.extend_from_slice(value);
+        self.buf.push(0);
+        self.entries.push(entry);
     }
 
-    #[inline(always)]
-    pub fn set(&mut self, key: &[u8], value: &[u8]) {
-        for (k, curr_v) in &mut self.env {
-            if &**k == key {
-                let old_impact = Self::len_change(key, curr_v);
+    /// Removes the entry at `index` leaving its bytes behind as garbage.
+    fn remove_at(&mut self, index: usize) {
+        let entry = self.entries.swap_remove(index);
+        self.garbage += entry.raw_len();
 
-                let new_value = CString::new(value)
-                    .unwrap_or_else(|_| CStr::from_bytes_until_nul(value).unwrap().into());
-                *curr_v = new_value.into_boxed_c_str();
+        if self.garbage > self.buf.len() / 2 {
+            self.compact();
+        }
+    }
 
-                let new_impact = Self::len_change(key, curr_v);
+    /// Reclaims the bytes left behind by removed or replaced variables.
+    fn compact(&mut self) {
+        let mut buf = Vec::with_capacity(self.buf.len() - self.garbage);
+        for entry in &mut self.entries {
+            let start = entry.offset;
+            entry.offset = buf.len();
+            buf.extend_from_slice(&self.buf[start..start + entry.raw_len()]);
+        }
+
+        self.buf = buf;
+        self.garbage = 0;
+    }
 
-                self.raw_len -= old_impact;
-                self.raw_len += new_impact;
-                return;
-            }
+    #[inline(always)]
+    pub fn set(&mut self, key: &[u8], value: &[u8]) {
+        if let Some(i) = self.find(key) {
+            self.remove_at(i);
         }
 
         unsafe {
@@ -105,20 +183,11 @@ impl EnvVars {
         }
     }
 
-    // Returns the impact a key value pair will have on the [`Self::raw_len`] if added or removed.
-    const fn len_change(key: &[u8], value: &CStr) -> usize {
-        key.len() + 1 + value.count_bytes() + 1
-    }
-
     #[inline(always)]
     pub fn remove(&mut self, key: &[u8]) {
-        for (i, (k, v)) in self.env.iter().enumerate() {
-            if &**k == key {
-                self.raw_len -= Self::len_change(key, &v);
-                // order doesn't matter
-                self.env.swap_remove(i);
-                return;
-            }
+        if let Some(i) = self.find(key) {
+            // order doesn't matter
+            self.remove_at(i);
         }
     }
 
@@ -126,7 +195,9 @@ impl EnvVars {
     /// # Safety
     /// This function is unsafe because any usage of [`RawSlice<T>`] is unsafe.
     unsafe fn insert_raw(&mut self, raw: &[&[u8]]) {
-        self.env.reserve(raw.len());
+        self.entries.reserve(raw.len());
+        self.buf
+            .reserve(raw.iter().map(|slice| slice.len() + 2).sum());
 
         for slice in raw {
             let mut spilt = slice.splitn(2, |c| *c == b'=');
@@ -143,33 +214,28 @@ impl EnvVars {
     }
 
     pub fn clear(&mut self) {
-        self.env.clear();
-        self.raw_len = 0;
+        self.entries.clear();
+        self.buf.clear();
+        self.garbage = 0;
     }
 
     fn duplicate(&self) -> DuplicatedEnv {
-        let mut buf: Vec<u8> = Vec::with_capacity(self.raw_len);
-        unsafe { buf.set_len(self.raw_len) };
-        let mut buf = buf.into_boxed_slice();
-        let mut offset = 0;
-
-        let mut slices = Vec::with_capacity(self.env.len());
-
-        for (key, value) in &self.env {
-            let ptr = unsafe { buf.as_mut_ptr().add(offset) };
-            slices.push(unsafe { Slice::from_raw_parts(ptr, key.len() + 1 + value.count_bytes()) });
+        let mut buf: Vec<u8> = Vec::with_capacity(self.buf.len() - self.garbage);
+        let mut offsets = Vec::with_capacity(self.entries.len());
 
-            buf[offset..offset + key.len()].copy_from_slice(key);
-            offset += key.len();
-
-            buf[offset] = b'=';
-            offset += 1;
-
-            let value_len = value.count_bytes() + 1;
-            buf[offset..offset + value_len].copy_from_slice(value.to_bytes_with_nul());
-            offset += value_len;
+        for entry in &self.entries {
+            offsets.push((buf.len(), entry.raw_len() - 1));
+            buf.extend_from_slice(&self.buf[entry.offset..entry.offset + entry.raw_len()]);
         }
 
+        let mut buf = buf.into_boxed_slice();
+        let slices = offsets
+            .into_iter()
+            .map(|(offset, len)| unsafe {
+                Slice::from_raw_parts(buf.as_mut_ptr().add(offset), len)
+            })
+            .collect();
+
         DuplicatedEnv {
             _raw_data: buf,
             slices_ref: slices,
@@ -183,6 +249,7 @@ pub(super) struct RawEnv {
 }
 
 impl RawEnv {
+    #[cfg(not(feature = "std"))]
     pub const fn new(args: Option<NonNull<[&'static [u8]]>>) -> Self {
         Self {
             args: match args {
@@ -205,6 +272,7 @@ impl RawEnvStatic {
         Self(UnsafeCell::new(MaybeUninit::uninit()))
     }
 
+    #[cfg(not(feature = "std"))]
     pub unsafe fn init(&self, env: RawEnv) {
         unsafe {
             self.0.get().write(MaybeUninit::new(env));
@@ -228,114 +296,293 @@ impl RawEnvStatic {
 // TODO: refactor all of this
 pub(super) static SAAPI_RAW_ENV: RawEnvStatic = RawEnvStatic::new();
 
-// FIXME: use a RwLock
-static ENV: LazyCell<Mutex<EnvVars>> = LazyCell::new(|| {
+static ENV: LazyCell<RwLock<EnvVars>> = LazyCell::new(|| {
     let mut env = EnvVars::new();
     unsafe { env.insert_raw(SAAPI_RAW_ENV.as_slice()) };
-    Mutex::new(env)
+    RwLock::new(env)
 });
 
 /// Gets all the environment variables in the current process
+///
+/// Values are stored in full even if they contain null bytes, but the values returned here are C strings,
+/// so they end at the first null byte, use [`env_get`] to get a value in full.
+///
+/// Copies the whole environment, use [`vars`] to iterate over it without copying.
 #[inline]
 pub fn env_get_all() -> Vec<(Box<[u8]>, Box<CStr>)> {
-    let env = ENV.lock();
-    env.env.clone()
+    let env = ENV.read();
+    env.iter_c_str()
+        .map(|(key, value)| (Box::from(key), Box::from(value)))
+        .collect()
 }
 
 #[inline]
 pub fn env_get(key: &[u8]) -> Option<Box<[u8]>> {
-    let env = ENV.lock();
+    let env = ENV.read();
     env.get(key).map(|v| v.to_vec().into_boxed_slice())
 }
 
+/// Gets an environment variable by key, comparing keys ignoring ASCII case,
+/// if multiple variables match (for example `PATH` and `Path`) the first one set is returned.
+#[inline]
+pub fn env_get_ignore_case(key: &[u8]) -> Option<Box<[u8]>> {
+    let env = ENV.read();
+    env.get_ignore_case(key).map(Box::from)
+}
+
+/// An iterator over environment variables as `(key, value)` pairs, see [`vars_with_prefix`]
+pub type VarsWithPrefix = alloc::vec::IntoIter<(Box<[u8]>, Box<[u8]>)>;
+
+/// Returns an iterator over the `(key, value)` pairs of the environment variables whose key starts with `prefix`,
+/// for example `vars_with_prefix(b"SAFA_")`.
+///
+/// The matching variables are copied when this is called, changes to the environment afterwards aren't reflected.
+pub fn vars_with_prefix(prefix: &[u8]) -> VarsWithPrefix {
+    let env = ENV.read();
+    env.iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| (Box::from(key), Box::from(value)))
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// A read-locked view of the environment variables, see [`vars`]
+///
+/// The environment can't be changed while the view is alive, [`env_set`], [`env_remove`] and [`env_clear`] block until it is dropped,
+/// so it should be dropped as soon as possible, calling them from the thread holding it deadlocks.
+pub struct Vars {
+    env: RwLockReadGuard<'static, EnvVars>,
+}
+
+impl Vars {
+    /// Returns an iterator over the `(key, value)` pairs of the environment variables, in the order they were set.
+    ///
+    /// Keys and values are returned as raw bytes, values in full even if they contain null bytes.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.env.iter()
+    }
+
+    /// Same as [`Vars::iter`] but only returns the variables whose key and value are valid UTF-8, as strings.
+    pub fn iter_str(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env.iter().filter_map(|(key, value)| {
+            Some((
+                core::str::from_utf8(key).ok()?,
+                core::str::from_utf8(value).ok()?,
+            ))
+        })
+    }
+
+    /// Gets an environment variable by key from the view.
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.env.get(key)
+    }
+
+    /// The amount of environment variables.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.env.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl core::fmt::Debug for Vars {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(key, value)| {
+                (
+                    alloc::string::String::from_utf8_lossy(key),
+                    alloc::string::String::from_utf8_lossy(value),
+                )
+            }))
+            .finish()
+    }
+}
+
+/// Returns a read-locked view of the environment variables, for iterating over them without copying them:
+/// ```ignore
+/// let vars = env::vars();
+/// for (key, value) in vars.iter_str() {
+///     println!("{key}={value}");
+/// }
+/// ```
+///
+/// See [`Vars`] for the restrictions while the view is alive, use [`env_get_all`] for a copy that can be kept around.
+#[inline]
+pub fn vars() -> Vars {
+    Vars { env: ENV.read() }
+}
+
 #[inline]
 pub fn env_set(key: &[u8], value: &[u8]) {
-    let mut env = ENV.lock();
+    let mut env = ENV.write();
     env.set(key, value);
 }
 
 #[inline]
 pub fn env_remove(key: &[u8]) {
-    let mut env = ENV.lock();
+    let mut env = ENV.write();
     env.remove(key);
 }
 
+/// Calls `f` with the value of the environment variable `key` without blocking,
+/// returns [`None`] if the environment is locked, for use in contexts such as panic handlers.
+pub(crate) fn try_env_with<R>(key: &[u8], f: impl FnOnce(Option<&[u8]>) -> R) -> Option<R> {
+    let env = ENV.try_read()?;
+    Some(f(env.get(key)))
+}
+
+/// The size of the environment in bytes, counting each variable as `key=value\0`, computed without copying it.
+pub(crate) fn env_size() -> usize {
+    let env = ENV.read();
+    env.entries.iter().map(EnvEntry::raw_len).sum()
+}
+
 /// Duplicate the environment variables so that they can be used in a child process by being passed to `_start`.
 #[inline]
 pub(crate) fn duplicate_env() -> DuplicatedEnv {
-    let env = ENV.lock();
+    let env = ENV.read();
     env.duplicate()
 }
 
 #[inline]
 pub fn env_clear() {
-    let mut env = ENV.lock();
+    let mut env = ENV.write();
     env.clear();
 }
 
+/// Validates a key passed through the C ABI, keys must be non-empty and must not contain `=` or null bytes.
+unsafe fn c_key<'a>(key: Slice<u8>) -> Result<&'a [u8], ErrorStatus> {
+    let key = unsafe { key.try_as_slice() }.map_err(|_| ErrorStatus::InvalidPtr)?;
+    if key.is_empty() || key.iter().any(|c| *c == b'=' || *c == 0) {
+        return Err(ErrorStatus::InvalidArgument);
+    }
+    Ok(key)
+}
+
+/// Converts the results of a C ABI environment function to a [`SyscallResults`].
+fn c_results<T: OkSyscallResult>(results: Result<usize, ErrorStatus>) -> SyscallResults<T> {
+    match results {
+        Ok(value) => SyscallResults::from_ok(value),
+        Err(err) => SyscallResults::from_err(err),
+    }
+}
+
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
 #[cfg_attr(
-    not(any(feature = "std", feature = "rustc-dep-of-std")),
-    unsafe(no_mangle)
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
 )]
-
 /// Get an environment variable by key.
 ///
+/// The value is returned in full as a length-tagged slice, it isn't null terminated and may contain null bytes,
+/// returns null if the key doesn't exist or is invalid.
+///
 /// # Safety
-/// unsafe because it returns a pointer to the environment variable, which may be invalid after the environment is modified.
+/// unsafe because it returns a pointer to the environment variable, which may be invalid after the environment is modified,
+/// use [`sysenv_get_into`] to copy the value instead.
 pub unsafe extern "C" fn sysenv_get(key: OptZero<Slice<u8>>) -> OptZero<Slice<u8>> {
     unsafe {
         let Some(key) = key.into_option() else {
             return OptZero::none();
         };
+        let Ok(key) = c_key(key) else {
+            return OptZero::none();
+        };
 
-        ENV.lock()
-            .get(key.as_slice_unchecked())
-            .map(|slice| Slice::from_slice(slice))
-            .into()
+        ENV.read().get(key).map(Slice::from_slice).into()
     }
 }
 
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
 #[cfg_attr(
-    not(any(feature = "std", feature = "rustc-dep-of-std")),
-    unsafe(no_mangle)
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
+)]
+/// Copies the value of an environment variable into `dest`.
+///
+/// If the value is longer than `dest` only the first `dest.len()` bytes are copied,
+/// no null terminator is written.
+///
+/// # Returns
+/// - the length of the whole value on success, which is larger than `dest.len()` if the value was truncated
+/// - [`ErrorStatus::NoSuchAFileOrDirectory`] if there is no variable with that key
+/// - [`ErrorStatus::InvalidPtr`] if `key` or `dest` are invalid slices
+/// - [`ErrorStatus::InvalidArgument`] if `key` is empty or contains `=` or null bytes
+pub extern "C" fn sysenv_get_into(key: Slice<u8>, dest: Slice<u8>) -> SyscallResults<usize> {
+    let results = (|| unsafe {
+        let key = c_key(key)?;
+        let dest = dest.try_as_slice().map_err(|_| ErrorStatus::InvalidPtr)?;
+        let dest = core::slice::from_raw_parts_mut(dest.as_ptr().cast_mut(), dest.len());
+
+        let env = ENV.read();
+        let value = env.get(key).ok_or(ErrorStatus::NoSuchAFileOrDirectory)?;
+
+        let amount = value.len().min(dest.len());
+        dest[..amount].copy_from_slice(&value[..amount]);
+        Ok(value.len())
+    })();
+
+    c_results(results)
+}
+
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
+#[cfg_attr(
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
 )]
 /// Set an environment variable by key.
-pub extern "C" fn sysenv_set(key: Slice<u8>, value: OptZero<Slice<u8>>) {
-    unsafe {
-        let key = key
-            .try_as_slice()
-            .expect("invalid key passed to sysenv_set");
+///
+/// `value` is stored in full as a length-tagged value, it doesn't need to be null terminated and may contain null bytes,
+/// if `value` is null the variable is set to an empty value.
+///
+/// # Returns
+/// - [`ErrorStatus::InvalidPtr`] if `key` or `value` are invalid slices
+/// - [`ErrorStatus::InvalidArgument`] if `key` is empty or contains `=` or null bytes
+pub extern "C" fn sysenv_set(key: Slice<u8>, value: OptZero<Slice<u8>>) -> SyscallResults {
+    let results = (|| unsafe {
+        let key = c_key(key)?;
 
         let value = if let Some(value) = value.into_option() {
-            value
-                .try_as_slice()
-                .expect("invalid value passed to sysenv_set")
+            value.try_as_slice().map_err(|_| ErrorStatus::InvalidPtr)?
         } else {
             &[]
         };
 
         env_set(key, value);
-    }
+        Ok(0)
+    })();
+
+    c_results(results)
 }
 
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
 #[cfg_attr(
-    not(any(feature = "std", feature = "rustc-dep-of-std")),
-    unsafe(no_mangle)
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
 )]
-/// Remove an environment variable by key.
-pub extern "C" fn sysenv_remove(key: Slice<u8>) {
-    unsafe {
-        let key = key
-            .try_as_slice()
-            .expect("invalid key passed to sysenv_remove");
-
+/// Remove an environment variable by key, removing a variable that doesn't exist succeeds.
+///
+/// # Returns
+/// - [`ErrorStatus::InvalidPtr`] if `key` is an invalid slice
+/// - [`ErrorStatus::InvalidArgument`] if `key` is empty or contains `=` or null bytes
+pub extern "C" fn sysenv_remove(key: Slice<u8>) -> SyscallResults {
+    let results = unsafe { c_key(key) }.map(|key| {
         env_remove(key);
-    }
+        0
+    });
+
+    c_results(results)
 }
 
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
 #[cfg_attr(
-    not(any(feature = "std", feature = "rustc-dep-of-std")),
-    unsafe(no_mangle)
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
 )]
 /// Clear all environment variables.
 pub extern "C" fn sysenv_clear() {
@@ -1,22 +1,28 @@
 //! contains api initialization functions, that should be called before using the api
-use core::ptr::NonNull;
+use core::{
+    fmt::{self, Write},
+    ptr::NonNull,
+};
 
 use safa_abi::{
     ffi::{slice::Slice, str::Str},
     process::AbiStructures,
 };
 
+#[cfg(feature = "alloc")]
+use crate::process::env::{RawEnv, SAAPI_RAW_ENV};
 use crate::{
+    abi_check,
     alloc::GLOBAL_SYSTEM_ALLOCATOR,
-    exported_func,
-    process::env::RawEnv,
-    syscalls::{self},
+    debug, exported_func,
+    ffi_util::{self, InvalidStr},
+    syscalls,
 };
 
 use super::{
     args::{RawArgs, SAAPI_RAW_ARGS},
-    env::SAAPI_RAW_ENV,
     init_proc_meta,
+    stdio::{BufWriter, BufferMode},
 };
 
 // Initialization
@@ -28,6 +34,27 @@ fn init_args(args: Option<NonNull<[&'static str]>>) {
     }
 }
 
+/// Enables the diagnostics listed in the environment, see [`crate::debug`].
+unsafe fn init_debug(env: &Slice<Slice<u8>>) {
+    let Ok(vars) = (unsafe { env.try_as_slice() }) else {
+        return;
+    };
+
+    for var in vars {
+        let Ok(var) = (unsafe { var.try_as_slice() }) else {
+            continue;
+        };
+
+        if let Some(value) = var
+            .strip_prefix(debug::DEBUG_VAR.as_bytes())
+            .and_then(|rest| rest.strip_prefix(b"="))
+        {
+            debug::init(value);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
 fn init_env(env: Option<NonNull<[&'static [u8]]>>) {
     unsafe {
         let raw = RawEnv::new(env);
@@ -35,28 +62,145 @@ fn init_env(env: Option<NonNull<[&'static [u8]]>>) {
     }
 }
 
+/// An error that occurred while initializing the API, see [`try_api_init`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitError {
+    /// The arguments slice points to invalid memory
+    InvalidArgs,
+    /// The argument at the given index isn't valid UTF-8
+    InvalidArg(usize, InvalidStr),
+    /// The environment slice points to invalid memory
+    InvalidEnv,
+    /// The environment variable at the given index points to invalid memory
+    InvalidEnvEntry(usize),
+    /// The ABI structures passed by the kernel break an invariant, usually because safa-api was built against another safa-abi
+    BadAbiStructures(&'static str),
+}
+
+impl InitError {
+    /// The exit code [`_c_api_init`] exits with when initialization fails with this error,
+    /// picked from the top of the range to stay apart from the codes programs usually exit with.
+    pub const fn exit_code(&self) -> usize {
+        match self {
+            Self::InvalidArgs | Self::InvalidArg(..) => 0xF1,
+            Self::InvalidEnv | Self::InvalidEnvEntry(_) => 0xF2,
+            Self::BadAbiStructures(_) => 0xF3,
+        }
+    }
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgs => f.write_str("the arguments point to invalid memory"),
+            Self::InvalidArg(index, err) => write!(f, "argument {index} is invalid: {err}"),
+            Self::InvalidEnv => f.write_str("the environment points to invalid memory"),
+            Self::InvalidEnvEntry(index) => {
+                write!(f, "environment variable {index} points to invalid memory")
+            }
+            Self::BadAbiStructures(details) => write!(
+                f,
+                "ABI mismatch between safa-api {} and the kernel in AbiStructures: {details}, rebuild against the kernel's safa-abi",
+                env!("CARGO_PKG_VERSION")
+            ),
+        }
+    }
+}
+
+/// Same as [`sysapi_init`] but returns an error instead of panicking if the arguments, the environment,
+/// or the ABI structures passed by the kernel are malformed.
+///
+/// Everything is validated before anything is initialized, on error the API is left uninitialized.
+///
+/// # Safety
+/// Must be called once, before using the API, with the values the kernel passed to the process.
+pub unsafe fn try_api_init(
+    args: Slice<Str>,
+    env: Slice<Slice<u8>>,
+    task_abi_structures: AbiStructures,
+) -> Result<(), InitError> {
+    abi_check::validate_abi_structures(&task_abi_structures)
+        .map_err(InitError::BadAbiStructures)?;
+
+    unsafe {
+        // validated before being turned into `&str`s
+        let raw_args = args.try_as_slice().map_err(|_| InitError::InvalidArgs)?;
+        for (index, arg) in raw_args.iter().enumerate() {
+            ffi_util::str_from_kernel_raw(*arg).map_err(|err| InitError::InvalidArg(index, err))?;
+        }
+
+        let raw_env = env.try_as_slice().map_err(|_| InitError::InvalidEnv)?;
+        for (index, var) in raw_env.iter().enumerate() {
+            var.try_as_slice()
+                .map_err(|_| InitError::InvalidEnvEntry(index))?;
+        }
+
+        let args = args
+            .try_into_str_slices_mut(|_| true)
+            .map_err(|_| InitError::InvalidArgs)?;
+        let args_ptr = NonNull::new_unchecked(args as *mut [&'static str]);
+
+        init_args(Some(args_ptr));
+        init_debug(&env);
+
+        // the environment is copied into the heap, without the `alloc` feature it is ignored
+        #[cfg(feature = "alloc")]
+        {
+            let env = env
+                .try_into_slices_ptr_mut(|_| true)
+                .map_err(|_| InitError::InvalidEnv)?;
+            let env_ptr = NonNull::new_unchecked(env as *mut [&'static [u8]]);
+            init_env(Some(env_ptr));
+        }
+        #[cfg(all(
+            feature = "alloc",
+            not(any(
+                feature = "std",
+                feature = "rustc-dep-of-std",
+                feature = "shadow-stack"
+            ))
+        ))]
+        crate::thread::local::init_thread();
+        init_proc_meta(task_abi_structures);
+    }
+    Ok(())
+}
+
 exported_func! {
     /// Initializes the safa-api
     /// if your programs are designed as C main function,
     ///
     /// use [`_c_api_init`] instead
+    ///
+    /// # Panics
+    /// If the arguments, the environment or the ABI structures are malformed, see [`try_api_init`]
     pub extern "C" fn sysapi_init(
         args: Slice<Str>,
         env: Slice<Slice<u8>>,
         task_abi_structures: AbiStructures,
     ) {
-        unsafe {
-        let args = args.try_into_str_slices_mut(|_| true).expect("invalid args passed to sysapi_init");
-        let args_ptr =  NonNull::new_unchecked(args as *mut [&'static str]) ;
+        if let Err(err) = unsafe { try_api_init(args, env, task_abi_structures) } {
+            panic!("failed to initialize safa-api: {err}");
+        }
+    }
+}
 
-        let env = env.try_into_slices_ptr_mut(|_| true).expect("invalid env passed to sysapi_init");
-        let env_ptr =  NonNull::new_unchecked(env as *mut [&'static [u8]]) ;
+/// Reports an initialization failure of [`_c_api_init`] and exits with [`InitError::exit_code`].
+#[cold]
+fn init_failed(err: InitError, task_abi_structures: &AbiStructures) -> ! {
+    // stderr is only known if the ABI structures can be trusted
+    let stderr = match err {
+        InitError::BadAbiStructures(_) => None,
+        _ => task_abi_structures.stdio.into_rust().2,
+    };
 
-        init_args(Some(args_ptr));
-        init_env(Some(env_ptr));
-        init_proc_meta(task_abi_structures);
-        }
+    if let Some(ri) = stderr.or_else(|| syscalls::fs::open_all("dev:/tty").ok()) {
+        let mut stderr = BufWriter::with_mode(ri, BufferMode::Block);
+        _ = writeln!(stderr, "safa-api: failed to initialize: {err}");
+        _ = stderr.flush();
     }
+    // nothing was set up to tear down, and the thread-local storage the teardown uses may not be either
+    syscalls::process::exit(err.exit_code())
 }
 
 /// Initializes the safa-api, converts arguments to C-style arguments, calls `main`, and exits with the result
@@ -64,9 +208,13 @@ exported_func! {
 ///
 /// this function is designed to be called from C code at _start before main,
 /// main should be passed as a parameter
+///
+/// # Safety
+/// Same as [`try_api_init`], `task_abi_structures` must point to the structures the kernel passed to the process.
+#[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
 #[cfg_attr(
-    not(any(feature = "std", feature = "rustc-dep-of-std")),
-    unsafe(no_mangle)
+    all(feature = "c-exports", not(feature = "std"), feature = "linkonce"),
+    linkage = "weak"
 )]
 pub unsafe extern "C" fn _c_api_init(
     args: Slice<Str>,
@@ -75,7 +223,10 @@ pub unsafe extern "C" fn _c_api_init(
     main: extern "C" fn(argc: i32, argv: *const *const u8) -> i32,
     atexit: extern "C" fn(i32),
 ) -> ! {
-    sysapi_init(args, env, *task_abi_structures);
+    let task_abi_structures = *task_abi_structures;
+    if let Err(err) = try_api_init(args, env, task_abi_structures) {
+        init_failed(err, &task_abi_structures);
+    }
 
     // Convert SafaOS `_start` arguments to `main` arguments
     fn c_main_args(args: Slice<Str>) -> (i32, *const *const u8) {
@@ -107,5 +258,5 @@ pub unsafe extern "C" fn _c_api_init(
     let (argc, argv) = c_main_args(args);
     let result = main(argc, argv);
     atexit(result);
-    syscalls::process::exit(result as usize)
+    super::teardown::exit(result as usize)
 }
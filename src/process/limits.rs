@@ -0,0 +1,73 @@
+//! Resource limits of processes, see [`Limits`]
+//!
+//! The kernel doesn't enforce any per-process limits yet, so every process is unlimited,
+//! [`current_limits`] reports that and spawning a process with limits set fails with [`ErrorStatus::NotSupported`]
+//! instead of silently ignoring them.
+
+use core::num::NonZero;
+
+use safa_abi::errors::ErrorStatus;
+
+/// The resource limits of a process, [`None`] means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum amount of pages the process can have mapped for its heap
+    pub max_heap_pages: Option<NonZero<usize>>,
+    /// The maximum amount of threads the process can have alive at once
+    pub max_threads: Option<NonZero<usize>>,
+    /// The maximum amount of resources the process can have open at once
+    pub max_resources: Option<NonZero<usize>>,
+}
+
+impl Limits {
+    /// No limits at all.
+    pub const UNLIMITED: Self = Self {
+        max_heap_pages: None,
+        max_threads: None,
+        max_resources: None,
+    };
+
+    /// Returns whether or not no limits are set.
+    pub const fn is_unlimited(&self) -> bool {
+        self.max_heap_pages.is_none() && self.max_threads.is_none() && self.max_resources.is_none()
+    }
+
+    /// Sets the maximum amount of pages the process can have mapped for its heap.
+    pub const fn set_max_heap_pages(&mut self, pages: Option<NonZero<usize>>) -> &mut Self {
+        self.max_heap_pages = pages;
+        self
+    }
+
+    /// Sets the maximum amount of threads the process can have alive at once.
+    pub const fn set_max_threads(&mut self, threads: Option<NonZero<usize>>) -> &mut Self {
+        self.max_threads = threads;
+        self
+    }
+
+    /// Sets the maximum amount of resources the process can have open at once.
+    pub const fn set_max_resources(&mut self, resources: Option<NonZero<usize>>) -> &mut Self {
+        self.max_resources = resources;
+        self
+    }
+
+    /// Checks whether or not the limits can be applied to a spawned process.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::NotSupported`] if any limit is set, as the kernel doesn't support limits yet
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub(crate) const fn check_supported(&self) -> Result<(), ErrorStatus> {
+        if self.is_unlimited() {
+            Ok(())
+        } else {
+            Err(ErrorStatus::NotSupported)
+        }
+    }
+}
+
+/// Returns the resource limits of the current process.
+///
+/// Always [`Limits::UNLIMITED`] for now, as the kernel doesn't support limits yet.
+#[inline]
+pub const fn current_limits() -> Limits {
+    Limits::UNLIMITED
+}
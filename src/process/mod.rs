@@ -7,15 +7,38 @@ use core::{cell::UnsafeCell, mem::MaybeUninit};
 use safa_abi::process::AbiStructures;
 
 pub mod args;
+#[cfg(feature = "alloc")]
+pub mod command;
+#[cfg(feature = "alloc")]
+pub mod cwd;
+#[cfg(feature = "alloc")]
 pub mod env;
 #[cfg(not(feature = "std"))]
 pub mod init;
+pub mod limits;
+pub mod name;
+pub mod pipe;
+#[cfg(feature = "alloc")]
+pub mod reexec;
 pub mod stdio;
+pub mod teardown;
+pub mod umask;
+#[cfg(feature = "alloc")]
+pub mod watchdog;
+#[cfg(not(feature = "std"))]
 pub use init::*;
+pub use limits::{current_limits, Limits};
+pub use name::{name, set_local_name, set_name};
+pub use pipe::pipe;
+#[cfg(feature = "alloc")]
+pub use reexec::{inherited_resources, reexec};
+pub use teardown::{at_exit, exit};
+pub use umask::{current_umask, umask};
 
 struct StaticAbiStructures(UnsafeCell<MaybeUninit<AbiStructures>>);
 
 impl StaticAbiStructures {
+    #[cfg(not(feature = "std"))]
     pub unsafe fn init(&self, structures: AbiStructures) {
         let ptr = self.0.get();
         ptr.write(MaybeUninit::new(structures));
@@ -69,6 +92,7 @@ impl ElfExeInfo {
 }
 
 /// Sets the [`AbiStructures`].
+#[cfg(not(feature = "std"))]
 pub(self) unsafe fn init_proc_meta(value: AbiStructures) {
     unsafe { SAAPI_ABI_STRUCTURES.init(value) }
 }
@@ -0,0 +1,118 @@
+//! The name of the current process, see [`name`]
+//!
+//! The kernel names a process when spawning it (after its path unless the parent gave another name, see [`super::command::Command::name`])
+//! and can't rename it afterwards yet, nor tell the process which name it was given, so:
+//! - [`set_name`], which would change the name shown in process listings, fails with [`ErrorStatus::NotSupported`]
+//! - [`set_local_name`] changes the name kept by this crate instead, which starts as the path of the process,
+//!   is returned by [`name`] and given to the new process by [`super::reexec`], but isn't seen by anything else
+
+use core::{fmt, ops::Deref};
+
+use safa_abi::errors::ErrorStatus;
+
+use crate::{limits::MAX_NAME_LENGTH, sync::locks::Mutex};
+
+use super::args::ArgsIter;
+
+/// A copy of the name of the process, returned by [`name`]
+#[derive(Clone, Copy)]
+pub struct ProcessName {
+    buf: [u8; MAX_NAME_LENGTH],
+    len: usize,
+}
+
+impl ProcessName {
+    /// Copies `name`, truncated to the last character boundary that fits in [`MAX_NAME_LENGTH`] bytes.
+    fn truncated(name: &str) -> Self {
+        let mut len = name.len().min(MAX_NAME_LENGTH);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut buf = [0; MAX_NAME_LENGTH];
+        buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self { buf, len }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // only ever copied from a `&str` at a character boundary
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+}
+
+impl Deref for ProcessName {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for ProcessName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for ProcessName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// The name given with [`set_local_name`], `None` until then
+static NAME: Mutex<Option<ProcessName>> = Mutex::new(None);
+
+/// Changes the name of the current process shown in process listings to `name`, such as `worker: idle`.
+///
+/// # Returns
+/// - [`ErrorStatus::StrTooLong`] if `name` is longer than [`MAX_NAME_LENGTH`] bytes
+/// - [`ErrorStatus::NotSupported`] otherwise, as the kernel can't rename processes yet, nothing is changed,
+///   see [`set_local_name`] for changing the name kept by this crate
+pub fn set_name(name: &str) -> Result<(), ErrorStatus> {
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(ErrorStatus::StrTooLong);
+    }
+
+    Err(ErrorStatus::NotSupported)
+}
+
+/// Changes the name of the current process kept by this crate to `name`, as returned by [`name`] and used by [`super::reexec`].
+///
+/// Process listings don't see it, see the [module documentation](self).
+///
+/// # Returns
+/// - [`ErrorStatus::StrTooLong`] if `name` is longer than [`MAX_NAME_LENGTH`] bytes
+pub fn set_local_name(name: &str) -> Result<(), ErrorStatus> {
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(ErrorStatus::StrTooLong);
+    }
+
+    let mut buf = [0; MAX_NAME_LENGTH];
+    buf[..name.len()].copy_from_slice(name.as_bytes());
+    *NAME.lock() = Some(ProcessName {
+        buf,
+        len: name.len(),
+    });
+    Ok(())
+}
+
+/// Returns the name of the current process, the one given with [`set_local_name`] if any,
+/// otherwise its path (the first argument) truncated to [`MAX_NAME_LENGTH`] bytes.
+///
+/// Must be called after the API is initialized, see [`super::init`].
+pub fn name() -> ProcessName {
+    if let Some(name) = *NAME.lock() {
+        return name;
+    }
+
+    ProcessName::truncated(ArgsIter::get().get_index(0).unwrap_or_default())
+}
+
+/// Returns the name given with [`set_local_name`], if any.
+#[cfg(feature = "alloc")]
+pub(super) fn custom_name() -> Option<ProcessName> {
+    *NAME.lock()
+}
@@ -0,0 +1,127 @@
+//! Pipes for passing data between processes, see [`pipe`]
+//!
+//! SafaOS has no dedicated pipe resource yet, so each pipe is backed by a VTTY (see [`crate::vtty`]),
+//! the only kernel resource with two connected ends: what is written to one end is read from the other.
+//!
+//! VTTYs don't report the end of the data once the writing end is destroyed,
+//! so a reader should stop on its own condition, such as the writing process exiting (see [`super::command::Command::output`]),
+//! instead of reading until a read returns nothing.
+
+use core::time::Duration;
+
+use safa_abi::{
+    errors::ErrorStatus,
+    poll::{PollEntry, PollEvents},
+};
+
+use crate::{
+    resource::Resource,
+    syscalls::{self, types::Ri},
+};
+
+/// The reading end of a pipe, see [`pipe`]
+///
+/// Destroyed on drop.
+#[derive(Debug)]
+pub struct PipeReader {
+    resource: Resource,
+}
+
+/// The writing end of a pipe, see [`pipe`]
+///
+/// Destroyed on drop.
+#[derive(Debug)]
+pub struct PipeWriter {
+    resource: Resource,
+}
+
+impl PipeReader {
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.resource.ri()
+    }
+
+    /// Converts the reader into the resource backing it.
+    #[inline]
+    pub fn into_resource(self) -> Resource {
+        self.resource
+    }
+
+    /// A [`PollEntry`] that waits for data to read,
+    /// for use with [`syscalls::io::poll_resources`] alongside other resources.
+    #[inline]
+    pub const fn poll_entry(&self) -> PollEntry {
+        PollEntry::new(self.ri(), PollEvents::DATA_AVAILABLE)
+    }
+
+    /// Reads data from the pipe into `buf`, blocks until there is data available.
+    #[inline]
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        unsafe { self.resource.read(-1, buf) }
+    }
+
+    /// Reads data from the pipe into `buf` without blocking,
+    /// returns [`None`] if there is no data available.
+    pub fn try_read(&self, buf: &mut [u8]) -> Result<Option<usize>, ErrorStatus> {
+        // the zero timeout running out returns no events, which means that there is no data
+        let mut entries = [self.poll_entry()];
+        syscalls::io::poll_resources(&mut entries, Some(Duration::ZERO))?;
+        if !entries[0]
+            .returned_events()
+            .contains(PollEvents::DATA_AVAILABLE)
+        {
+            return Ok(None);
+        }
+
+        self.read(buf).map(Some)
+    }
+}
+
+impl PipeWriter {
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.resource.ri()
+    }
+
+    /// Converts the writer into the resource backing it.
+    #[inline]
+    pub fn into_resource(self) -> Resource {
+        self.resource
+    }
+
+    /// Writes `buf` to the pipe, returns the amount of bytes written.
+    #[inline]
+    pub fn write(&self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        unsafe { self.resource.write(-1, buf) }
+    }
+}
+
+/// Allocates the VTTY backing a pipe, returns its (mother, child) ends.
+fn alloc_ends() -> Result<(Resource, Resource), ErrorStatus> {
+    let (mother, child) = syscalls::io::vtty_alloc()?;
+    unsafe { Ok((Resource::from_raw(mother), Resource::from_raw(child))) }
+}
+
+/// Creates a new pipe, returns its connected (reader, writer) ends.
+///
+/// The writer is meant to be passed to another process, for example as its stdout,
+/// [`super::command::Stdio::piped`] does this for spawned processes.
+pub fn pipe() -> Result<(PipeReader, PipeWriter), ErrorStatus> {
+    // the mother side reads what is written to the child side, like a terminal reads the output of its processes
+    let (mother, child) = alloc_ends()?;
+    Ok((
+        PipeReader { resource: mother },
+        PipeWriter { resource: child },
+    ))
+}
+
+/// Creates a new pipe with the ends of the backing VTTY the other way around,
+/// so that the reader is the end a process reads its input from.
+#[cfg(feature = "alloc")]
+pub(crate) fn input_pipe() -> Result<(PipeReader, PipeWriter), ErrorStatus> {
+    let (mother, child) = alloc_ends()?;
+    Ok((
+        PipeReader { resource: child },
+        PipeWriter { resource: mother },
+    ))
+}
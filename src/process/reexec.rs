@@ -0,0 +1,103 @@
+//! Replacing the current process with a fresh instance of its executable, see [`reexec`]
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use safa_abi::{errors::ErrorStatus, process::SpawnFlags};
+
+use crate::{
+    process::{
+        args::ArgsIter,
+        command::{Command, SpawnError},
+        env,
+    },
+    syscalls::types::Ri,
+};
+
+/// The environment variable listing the resources inherited through [`reexec`], as comma-separated resource IDs.
+pub const INHERITED_RESOURCES_VAR: &str = "SAFA_INHERITED_RIS";
+
+/// Spawns a new instance of the current executable with the current arguments, working directory and environment,
+/// plus the variables `extra_env`, passing it the resources `preserved_resources`, then exits the current process with [`super::exit`], running its teardown.
+///
+/// This is the building block of in-place upgrades: a daemon hands its listening sockets over to its new executable,
+/// which picks them up using [`inherited_resources`], without ever closing them.
+///
+/// The executable is found using the first argument of the process, the same resource IDs are used in the new process,
+/// which keeps the name given with [`super::name::set_local_name`] and also receives every other resource of the current process, see [`Command::map_resource`].
+///
+/// Only returns if spawning fails, in which case the environment is left unchanged.
+pub fn reexec(extra_env: &[(&str, &str)], preserved_resources: &[Ri]) -> SpawnError {
+    let mut args = ArgsIter::get();
+    let Some(path) = args.next() else {
+        return SpawnError::System(ErrorStatus::InvalidArgument);
+    };
+
+    let mut command = Command::new(path);
+    while let Some(arg) = args.next() {
+        command.arg(arg);
+    }
+
+    command.flags(SpawnFlags::CLONE_CWD);
+    if let Some(name) = super::name::custom_name() {
+        command.name(&name);
+    }
+    for &ri in preserved_resources {
+        command.inherit_resource(ri);
+    }
+
+    let mut inherited = String::new();
+    for (i, ri) in preserved_resources.iter().enumerate() {
+        if i != 0 {
+            inherited.push(',');
+        }
+        _ = write!(inherited, "{ri}");
+    }
+
+    // the spawned process gets a copy of the environment, so the changes are made to ours and undone on failure
+    let previous = extra_env
+        .iter()
+        .map(|(key, _)| key.as_bytes())
+        .chain(core::iter::once(INHERITED_RESOURCES_VAR.as_bytes()))
+        .map(|key| (key, env::env_get(key)))
+        .collect::<Vec<_>>();
+
+    for (key, value) in extra_env {
+        env::env_set(key.as_bytes(), value.as_bytes());
+    }
+    env::env_set(INHERITED_RESOURCES_VAR.as_bytes(), inherited.as_bytes());
+
+    let err = match command.spawn() {
+        Ok(_) => super::teardown::exit(0),
+        Err(err) => err,
+    };
+
+    // restored in reverse so that a key given more than once gets its original value back
+    for (key, value) in previous.into_iter().rev() {
+        match value {
+            Some(value) => env::env_set(key, &value),
+            None => env::env_remove(key),
+        }
+    }
+    err
+}
+
+/// Returns the resources passed to this process by the [`reexec`] of its previous instance,
+/// empty if it wasn't started by [`reexec`], invalid entries are ignored.
+pub fn inherited_resources() -> Vec<Ri> {
+    let Some(value) = env::env_get(INHERITED_RESOURCES_VAR.as_bytes()) else {
+        return Vec::new();
+    };
+
+    core::str::from_utf8(&value)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ri| ri.parse().ok())
+        .collect()
+}
@@ -1,12 +1,18 @@
 //! contains functions related to standard input/output/error streams descriptors
 //! api must be initialized before using these functions, see [`super::init`]
 
+use core::fmt;
+
 use crate::{
     exported_func,
-    process::proc_meta,
-    syscalls::{self, types::Ri},
+    process::{
+        proc_meta,
+        teardown::{self, Stage},
+    },
+    sync::locks::Mutex,
+    syscalls::{self, resources::ResourceKind, types::Ri},
 };
-use safa_abi::{ffi::option::COption, process::ProcessStdio};
+use safa_abi::{errors::ErrorStatus, ffi::option::COption, process::ProcessStdio};
 
 use crate::sync::cell::LazyCell;
 
@@ -16,7 +22,10 @@ static STDIN: LazyCell<Ri> = LazyCell::new(|| {
     if let Some(stdin) = stdin {
         stdin
     } else {
-        syscalls::fs::open_all("dev:/tty").expect("failed to fall back to `dev:/tty` for stdin")
+        let ri = syscalls::fs::open_all("dev:/tty")
+            .expect("failed to fall back to `dev:/tty` for stdin");
+        teardown::register(Stage::Release, || _ = syscalls::resources::destroy(*STDIN));
+        ri
     }
 });
 
@@ -25,7 +34,10 @@ static STDOUT: LazyCell<Ri> = LazyCell::new(|| {
     if let Some(stdout) = stdout {
         stdout
     } else {
-        syscalls::fs::open_all("dev:/tty").expect("failed to fall back to `dev:/tty` for stdout")
+        let ri = syscalls::fs::open_all("dev:/tty")
+            .expect("failed to fall back to `dev:/tty` for stdout");
+        teardown::register(Stage::Release, || _ = syscalls::resources::destroy(*STDOUT));
+        ri
     }
 });
 
@@ -34,7 +46,10 @@ static STDERR: LazyCell<Ri> = LazyCell::new(|| {
     if let Some(stderr) = stderr {
         stderr
     } else {
-        syscalls::fs::open_all("dev:/tty").expect("failed to fall back to `dev:/tty` for stderr")
+        let ri = syscalls::fs::open_all("dev:/tty")
+            .expect("failed to fall back to `dev:/tty` for stderr");
+        teardown::register(Stage::Release, || _ = syscalls::resources::destroy(*STDERR));
+        ri
     }
 });
 
@@ -85,3 +100,187 @@ exported_func! {
         *STDIN
     }
 }
+
+/// The size of the buffer of a [`BufWriter`]
+pub const STDIO_BUF_SIZE: usize = 1024;
+/// The offset the kernel treats as the end of the resource
+const END_OFFSET: isize = -1;
+
+/// When a [`BufWriter`] sends its buffered data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferMode {
+    /// Whenever a newline is written or the buffer is full, for interactive output such as terminals
+    Line,
+    /// Only when the buffer is full, for output redirected to files
+    Block,
+}
+
+impl BufferMode {
+    /// Picks the mode suited to the resource `ri`: [`BufferMode::Block`] for files and [`BufferMode::Line`] for anything else,
+    /// as the kernel doesn't tell terminals apart from other devices.
+    pub fn detect(ri: Ri) -> Self {
+        match syscalls::resources::kind(ri) {
+            Ok(ResourceKind::File) => Self::Block,
+            _ => Self::Line,
+        }
+    }
+}
+
+/// Buffers writes to a resource, reducing the amount of syscalls made by programs that write in small pieces.
+///
+/// Unlike [`printerr!`](crate::printerr), which makes a write and a sync syscall per call,
+/// the data is only written once a newline is written or the buffer fills up (depending on the [`BufferMode`]),
+/// or when [`BufWriter::flush`] is called, the buffer is flushed on drop, ignoring any errors.
+///
+/// The buffer of [`stdout`] is flushed when the process exits, see [`flush_stdout`].
+pub struct BufWriter {
+    ri: Ri,
+    mode: BufferMode,
+    buf: [u8; STDIO_BUF_SIZE],
+    len: usize,
+}
+
+impl BufWriter {
+    /// Buffers writes to `ri`, picking the mode using [`BufferMode::detect`].
+    pub fn new(ri: Ri) -> Self {
+        Self::with_mode(ri, BufferMode::detect(ri))
+    }
+
+    /// Buffers writes to `ri` using the mode `mode`.
+    pub const fn with_mode(ri: Ri, mode: BufferMode) -> Self {
+        Self {
+            ri,
+            mode,
+            buf: [0; STDIO_BUF_SIZE],
+            len: 0,
+        }
+    }
+
+    /// The resource written to.
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.ri
+    }
+
+    #[inline]
+    pub const fn mode(&self) -> BufferMode {
+        self.mode
+    }
+
+    /// Changes the mode, the data buffered so far is kept.
+    #[inline]
+    pub const fn set_mode(&mut self, mode: BufferMode) {
+        self.mode = mode;
+    }
+
+    /// Returns the data that was written but not yet sent.
+    #[inline]
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn write_direct(&self, mut bytes: &[u8]) -> Result<(), ErrorStatus> {
+        while !bytes.is_empty() {
+            match syscalls::io::write(self.ri, END_OFFSET, bytes)? {
+                0 => return Err(ErrorStatus::Generic),
+                written => bytes = &bytes[written..],
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_buf(&mut self) -> Result<(), ErrorStatus> {
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        // the data is dropped even on failure, so that a broken output doesn't fail every later write
+        let results = self.write_direct(&self.buf[..self.len]);
+        self.len = 0;
+        results
+    }
+
+    /// Writes all of `bytes`, sending them depending on the mode, see [`BufWriter`].
+    pub fn write_all(&mut self, bytes: &[u8]) -> Result<(), ErrorStatus> {
+        if self.len + bytes.len() > self.buf.len() {
+            self.flush_buf()?;
+        }
+
+        // writes that can't fit into the buffer skip it entirely
+        if bytes.len() >= self.buf.len() {
+            return self.write_direct(bytes);
+        }
+
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+
+        if self.mode == BufferMode::Line && bytes.contains(&b'\n') {
+            self.flush_buf()?;
+        }
+        Ok(())
+    }
+
+    /// Sends all of the buffered data and syncs the resource.
+    pub fn flush(&mut self) -> Result<(), ErrorStatus> {
+        self.flush_buf()?;
+        syscalls::io::sync(self.ri)
+    }
+}
+
+impl fmt::Write for BufWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl fmt::Debug for BufWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufWriter")
+            .field("ri", &self.ri)
+            .field("mode", &self.mode)
+            .field("buffered", &self.len)
+            .finish()
+    }
+}
+
+impl Drop for BufWriter {
+    fn drop(&mut self) {
+        _ = self.flush();
+    }
+}
+
+/// The buffered writer of stdout, created on first use
+static STDOUT_WRITER: Mutex<Option<BufWriter>> = Mutex::new(None);
+
+/// Calls `f` with the buffered writer of stdout, used by [`print!`](crate::print) and [`println!`](crate::println).
+pub fn stdout<R>(f: impl FnOnce(&mut BufWriter) -> R) -> R {
+    let mut writer = STDOUT_WRITER.lock();
+    f(writer.get_or_insert_with(|| {
+        teardown::register(Stage::Flush, || _ = flush_stdout());
+        BufWriter::new(sysget_stdout())
+    }))
+}
+
+/// Flushes the buffered writer of stdout, called at exit once stdout was written to (see [`super::teardown`]),
+/// programs exiting in other ways should call it first.
+pub fn flush_stdout() -> Result<(), ErrorStatus> {
+    match STDOUT_WRITER.lock().as_mut() {
+        Some(writer) => writer.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Same as [`flush_stdout`] but does nothing if stdout is being written to, for use in contexts such as panic handlers.
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+pub(crate) fn try_flush_stdout() {
+    if let Some(mut writer) = STDOUT_WRITER.try_lock() {
+        if let Some(writer) = writer.as_mut() {
+            _ = writer.flush();
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    stdout(|writer| _ = fmt::Write::write_fmt(writer, args));
+}
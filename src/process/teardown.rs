@@ -0,0 +1,120 @@
+//! Ordered teardown of the process at exit, see [`exit`]
+//!
+//! When the process exits through [`exit`] (which `main` returning to [`super::init::_c_api_init`] and [`super::reexec`] do),
+//! the following run in order:
+//! 1. the handlers registered with [`at_exit`], the most recently registered first
+//! 2. the destructors of the main thread's thread-local values, see [`crate::thread::local`]
+//! 3. flushing buffered output, such as the buffer of [`stdout`](super::stdio::stdout)
+//! 4. releasing the kernel resources held by the crate's globals,
+//!    such as the `dev:/tty` resources the standard streams fall back to
+//!
+//! The crate's globals register their handlers when they are initialized, so only the globals that were used are torn down.
+//! Their memory (such as the environment's) isn't freed, as the whole address space is reclaimed with the process.
+//!
+//! Exiting in any other way, such as with [`crate::syscalls::process::exit`] or by panicking, skips the teardown,
+//! the panic handler only tries to flush [`stdout`](super::stdio::stdout).
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use safa_abi::errors::ErrorStatus;
+
+use crate::{sync::locks::Mutex, syscalls};
+
+/// The maximum amount of handlers, including the ones registered by the crate's globals
+pub const MAX_EXIT_HANDLERS: usize = 32;
+
+/// The stages of the teardown, in the order they run, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stage {
+    User,
+    Flush,
+    Release,
+}
+
+/// A registered handler and the stage it runs in
+type Handler = (Stage, fn());
+
+struct Handlers {
+    handlers: [Option<Handler>; MAX_EXIT_HANDLERS],
+}
+
+static HANDLERS: Mutex<Handlers> = Mutex::new(Handlers {
+    handlers: [None; MAX_EXIT_HANDLERS],
+});
+static TORN_DOWN: AtomicBool = AtomicBool::new(false);
+
+impl Handlers {
+    fn push(&mut self, stage: Stage, handler: fn()) -> Result<(), ErrorStatus> {
+        let slot = self
+            .handlers
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(ErrorStatus::OutOfMemory)?;
+        *slot = Some((stage, handler));
+        Ok(())
+    }
+
+    /// Takes out the most recently registered handler of `stage`.
+    fn pop(&mut self, stage: Stage) -> Option<fn()> {
+        self.handlers
+            .iter_mut()
+            .rev()
+            .find(|slot| matches!(slot, Some((s, _)) if *s == stage))
+            .and_then(Option::take)
+            .map(|(_, handler)| handler)
+    }
+}
+
+/// Registers a teardown handler of the crate's globals.
+pub(crate) fn register(stage: Stage, handler: fn()) {
+    if HANDLERS.lock().push(stage, handler).is_err() {
+        crate::printerrln!("safa-api: too many exit handlers, a global won't be torn down at exit");
+    }
+}
+
+/// Registers `handler` to be called when the process exits through [`exit`], before the crate's globals are torn down.
+///
+/// Handlers are called in the reverse order of their registration, and may register more handlers.
+///
+/// # Returns
+/// - [`ErrorStatus::OutOfMemory`] if [`MAX_EXIT_HANDLERS`] handlers are already registered
+pub fn at_exit(handler: fn()) -> Result<(), ErrorStatus> {
+    HANDLERS.lock().push(Stage::User, handler)
+}
+
+/// Runs the teardown described in the [module documentation](self), only the first call does anything.
+///
+/// Called by [`exit`], only needed by programs exiting with the raw syscalls.
+pub fn run_teardown() {
+    if TORN_DOWN.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    // the lock isn't held while a handler runs, so that handlers can register more handlers
+    let run_stage = |stage| {
+        while let Some(handler) = { HANDLERS.lock().pop(stage) } {
+            handler();
+        }
+    };
+
+    run_stage(Stage::User);
+    #[cfg(all(
+        feature = "alloc",
+        not(any(
+            feature = "std",
+            feature = "rustc-dep-of-std",
+            feature = "shadow-stack"
+        ))
+    ))]
+    crate::thread::local::run_destructors();
+    run_stage(Stage::Flush);
+    run_stage(Stage::Release);
+}
+
+/// Tears the process down (see the [module documentation](self)) and exits with the code `code`.
+///
+/// A handler calling this exits immediately, without running the rest of the teardown.
+pub fn exit(code: usize) -> ! {
+    run_teardown();
+    syscalls::process::exit(code)
+}
@@ -0,0 +1,34 @@
+//! The file mode creation mask of the current process, see [`umask`]
+//!
+//! The kernel doesn't have file permissions yet, so the mask is kept by this crate for ported software that expects it,
+//! the fs create helpers ([`crate::fs::create_file`] and [`crate::fs::create_dir`]) apply it to the requested mode,
+//! but the resulting mode isn't enforced until the kernel supports permissions, and spawned processes start with [`DEFAULT_UMASK`].
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// The mask every process starts with, removes write permissions for the group and others.
+pub const DEFAULT_UMASK: u32 = 0o022;
+/// The permission bits, bits outside of it are ignored by the mask.
+pub const PERMISSION_BITS: u32 = 0o777;
+
+static UMASK: AtomicU32 = AtomicU32::new(DEFAULT_UMASK);
+
+/// Sets the file mode creation mask of the current process to `mask`, returning the previous mask.
+///
+/// Only the [`PERMISSION_BITS`] of `mask` are kept.
+#[inline]
+pub fn umask(mask: u32) -> u32 {
+    UMASK.swap(mask & PERMISSION_BITS, Ordering::Relaxed)
+}
+
+/// Returns the file mode creation mask of the current process.
+#[inline]
+pub fn current_umask() -> u32 {
+    UMASK.load(Ordering::Relaxed)
+}
+
+/// Returns `mode` with the bits of the current mask removed, the mode a file created with `mode` should get.
+#[inline]
+pub fn apply_umask(mode: u32) -> u32 {
+    mode & !current_umask()
+}
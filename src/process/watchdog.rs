@@ -0,0 +1,219 @@
+//! Supervising a child process through heartbeats, see [`Parent`] and [`Child`]
+//!
+//! The parent binds a unix socket the child connects to, each [`Child::pet`] sends a heartbeat over the connection,
+//! the parent notices a child that stopped heartbeating for longer than the deadline when it checks the watchdog.
+//!
+//! The kernel has no timers that can be polled, so the parent has to check the watchdog once [`Parent::time_left`] runs out,
+//! either by blocking in [`Parent::wait`] or by passing it as the timeout of its own [`Poller`].
+
+use core::time::Duration;
+
+use safa_abi::{
+    errors::ErrorStatus,
+    poll::{PollEntry, PollEvents},
+};
+
+use crate::{
+    poll::Poller,
+    process::{
+        command::{self, Command, SpawnError},
+        env,
+    },
+    sockets::{UnixListener, UnixListenerBuilder, UnixSockConnection, UnixSockConnectionBuilder},
+    syscalls,
+    time::Instant,
+};
+
+/// The environment variable holding the address of the watchdog a child spawned by [`Parent::spawn`] reports to.
+pub const WATCHDOG_ADDR_VAR: &str = "SAFA_WATCHDOG_ADDR";
+
+/// The state of a watched child, see [`Parent::check`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The child heartbeated (or was spawned) within the deadline
+    Alive,
+    /// The child didn't heartbeat within the deadline
+    Expired,
+    /// The child closed its connection to the watchdog, usually because it exited
+    Disconnected,
+}
+
+/// The supervising side of a watchdog, see the [module documentation](self)
+pub struct Parent {
+    listener: UnixListener,
+    conn: Option<UnixSockConnection>,
+    deadline: Duration,
+    last_beat: Instant,
+}
+
+impl Parent {
+    /// Creates a new watchdog expecting a heartbeat at least every `deadline`.
+    pub fn new(deadline: Duration) -> Result<Self, ErrorStatus> {
+        let mut builder = UnixListenerBuilder::autobind();
+        builder.set_non_blocking(true).set_backlog(1);
+
+        Ok(Self {
+            listener: builder.bind()?,
+            conn: None,
+            deadline,
+            last_beat: Instant::now(),
+        })
+    }
+
+    /// The address children connect to, see [`Child::connect`].
+    #[inline]
+    pub fn addr(&self) -> &str {
+        self.listener.local_addr()
+    }
+
+    /// The maximum time allowed between two heartbeats.
+    #[inline]
+    pub const fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Spawns `command` as the watched child, with [`WATCHDOG_ADDR_VAR`] set to [`Parent::addr`] so that it can use [`Child::from_env`].
+    ///
+    /// Used both for the first spawn and to restart a child that expired or disconnected,
+    /// the connection to the previous child is dropped and the deadline starts over.
+    pub fn spawn(&mut self, command: &Command) -> Result<command::Child, SpawnError> {
+        // the spawned process gets a copy of the environment, so the variable is only set for the duration of the spawn
+        let key = WATCHDOG_ADDR_VAR.as_bytes();
+        let previous = env::env_get(key);
+        env::env_set(key, self.addr().as_bytes());
+
+        let results = command.spawn();
+
+        match previous {
+            Some(value) => env::env_set(key, &value),
+            None => env::env_remove(key),
+        }
+
+        let child = results?;
+        self.conn = None;
+        self.last_beat = Instant::now();
+        Ok(child)
+    }
+
+    /// Processes the pending heartbeats without blocking and returns the state of the child.
+    ///
+    /// After [`Status::Disconnected`] is returned the watchdog waits for a new child to connect,
+    /// reporting [`Status::Expired`] once the deadline passes unless one does.
+    pub fn check(&mut self) -> Result<Status, ErrorStatus> {
+        if self.conn.is_none() {
+            match self.listener.accept() {
+                Ok(mut conn) => {
+                    conn.set_can_block(false)?;
+                    self.conn = Some(conn);
+                    self.last_beat = Instant::now();
+                }
+                Err(ErrorStatus::WouldBlock) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if let Some(conn) = &mut self.conn {
+            let mut buf = [0u8; 64];
+            loop {
+                match conn.read(&mut buf) {
+                    Ok(0) => {
+                        self.conn = None;
+                        return Ok(Status::Disconnected);
+                    }
+                    Ok(_) => self.last_beat = Instant::now(),
+                    Err(ErrorStatus::WouldBlock) => break,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        if self.last_beat.elapsed() >= self.deadline {
+            Ok(Status::Expired)
+        } else {
+            Ok(Status::Alive)
+        }
+    }
+
+    /// The time left before the child expires unless it heartbeats, the longest a caller should go without calling [`Parent::check`].
+    pub fn time_left(&self) -> Duration {
+        self.deadline.saturating_sub(self.last_beat.elapsed())
+    }
+
+    /// Blocks until the child expires or disconnects, returning which one happened.
+    pub fn wait(&mut self) -> Result<Status, ErrorStatus> {
+        loop {
+            let status = self.check()?;
+            if status != Status::Alive {
+                return Ok(status);
+            }
+
+            let mut entries = [
+                PollEntry::new(self.listener.ri(), PollEvents::DATA_AVAILABLE),
+                PollEntry::new(0, PollEvents::NONE),
+            ];
+            let count = match &self.conn {
+                Some(conn) => {
+                    entries[1] = PollEntry::new(conn.ri(), PollEvents::DATA_AVAILABLE);
+                    2
+                }
+                None => 1,
+            };
+
+            syscalls::io::poll_resources(&mut entries[..count], Some(self.time_left()))?;
+        }
+    }
+
+    /// Registers the resources the heartbeats arrive on in `poller`, [`Parent::check`] should be called when any of them is ready.
+    ///
+    /// The child's connection is only known after a call to [`Parent::check`] accepted it, so this should be called again after each check,
+    /// already registered resources are skipped.
+    pub fn register(&self, poller: &mut Poller) -> Result<(), ErrorStatus> {
+        let conn = self.conn.as_ref().map(UnixSockConnection::ri);
+        for ri in core::iter::once(self.listener.ri()).chain(conn) {
+            match poller.register(ri, PollEvents::DATA_AVAILABLE) {
+                Ok(()) | Err(ErrorStatus::AlreadyExists) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The supervised side of a watchdog, see the [module documentation](self)
+pub struct Child {
+    conn: UnixSockConnection,
+}
+
+impl Child {
+    /// Connects to the watchdog at `addr`, see [`Parent::addr`].
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::StrTooLong`] if `addr` isn't a valid abstract address
+    pub fn connect(addr: &str) -> Result<Self, ErrorStatus> {
+        let builder = UnixSockConnectionBuilder::from_abstract_path(addr)
+            .map_err(|()| ErrorStatus::StrTooLong)?;
+
+        let mut conn = builder.connect()?;
+        // a parent that is slow to check shouldn't block the child, pending heartbeats are enough
+        conn.set_can_block(false)?;
+        Ok(Self { conn })
+    }
+
+    /// Connects to the watchdog of the parent, returns [`None`] if the process wasn't spawned by [`Parent::spawn`].
+    pub fn from_env() -> Result<Option<Self>, ErrorStatus> {
+        let Some(addr) = env::env_get(WATCHDOG_ADDR_VAR.as_bytes()) else {
+            return Ok(None);
+        };
+
+        let addr = core::str::from_utf8(&addr).map_err(|_| ErrorStatus::InvalidStr)?;
+        Self::connect(addr).map(Some)
+    }
+
+    /// Sends a heartbeat, telling the parent the process is still alive.
+    pub fn pet(&mut self) -> Result<(), ErrorStatus> {
+        match self.conn.write(b".") {
+            Ok(_) | Err(ErrorStatus::WouldBlock) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
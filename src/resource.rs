@@ -9,7 +9,8 @@ use crate::syscalls::{self, types::Ri};
 pub struct Resource(Ri);
 
 impl Resource {
-    /// Safety: `raw` must be a valid resource.
+    /// # Safety
+    /// `raw` must be a valid resource.
     #[inline]
     pub unsafe fn from_raw(raw: Ri) -> Self {
         Self(raw)
@@ -23,17 +24,23 @@ impl Resource {
     #[inline]
     /// [`syscalls::fs::open`].
     pub fn open(path: &str, options: OpenOptions) -> Result<Self, ErrorStatus> {
-        syscalls::fs::open(path, options).map(|ri| Resource(ri))
+        syscalls::fs::open(path, options).map(Resource)
     }
 
     #[inline]
     /// [`syscalls::io::read`].
+    ///
+    /// # Safety
+    /// The resource must support reading, what the kernel does for other resources is unspecified.
     pub unsafe fn read(&self, offset: isize, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
         syscalls::io::read(self.0, offset, buf)
     }
 
     #[inline]
     /// [`syscalls::io::write`].
+    ///
+    /// # Safety
+    /// The resource must support writing, what the kernel does for other resources is unspecified.
     pub unsafe fn write(&self, offset: isize, buf: &[u8]) -> Result<usize, ErrorStatus> {
         syscalls::io::write(self.0, offset, buf)
     }
@@ -52,14 +59,23 @@ impl Resource {
 
     #[inline]
     /// [`syscalls::io::io_command`].
+    ///
+    /// # Safety
+    /// `cmd` and `arg` must be a command understood by the resource, commands may change the state of the resource in any way.
     pub unsafe fn io_command(&self, cmd: u16, arg: u64) -> Result<(), ErrorStatus> {
         syscalls::io::io_command(self.0, cmd, arg)
     }
 
+    #[inline]
+    /// [`syscalls::resources::kind`].
+    pub fn kind(&self) -> Result<syscalls::resources::ResourceKind, ErrorStatus> {
+        syscalls::resources::kind(self.0)
+    }
+
     #[inline]
     /// Attempts to create a new resource pointing to the same data.
     pub fn clone(&self) -> Result<Resource, ErrorStatus> {
-        syscalls::resources::dup(self.ri()).map(|ri| Resource(ri))
+        syscalls::resources::dup(self.ri()).map(Resource)
     }
 }
 
@@ -6,6 +6,7 @@ use safa_abi::errors::ErrorStatus;
 
 use crate::{
     abi::mem::ShmFlags,
+    limits::page_size,
     mem::MemoryMapper,
     resource::Resource,
     syscalls::{self},
@@ -50,7 +51,8 @@ impl SharedObject {
         let shm_res = syscalls::mem::shm_open(key, ShmFlags::NONE)
             .map(|ri| unsafe { Resource::from_raw(ri) })?;
 
-        let (mem_map, buf) = mem_mapper.map_next_resource(size.div_ceil(4096), &shm_res, None)?;
+        let (mem_map, buf) =
+            mem_mapper.map_next_resource(size.div_ceil(page_size()), &shm_res, None)?;
         Ok(Self {
             key,
             _mem_map: mem_map,
@@ -62,7 +64,7 @@ impl SharedObject {
     ///
     /// Returns a Result containing the SharedObject or an ErrorStatus if allocation fails.
     pub fn allocate(size: usize) -> Result<Self, crate::errors::ErrorStatus> {
-        let pages = size.div_ceil(4096);
+        let pages = size.div_ceil(page_size());
         let flags = ShmFlags::NONE;
 
         let (key, shm) = raw_create(pages, flags).expect("Failed to open ShmKey");
@@ -94,14 +96,15 @@ impl SharedObject {
 
     /// Returns a reference to the shared memory buffer.
     ///
-    /// # Safety:
+    /// # Safety
     /// Synchorization should be done between the memory-spaces that share that memory, as by using IPC and such.
     #[inline(always)]
     pub const unsafe fn data(&self) -> &[u8] {
         unsafe { self.buf.as_ref() }
     }
     /// Returns a muttable reference to the shared memory buffer.
-    /// # Safety:
+    ///
+    /// # Safety
     /// Synchorization should be done between the memory-spaces that share that memory, as by using IPC and such.
     #[inline(always)]
     pub const unsafe fn data_mut(&mut self) -> &mut [u8] {
@@ -0,0 +1,457 @@
+//! Buffered socket connections for text-based protocols, see [`BufStream`]
+
+use core::{mem::ManuallyDrop, time::Duration};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use safa_abi::errors::ErrorStatus;
+
+use super::{Socket, TCPStream, UnixSockConnection};
+use crate::time::Instant;
+
+/// The default size of each of the read and write buffers of a [`BufStream`]
+pub const DEFAULT_BUF_SIZE: usize = 4096;
+
+/// Makes a [`BufStream`] send small writes as soon as enough of them piled up or once they waited long enough,
+/// instead of only once the write buffer is full, see [`BufStream::set_coalescing`].
+///
+/// Useful for chatty protocols where every small write would otherwise cost a syscall (and a context switch to the peer),
+/// while still bounding how long a write is held back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coalescing {
+    /// The amount of buffered bytes that triggers a send
+    pub threshold: usize,
+    /// The longest time written data is held back before being sent
+    pub max_delay: Duration,
+}
+
+impl Coalescing {
+    /// Sends once `threshold` bytes are buffered or once the oldest buffered data waited for `max_delay`.
+    pub const fn new(threshold: usize, max_delay: Duration) -> Self {
+        Self {
+            threshold,
+            max_delay,
+        }
+    }
+}
+
+/// Counters of the writes made to a [`BufStream`], see [`BufStream::write_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    /// The amount of writes made to the stream
+    pub writes: u64,
+    /// The amount of bytes written to the stream
+    pub bytes: u64,
+    /// The amount of writes made to the underlying connection, each costing a syscall
+    pub sends: u64,
+    /// The amount of sends triggered by the [`Coalescing::max_delay`] passing
+    pub delayed_sends: u64,
+}
+
+/// A connected socket that can be read from and written to, see [`BufStream`]
+pub trait Connection {
+    /// Reads into `buf`, returning the amount of bytes read, 0 meaning the peer closed the connection.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus>;
+    /// Writes `buf`, returning the amount of bytes written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus>;
+}
+
+impl Connection for Socket {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        Socket::read(self, buf)
+    }
+
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        Socket::write(self, buf)
+    }
+}
+
+impl Connection for UnixSockConnection {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        UnixSockConnection::read(self, buf)
+    }
+
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        UnixSockConnection::write(self, buf)
+    }
+}
+
+impl Connection for TCPStream {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        TCPStream::read(self, buf)
+    }
+
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        TCPStream::write(self, buf)
+    }
+}
+
+impl<C: Connection + ?Sized> Connection for &mut C {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        (**self).read(buf)
+    }
+
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        (**self).write(buf)
+    }
+}
+
+/// Wraps a [`Connection`] buffering both reads and writes,
+/// reducing the amount of syscalls made by protocols that read and write in small pieces (lines, headers, length prefixes).
+///
+/// Written data is only sent once the write buffer is full or when [`BufStream::flush`] is called
+/// (or earlier if [coalescing](BufStream::set_coalescing) is enabled), the write buffer is flushed on drop, ignoring any errors, call [`BufStream::flush`] to handle them.
+pub struct BufStream<C: Connection> {
+    inner: C,
+    read_buf: Box<[u8]>,
+    /// the start of the unread data in `read_buf`
+    read_pos: usize,
+    /// the end of the unread data in `read_buf`
+    read_filled: usize,
+    write_buf: Vec<u8>,
+    coalescing: Option<Coalescing>,
+    /// when the oldest data in `write_buf` was written
+    pending_since: Option<Instant>,
+    stats: WriteStats,
+}
+
+impl<C: Connection> BufStream<C> {
+    /// Wraps `inner` with read and write buffers of [`DEFAULT_BUF_SIZE`] each.
+    pub fn new(inner: C) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Wraps `inner` with a read buffer of `read_capacity` bytes and a write buffer of `write_capacity` bytes.
+    pub fn with_capacity(read_capacity: usize, write_capacity: usize, inner: C) -> Self {
+        Self {
+            inner,
+            read_buf: alloc::vec![0; read_capacity].into_boxed_slice(),
+            read_pos: 0,
+            read_filled: 0,
+            write_buf: Vec::with_capacity(write_capacity),
+            coalescing: None,
+            pending_since: None,
+            stats: WriteStats::default(),
+        }
+    }
+
+    /// Gets a reference to the underlying connection.
+    #[inline]
+    pub const fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying connection,
+    /// reading from or writing to it directly bypasses the buffers.
+    #[inline]
+    pub const fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Returns the data that was read from the connection but not yet consumed.
+    #[inline]
+    pub fn buffer(&self) -> &[u8] {
+        &self.read_buf[self.read_pos..self.read_filled]
+    }
+
+    /// Returns the data that was written but not yet sent.
+    #[inline]
+    pub fn pending_writes(&self) -> &[u8] {
+        &self.write_buf
+    }
+
+    /// Enables sending the buffered data early as described by `coalescing`, [`None`] (the default) only sends when the buffer is full.
+    ///
+    /// There is no timer behind the [`Coalescing::max_delay`], it is only checked on each write and by [`BufStream::flush_if_due`],
+    /// callers that may stop writing for a while should call the latter once [`BufStream::flush_deadline`] passes,
+    /// for example by using it as the timeout of a [`crate::poll::Poller`].
+    ///
+    /// A `threshold` larger than the write buffer behaves like the write buffer's capacity.
+    pub fn set_coalescing(&mut self, coalescing: Option<Coalescing>) {
+        self.coalescing = coalescing;
+        // data buffered before coalescing was enabled starts waiting now
+        if coalescing.is_some() && !self.write_buf.is_empty() && self.pending_since.is_none() {
+            self.pending_since = Some(Instant::now());
+        }
+    }
+
+    #[inline]
+    pub const fn coalescing(&self) -> Option<Coalescing> {
+        self.coalescing
+    }
+
+    /// Returns the counters of the writes made so far, comparing [`WriteStats::writes`] to [`WriteStats::sends`]
+    /// shows how many syscalls were saved by buffering.
+    #[inline]
+    pub const fn write_stats(&self) -> WriteStats {
+        self.stats
+    }
+
+    /// Resets the counters returned by [`BufStream::write_stats`] to zero.
+    #[inline]
+    pub fn reset_write_stats(&mut self) {
+        self.stats = WriteStats::default();
+    }
+
+    /// Returns when the buffered data has to be sent by, [`None`] if there is nothing buffered or coalescing is disabled.
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        let coalescing = self.coalescing?;
+        self.pending_since?.checked_add(coalescing.max_delay)
+    }
+
+    /// Sends the buffered data if it waited for longer than the [`Coalescing::max_delay`], returns whether it was sent.
+    pub fn flush_if_due(&mut self) -> Result<bool, ErrorStatus> {
+        match self.flush_deadline() {
+            Some(deadline) if Instant::now() >= deadline => {
+                self.stats.delayed_sends += 1;
+                self.flush_buf()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Flushes the write buffer and returns the underlying connection, discarding any unconsumed read data.
+    ///
+    /// On failure the stream is returned alongside the error so that no data is lost.
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(mut self) -> Result<C, (Self, ErrorStatus)> {
+        if let Err(err) = self.flush() {
+            return Err((self, err));
+        }
+
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            // the buffers are dropped here, the connection is moved out, `this` is never used again
+            drop(core::ptr::read(&this.read_buf));
+            drop(core::ptr::read(&this.write_buf));
+            Ok(core::ptr::read(&this.inner))
+        }
+    }
+
+    /// Returns the unconsumed read data, reading more from the connection if there is none.
+    ///
+    /// An empty slice means the peer closed the connection, use [`BufStream::consume`] to mark the returned data as consumed.
+    pub fn fill_buf(&mut self) -> Result<&[u8], ErrorStatus> {
+        if self.read_pos >= self.read_filled {
+            self.read_filled = self.inner.read(&mut self.read_buf)?;
+            self.read_pos = 0;
+        }
+
+        Ok(self.buffer())
+    }
+
+    /// Marks `amount` bytes of the data returned by [`BufStream::fill_buf`] as consumed.
+    #[inline]
+    pub fn consume(&mut self, amount: usize) {
+        self.read_pos = (self.read_pos + amount).min(self.read_filled);
+    }
+
+    /// Reads into `buf`, returning the amount of bytes read, 0 meaning the peer closed the connection.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        // large reads with nothing buffered skip the buffer entirely
+        if self.read_pos >= self.read_filled && buf.len() >= self.read_buf.len() {
+            return self.inner.read(buf);
+        }
+
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+        buf[..amount].copy_from_slice(&available[..amount]);
+        self.consume(amount);
+        Ok(amount)
+    }
+
+    /// Reads exactly enough bytes to fill `buf`,
+    /// fails with [`ErrorStatus::ConnectionClosed`] if the peer closes the connection before that.
+    pub fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), ErrorStatus> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(ErrorStatus::ConnectionClosed),
+                read => buf = &mut buf[read..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads until `delim` (included) or until the peer closes the connection, appending the data to `out`.
+    ///
+    /// Returns the amount of bytes appended, 0 meaning the peer closed the connection.
+    pub fn read_until(&mut self, delim: u8, out: &mut Vec<u8>) -> Result<usize, ErrorStatus> {
+        let mut total = 0;
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(total);
+            }
+
+            let (amount, done) = match available.iter().position(|b| *b == delim) {
+                Some(index) => (index + 1, true),
+                None => (available.len(), false),
+            };
+
+            out.extend_from_slice(&available[..amount]);
+            self.consume(amount);
+            total += amount;
+
+            if done {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Reads a line including its `\n` (or until the peer closes the connection), appending it to `out`.
+    ///
+    /// Returns the amount of bytes appended, 0 meaning the peer closed the connection,
+    /// fails with [`ErrorStatus::InvalidStr`] without modifying `out` if the line isn't valid utf-8, the line is consumed regardless.
+    pub fn read_line(&mut self, out: &mut String) -> Result<usize, ErrorStatus> {
+        let mut line = Vec::new();
+        let amount = self.read_until(b'\n', &mut line)?;
+        let line = core::str::from_utf8(&line).map_err(|_| ErrorStatus::InvalidStr)?;
+
+        out.push_str(line);
+        Ok(amount)
+    }
+
+    /// Buffers `buf`, sending the buffered data first if `buf` doesn't fit,
+    /// returns the amount of bytes written.
+    ///
+    /// With [coalescing](BufStream::set_coalescing) enabled the buffered data is also sent once it reaches the threshold or waited long enough.
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        self.stats.writes += 1;
+
+        if self.write_buf.len() + buf.len() > self.write_buf.capacity() {
+            self.flush_buf()?;
+        }
+
+        // writes that can't fit into the buffer skip it entirely
+        if buf.len() >= self.write_buf.capacity() {
+            let written = self.inner.write(buf)?;
+            self.stats.sends += 1;
+            self.stats.bytes += written as u64;
+            return Ok(written);
+        }
+
+        if self.write_buf.is_empty() && self.coalescing.is_some() {
+            self.pending_since = Some(Instant::now());
+        }
+
+        self.write_buf.extend_from_slice(buf);
+        self.stats.bytes += buf.len() as u64;
+
+        if let Some(coalescing) = self.coalescing {
+            if self.write_buf.len() >= coalescing.threshold {
+                self.flush_buf()?;
+            } else {
+                self.flush_if_due()?;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Writes all of `buf`, see [`BufStream::write`].
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<(), ErrorStatus> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(ErrorStatus::ConnectionClosed),
+                written => buf = &buf[written..],
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_buf(&mut self) -> Result<(), ErrorStatus> {
+        let mut written = 0;
+        let results = loop {
+            if written >= self.write_buf.len() {
+                break Ok(());
+            }
+
+            let results = self.inner.write(&self.write_buf[written..]);
+            self.stats.sends += 1;
+
+            match results {
+                Ok(0) => break Err(ErrorStatus::ConnectionClosed),
+                Ok(amount) => written += amount,
+                Err(err) => break Err(err),
+            }
+        };
+
+        // keeps whatever wasn't sent so that a retry doesn't send data twice
+        self.write_buf.drain(..written);
+        if self.write_buf.is_empty() {
+            self.pending_since = None;
+        }
+        results
+    }
+
+    /// Sends all of the buffered data.
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), ErrorStatus> {
+        self.flush_buf()
+    }
+}
+
+impl<C: Connection> Drop for BufStream<C> {
+    fn drop(&mut self) {
+        _ = self.flush_buf();
+    }
+}
+
+impl<C: Connection + core::fmt::Debug> core::fmt::Debug for BufStream<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BufStream")
+            .field("inner", &self.inner)
+            .field("buffered_reads", &self.buffer().len())
+            .field("pending_writes", &self.write_buf.len())
+            .field("coalescing", &self.coalescing)
+            .field("write_stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+mod _std {
+    use std::io;
+
+    use super::{BufStream, Connection};
+
+    impl<C: Connection> io::Read for BufStream<C> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            BufStream::read(self, buf).map_err(crate::errors::into_io_error)
+        }
+    }
+
+    impl<C: Connection> io::BufRead for BufStream<C> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            BufStream::fill_buf(self).map_err(crate::errors::into_io_error)
+        }
+
+        fn consume(&mut self, amount: usize) {
+            BufStream::consume(self, amount)
+        }
+    }
+
+    impl<C: Connection> io::Write for BufStream<C> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            BufStream::write(self, buf).map_err(crate::errors::into_io_error)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            BufStream::flush(self).map_err(crate::errors::into_io_error)
+        }
+    }
+}
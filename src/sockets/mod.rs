@@ -1,7 +1,20 @@
+#[cfg(feature = "alloc")]
+pub mod buf;
 pub mod socket;
+pub mod tcp;
+pub mod udp;
+#[cfg(feature = "alloc")]
 pub mod unix;
 
-pub use socket::{Socket, SocketBuilder, SocketDomain, SocketKind};
+#[cfg(feature = "alloc")]
+pub use buf::{BufStream, Coalescing, Connection, WriteStats};
+pub use socket::{
+    DetailedSocketError, Socket, SocketBuilder, SocketDomain, SocketKind, SocketStats,
+};
+pub use tcp::{TCPListener, TCPStream};
+pub use udp::{RecvMsgInfo, UDPSocket};
+#[cfg(feature = "alloc")]
 pub use unix::{
-    UnixListener, UnixListenerBuilder, UnixSockConnection, UnixSockConnectionBuilder, UnixSockKind,
+    ListenerStats, UnixListener, UnixListenerBuilder, UnixSockConnection,
+    UnixSockConnectionBuilder, UnixSockKind, AUTOBIND_PREFIX,
 };
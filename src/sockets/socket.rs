@@ -1,13 +1,22 @@
-use core::{net::Ipv4Addr, ptr::NonNull};
+use core::{
+    net::Ipv4Addr,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use safa_abi::{
     errors::ErrorStatus,
+    poll::{PollEntry, PollEvents},
     sockets::{InetV4SocketAddr, SockMsgFlags, SocketAddr, ToSocketAddr},
 };
 
 use crate::{
+    errors::{TimeoutError, UnknownError, WaitError},
     resource::Resource,
+    sync::cancel::{self, CancellationToken},
     syscalls::{self, types::Ri},
+    time::Instant,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -23,6 +32,7 @@ pub enum SocketOpt {
     IpTTL = 3,
     /// Broad cast permissions.
     IpBroadcast = 4,
+    /// The pending asynchronous error of the socket, cleared when read, see [`Socket::take_detailed_error`].
     SocketError = 5,
 }
 
@@ -45,6 +55,7 @@ impl SocketKind {
         }
     }
 
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
     #[inline(always)]
     pub(crate) const fn from_raw(kind: AbiSocketKind) -> Option<(Self, bool)> {
         if kind.contains(AbiSocketKind::UNKNOWN) {
@@ -82,6 +93,7 @@ impl SocketDomain {
             Self::Local => AbiSocketDomain::LOCAL,
         }
     }
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
     #[inline(always)]
     pub(crate) const fn from_raw(r: AbiSocketDomain) -> Option<Self> {
         // FIXME: add to the ABI
@@ -96,9 +108,134 @@ impl SocketDomain {
     }
 }
 
+/// An error that occurred asynchronously on a socket, for example a previously sent datagram turning out to be unreachable,
+/// see [`Socket::take_detailed_error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetailedSocketError {
+    error: Result<ErrorStatus, UnknownError>,
+    origin: Option<core::net::SocketAddr>,
+}
+
+impl DetailedSocketError {
+    /// The kind of the error, or the raw error code if this crate doesn't know about it.
+    #[inline]
+    pub const fn error(&self) -> Result<ErrorStatus, UnknownError> {
+        self.error
+    }
+
+    /// The address the error originated from (for example the host that reported a destination as unreachable),
+    /// currently always [`None`] as the kernel only reports the error kind.
+    #[inline]
+    pub const fn origin(&self) -> Option<core::net::SocketAddr> {
+        self.origin
+    }
+}
+
+impl core::fmt::Display for DetailedSocketError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.error {
+            Ok(err) => write!(f, "{}", err.as_str())?,
+            Err(unknown) => write!(f, "{unknown}")?,
+        }
+
+        if let Some(origin) = self.origin {
+            write!(f, " (reported by {origin})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts a timeout to the milliseconds taken by [`SocketOpt::ReadTimeout`] and [`SocketOpt::WriteTimeout`],
+/// [`None`] becomes [`u64::MAX`], the same convention as polling, meaning no timeout.
+pub(crate) fn timeout_to_ms(timeout: Option<Duration>) -> u64 {
+    timeout.map_or(u64::MAX, |t| t.as_millis().min(u64::MAX as u128) as u64)
+}
+
+/// Counters of the data sent and received through a [`Socket`], see [`Socket::stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketStats {
+    /// The amount of bytes sent
+    pub bytes_sent: u64,
+    /// The amount of bytes received
+    pub bytes_received: u64,
+    /// The amount of successful sends, each sending a datagram or a chunk of a stream
+    pub packets_sent: u64,
+    /// The amount of successful receives, each receiving a datagram or a chunk of a stream
+    pub packets_received: u64,
+    /// The amount of sends and receives that failed, excluding [`ErrorStatus::WouldBlock`]
+    pub errors: u64,
+}
+
+#[derive(Debug)]
+struct StatsCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl StatsCounters {
+    const fn new() -> Self {
+        Self {
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            packets_sent: AtomicU64::new(0),
+            packets_received: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, results: &Result<usize, ErrorStatus>, packets: &AtomicU64, bytes: &AtomicU64) {
+        match results {
+            Ok(amount) => {
+                packets.fetch_add(1, Ordering::Relaxed);
+                bytes.fetch_add(*amount as u64, Ordering::Relaxed);
+            }
+            Err(ErrorStatus::WouldBlock) => {}
+            Err(_) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn record_send(&self, results: &Result<usize, ErrorStatus>) {
+        self.record(results, &self.packets_sent, &self.bytes_sent);
+    }
+
+    fn record_recv(&self, results: &Result<usize, ErrorStatus>) {
+        self.record(results, &self.packets_received, &self.bytes_received);
+    }
+
+    fn reset(&self) {
+        for counter in [
+            &self.bytes_sent,
+            &self.bytes_received,
+            &self.packets_sent,
+            &self.packets_received,
+            &self.errors,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self) -> SocketStats {
+        SocketStats {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
 /// Represents a socket.
 #[derive(Debug)]
-pub struct Socket(Resource);
+pub struct Socket {
+    resource: Resource,
+    stats: StatsCounters,
+}
 
 /// Represents a builder for creating sockets.
 #[derive(Debug, Clone, Copy)]
@@ -147,23 +284,45 @@ impl SocketBuilder {
         }
 
         syscalls::sockets::create(domain, kind, protocol)
-            .map(|ri| Socket(unsafe { Resource::from_raw(ri) }))
+            .map(|ri| Socket::new(unsafe { Resource::from_raw(ri) }))
     }
 }
 
 impl Socket {
-    /// Safety: resource must be a socket.
+    const fn new(resource: Resource) -> Self {
+        Self {
+            resource,
+            stats: StatsCounters::new(),
+        }
+    }
+
+    /// # Safety
+    /// resource must be a socket.
     pub unsafe fn from_resource(resource: Resource) -> Self {
-        Self(resource)
+        Self::new(resource)
     }
 
     #[inline]
     pub fn into_resource(self) -> Resource {
-        self.0
+        self.resource
     }
     #[inline]
     pub const fn resource(&self) -> &Resource {
-        &self.0
+        &self.resource
+    }
+
+    /// Returns the counters of the data sent and received through this handle so far,
+    /// including through the wrappers built on top of it (such as [`super::TCPStream`] and [`super::UDPSocket`]).
+    ///
+    /// The counters are maintained by this crate as the kernel doesn't expose per-socket counters yet,
+    /// so they only cover this handle, handles created with [`Self::duplicate`] or [`Self::from_resource`] start from zero.
+    pub fn stats(&self) -> SocketStats {
+        self.stats.snapshot()
+    }
+
+    /// Resets the counters returned by [`Self::stats`] to zero.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
     }
 
     /// Returns a new socket builder.
@@ -174,13 +333,13 @@ impl Socket {
     /// Wrapper around [`syscalls::sockets::listen`], configures the socket to listen for incoming connections.
     #[inline]
     pub fn listen(&self, backlog: usize) -> Result<(), ErrorStatus> {
-        syscalls::sockets::listen(self.0.ri(), backlog)
+        syscalls::sockets::listen(self.ri(), backlog)
     }
 
     /// Wrapper around [`syscalls::sockets::bind`], binds the socket to a specific address.
     #[inline]
     pub fn bind(&self, addr: &SocketAddr, size: usize) -> Result<(), ErrorStatus> {
-        syscalls::sockets::bind(self.0.ri(), addr, size)
+        syscalls::sockets::bind(self.ri(), addr, size)
     }
 
     /// Same as [`Self::bind`] but takes in a [`core::net::SocketAddrV4`].
@@ -193,7 +352,14 @@ impl Socket {
     /// Wrapper around [`syscalls::sockets::connect`], connects the socket to an address.
     #[inline]
     pub fn connect(&self, addr: &SocketAddr, size: usize) -> Result<(), ErrorStatus> {
-        syscalls::sockets::connect(self.0.ri(), &addr, size)
+        syscalls::sockets::connect(self.ri(), addr, size)
+    }
+
+    /// Same as [`Self::connect`] but takes in a [`core::net::SocketAddrV4`].
+    #[inline]
+    pub fn connect_to_addr(&self, addr: core::net::SocketAddrV4) -> Result<(), ErrorStatus> {
+        let abi = InetV4SocketAddr::new(addr.port(), *addr.ip());
+        self.connect(abi.as_generic(), size_of::<InetV4SocketAddr>())
     }
 
     /// Wrapper around [`syscalls::sockets::send_to`], sends data with flags to a specific address or to the connected address.
@@ -204,10 +370,15 @@ impl Socket {
         flags: SockMsgFlags,
         addr: Option<(&SocketAddr, usize)>,
     ) -> Result<usize, ErrorStatus> {
-        syscalls::sockets::send_to(self.0.ri(), buf, flags, addr)
+        let results = syscalls::sockets::send_to(self.ri(), buf, flags, addr);
+        self.stats.record_send(&results);
+        results
     }
 
     /// Like [`Self::send_to`] but takes in a [`core::net::SocketAddr`].
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::NotSupported`] if `addr` is an Ipv6 address, as the ABI has no Ipv6 socket addresses yet
     #[inline]
     pub fn send_to_addr(
         &self,
@@ -224,7 +395,7 @@ impl Socket {
                     Some((raw_addr.as_generic(), size_of::<InetV4SocketAddr>())),
                 )
             }
-            _ => todo!("IPV6 isn't yet implemented"),
+            core::net::SocketAddr::V6(_) => Err(ErrorStatus::NotSupported),
         }
     }
 
@@ -243,8 +414,9 @@ impl Socket {
         flags: SockMsgFlags,
         store_addr: Option<&mut (NonNull<SocketAddr>, usize)>,
     ) -> Result<usize, ErrorStatus> {
-        let results = syscalls::sockets::recv_from(self.0.ri(), buf, flags, store_addr)?;
-        Ok(results)
+        let results = syscalls::sockets::recv_from(self.ri(), buf, flags, store_addr);
+        self.stats.record_recv(&results);
+        results
     }
 
     /// Same as [`Self::recv_from`] but instead returns a [`core::net::SocketAddrV4`].
@@ -288,8 +460,8 @@ impl Socket {
         &self,
         store_addr: Option<&mut (NonNull<SocketAddr>, usize)>,
     ) -> Result<Socket, ErrorStatus> {
-        let results = syscalls::sockets::accept(self.0.ri(), store_addr)?;
-        let results = Socket(unsafe { Resource::from_raw(results) });
+        let results = syscalls::sockets::accept(self.ri(), store_addr)?;
+        let results = Socket::new(unsafe { Resource::from_raw(results) });
 
         Ok(results)
     }
@@ -311,18 +483,41 @@ impl Socket {
         self.accept_inner(Some(store_addr))
     }
 
+    /// Same as [`Self::accept_from`] but instead returns a [`core::net::SocketAddrV4`].
+    pub fn accept_from_addr(&self) -> Result<(Socket, core::net::SocketAddrV4), ErrorStatus> {
+        let mut addr = InetV4SocketAddr::new(0, Ipv4Addr::UNSPECIFIED);
+        let addr_ref = addr.as_non_null();
+        let socket = self.accept_from(&mut (addr_ref, size_of::<InetV4SocketAddr>()))?;
+
+        Ok((socket, core::net::SocketAddrV4::new(addr.ip(), addr.port())))
+    }
+
+    /// Reads data without consuming it, see [`SockMsgFlags::PEEK`].
+    #[inline]
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.recv(buf, SockMsgFlags::PEEK)
+    }
+
     /// Wrapper around [`syscalls::io::read`].
     pub fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
-        unsafe { self.0.read(0, buf) }
+        let results = unsafe { self.resource.read(0, buf) };
+        self.stats.record_recv(&results);
+        results
     }
 
     /// Wrapper around [`syscalls::io::write`].
     pub fn write(&self, buf: &[u8]) -> Result<usize, ErrorStatus> {
-        unsafe { self.0.write(0, buf) }
+        let results = unsafe { self.resource.write(0, buf) };
+        self.stats.record_send(&results);
+        results
     }
 
+    /// Sends the IO command `cmd` with `arg` to the socket's resource.
+    ///
+    /// # Safety
+    /// See [`Resource::io_command`].
     pub unsafe fn io_cmd(&self, cmd: u16, arg: u64) -> Result<(), ErrorStatus> {
-        self.0.io_command(cmd, arg)
+        self.resource.io_command(cmd, arg)
     }
 
     pub fn set_sock_opt<T: Into<u64>>(&self, opt: SocketOpt, arg: T) -> Result<(), ErrorStatus> {
@@ -334,6 +529,182 @@ impl Socket {
         self.io_cmd(opt as u16 | (1 << 15), arg as *mut T as u64)
     }
 
+    /// Takes the pending asynchronous error of the socket, clearing it,
+    /// returns [`None`] if there is no pending error.
+    ///
+    /// Asynchronous errors are errors that couldn't be reported by the call that caused them,
+    /// such as a datagram sent using [`Self::send_to`] being later reported as unreachable.
+    pub fn take_detailed_error(&self) -> Result<Option<DetailedSocketError>, ErrorStatus> {
+        // the kernel writes the raw error code, zero meaning no error, a u64 holds any width it may use
+        let mut raw: u64 = 0;
+        unsafe { self.get_sock_opt(SocketOpt::SocketError, &mut raw)? };
+
+        if raw == 0 {
+            return Ok(None);
+        }
+
+        let error = u16::try_from(raw)
+            .map_err(|_| UnknownError(u16::MAX))
+            .and_then(crate::errors::from_raw);
+
+        Ok(Some(DetailedSocketError {
+            error,
+            origin: None,
+        }))
+    }
+
+    /// Like [`Self::take_detailed_error`] but only returns the kind of the error,
+    /// errors unknown to this crate are returned as [`ErrorStatus::Unknown`].
+    pub fn take_error(&self) -> Result<Option<ErrorStatus>, ErrorStatus> {
+        let error = self.take_detailed_error()?;
+        Ok(error.map(|e| e.error().unwrap_or(ErrorStatus::Unknown)))
+    }
+
+    /// Sets the maximum time a read can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.set_sock_opt(SocketOpt::ReadTimeout, timeout_to_ms(timeout))
+    }
+
+    /// Sets the maximum time a write can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.set_sock_opt(SocketOpt::WriteTimeout, timeout_to_ms(timeout))
+    }
+
+    /// Sets the time to live of the sent Ip packets.
+    #[inline]
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), ErrorStatus> {
+        self.set_sock_opt(SocketOpt::IpTTL, ttl)
+    }
+
+    /// Returns the time to live of the sent Ip packets.
+    pub fn ttl(&self) -> Result<u32, ErrorStatus> {
+        // a u64 holds any width the kernel may use
+        let mut ttl: u64 = 0;
+        unsafe { self.get_sock_opt(SocketOpt::IpTTL, &mut ttl)? };
+        Ok(ttl as u32)
+    }
+
+    /// Connects the socket to `addr`, waiting at most `timeout` for the connection to be established.
+    ///
+    /// The kernel has no non-blocking connect yet, so the attempt is bounded by setting the socket's write timeout for its duration,
+    /// the write timeout is reset to blocking forever afterwards.
+    pub fn connect_timeout(
+        &self,
+        addr: core::net::SocketAddrV4,
+        timeout: Duration,
+    ) -> Result<(), ErrorStatus> {
+        if timeout.is_zero() {
+            return Err(ErrorStatus::InvalidArgument);
+        }
+
+        self.set_write_timeout(Some(timeout))?;
+        let results = self.connect_to_addr(addr);
+        self.set_write_timeout(None)?;
+        results
+    }
+
+    /// Same as [`Self::connect_timeout`] but waits until `deadline` instead of for a duration,
+    /// so that retry loops can bound their total time.
+    ///
+    /// # Returns
+    /// - [`WaitError::Timeout`] if `deadline` passes, including if it already passed
+    pub fn connect_until(
+        &self,
+        addr: core::net::SocketAddrV4,
+        deadline: Instant,
+    ) -> Result<(), WaitError> {
+        let start = Instant::now();
+        let timeout = deadline.duration_since(start);
+        if timeout.is_zero() {
+            return Err(TimeoutError::new("connect", Duration::ZERO).into());
+        }
+        self.connect_timeout(addr, timeout)
+            .map_err(|err| WaitError::from_status(err, "connect", start))
+    }
+
+    /// Blocks until any of `events` occur on the socket or until `deadline` passes,
+    /// returns [`WaitError::Timeout`] for `operation` (which started at `start`) in the latter case.
+    ///
+    /// With a `cancel` token the socket is polled in slices of [`cancel::CHECK_INTERVAL`],
+    /// returning [`WaitError::Cancelled`] once the token is cancelled.
+    pub(crate) fn wait_until(
+        &self,
+        events: PollEvents,
+        deadline: Instant,
+        operation: &'static str,
+        start: Instant,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), WaitError> {
+        loop {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return Err(WaitError::Cancelled);
+            }
+
+            let remaining = deadline.duration_since(Instant::now());
+            let timeout = match cancel {
+                Some(_) => remaining.min(cancel::CHECK_INTERVAL),
+                None => remaining,
+            };
+
+            let mut entries = [PollEntry::new(self.ri(), events)];
+            syscalls::io::poll_resources(&mut entries, Some(timeout))?;
+
+            if entries[0].returned_events() != PollEvents::NONE {
+                return Ok(());
+            }
+
+            if timeout == remaining {
+                return Err(TimeoutError::since(operation, start).into());
+            }
+        }
+    }
+
+    /// Same as [`Self::recv`] but gives up with [`WaitError::Timeout`] once `deadline` passes without data,
+    /// regardless of the socket's read timeout, which isn't changed.
+    ///
+    /// Unlike read timeouts the deadline doesn't restart on each call, so that retry loops can bound their total time.
+    pub fn recv_until(
+        &self,
+        buf: &mut [u8],
+        flags: SockMsgFlags,
+        deadline: Instant,
+    ) -> Result<usize, WaitError> {
+        let start = Instant::now();
+        loop {
+            self.wait_until(PollEvents::DATA_AVAILABLE, deadline, "recv", start, None)?;
+            // another handle might have taken the data in the meantime
+            match self.recv(buf, flags) {
+                Err(ErrorStatus::WouldBlock) => continue,
+                results => return Ok(results?),
+            }
+        }
+    }
+
+    /// Same as [`Self::recv_from_addr`] but gives up once `deadline` passes, see [`Self::recv_until`].
+    pub fn recv_from_addr_until(
+        &self,
+        buf: &mut [u8],
+        flags: SockMsgFlags,
+        deadline: Instant,
+    ) -> Result<(usize, core::net::SocketAddrV4), WaitError> {
+        let start = Instant::now();
+        loop {
+            self.wait_until(PollEvents::DATA_AVAILABLE, deadline, "recv", start, None)?;
+            match self.recv_from_addr(buf, flags) {
+                Err(ErrorStatus::WouldBlock) => continue,
+                results => return Ok(results?),
+            }
+        }
+    }
+
+    /// Creates a new independently owned handle to the same socket.
+    #[inline]
+    pub fn duplicate(&self) -> Result<Socket, ErrorStatus> {
+        self.resource.clone().map(Socket::new)
+    }
+
     /// Configures the socket to block when necessary.
     pub fn set_blocking(&self, blocking: bool) -> Result<(), ErrorStatus> {
         self.set_sock_opt(SocketOpt::Blocking, blocking)
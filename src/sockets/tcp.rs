@@ -0,0 +1,236 @@
+use core::{net::SocketAddrV4, time::Duration};
+
+use safa_abi::{errors::ErrorStatus, sockets::SockMsgFlags};
+
+use crate::{
+    errors::WaitError,
+    sockets::{Socket, SocketDomain, SocketKind},
+    syscalls::types::Ri,
+    time::Instant,
+};
+
+/// The backlog used by [`TCPListener::bind`]
+pub const DEFAULT_BACKLOG: usize = 128;
+
+/// An Ipv4 TCP connection
+#[derive(Debug)]
+pub struct TCPStream(Socket);
+
+impl TCPStream {
+    fn new_socket() -> Result<Socket, ErrorStatus> {
+        Socket::builder(SocketDomain::Ipv4, SocketKind::Stream, 0).build()
+    }
+
+    /// Connects to `addr`.
+    pub fn connect(addr: SocketAddrV4) -> Result<Self, ErrorStatus> {
+        let socket = Self::new_socket()?;
+        socket.connect_to_addr(addr)?;
+        Ok(Self(socket))
+    }
+
+    /// Connects to `addr`, waiting at most `timeout` for the connection to be established, see [`Socket::connect_timeout`].
+    pub fn connect_timeout(addr: SocketAddrV4, timeout: Duration) -> Result<Self, ErrorStatus> {
+        let socket = Self::new_socket()?;
+        socket.connect_timeout(addr, timeout)?;
+        Ok(Self(socket))
+    }
+
+    /// Connects to `addr`, giving up with [`WaitError::Timeout`] once `deadline` passes, see [`Socket::connect_until`].
+    pub fn connect_until(addr: SocketAddrV4, deadline: Instant) -> Result<Self, WaitError> {
+        let socket = Self::new_socket()?;
+        socket.connect_until(addr, deadline)?;
+        Ok(Self(socket))
+    }
+
+    /// Reads into `buf`, returning the amount of bytes read, 0 meaning the peer closed the connection.
+    #[inline]
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.0.read(buf)
+    }
+
+    /// Same as [`Self::read`] but gives up with [`WaitError::Timeout`] once `deadline` passes, see [`Socket::recv_until`].
+    #[inline]
+    pub fn read_until(&self, buf: &mut [u8], deadline: Instant) -> Result<usize, WaitError> {
+        self.0.recv_until(buf, SockMsgFlags::NONE, deadline)
+    }
+
+    /// Like [`Self::read`] but doesn't consume the data.
+    #[inline]
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.0.peek(buf)
+    }
+
+    /// Writes `buf`, returning the amount of bytes written.
+    #[inline]
+    pub fn write(&self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        self.0.write(buf)
+    }
+
+    /// Sets the maximum time a read can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.0.set_read_timeout(timeout)
+    }
+
+    /// Sets the maximum time a write can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.0.set_write_timeout(timeout)
+    }
+
+    /// Sets the time to live of the sent packets.
+    #[inline]
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), ErrorStatus> {
+        self.0.set_ttl(ttl)
+    }
+
+    /// Returns the time to live of the sent packets.
+    #[inline]
+    pub fn ttl(&self) -> Result<u32, ErrorStatus> {
+        self.0.ttl()
+    }
+
+    /// Takes the pending asynchronous error of the socket, see [`Socket::take_error`].
+    #[inline]
+    pub fn take_error(&self) -> Result<Option<ErrorStatus>, ErrorStatus> {
+        self.0.take_error()
+    }
+
+    /// Creates a new independently owned handle to the same connection.
+    #[inline]
+    pub fn duplicate(&self) -> Result<Self, ErrorStatus> {
+        self.0.duplicate().map(Self)
+    }
+
+    /// Configures the socket to block when necessary.
+    #[inline]
+    pub fn set_blocking(&self, blocking: bool) -> Result<(), ErrorStatus> {
+        self.0.set_blocking(blocking)
+    }
+
+    /// The raw resource ID of self
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.0.ri()
+    }
+
+    #[inline]
+    pub const fn raw_socket(&self) -> &Socket {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_socket(self) -> Socket {
+        self.0
+    }
+}
+
+/// An Ipv4 TCP socket accepting incoming connections
+#[derive(Debug)]
+pub struct TCPListener {
+    socket: Socket,
+    local_addr: SocketAddrV4,
+}
+
+impl TCPListener {
+    /// Creates a new listener bound to `addr` with a backlog of [`DEFAULT_BACKLOG`].
+    pub fn bind(addr: SocketAddrV4) -> Result<Self, ErrorStatus> {
+        Self::bind_with_backlog(addr, DEFAULT_BACKLOG)
+    }
+
+    /// Creates a new listener bound to `addr` that can hold up to `backlog` pending connections.
+    pub fn bind_with_backlog(addr: SocketAddrV4, backlog: usize) -> Result<Self, ErrorStatus> {
+        let socket = TCPStream::new_socket()?;
+        socket.bind_to_addr(addr)?;
+        socket.listen(backlog)?;
+
+        Ok(Self {
+            socket,
+            local_addr: addr,
+        })
+    }
+
+    /// The address this listener was bound to.
+    #[inline]
+    pub const fn local_addr(&self) -> SocketAddrV4 {
+        self.local_addr
+    }
+
+    /// Accepts a pending connection, returning it alongside the address of the peer.
+    #[inline]
+    pub fn accept(&self) -> Result<(TCPStream, SocketAddrV4), ErrorStatus> {
+        let (socket, addr) = self.socket.accept_from_addr()?;
+        Ok((TCPStream(socket), addr))
+    }
+
+    /// Sets the time to live of the packets sent by accepted connections.
+    #[inline]
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), ErrorStatus> {
+        self.socket.set_ttl(ttl)
+    }
+
+    /// Returns the time to live of the packets sent by accepted connections.
+    #[inline]
+    pub fn ttl(&self) -> Result<u32, ErrorStatus> {
+        self.socket.ttl()
+    }
+
+    /// Takes the pending asynchronous error of the socket, see [`Socket::take_error`].
+    #[inline]
+    pub fn take_error(&self) -> Result<Option<ErrorStatus>, ErrorStatus> {
+        self.socket.take_error()
+    }
+
+    /// Creates a new independently owned handle to the same listener.
+    pub fn duplicate(&self) -> Result<Self, ErrorStatus> {
+        Ok(Self {
+            socket: self.socket.duplicate()?,
+            local_addr: self.local_addr,
+        })
+    }
+
+    /// Configures the socket to block when necessary.
+    #[inline]
+    pub fn set_blocking(&self, blocking: bool) -> Result<(), ErrorStatus> {
+        self.socket.set_blocking(blocking)
+    }
+
+    /// The raw resource ID of self
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.socket.ri()
+    }
+
+    #[inline]
+    pub const fn raw_socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    #[inline]
+    pub fn into_socket(self) -> Socket {
+        self.socket
+    }
+}
+
+#[cfg(feature = "std")]
+mod _std {
+    use std::io;
+    use std::io::Read;
+    use std::io::Write;
+
+    impl Read for super::TCPStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            super::TCPStream::read(self, buf).map_err(crate::errors::into_io_error)
+        }
+    }
+
+    impl Write for super::TCPStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            super::TCPStream::write(self, buf).map_err(crate::errors::into_io_error)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use core::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use safa_abi::{errors::ErrorStatus, sockets::SockMsgFlags};
+
+use crate::{
+    errors::WaitError,
+    sockets::{socket::SocketOpt, Socket, SocketDomain, SocketKind},
+    syscalls::types::Ri,
+    time::Instant,
+};
+
+/// Information about a datagram received using [`UDPSocket::recv_msg_info`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvMsgInfo {
+    /// The amount of bytes received.
+    pub len: usize,
+    /// The address of the sender.
+    pub sender: SocketAddrV4,
+    /// The address the datagram was sent to, which is the address to reply from on multihomed hosts,
+    /// the kernel doesn't report it yet so this is only known when the socket is bound to a specific address.
+    pub destination: Option<SocketAddrV4>,
+    /// The index of the interface the datagram was received on,
+    /// currently always [`None`] as the kernel doesn't report it.
+    pub interface_index: Option<u32>,
+}
+
+/// An Ipv4 UDP socket
+#[derive(Debug)]
+pub struct UDPSocket {
+    socket: Socket,
+    local_addr: SocketAddrV4,
+}
+
+impl UDPSocket {
+    /// Creates a new UDP socket bound to `addr`, use [`Ipv4Addr::UNSPECIFIED`] and port 0 to let the kernel pick.
+    pub fn bind(addr: SocketAddrV4) -> Result<Self, ErrorStatus> {
+        let socket = Socket::builder(SocketDomain::Ipv4, SocketKind::Datagram, 0).build()?;
+        socket.bind_to_addr(addr)?;
+
+        Ok(Self {
+            socket,
+            local_addr: addr,
+        })
+    }
+
+    /// Creates a new UDP socket bound to any address and a port picked by the kernel.
+    #[inline]
+    pub fn bind_any() -> Result<Self, ErrorStatus> {
+        Self::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+    }
+
+    /// The address this socket was bound to.
+    #[inline]
+    pub const fn local_addr(&self) -> SocketAddrV4 {
+        self.local_addr
+    }
+
+    /// Sets the default destination of [`Self::send`] and only receive datagrams from `addr`.
+    #[inline]
+    pub fn connect(&self, addr: SocketAddrV4) -> Result<(), ErrorStatus> {
+        self.socket.connect_to_addr(addr)
+    }
+
+    /// Sends `buf` as a single datagram to `addr`, returns the amount of bytes sent.
+    #[inline]
+    pub fn send_to(&self, buf: &[u8], addr: SocketAddrV4) -> Result<usize, ErrorStatus> {
+        self.socket
+            .send_to_addr(buf, SockMsgFlags::NONE, SocketAddr::V4(addr))
+    }
+
+    /// Sends `buf` as a single datagram to the connected address, see [`Self::connect`].
+    #[inline]
+    pub fn send(&self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        self.socket.send(buf, SockMsgFlags::NONE)
+    }
+
+    /// Receives a single datagram into `buf`, returns the amount of bytes received and the address of the sender.
+    #[inline]
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4), ErrorStatus> {
+        self.socket.recv_from_addr(buf, SockMsgFlags::NONE)
+    }
+
+    /// Like [`Self::recv_from`] but leaves the datagram in the queue.
+    #[inline]
+    pub fn peek_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4), ErrorStatus> {
+        self.socket.recv_from_addr(buf, SockMsgFlags::PEEK)
+    }
+
+    /// Like [`Self::recv`] but leaves the datagram in the queue.
+    #[inline]
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.socket.peek(buf)
+    }
+
+    /// Receives a single datagram into `buf`, returns the amount of bytes received.
+    #[inline]
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.socket.recv(buf, SockMsgFlags::NONE)
+    }
+
+    /// Same as [`Self::recv_from`] but gives up with [`WaitError::Timeout`] once `deadline` passes, see [`Socket::recv_until`].
+    #[inline]
+    pub fn recv_from_until(
+        &self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> Result<(usize, SocketAddrV4), WaitError> {
+        self.socket
+            .recv_from_addr_until(buf, SockMsgFlags::NONE, deadline)
+    }
+
+    /// Same as [`Self::recv`] but gives up with [`WaitError::Timeout`] once `deadline` passes, see [`Socket::recv_until`].
+    #[inline]
+    pub fn recv_until(&self, buf: &mut [u8], deadline: Instant) -> Result<usize, WaitError> {
+        self.socket.recv_until(buf, SockMsgFlags::NONE, deadline)
+    }
+
+    /// Receives a single datagram into `buf`, returns the amount of bytes received alongside information about the datagram,
+    /// see [`RecvMsgInfo`] for what the kernel currently reports.
+    pub fn recv_msg_info(&self, buf: &mut [u8]) -> Result<RecvMsgInfo, ErrorStatus> {
+        let (len, sender) = self.recv_from(buf)?;
+        let destination = (!self.local_addr.ip().is_unspecified()).then_some(self.local_addr);
+
+        Ok(RecvMsgInfo {
+            len,
+            sender,
+            destination,
+            interface_index: None,
+        })
+    }
+
+    /// Sets the maximum time a receive can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.socket.set_read_timeout(timeout)
+    }
+
+    /// Sets the maximum time a send can block for, [`None`] blocks forever.
+    #[inline]
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        self.socket.set_write_timeout(timeout)
+    }
+
+    /// Sets the time to live of the sent packets.
+    #[inline]
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), ErrorStatus> {
+        self.socket.set_ttl(ttl)
+    }
+
+    /// Returns the time to live of the sent packets.
+    #[inline]
+    pub fn ttl(&self) -> Result<u32, ErrorStatus> {
+        self.socket.ttl()
+    }
+
+    /// Takes the pending asynchronous error of the socket, see [`Socket::take_error`].
+    #[inline]
+    pub fn take_error(&self) -> Result<Option<ErrorStatus>, ErrorStatus> {
+        self.socket.take_error()
+    }
+
+    /// Creates a new independently owned handle to the same socket.
+    pub fn duplicate(&self) -> Result<Self, ErrorStatus> {
+        Ok(Self {
+            socket: self.socket.duplicate()?,
+            local_addr: self.local_addr,
+        })
+    }
+
+    /// Sets whether or not datagrams can be sent to broadcast addresses.
+    #[inline]
+    pub fn set_broadcast(&self, broadcast: bool) -> Result<(), ErrorStatus> {
+        self.socket.set_sock_opt(SocketOpt::IpBroadcast, broadcast)
+    }
+
+    /// Configures the socket to block when necessary.
+    #[inline]
+    pub fn set_blocking(&self, blocking: bool) -> Result<(), ErrorStatus> {
+        self.socket.set_blocking(blocking)
+    }
+
+    /// The raw resource ID of self
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.socket.ri()
+    }
+
+    #[inline]
+    pub const fn raw_socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    #[inline]
+    pub fn into_socket(self) -> Socket {
+        self.socket
+    }
+}
@@ -1,10 +1,30 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::{format, string::String};
+
 use safa_abi::{
     consts::MAX_NAME_LENGTH,
     errors::ErrorStatus,
     sockets::{LocalSocketAddr, SockMsgFlags, ToSocketAddr},
 };
 
-use crate::{sockets::Socket, syscalls::types::Ri};
+use crate::{
+    errors::{TimeoutError, WaitError},
+    sockets::Socket,
+    syscalls::types::Ri,
+    time::Instant,
+};
+
+/// The prefix of the abstract addresses picked by autobinding, see [`UnixListenerBuilder::autobind`]
+pub const AUTOBIND_PREFIX: &str = "autobind-";
+/// How many addresses autobinding tries before giving up.
+const AUTOBIND_ATTEMPTS: usize = 8;
 
 /// Describes the kind of a local domain socket.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,11 +35,45 @@ pub enum UnixSockKind {
 
 enum SockAddr<'a> {
     Abstract(&'a str),
+    /// A unique abstract address picked on bind
+    Auto,
+}
+
+fn bind_abstract(socket: &Socket, path: &str) -> Result<(), ErrorStatus> {
+    let (addr, size) = LocalSocketAddr::new_abstract_from(path);
+    socket.bind(addr.as_generic(), size)
+}
+
+/// Binds `socket` to a unique abstract address, returns the address.
+///
+/// The kernel doesn't assign addresses to sockets bound to an empty address,
+/// so the address is made of [`AUTOBIND_PREFIX`] followed by a [`unique_id`](crate::util::unique_id()).
+fn autobind(socket: &Socket) -> Result<String, ErrorStatus> {
+    // identifiers never repeat within a process, but another process could still have bound the same address
+    for _ in 0..AUTOBIND_ATTEMPTS {
+        let path = format!("{AUTOBIND_PREFIX}{}", crate::util::unique_id());
+        match bind_abstract(socket, &path) {
+            Ok(()) => return Ok(path),
+            Err(ErrorStatus::AddressAlreadyInUse) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(ErrorStatus::AddressAlreadyInUse)
+}
+
+/// Binds `socket` to `addr`, returns the address it was bound to.
+fn bind(socket: &Socket, addr: SockAddr) -> Result<String, ErrorStatus> {
+    match addr {
+        SockAddr::Abstract(path) => bind_abstract(socket, path).map(|()| String::from(path)),
+        SockAddr::Auto => autobind(socket),
+    }
 }
 
 // Describes a Unix Socket Connection Builder
 pub struct UnixSockConnectionBuilder<'a> {
-    addr: SockAddr<'a>,
+    addr: &'a str,
+    autobind: bool,
     non_blocking: bool,
     kind: UnixSockKind,
 }
@@ -31,14 +85,21 @@ impl<'a> UnixSockConnectionBuilder<'a> {
             return Err(());
         }
 
-        let addr = SockAddr::Abstract(path);
         Ok(Self {
             kind: UnixSockKind::Stream,
-            addr,
+            addr: path,
+            autobind: false,
             non_blocking: false,
         })
     }
 
+    /// Binds the connecting socket to a unique abstract address before connecting,
+    /// giving the peer an address to reply to, see [`UnixSockConnection::local_addr`].
+    pub const fn set_autobind(&mut self, autobind: bool) -> &mut Self {
+        self.autobind = autobind;
+        self
+    }
+
     /// Marks the connection as non-blocking if `non-blocking` was true
     pub const fn set_non_blocking(&mut self, non_blocking: bool) -> &mut Self {
         self.non_blocking = non_blocking;
@@ -52,7 +113,21 @@ impl<'a> UnixSockConnectionBuilder<'a> {
     }
 
     /// Builds the final connection
+    #[inline]
     pub fn connect(self) -> Result<UnixSockConnection, ErrorStatus> {
+        self.connect_inner(None).map_err(|err| err.status())
+    }
+
+    /// Builds the final connection, giving up with [`WaitError::Timeout`] once `deadline` passes.
+    ///
+    /// The kernel has no non-blocking connect yet, so the attempt is bounded by setting the socket's write timeout for its duration,
+    /// see [`Socket::connect_timeout`].
+    #[inline]
+    pub fn connect_until(self, deadline: Instant) -> Result<UnixSockConnection, WaitError> {
+        self.connect_inner(Some(deadline))
+    }
+
+    fn connect_inner(self, deadline: Option<Instant>) -> Result<UnixSockConnection, WaitError> {
         let domain = super::SocketDomain::Local;
         let kind = match self.kind {
             UnixSockKind::SeqPacket => super::SocketKind::SeqPacket,
@@ -63,45 +138,83 @@ impl<'a> UnixSockConnectionBuilder<'a> {
             .set_non_blocking(self.non_blocking)
             .build()?;
 
-        let (addr, size) = match self.addr {
-            SockAddr::Abstract(path) => LocalSocketAddr::new_abstract_from(path),
+        let local_addr = if self.autobind {
+            Some(autobind(&socket)?)
+        } else {
+            None
         };
-        socket.connect(addr.as_generic(), size)?;
 
-        Ok(UnixSockConnection(socket))
+        let (addr, size) = LocalSocketAddr::new_abstract_from(self.addr);
+        match deadline {
+            None => socket.connect(addr.as_generic(), size)?,
+            Some(deadline) => {
+                let start = Instant::now();
+                let timeout = deadline.duration_since(start);
+                if timeout.is_zero() {
+                    return Err(TimeoutError::new("connect", timeout).into());
+                }
+
+                socket.set_write_timeout(Some(timeout))?;
+                socket
+                    .connect(addr.as_generic(), size)
+                    .map_err(|err| WaitError::from_status(err, "connect", start))?;
+                socket.set_write_timeout(None)?;
+            }
+        }
+
+        Ok(UnixSockConnection { socket, local_addr })
     }
 }
 
-pub struct UnixSockConnection(Socket);
+pub struct UnixSockConnection {
+    socket: Socket,
+    local_addr: Option<String>,
+}
 
 impl UnixSockConnection {
     /// Performs a read operation on this socket
     pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
-        self.0.read(buf)
+        self.socket.read(buf)
+    }
+
+    /// Same as [`Self::read`] but gives up with [`WaitError::Timeout`] once `deadline` passes, see [`Socket::recv_until`].
+    pub fn read_until(&mut self, buf: &mut [u8], deadline: Instant) -> Result<usize, WaitError> {
+        self.socket.recv_until(buf, SockMsgFlags::NONE, deadline)
     }
 
     /// Performs a peek operation on this socket, doesn't consume the data...
     pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
-        self.0.recv(buf, SockMsgFlags::PEEK)
+        self.socket.recv(buf, SockMsgFlags::PEEK)
     }
 
     /// Performs a write operation on this socket
     pub fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorStatus> {
-        self.0.write(buf)
+        self.socket.write(buf)
     }
 
     /// Set the ability for the socket to block to `can_block`
     pub fn set_can_block(&mut self, can_block: bool) -> Result<(), ErrorStatus> {
-        self.0.set_blocking(can_block)
+        self.socket.set_blocking(can_block)
+    }
+
+    /// The abstract address this side of the connection was bound to,
+    /// [`None`] for connections that weren't bound, including the server side of accepted connections.
+    pub fn local_addr(&self) -> Option<&str> {
+        self.local_addr.as_deref()
     }
 
     /// The raw Resource ID of self
     pub const fn ri(&self) -> Ri {
-        self.0.ri()
+        self.socket.ri()
     }
 
     pub const fn raw_socket(&self) -> &Socket {
-        &self.0
+        &self.socket
+    }
+
+    #[inline]
+    pub fn into_socket(self) -> Socket {
+        self.socket
     }
 }
 
@@ -127,6 +240,17 @@ impl<'a> UnixListenerBuilder<'a> {
         })
     }
 
+    /// Construct a local Unix Socket Listener bound to a unique abstract address picked on bind,
+    /// for servers that hand out their address themselves such as reply channels, see [`UnixListener::local_addr`].
+    pub const fn autobind() -> Self {
+        Self {
+            kind: UnixSockKind::Stream,
+            addr: SockAddr::Auto,
+            non_blocking: false,
+            backlog: usize::MAX,
+        }
+    }
+
     /// Marks the connection as non-blocking if `non-blocking` was true
     pub const fn set_non_blocking(&mut self, non_blocking: bool) -> &mut Self {
         self.non_blocking = non_blocking;
@@ -157,33 +281,115 @@ impl<'a> UnixListenerBuilder<'a> {
             .set_non_blocking(self.non_blocking)
             .build()?;
 
-        let (addr, size) = match self.addr {
-            SockAddr::Abstract(path) => LocalSocketAddr::new_abstract_from(path),
-        };
-
-        socket.bind(addr.as_generic(), size)?;
+        let local_addr = bind(&socket, self.addr)?;
         socket.listen(self.backlog)?;
-        Ok(UnixListener(socket))
+        Ok(UnixListener {
+            socket,
+            local_addr,
+            backlog: AtomicUsize::new(self.backlog),
+            accepted: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        })
     }
 }
 
+/// Statistics about a [`UnixListener`], see [`UnixListener::stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerStats {
+    /// The amount of connections accepted.
+    pub accepted: u64,
+    /// The amount of accept calls that failed, not counting [`ErrorStatus::WouldBlock`] from non-blocking listeners.
+    pub failed: u64,
+    /// The amount of connection requests refused by the kernel because the listening queue was full,
+    /// [`None`] as the kernel doesn't currently report it.
+    pub refused: Option<u64>,
+    /// The amount of connection requests currently waiting to be accepted,
+    /// [`None`] as the kernel doesn't currently report it.
+    pub pending: Option<usize>,
+    /// The current size of the listening queue, see [`UnixListener::set_backlog`].
+    pub backlog: usize,
+}
+
 /// A Server Unix Socket that can accept incoming connections
-pub struct UnixListener(Socket);
+pub struct UnixListener {
+    socket: Socket,
+    local_addr: String,
+    backlog: AtomicUsize,
+    accepted: AtomicU64,
+    failed: AtomicU64,
+}
 
 impl UnixListener {
     /// Accepts 1 pending connection request, returns the Server's Side of the connection
     pub fn accept(&self) -> Result<UnixSockConnection, ErrorStatus> {
-        let socket = self.0.accept()?;
-        Ok(UnixSockConnection(socket))
+        match self.socket.accept() {
+            Ok(socket) => {
+                self.accepted.fetch_add(1, Ordering::Relaxed);
+                Ok(UnixSockConnection {
+                    socket,
+                    local_addr: None,
+                })
+            }
+            Err(err) => {
+                if err != ErrorStatus::WouldBlock {
+                    self.failed.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Same as [`Self::accept`] but gives up with [`WaitError::Timeout`] once `deadline` passes.
+    pub fn accept_until(&self, deadline: Instant) -> Result<UnixSockConnection, WaitError> {
+        let start = Instant::now();
+        loop {
+            self.socket.wait_until(
+                safa_abi::poll::PollEvents::DATA_AVAILABLE,
+                deadline,
+                "accept",
+                start,
+                None,
+            )?;
+            // another handle might have accepted the connection in the meantime
+            match self.accept() {
+                Err(ErrorStatus::WouldBlock) => continue,
+                results => return Ok(results?),
+            }
+        }
+    }
+
+    /// The abstract address this listener is bound to, including addresses picked by [`UnixListenerBuilder::autobind`].
+    pub fn local_addr(&self) -> &str {
+        &self.local_addr
+    }
+
+    /// Changes the max amount of pending connections the listening queue can hold to `backlog`,
+    /// by listening again with the new backlog.
+    pub fn set_backlog(&self, backlog: usize) -> Result<(), ErrorStatus> {
+        self.socket.listen(backlog)?;
+        self.backlog.store(backlog, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns statistics about this listener, the counters are maintained by this wrapper
+    /// and don't include connections accepted through [`Self::raw_socket`].
+    pub fn stats(&self) -> ListenerStats {
+        ListenerStats {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            refused: None,
+            pending: None,
+            backlog: self.backlog.load(Ordering::Relaxed),
+        }
     }
 
     /// The raw resource ID of self
     pub const fn ri(&self) -> Ri {
-        self.0.ri()
+        self.socket.ri()
     }
 
     pub const fn raw_socket(&self) -> &Socket {
-        &self.0
+        &self.socket
     }
 }
 
@@ -195,13 +401,13 @@ mod _std {
 
     impl Read for super::UnixSockConnection {
         fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            super::UnixSockConnection::read(self, buf).map_err(|e| crate::errors::into_io_error(e))
+            super::UnixSockConnection::read(self, buf).map_err(crate::errors::into_io_error)
         }
     }
 
     impl Write for super::UnixSockConnection {
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            super::UnixSockConnection::write(self, buf).map_err(|e| crate::errors::into_io_error(e))
+            super::UnixSockConnection::write(self, buf).map_err(crate::errors::into_io_error)
         }
 
         fn flush(&mut self) -> io::Result<()> {
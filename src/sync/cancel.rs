@@ -0,0 +1,78 @@
+//! Cooperative cancellation of long operations, see [`CancellationToken`]
+
+use core::{sync::atomic::Ordering, time::Duration};
+
+use safa_abi::errors::ErrorStatus;
+
+use super::Futex;
+use crate::time::Instant;
+
+/// The error cancelled operations return where only an [`ErrorStatus`] can be returned,
+/// check [`CancellationToken::is_cancelled`] to tell it apart from a process being terminated.
+pub const CANCELLED: ErrorStatus = ErrorStatus::ForceTerminated;
+
+/// How often operations that can't be woken up by a token (such as polling a socket) check it,
+/// bounding how late they notice a cancellation.
+pub const CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+const ACTIVE: u32 = 0;
+const CANCELLED_STATE: u32 = 1;
+
+/// A flag shared between an operation and whoever may want to abort it, such as a UI or a supervisor
+///
+/// Operations accepting a token check it between their steps (retries, polls) and give up once it is cancelled,
+/// a step that is already blocked in the kernel (such as a single connect attempt) still runs to completion.
+///
+/// Cancellation is permanent, a token can't be reused once cancelled.
+#[derive(Debug, Default)]
+pub struct CancellationToken {
+    state: Futex,
+}
+
+impl CancellationToken {
+    pub const fn new() -> Self {
+        Self {
+            state: Futex::new(ACTIVE),
+        }
+    }
+
+    /// Cancels the operations using this token, waking up any thread sleeping in [`Self::sleep`].
+    pub fn cancel(&self) {
+        if self.state.swap(CANCELLED_STATE, Ordering::Release) == ACTIVE {
+            _ = self.state.wake_all();
+        }
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.load(Ordering::Acquire) == CANCELLED_STATE
+    }
+
+    /// Returns [`CANCELLED`] if the token was cancelled.
+    #[inline]
+    pub fn check(&self) -> Result<(), ErrorStatus> {
+        if self.is_cancelled() {
+            Err(CANCELLED)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sleeps for `duration` unless the token is cancelled first, returns [`CANCELLED`] in the latter case.
+    pub fn sleep(&self, duration: Duration) -> Result<(), ErrorStatus> {
+        let start = Instant::now();
+        loop {
+            self.check()?;
+            let remaining = duration.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+
+            // the wait returns on timeout, on wake up and spuriously, the loop tells them apart
+            match self.state.wait(ACTIVE, Some(remaining)) {
+                Ok(()) | Err(ErrorStatus::Timeout) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
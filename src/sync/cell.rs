@@ -1,93 +1,176 @@
 use core::{
-    cell::UnsafeCell,
-    marker::PhantomData,
-    ops::Deref,
-    sync::atomic::{AtomicBool, Ordering},
+    cell::UnsafeCell, convert::Infallible, fmt, marker::PhantomData, ops::Deref,
+    sync::atomic::Ordering,
 };
 
-use crate::syscalls;
+use super::Futex;
 
-enum LazyData<T, F: FnOnce() -> T> {
-    Uninitialized(F),
-    Initialized(T),
-    Initializing,
+const UNINITIALIZED: u32 = 0;
+/// A thread is running the initializer, others wait on the state until it changes
+const INITIALIZING: u32 = 1;
+const INITIALIZED: u32 = 2;
+/// An initializer panicked, the cell can never be initialized
+const POISONED: u32 = 3;
+
+/// An error returned by [`LazyCell::force_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitError<E> {
+    /// A previous initializer panicked, see [`LazyCell::is_poisoned`]
+    Poisoned,
+    /// The initializer returned an error, the cell was left uninitialized
+    Failed(E),
 }
 
-impl<T, F: FnOnce() -> T> LazyData<T, F> {
-    fn get_value(&self) -> Option<&T> {
+impl<E: fmt::Display> fmt::Display for InitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LazyData::Initialized(ref value) => Some(value),
-            _ => None,
+            Self::Poisoned => f.write_str("LazyCell poisoned by a panicking initializer"),
+            Self::Failed(err) => write!(f, "LazyCell initialization failed: {err}"),
         }
     }
+}
 
-    fn start_initialize(&mut self) -> F {
-        match self {
-            LazyData::Uninitialized(_) => {
-                let LazyData::Uninitialized(res) = core::mem::replace(self, LazyData::Initializing)
-                else {
-                    unreachable!()
-                };
-                res
-            }
-            _ => panic!("LazyData::start_initialize called on initialized data"),
-        }
+/// Poisons the cell and wakes up the waiters if dropped before [`InitGuard::finish`],
+/// which only happens when the initializer unwinds
+struct InitGuard<'a> {
+    state: &'a Futex,
+}
+
+impl InitGuard<'_> {
+    /// Publishes `state` and wakes up the waiters.
+    fn finish(self, state: u32) {
+        self.state.store(state, Ordering::Release);
+        self.state
+            .wake_all()
+            .expect("System error while waking LazyCell waiters");
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for InitGuard<'_> {
+    fn drop(&mut self) {
+        self.state.store(POISONED, Ordering::Release);
+        _ = self.state.wake_all();
     }
 }
 
 /// Synchronous Lazily initialized value
+///
+/// Threads calling [`LazyCell::get`] while another thread initializes the value sleep on a [`Futex`] until it is done.
+///
+/// # Failures
+/// - If an initializer panics, the cell is poisoned: the waiting threads and any later caller of [`LazyCell::get`] panic as well,
+///   [`LazyCell::force_with`] returns [`InitError::Poisoned`] instead.
+/// - If the initializer given to [`LazyCell::force_with`] returns an error, the cell is left uninitialized,
+///   and the next caller (possibly a waiting thread) tries to initialize it again.
 pub struct LazyCell<T> {
-    running_init: AtomicBool,
-    value: UnsafeCell<LazyData<T, fn() -> T>>,
+    state: Futex,
+    init: fn() -> T,
+    value: UnsafeCell<Option<T>>,
     _marker: PhantomData<T>,
 }
 
 impl<T> LazyCell<T> {
     pub const fn new(call: fn() -> T) -> Self {
         Self {
-            running_init: AtomicBool::new(false),
-            value: UnsafeCell::new(LazyData::Uninitialized(call)),
+            state: Futex::new(UNINITIALIZED),
+            init: call,
+            value: UnsafeCell::new(None),
             _marker: PhantomData,
         }
     }
 
-    /// Gets the value or initializes it synchronously if not already initialized.
-    pub fn get(&self) -> &T {
-        let wait_for_init = || {
-            while self.running_init.load(Ordering::Acquire) {
-                syscalls::thread::yield_now();
-            }
+    /// # Safety
+    /// The state must have been observed as [`INITIALIZED`] with an `Acquire` ordering.
+    unsafe fn get_initialized(&self) -> &T {
+        unsafe {
+            (*self.value.get())
+                .as_ref()
+                .expect("LazyCell was marked initialized but the value was never initialized")
+        }
+    }
 
-            unsafe {
-                (&*self.value.get())
-                    .get_value()
-                    .expect("Lazy awaited initialization but the value was never initialized")
-            }
-        };
-
-        match unsafe { &*self.value.get() } {
-            LazyData::Initialized(ref value) => value,
-            LazyData::Initializing => wait_for_init(),
-            LazyData::Uninitialized(_) => {
-                if self
-                    .running_init
-                    .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
-                    .is_err()
-                {
-                    wait_for_init()
-                } else {
-                    let f = unsafe { (*self.value.get()).start_initialize() };
-                    let value = (f)();
-                    unsafe {
-                        *self.value.get() = LazyData::Initialized(value);
+    /// Initializes the value with `f` unless it is already initialized,
+    /// sleeps until the value is initialized if another thread is initializing it.
+    fn initialize<E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<&T, InitError<E>> {
+        let mut f = Some(f);
+        loop {
+            match self.state.compare_exchange(
+                UNINITIALIZED,
+                INITIALIZING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    let guard = InitGuard { state: &self.state };
+                    let f = f.take().expect("LazyCell initializer called twice");
+                    match f() {
+                        Ok(value) => {
+                            unsafe {
+                                *self.value.get() = Some(value);
+                            }
+                            guard.finish(INITIALIZED);
+                            return Ok(unsafe { self.get_initialized() });
+                        }
+                        Err(err) => {
+                            guard.finish(UNINITIALIZED);
+                            return Err(InitError::Failed(err));
+                        }
                     }
-
-                    self.running_init.store(false, Ordering::Release);
-                    unsafe { (&*self.value.get()).get_value().unwrap() }
+                }
+                Err(INITIALIZED) => return Ok(unsafe { self.get_initialized() }),
+                Err(POISONED) => return Err(InitError::Poisoned),
+                Err(_) => {
+                    // returns immediately if the state already changed, spurious wake ups are rechecked by the loop
+                    self.state
+                        .wait(INITIALIZING, None)
+                        .expect("System error while waiting for a LazyCell to be initialized");
                 }
             }
         }
     }
+
+    /// Gets the value or initializes it synchronously if not already initialized,
+    /// sleeps until the value is initialized if another thread is initializing it.
+    ///
+    /// # Panics
+    /// If the cell is poisoned, see [`Self::is_poisoned`].
+    pub fn get(&self) -> &T {
+        match self.initialize(|| Ok::<_, Infallible>((self.init)())) {
+            Ok(value) => value,
+            Err(InitError::Poisoned) => panic!("LazyCell poisoned by a panicking initializer"),
+            Err(InitError::Failed(never)) => match never {},
+        }
+    }
+
+    /// Gets the value or initializes it with `f` instead of the initializer given to [`Self::new`] if not already initialized,
+    /// sleeps until the value is initialized if another thread is initializing it.
+    ///
+    /// # Returns
+    /// - [`InitError::Failed`] if `f` returned an error, the cell is left uninitialized
+    /// - [`InitError::Poisoned`] if the cell is poisoned, see [`Self::is_poisoned`]
+    pub fn force_with<E, F>(&self, f: F) -> Result<&T, InitError<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        self.initialize(f)
+    }
+
+    /// Returns the value if it is already initialized, without initializing it or waiting for another thread to.
+    #[inline]
+    pub fn try_get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == INITIALIZED {
+            Some(unsafe { self.get_initialized() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if an initializer panicked, in which case the cell can never be initialized.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.load(Ordering::Acquire) == POISONED
+    }
 }
 
 impl<T> Deref for LazyCell<T> {
@@ -0,0 +1,120 @@
+//! A typed interface over Futexes, see [`Futex`]
+
+use core::{ops::Deref, sync::atomic::AtomicU32, time::Duration};
+
+use safa_abi::errors::ErrorStatus;
+
+use crate::{
+    errors::WaitError,
+    syscalls::futex::{
+        futex_requeue, futex_wait, futex_wait_bitset, futex_wake, futex_wake_bitset,
+    },
+    time::Instant,
+};
+
+/// A Futex word, an [`AtomicU32`] that threads can wait on until it changes.
+///
+/// Dereferences to the inner [`AtomicU32`] for the usual atomic operations.
+///
+/// # Memory Ordering
+/// Waiting and waking aren't memory barriers by themselves, the value should be changed with a `Release` (or stronger) ordering
+/// before waking the waiters, and read with an `Acquire` (or stronger) ordering after a wait returns,
+/// for the changes made by the waking thread to be visible to the woken one.
+///
+/// Waits can also return spuriously, so the value should always be rechecked after a wait returns.
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct Futex(AtomicU32);
+
+impl Futex {
+    /// Constructs a new Futex word with the value `value`.
+    #[inline]
+    pub const fn new(value: u32) -> Self {
+        Self(AtomicU32::new(value))
+    }
+
+    /// The inner atomic
+    #[inline]
+    pub const fn as_atomic(&self) -> &AtomicU32 {
+        &self.0
+    }
+
+    /// Blocks the current thread as long as the value is `expected`, until woken up by one of the wake operations,
+    /// or until `timeout` passes, waits forever if `timeout` is [`None`].
+    ///
+    /// Returns immediately if the value isn't `expected`.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::Timeout`] if the timeout was reached
+    #[inline]
+    pub fn wait(&self, expected: u32, timeout: Option<Duration>) -> Result<(), ErrorStatus> {
+        futex_wait(&self.0, expected, timeout.unwrap_or(Duration::MAX))
+    }
+
+    /// Same as [`Self::wait`] but always waits with `timeout`,
+    /// returning a [`WaitError::Timeout`] carrying how long was waited when it is reached.
+    pub fn wait_timeout(&self, expected: u32, timeout: Duration) -> Result<(), WaitError> {
+        let start = Instant::now();
+        futex_wait(&self.0, expected, timeout)
+            .map_err(|err| WaitError::from_status(err, "futex wait", start))
+    }
+
+    /// Wakes up to `n` threads waiting on the Futex, returns the amount of threads that were woken up.
+    #[inline]
+    pub fn wake_n(&self, n: usize) -> Result<usize, ErrorStatus> {
+        futex_wake(&self.0, n)
+    }
+
+    /// Wakes up one thread waiting on the Futex, returns whether or not a thread was woken up.
+    #[inline]
+    pub fn wake_one(&self) -> Result<bool, ErrorStatus> {
+        self.wake_n(1).map(|n| n != 0)
+    }
+
+    /// Wakes up all the threads waiting on the Futex, returns the amount of threads that were woken up.
+    #[inline]
+    pub fn wake_all(&self) -> Result<usize, ErrorStatus> {
+        self.wake_n(usize::MAX)
+    }
+
+    /// Wakes up to `n` threads waiting on the Futex, and moves the remaining waiters to wait on `to` instead,
+    /// returns the amount of threads that were woken up.
+    ///
+    /// See [`futex_requeue`] for the current kernel support.
+    #[inline]
+    pub fn requeue(&self, to: &Futex, n: usize) -> Result<usize, ErrorStatus> {
+        futex_requeue(&self.0, &to.0, n)
+    }
+
+    /// Like [`Futex::wait`] but only woken up by [`Futex::wake_bitset`] calls with a `bitset` sharing at least one bit with `bitset`,
+    /// or by the other wake operations.
+    ///
+    /// See [`futex_wait_bitset`] for the current kernel support.
+    #[inline]
+    pub fn wait_bitset(
+        &self,
+        expected: u32,
+        timeout: Option<Duration>,
+        bitset: u32,
+    ) -> Result<(), ErrorStatus> {
+        futex_wait_bitset(&self.0, expected, timeout.unwrap_or(Duration::MAX), bitset)
+    }
+
+    /// Wakes up to `n` threads waiting on the Futex with [`Futex::wait_bitset`] using a bitset sharing at least one bit with `bitset`,
+    /// returns the amount of threads that were woken up.
+    ///
+    /// See [`futex_wake_bitset`] for the current kernel support.
+    #[inline]
+    pub fn wake_bitset(&self, n: usize, bitset: u32) -> Result<usize, ErrorStatus> {
+        futex_wake_bitset(&self.0, n, bitset)
+    }
+}
+
+impl Deref for Futex {
+    type Target = AtomicU32;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
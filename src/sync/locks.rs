@@ -1,15 +1,21 @@
-//! Provides various locking mechanisms for synchronization such as Mutex
+//! Provides various locking mechanisms for synchronization such as Mutex and RwLock
 //!
 //! uses Futexes internally
+//!
+//! The kernel has no priority-inheritance futexes, so none of the locks perform priority inheritance,
+//! [`PiMutex`] emulates it by raising the priority of the thread holding it instead, see its documentation.
 
 use core::{
+    cell::UnsafeCell,
     marker::PhantomData,
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicU32, Ordering},
-    time::Duration,
+    sync::atomic::Ordering,
 };
 
-use crate::syscalls::futex::{futex_wait, futex_wake};
+use safa_abi::process::ContextPriority;
+
+use super::Futex;
+use crate::thread::priority::PriorityGuard;
 
 const M_AVAILABLE: u32 = 0;
 const M_LOCKED: u32 = 1;
@@ -45,7 +51,7 @@ impl<'a, T> DerefMut for MutexGuard<'a, T> {
 
 #[derive(Debug)]
 pub struct Mutex<T> {
-    state: AtomicU32,
+    state: Futex,
     inner: T,
 }
 
@@ -53,7 +59,7 @@ impl<T> Mutex<T> {
     /// Constructs a new free Mutex.
     pub const fn new(inner: T) -> Self {
         Self {
-            state: AtomicU32::new(M_AVAILABLE),
+            state: Futex::new(M_AVAILABLE),
             inner,
         }
     }
@@ -80,7 +86,8 @@ impl<T> Mutex<T> {
             }
 
             while s != M_AVAILABLE {
-                futex_wait(&self.state, M_WAITED_ON, Duration::MAX)
+                self.state
+                    .wait(M_WAITED_ON, None)
                     .expect("System error while waiting for a Futex");
 
                 s = self.state.swap(M_WAITED_ON, Ordering::Acquire);
@@ -112,11 +119,16 @@ impl<T> Mutex<T> {
         }
     }
     /// Forces the mutex to be unlocked, even if it is currently locked.
+    ///
+    /// # Safety
+    /// No guard of the mutex may be used afterwards, as another thread can then lock it.
     pub unsafe fn force_unlock(&self) {
         if self.state.fetch_sub(1, Ordering::Acquire) != M_LOCKED {
             // will also handle the case where the mutex is already unlocked
             self.state.store(M_AVAILABLE, Ordering::Release);
-            futex_wake(&self.state, 1).expect("System error while waking 1 Futex");
+            self.state
+                .wake_one()
+                .expect("System error while waking 1 Futex");
         }
     }
 }
@@ -124,8 +136,290 @@ impl<T> Mutex<T> {
 impl<T: Clone> Clone for Mutex<T> {
     fn clone(&self) -> Self {
         Mutex {
-            state: AtomicU32::new(M_AVAILABLE),
+            state: Futex::new(M_AVAILABLE),
             inner: self.inner.clone(),
         }
     }
 }
+
+#[must_use = "if unused the PiMutex will immediately unlock"]
+pub struct PiMutexGuard<'a, T> {
+    // dropped first, so that the mutex is unlocked before the priority is restored
+    guard: MutexGuard<'a, T>,
+    _priority: Option<PriorityGuard>,
+}
+
+impl<'a, T> Deref for PiMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<'a, T> DerefMut for PiMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+/// A [`Mutex`] that avoids priority inversion, for locks shared between threads of different priorities
+///
+/// Without priority-inheritance futexes a waiting thread can't raise the priority of the thread holding the lock,
+/// so this emulates inheritance with a priority ceiling: a thread locking the mutex raises its own priority to the ceiling
+/// given to [`PiMutex::new`] (the priority of the highest priority thread using the lock) until it unlocks it,
+/// so that a higher priority thread never waits on a lower priority holder that can be preempted.
+///
+/// The priority is changed with [`crate::thread::set_priority`], which the kernel doesn't support yet,
+/// when it fails the mutex is locked without changing the priority, so for now it behaves like a [`Mutex`].
+#[derive(Debug)]
+pub struct PiMutex<T> {
+    ceiling: ContextPriority,
+    inner: Mutex<T>,
+}
+
+impl<T> PiMutex<T> {
+    /// Constructs a new free PiMutex, with `ceiling` as the priority of the threads holding it.
+    pub const fn new(ceiling: ContextPriority, inner: T) -> Self {
+        Self {
+            ceiling,
+            inner: Mutex::new(inner),
+        }
+    }
+    /// The priority of the threads holding the mutex.
+    pub const fn ceiling(&self) -> ContextPriority {
+        self.ceiling
+    }
+    /// Gets a mutable reference to the inner value.
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+    /// Raises the priority of the current thread to the ceiling, then locks the mutex,
+    /// blocking the current thread until it can be acquired.
+    ///
+    /// the PiMutex is locked until the returned PiMutexGuard is dropped, which also restores the priority.
+    pub fn lock(&self) -> PiMutexGuard<'_, T> {
+        let priority = PriorityGuard::new(self.ceiling).ok();
+        PiMutexGuard {
+            guard: self.inner.lock(),
+            _priority: priority,
+        }
+    }
+    /// Attempts to acquire the mutex without blocking, returning `None` if the mutex is currently locked,
+    /// see [`Self::lock`].
+    pub fn try_lock(&self) -> Option<PiMutexGuard<'_, T>> {
+        let priority = PriorityGuard::new(self.ceiling).ok();
+        let guard = self.inner.try_lock()?;
+        Some(PiMutexGuard {
+            guard,
+            _priority: priority,
+        })
+    }
+}
+
+/// The amount of readers holding the lock
+const RW_READERS: u32 = (1 << 30) - 1;
+/// Set while a writer holds the lock
+const RW_WRITE_LOCKED: u32 = 1 << 30;
+/// Set by threads before waiting for the lock, so that unlocking only wakes them up when needed
+const RW_WAITED_ON: u32 = 1 << 31;
+
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        let previous = self.lock.state.fetch_sub(1, Ordering::Release);
+        if previous & RW_READERS == 1 && previous & RW_WAITED_ON != 0 {
+            self.lock.wake_waiters();
+        }
+    }
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        if self.lock.state.swap(0, Ordering::Release) & RW_WAITED_ON != 0 {
+            self.lock
+                .state
+                .wake_all()
+                .expect("System error while waking RwLock waiters");
+        }
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
+
+/// A reader-writer lock, any amount of readers or a single writer can hold the lock at a time
+///
+/// Waiting threads sleep on a [`Futex`], readers and writers are woken up together and race for the lock,
+/// so a steady stream of readers can keep a writer waiting.
+#[derive(Debug)]
+pub struct RwLock<T> {
+    state: Futex,
+    inner: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Constructs a new free RwLock.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            state: Futex::new(0),
+            inner: UnsafeCell::new(inner),
+        }
+    }
+    /// Gets a mutable reference to the inner value.
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    /// Marks the lock as waited on from the state `state` observed while it couldn't be acquired,
+    /// then sleeps until the state changes.
+    fn wait_for_unlock(&self, state: u32) {
+        let waited_on = state | RW_WAITED_ON;
+        if state != waited_on
+            && self
+                .state
+                .compare_exchange(state, waited_on, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            // the state changed, so the lock may be available now
+            return;
+        }
+
+        self.state
+            .wait(waited_on, None)
+            .expect("System error while waiting for a RwLock");
+    }
+
+    fn wake_waiters(&self) {
+        self.state.fetch_and(!RW_WAITED_ON, Ordering::Relaxed);
+        self.state
+            .wake_all()
+            .expect("System error while waking RwLock waiters");
+    }
+
+    /// Returns true if a reader can be added to the lock state `state`.
+    const fn can_read(state: u32) -> bool {
+        state & RW_WRITE_LOCKED == 0 && state & RW_READERS != RW_READERS
+    }
+
+    /// Returns true if a writer can lock from the lock state `state`.
+    const fn can_write(state: u32) -> bool {
+        state & (RW_WRITE_LOCKED | RW_READERS) == 0
+    }
+
+    /// Attempts to add a reader to the lock state `state`, returns the new state on failure.
+    fn try_read_from(&self, state: u32) -> Result<RwLockReadGuard<'_, T>, u32> {
+        if !Self::can_read(state) {
+            return Err(state);
+        }
+
+        self.state
+            .compare_exchange_weak(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| RwLockReadGuard { lock: self })
+    }
+
+    /// Attempts to write-lock the lock from the lock state `state`, returns the new state on failure.
+    fn try_write_from(&self, state: u32) -> Result<RwLockWriteGuard<'_, T>, u32> {
+        if !Self::can_write(state) {
+            return Err(state);
+        }
+
+        self.state
+            .compare_exchange_weak(
+                state,
+                state | RW_WRITE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .map(|_| RwLockWriteGuard { lock: self })
+    }
+
+    /// Locks the lock for reading, blocking the current thread while a writer holds it.
+    ///
+    /// the lock is held until the returned RwLockReadGuard is dropped.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            match self.try_read_from(state) {
+                Ok(guard) => return guard,
+                Err(current) if !Self::can_read(current) => {
+                    self.wait_for_unlock(current);
+                    state = self.state.load(Ordering::Relaxed);
+                }
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Locks the lock for writing, blocking the current thread while any reader or writer holds it.
+    ///
+    /// the lock is held until the returned RwLockWriteGuard is dropped.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            match self.try_write_from(state) {
+                Ok(guard) => return guard,
+                Err(current) if !Self::can_write(current) => {
+                    self.wait_for_unlock(current);
+                    state = self.state.load(Ordering::Relaxed);
+                }
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Attempts to lock the lock for reading without blocking, returning `None` if a writer holds it.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            match self.try_read_from(state) {
+                Ok(guard) => return Some(guard),
+                Err(current) if !Self::can_read(current) => return None,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Attempts to lock the lock for writing without blocking, returning `None` if any reader or writer holds it.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            match self.try_write_from(state) {
+                Ok(guard) => return Some(guard),
+                Err(current) if !Self::can_write(current) => return None,
+                Err(current) => state = current,
+            }
+        }
+    }
+}
@@ -1,2 +1,13 @@
+pub mod cancel;
 pub mod cell;
+pub mod futex;
 pub mod locks;
+pub mod seqlock;
+#[cfg(feature = "alloc")]
+pub mod waitset;
+
+pub use cancel::CancellationToken;
+pub use futex::Futex;
+pub use seqlock::SeqLock;
+#[cfg(feature = "alloc")]
+pub use waitset::WaitSet;
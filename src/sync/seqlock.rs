@@ -0,0 +1,148 @@
+//! A sequence lock for publishing small [`Copy`] values to many readers, see [`SeqLock`]
+
+use core::{
+    cell::UnsafeCell,
+    hint,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{self, AtomicU32, Ordering},
+};
+
+/// A sequence lock, readers never block the writer and instead retry their read if it overlapped with a write.
+///
+/// Suitable for one writer publishing a value to many readers, including readers in other processes
+/// (for example a stats page published by a daemon through a [`crate::shm::SharedObject`]), see [`SeqLock::from_ptr`].
+///
+/// Writers are serialized against each other, but writes are expected to be rare compared to reads,
+/// and readers spin while a write is in progress.
+///
+/// The layout is stable (`repr(C)`): a [`u32`] sequence number followed by the value,
+/// the sequence number is odd while a write is in progress and is incremented by 2 with each write.
+#[repr(C)]
+pub struct SeqLock<T: Copy> {
+    seq: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    /// Constructs a new SeqLock holding `value`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Gets a SeqLock from a pointer to memory that is shared with other processes.
+    ///
+    /// Zeroed memory (such as a freshly created shared memory object) is a valid SeqLock if a zeroed `T` is valid.
+    /// # Safety
+    /// `ptr` must be properly aligned and point to a valid SeqLock that lives for `'a`,
+    /// and the memory must only be accessed through SeqLocks.
+    pub const unsafe fn from_ptr<'a>(ptr: NonNull<Self>) -> &'a Self {
+        unsafe { ptr.as_ref() }
+    }
+
+    /// Returns the current version of the value, the version changes with each write.
+    ///
+    /// An odd version means a write is in progress.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    /// Attempts to read the value once, returns `None` if the read overlapped with a write,
+    /// otherwise returns the value and the version it was read at.
+    pub fn try_read_versioned(&self) -> Option<(T, u32)> {
+        let before = self.seq.load(Ordering::Acquire);
+        if before & 1 != 0 {
+            return None;
+        }
+
+        // the value may be torn by a concurrent write, so it is read as possibly invalid bytes,
+        // and only assumed to be a valid `T` if the sequence number didn't change
+        let value = unsafe { self.data.get().cast::<MaybeUninit<T>>().read_volatile() };
+        atomic::fence(Ordering::Acquire);
+
+        let after = self.seq.load(Ordering::Relaxed);
+        (before == after).then(|| (unsafe { value.assume_init() }, before))
+    }
+
+    /// Reads the value and the version it was read at, retrying as long as the read overlaps with a write.
+    pub fn read_versioned(&self) -> (T, u32) {
+        loop {
+            if let Some(results) = self.try_read_versioned() {
+                return results;
+            }
+            hint::spin_loop();
+        }
+    }
+
+    /// Reads the value, retrying as long as the read overlaps with a write.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.read_versioned().0
+    }
+
+    /// Reads the value only if its version is no longer `version`,
+    /// useful for polling a published value for changes.
+    pub fn read_if_changed(&self, version: u32) -> Option<(T, u32)> {
+        if self.version() == version {
+            return None;
+        }
+
+        let (value, new_version) = self.read_versioned();
+        (new_version != version).then_some((value, new_version))
+    }
+
+    /// Writes `value`, waiting for any other write in progress to finish first.
+    pub fn write(&self, value: T) {
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq & 1 != 0 {
+                hint::spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+
+            match self.seq.compare_exchange_weak(
+                seq,
+                seq.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+
+        // makes sure the odd sequence number is visible before any of the data is changed
+        atomic::fence(Ordering::Release);
+        unsafe { self.data.get().write_volatile(value) };
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Gets a mutable reference to the value.
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Copy + Default> Default for SeqLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + core::fmt::Debug> core::fmt::Debug for SeqLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (value, version) = self.read_versioned();
+        f.debug_struct("SeqLock")
+            .field("value", &value)
+            .field("version", &version)
+            .finish()
+    }
+}
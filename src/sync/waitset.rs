@@ -0,0 +1,169 @@
+//! Waiting on multiple kinds of events at once, see [`WaitSet`]
+
+use core::time::Duration;
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use alloc::vec::Vec;
+use safa_abi::{
+    errors::ErrorStatus,
+    poll::{PollEntry, PollEvents},
+};
+
+use crate::syscalls::{self, types::Ri};
+
+use super::Futex;
+
+/// The longest a [`WaitSet`] blocks on one kind of event before checking the others,
+/// when waiting on both futexes and resources, or on more than one futex.
+pub const WAIT_SLICE: Duration = Duration::from_millis(10);
+
+/// What caused [`WaitSet::wait`] to return
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fired {
+    /// The futex at the given index (as returned by [`WaitSet::add_futex`]) no longer holds its expected value
+    Futex(usize),
+    /// The resource at the given index (as returned by [`WaitSet::add_resource`]) is ready, with the given events
+    Resource(usize, PollEvents),
+    /// The deadline was reached
+    Deadline,
+}
+
+/// A set of events to block on until any of them fires, the events can be:
+/// - a [`Futex`] word changing from an expected value
+/// - a pollable resource becoming ready
+/// - a deadline passing
+///
+/// The kernel can't wait on futexes and resources at the same time, so when the set contains both
+/// (or more than one futex) the wait is split into slices of at most [`WAIT_SLICE`],
+/// meaning a futex change can take up to [`WAIT_SLICE`] to be noticed,
+/// a set containing a single futex or only resources waits on the kernel directly.
+#[derive(Debug, Default)]
+pub struct WaitSet<'a> {
+    futexes: Vec<(&'a Futex, u32)>,
+    resources: Vec<PollEntry>,
+    deadline: Option<Duration>,
+}
+
+impl<'a> WaitSet<'a> {
+    /// Constructs a new empty WaitSet without a deadline.
+    pub const fn new() -> Self {
+        Self {
+            futexes: Vec::new(),
+            resources: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Adds `futex` to the set, firing when its value is no longer `expected`.
+    ///
+    /// Returns the index of the futex, see [`Fired::Futex`].
+    pub fn add_futex(&mut self, futex: &'a Futex, expected: u32) -> usize {
+        self.futexes.push((futex, expected));
+        self.futexes.len() - 1
+    }
+
+    /// Adds the resource `ri` to the set, firing when any of `events` occur on it.
+    ///
+    /// Returns the index of the resource, see [`Fired::Resource`].
+    pub fn add_resource(&mut self, ri: Ri, events: PollEvents) -> usize {
+        self.resources.push(PollEntry::new(ri, events));
+        self.resources.len() - 1
+    }
+
+    /// Sets the deadline of the set, as a point in time returned by [`crate::time::uptime`],
+    /// [`None`] removes the deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) -> &mut Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Sets the deadline of the set to `timeout` from now.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        let now = crate::time::uptime();
+        self.set_deadline(Some(now.saturating_add(timeout)))
+    }
+
+    /// Removes all the futexes, resources and the deadline from the set.
+    pub fn clear(&mut self) {
+        self.futexes.clear();
+        self.resources.clear();
+        self.deadline = None;
+    }
+
+    fn changed_futex(&self) -> Option<usize> {
+        self.futexes.iter().position(|(futex, expected)| {
+            futex.load(core::sync::atomic::Ordering::Acquire) != *expected
+        })
+    }
+
+    fn remaining(&self) -> Option<Option<Duration>> {
+        match self.deadline {
+            None => Some(None),
+            Some(deadline) => {
+                let now = crate::time::uptime();
+                deadline.checked_sub(now).filter(|d| !d.is_zero()).map(Some)
+            }
+        }
+    }
+
+    /// Blocks until any of the events in the set fire, returning which one did,
+    /// if multiple events fired at once, futexes are reported first, then resources, then the deadline.
+    ///
+    /// Blocks forever if the set is empty and has no deadline.
+    pub fn wait(&mut self) -> Result<Fired, ErrorStatus> {
+        loop {
+            if let Some(index) = self.changed_futex() {
+                return Ok(Fired::Futex(index));
+            }
+
+            let Some(remaining) = self.remaining() else {
+                return Ok(Fired::Deadline);
+            };
+
+            let sliced = |remaining: Option<Duration>| {
+                Some(remaining.map_or(WAIT_SLICE, |r| r.min(WAIT_SLICE)))
+            };
+
+            match (self.futexes.as_slice(), self.resources.is_empty()) {
+                ([], true) => {
+                    _ = syscalls::thread::sleep(remaining.unwrap_or(Duration::MAX));
+                }
+                ([(futex, expected), rest @ ..], true) => {
+                    let timeout = if rest.is_empty() {
+                        remaining
+                    } else {
+                        sliced(remaining)
+                    };
+
+                    match futex.wait(*expected, timeout) {
+                        Ok(()) | Err(ErrorStatus::Timeout) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                (futexes, false) => {
+                    let timeout = if futexes.is_empty() {
+                        remaining
+                    } else {
+                        sliced(remaining)
+                    };
+
+                    // a slice running out returns no events, the loop then checks the futexes and the deadline again
+                    syscalls::io::poll_resources(&mut self.resources, timeout)?;
+                    if let Some((index, entry)) = self
+                        .resources
+                        .iter()
+                        .enumerate()
+                        .find(|(_, entry)| entry.returned_events() != PollEvents::NONE)
+                    {
+                        return Ok(Fired::Resource(index, entry.returned_events()));
+                    }
+                }
+            }
+        }
+    }
+}
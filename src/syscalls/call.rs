@@ -1,7 +1,6 @@
+use crate::syscalls::syscall_backend;
 use crate::syscalls::types::{OkSyscallResult, SyscallResults};
 
-use core::arch::asm;
-
 /// Invokes a syscall with the given number and arguments
 /// Number must be of type [`SyscallNum`]
 /// Arguments must be of type [`usize`]
@@ -100,20 +99,8 @@ impl<const NUM: u16> SyscallCaller<NUM, (usize, usize, usize, usize, usize, usiz
 #[doc(hidden)]
 #[inline(always)]
 pub fn syscall0<const NUM: u16, R: OkSyscallResult>() -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap0::<NUM>();
         core::mem::transmute(result)
     }
 }
@@ -121,22 +108,8 @@ pub fn syscall0<const NUM: u16, R: OkSyscallResult>() -> SyscallResults<R> {
 #[doc(hidden)]
 #[inline(always)]
 pub fn syscall1<const NUM: u16, R: OkSyscallResult>(arg1: usize) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap1::<NUM>(arg1);
         core::mem::transmute(result)
     }
 }
@@ -144,24 +117,8 @@ pub fn syscall1<const NUM: u16, R: OkSyscallResult>(arg1: usize) -> SyscallResul
 #[doc(hidden)]
 #[inline(always)]
 pub fn syscall2<const NUM: u16, R: OkSyscallResult>(arg1: usize, arg2: usize) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            in("rsi") arg2,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            in("x1") arg2,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap2::<NUM>(arg1, arg2);
         core::mem::transmute(result)
     }
 }
@@ -173,26 +130,8 @@ pub fn syscall3<const NUM: u16, R: OkSyscallResult>(
     arg2: usize,
     arg3: usize,
 ) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            in("rsi") arg2,
-            in("rdx") arg3,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            in("x1") arg2,
-            in("x2") arg3,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap3::<NUM>(arg1, arg2, arg3);
         core::mem::transmute(result)
     }
 }
@@ -205,29 +144,8 @@ pub fn syscall4<const NUM: u16, R: OkSyscallResult>(
     arg3: usize,
     arg4: usize,
 ) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            in("rsi") arg2,
-            in("rdx") arg3,
-            in("rcx") arg4,
-            lateout("rax") result,
-        );
-
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            in("x1") arg2,
-            in("x2") arg3,
-            in("x3") arg4,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap4::<NUM>(arg1, arg2, arg3, arg4);
         core::mem::transmute(result)
     }
 }
@@ -241,30 +159,8 @@ pub fn syscall5<const NUM: u16, R: OkSyscallResult>(
     arg4: usize,
     arg5: usize,
 ) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            in("rsi") arg2,
-            in("rdx") arg3,
-            in("rcx") arg4,
-            in("r8") arg5,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            in("x1") arg2,
-            in("x2") arg3,
-            in("x3") arg4,
-            in("x4") arg5,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap5::<NUM>(arg1, arg2, arg3, arg4, arg5);
         core::mem::transmute(result)
     }
 }
@@ -279,32 +175,8 @@ pub fn syscall6<const NUM: u16, R: OkSyscallResult>(
     arg5: usize,
     arg6: usize,
 ) -> SyscallResults<R> {
-    let result: usize;
     unsafe {
-        #[cfg(target_arch = "x86_64")]
-        asm!(
-            "int 0x80",
-            in("rax") NUM as usize,
-            in("rdi") arg1,
-            in("rsi") arg2,
-            in("rdx") arg3,
-            in("rcx") arg4,
-            in("r8") arg5,
-            in("r9") arg6,
-            lateout("rax") result,
-        );
-        #[cfg(target_arch = "aarch64")]
-        asm!(
-            "svc #{num}",
-            num = const NUM,
-            in("x0") arg1,
-            in("x1") arg2,
-            in("x2") arg3,
-            in("x3") arg4,
-            in("x4") arg5,
-            in("x5") arg6,
-            lateout("x0") result
-        );
+        let result = syscall_backend::trap6::<NUM>(arg1, arg2, arg3, arg4, arg5, arg6);
         core::mem::transmute(result)
     }
 }
@@ -1,7 +1,7 @@
 use core::time::Duration;
 
 use crate::syscalls::{
-    types::{IntoSyscallArg, RequiredPtr, RequiredPtrMut},
+    types::{IntoSyscallArg, RequiredPtr, RequiredPtrMut, SyscallSafeArg},
     SyscallNum,
 };
 pub use safa_abi::clock::*;
@@ -14,29 +14,9 @@ impl IntoSyscallArg for Clock {
     }
 }
 
-define_syscall! {
-    SyscallNum::SysUptime => {
-        /// returns the system uptime in milliseconds
-        sysuptime(uptime: RequiredPtrMut<u64>)
-    },
-    SyscallNum::SysClockGetTime => {
-        /// Gets the [`core::time::Duration`] that has passed since a given [`Clock`].
-        sysclock_gettime(clock: Clock, results: RequiredPtrMut<CDuration>)
-    },
-    SyscallNum::SysClockGetRes => {
-        /// Gets the smallest [`core::time::Duration`] that a given [`Clock`] can produce. (clock resolution/precision).
-        sysclock_getres(clock: Clock, results: RequiredPtrMut<CDuration>)
-    },
-    SyscallNum::SysClockSetTime => {
-        /// Sets the time to the given `time` in a given [`Clock`].
-        /// Depending on the clock this might fail/require privileges.
-        sysclock_settime(clock: Clock, time: RequiredPtr<CDuration>)
-    },
-    SyscallNum::SysClockGetCntFreq => {
-        /// Gets the frequency of the hardware counter (such as the TSC on x86_64).
-        sysclock_getcntfreq(results: RequiredPtrMut<u64>, flags: u32)
-    }
-}
+unsafe impl SyscallSafeArg for Clock {}
+
+include!(concat!(env!("OUT_DIR"), "/syscalls/clock.rs"));
 
 #[inline]
 #[deprecated(
@@ -6,55 +6,22 @@ use safa_abi::{
 
 use super::define_syscall;
 use super::SyscallNum;
-use crate::syscalls::types::{OptionalPtrMut, RequiredPtrMut, Ri};
+use crate::{
+    abi_check,
+    syscalls::types::{OptionalPtrMut, RequiredPtrMut, Ri},
+};
 
-define_syscall!(SyscallNum::SysFGetDirEntry => {
-    /// Gets the directory entry for the path `path` and puts it in `dest_direntry`
-    /// path must be valid utf-8
-    /// if `dest_direntry` is not null, it will be set to the directory entry
-    sysgetdirentry(path: Str, dest_direntry: OptionalPtrMut<DirEntry>)
-});
+include!(concat!(env!("OUT_DIR"), "/syscalls/fs.rs"));
 
 #[inline]
 pub fn getdirentry(path: &str) -> Result<DirEntry, ErrorStatus> {
     let mut dest_direntry: DirEntry = unsafe { core::mem::zeroed() };
     let ptr = RequiredPtrMut::new(&raw mut dest_direntry).into();
 
-    sysgetdirentry(Str::from_str(path), ptr)
-        .get()
-        .map(|()| dest_direntry)
-}
+    sysgetdirentry(Str::from_str(path), ptr).get()?;
 
-define_syscall! {
-    SyscallNum::SysFSOpenAll => {
-        /// Opens the file with the path `path` and puts the resource id in `dest_fd`, with all permissions
-        ///
-        /// path must be valid utf-8
-        sysopen_all(path: Str) Ri
-    },
-    SyscallNum::SysFSOpen => {
-        /// Opens the file with the path `path` and puts the resource id in `dest_fd`, with a given mode (permissions and flags)
-        ///
-        /// path must be valid utf-8
-        sysopen(path: Str, options: OpenOptions) Ri
-    },
-    SyscallNum::SysFSCreate => {
-        /// Creates the file with the path `path`
-        /// path must be valid utf-8
-        syscreate_file(path: Str)
-    },
-    SyscallNum::SysFSCreateDir => {
-        /// Creates the directory with the path `path`
-        ///
-        /// path must be valid utf-8
-        syscreate_dir(path: Str)
-    },
-    SyscallNum::SysFSRemovePath => {
-        /// Deletes "removes" a given path
-        ///
-        /// path must be valid utf-8
-        sysremove_path(path: Str)
-    },
+    abi_check::check_dir_entry(&dest_direntry);
+    Ok(dest_direntry)
 }
 
 #[inline]
@@ -9,21 +9,7 @@ use crate::syscalls::types::RequiredPtrMut;
 use super::define_syscall;
 use super::SyscallNum;
 
-define_syscall! {
-    SyscallNum::SysTFutWake => {
-        /// Wakes up, up to `n` threads waiting on futex `addr` using [`syst_fut_wait`]
-        ///
-        /// returns the amount of threads that were woken up on success.
-        syst_fut_wake(addr: RequiredPtr<AtomicU32>, n: usize) usize
-    },
-    SyscallNum::SysTFutWait => {
-        /// Waits for *addr to not be equal to val
-        /// only stops waiting if *addr != val and signaled by [`syst_fut_wake`] or timeout is reached
-        ///
-        /// if timeout is reached returns [`ErrorStatus::Timeout`]
-        syst_fut_wait(addr: RequiredPtr<AtomicU32>, val: u32, timeout_ms: u64)
-    }
-}
+include!(concat!(env!("OUT_DIR"), "/syscalls/futex.rs"));
 
 /// Wakes up, up to `n` threads waiting on futex `addr` using [`futex_wait`]
 ///
@@ -46,8 +32,61 @@ pub fn futex_wait(
     val: u32,
     timeout_duration: Duration,
 ) -> Result<(), ErrorStatus> {
-    let timeout_ms = timeout_duration.as_millis() as u64;
+    let timeout_ms = timeout_duration.as_millis().min(u64::MAX as u128) as u64;
     let addr = unsafe { RequiredPtrMut::new_unchecked(addr as *const _ as *mut _) };
 
     syst_fut_wait(addr, val, timeout_ms).get()
 }
+
+/// Wakes up, up to `n` threads waiting on futex `from`, and requeues the remaining waiters to wait on futex `to` instead,
+/// without waking them up.
+///
+/// returns the amount of threads that were woken up on success
+///
+/// # Kernel support
+/// The kernel doesn't support requeueing yet, so all the waiters on `from` are woken up instead,
+/// this is still correct as waiters must recheck their condition after waking up, but it doesn't avoid the thundering herd.
+#[inline]
+pub fn futex_requeue(from: &AtomicU32, to: &AtomicU32, n: usize) -> Result<usize, ErrorStatus> {
+    _ = (to, n);
+    futex_wake(from, usize::MAX)
+}
+
+/// Like [`futex_wait`] but the waiter can only be woken up by [`futex_wake_bitset`] calls
+/// with a `bitset` that shares at least one bit with `bitset`, or by [`futex_wake`].
+///
+/// A `bitset` of 0 never matches, so this returns immediately.
+///
+/// # Kernel support
+/// The kernel doesn't support bitsets yet, so the waiter can be woken up by any wake operation on `addr`,
+/// which looks like a spurious wakeup to the caller.
+#[inline]
+pub fn futex_wait_bitset(
+    addr: &AtomicU32,
+    val: u32,
+    timeout_duration: Duration,
+    bitset: u32,
+) -> Result<(), ErrorStatus> {
+    if bitset == 0 {
+        return Ok(());
+    }
+
+    futex_wait(addr, val, timeout_duration)
+}
+
+/// Wakes up, up to `n` threads waiting on futex `addr` using [`futex_wait_bitset`] with a bitset that shares at least one bit with `bitset`,
+/// or using [`futex_wait`].
+///
+/// returns the amount of threads that were woken up on success
+///
+/// # Kernel support
+/// The kernel doesn't support bitsets yet, so all the waiters on `addr` are woken up instead
+/// (they observe a spurious wakeup), unless `bitset` is 0 in which case no waiters are woken up.
+#[inline]
+pub fn futex_wake_bitset(addr: &AtomicU32, n: usize, bitset: u32) -> Result<usize, ErrorStatus> {
+    if bitset == 0 || n == 0 {
+        return Ok(0);
+    }
+
+    futex_wake(addr, usize::MAX)
+}
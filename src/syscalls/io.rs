@@ -7,31 +7,19 @@ use safa_abi::{
     poll::PollEntry,
 };
 
-use crate::syscalls::types::{OptionalPtrMut, RequiredPtrMut, Ri};
+use crate::{
+    abi_check,
+    limits::MAX_POLL_ENTRIES,
+    syscalls::types::{OptionalPtrMut, RequiredPtrMut, Ri},
+};
 
 use super::{define_syscall, SyscallNum};
 
-#[cfg(not(feature = "rustc-dep-of-std"))]
+#[cfg(all(feature = "alloc", not(feature = "rustc-dep-of-std")))]
 extern crate alloc;
 
 // Directory Iterator related syscalls
-define_syscall! {
-    SyscallNum::SysFDirIterOpen =>
-    {
-        /// Opens a directory iterator for the directory with the resource id `dir_ri`
-        sysdiriter_open(dir_ri: Ri) Ri
-    },
-    SyscallNum::SysDirIterNext => {
-        /// Gets the next directory entry from a directory iterator,
-        ///
-        /// puts the results in `dest_direntry`,
-        ///
-        /// puts zeroed DirEntry in `dest_direntry` if there are no more entries
-        ///
-        /// returns [`ErrorStatus::Generic`] (1) if there are no more entries
-        sysdiriter_next(dir_ri: Ri, dest_direntry: OptionalPtrMut<DirEntry>)
-    }
-}
+include!(concat!(env!("OUT_DIR"), "/syscalls/io.rs"));
 
 #[inline]
 /// Opens a directory iterator for the directory with the resource id `dir_ri`,
@@ -49,69 +37,74 @@ pub fn diriter_open(dir_ri: Ri) -> Result<Ri, ErrorStatus> {
 pub fn diriter_next(dir_ri: Ri) -> Result<DirEntry, ErrorStatus> {
     let mut dest_direntry: DirEntry = unsafe { core::mem::zeroed() };
     let ptr = RequiredPtrMut::new(&raw mut dest_direntry).into();
-    sysdiriter_next(dir_ri, ptr).get().map(|()| dest_direntry)
+    sysdiriter_next(dir_ri, ptr).get()?;
+
+    abi_check::check_dir_entry(&dest_direntry);
+    Ok(dest_direntry)
 }
 
-// File related syscalls
-define_syscall! {
-    SyscallNum::SysIOWrite => {
-        /// Writes `len` bytes from `buf` to the file with the resource id `fd` at offset `offset`
-        ///
-        /// Returns the number of bytes written
-        syswrite(fd: Ri, offset: isize, buf: Slice<u8>) usize
-    },
-    SyscallNum::SysIOTruncate => {
-        /// Truncates the file with the resource id `fd` to `len` bytes
-        systruncate(fd: Ri, len: usize)
-    },
-    SyscallNum::SysFSize => {
-        /// Gets the size of the file with the resource id `fd` and returns it on success.
-        sysfsize(fd: Ri) usize
-    },
-    SyscallNum::SysFAttrs => {
-        /// Gets the file attributes of the file with the resource id `fd` and puts them in `dest_attrs`
-        sysfattrs(fd: Ri, dest_attrs: OptionalPtrMut<FileAttr>)
-    },
-    SyscallNum::SysIORead => {
-        /// Reads `len` bytes from the file with the resource id `fd` at offset `offset` into `buf`
-        ///
-        /// Returns the number of bytes read.
-        sysread(fd: Ri, offset: isize, buf: Slice<u8>) usize
-    },
-    SyscallNum::SysIOSync => {
-        /// Syncs the resource with the resource id `fd`
-        syssync(ri: Ri)
-    },
-    SyscallNum::SysIOCommand => {
-        /// Sends the command `cmd` to device on the resource `resource` taking an arg `arg`
-        sysio_command(ri: Ri, cmd: u16, arg: u64)
-    },
-    SyscallNum::SysVTTYAlloc => {
-        sysvtty_alloc(mother_ri: RequiredPtrMut<Ri>, child_ri: RequiredPtrMut<Ri>, _reserved_zero: usize)
-    },
-    SyscallNum::SysIOPoll => {
-        /// Given a set of resources, waits for any of them to become ready for I/O (with specified events), returns the events that occurred causing the thread to wake up.
-        /// # Arguments
-        /// * `entries` - A slice of [`PollEntry`] structures, each representing a resource to poll.
-        /// * `timeout` - The maximum time to wait for any resource to become ready, in milliseconds, if 0 returns immediately, if u64::MAX waits forever.
-        sysiopoll(entries: Slice<PollEntry>, timeout: u64)
+/// Fills `entries` with the next directory entries from a directory iterator, returns the amount of entries written,
+/// 0 meaning the end of the directory was reached (or `entries` is empty).
+///
+/// Fewer entries than requested doesn't mean the end was reached, only 0 does,
+/// an error met after some entries were written is returned by the next call instead.
+///
+/// The kernel has no batched syscall yet, so this falls back to one [`sysdiriter_next`] per entry,
+/// code using this gets batching for free once the kernel supports it.
+pub fn diriter_next_many(dir_ri: Ri, entries: &mut [DirEntry]) -> Result<usize, ErrorStatus> {
+    let mut count = 0;
+    for slot in entries.iter_mut() {
+        // the kernel signals the end either with an empty entry or with a generic error
+        match diriter_next(dir_ri) {
+            Ok(entry) if entry.name_length != 0 => {
+                *slot = entry;
+                count += 1;
+            }
+            Ok(_) | Err(ErrorStatus::Generic) => break,
+            Err(_) if count != 0 => break,
+            Err(err) => return Err(err),
+        }
     }
+    Ok(count)
 }
 
+// File related syscalls
+
 #[inline]
 /// Given a set of resources, waits for any of them to become ready for I/O (with specified events), returns the events that occurred causing the thread to wake up.
 /// # Arguments
 /// * `entries` - A slice of [`PollEntry`] structures, each representing a resource to poll.
 /// * `timeout_ms` - The maximum time to wait for any resource to become ready, in milliseconds, if None or [`Duration::MAX`] waits forever.
+///
+/// # Returns
+/// - `Ok(())` once any of the resources is ready, or once the timeout is reached,
+///   in which case the returned events of every entry are empty, reaching the timeout isn't an error
+/// - [`ErrorStatus::InvalidArgument`] if there are more than [`MAX_POLL_ENTRIES`] entries
 pub fn poll_resources(
     entries: &mut [PollEntry],
     timeout_ms: Option<Duration>,
 ) -> Result<(), ErrorStatus> {
-    sysiopoll(
+    if entries.len() > MAX_POLL_ENTRIES {
+        return Err(ErrorStatus::InvalidArgument);
+    }
+
+    // the kernel might not write the returned events of entries that aren't ready
+    for entry in entries.iter_mut() {
+        *entry = PollEntry::new(entry.resource(), entry.events());
+    }
+
+    let checksum = abi_check::poll_entries_checksum(entries);
+    let results = sysiopoll(
         Slice::from_slice_mut(entries),
         timeout_ms.map(|m| m.as_millis() as u64).unwrap_or(u64::MAX),
     )
-    .get()
+    .get();
+
+    abi_check::check_poll_entries(entries, checksum);
+    match results {
+        Err(ErrorStatus::Timeout) => Ok(()),
+        results => results,
+    }
 }
 
 /// Sends the command `cmd` to device on the resource `ri` taking a u64 argument `arg`
@@ -3,7 +3,8 @@ use core::ptr::NonNull;
 use safa_abi::errors::ErrorStatus;
 use safa_abi::mem::{MemMapFlags, RawMemMapConfig, ShmFlags};
 
-use crate::syscalls::types::{IntoSyscallArg, RequiredPtrMut, Ri};
+use crate::limits::page_size;
+use crate::syscalls::types::{IntoSyscallArg, RequiredPtrMut, Ri, SyscallSafeArg};
 
 use super::types::{OptionalPtrMut, RequiredPtr};
 use super::SyscallNum;
@@ -15,12 +16,9 @@ impl IntoSyscallArg for MemMapFlags {
     }
 }
 
-define_syscall! {
-    SyscallNum::SysMemMap => {
-        /// See [`SyscallNum::SysMemMap`]
-        sysmem_map(memmap_config: RequiredPtr<RawMemMapConfig>, flags: MemMapFlags, out_res_id: OptionalPtrMut<Ri>) NonNull<u8>
-    }
-}
+unsafe impl SyscallSafeArg for MemMapFlags {}
+
+include!(concat!(env!("OUT_DIR"), "/syscalls/mem.rs"));
 
 /// See [`SyscallNum::SysMemMap`] and [`RawMemMapConfig`]
 ///
@@ -62,8 +60,7 @@ pub fn map(
     };
     let result_ri = res_id_results;
 
-    // each Page is 4096 bytes
-    let len = page_count * 4096;
+    let len = page_count * page_size();
     let slice = unsafe { core::slice::from_raw_parts_mut(result_start_addr.as_ptr(), len) };
 
     unsafe { Ok((result_ri, NonNull::new_unchecked(slice))) }
@@ -77,42 +74,11 @@ impl IntoSyscallArg for ShmFlags {
     }
 }
 
+unsafe impl SyscallSafeArg for ShmFlags {}
+
 /// A Shared Memory Descriptor Key, that can be opened using [`sysmem_shm_open`] or created using [`sysmem_shm_create`].
 pub type ShmKey = usize;
 
-define_syscall! {
-    SyscallNum::SysMemShmCreate => {
-        /// Create a Shared Memory Descriptor, returning a key that points to it,
-        /// The life time of that descriptor is bound to the calling process or the thread if the flag [`ShmFlags::LOCAL`] was specified.
-        ///
-        /// The returned Key can then be opened from another process using [`sysmem_shm_open`] and then [`sysmem_map`]ped,
-        /// instead of calling [`sysmem_shm_open`] afterwards this returns an Optional Resource ID that can be mapped directly using [`sysmem_map`] from the calling process,
-        /// but the desired Process to communicate with, should use [`sysmem_shm_open`] to get it's own copy.
-        ///
-        /// The lifetime of the key is extended for each [`sysmem_shm_open`] call, so that it isn't dropped until all the threads/processes that owns it are dropped.
-        /// # Arguments
-        /// * `page_count` - The number of pages to allocate for the shared memory descriptor.
-        /// * `flags` - The flags to use when creating and opening the shared memory descriptor.
-        /// * `out_shm_key` - A pointer to a [`ShmKey`] that will be filled with the key of the created shared memory descriptor.
-        /// # Returns
-        /// * On Ok: The resource ID of the created shared memory descriptor, as if a call to [`sysmem_shm_open`] was made on the `out_shm_key`.
-        sysmem_shm_create(page_count: usize, flags: ShmFlags, out_shm_key: RequiredPtrMut<ShmKey>) Ri
-    },
-    SyscallNum::SysMemShmOpen => {
-        /// Creates a Resource that can be [`sysmem_map`]ped to a Shared Memory Descriptor,
-        /// Takes in a key that was created using [`sysmem_shm_create`].
-        ///
-        /// The lifetime of the Resource is bound to the process or a single thread if the flag [`ShmFlags::LOCAL`] was specified.
-        ///
-        /// # Arguments
-        /// * `shm_key` - The key of the shared memory descriptor to open.
-        /// * `flags` - The flags to use when opening the shared memory descriptor.
-        /// # Returns
-        /// * On Ok: The resource ID of the opened shared memory descriptor.
-        sysmem_shm_open(shm_key: ShmKey, flags: ShmFlags) Ri
-    },
-}
-
 /// Create a Shared Memory Descriptor, returning a key that points to it,
 /// The life time of that descriptor is bound to the calling process or the thread if the flag [`ShmFlags::LOCAL`] was specified.
 ///
@@ -1,6 +1,6 @@
 use safa_abi::arch::ArchOp;
 
-use crate::syscalls::types::IntoSyscallArg;
+use crate::syscalls::types::{IntoSyscallArg, SyscallSafeArg};
 
 use super::{define_syscall, SyscallNum};
 
@@ -11,19 +11,9 @@ impl IntoSyscallArg for ArchOp {
     }
 }
 
-define_syscall! {
-    SyscallNum::SysShutdown => {
-        /// Shuts down the system
-        sysshutdown() unreachable
-    },
-    SyscallNum::SysReboot => {
-        /// Reboots the system
-        sysreboot() unreachable
-    },
-    SyscallNum::SysACtrl => {
-        sysarch_ctrl(op: ArchOp, arg: u64)
-    }
-}
+unsafe impl SyscallSafeArg for ArchOp {}
+
+include!(concat!(env!("OUT_DIR"), "/syscalls/misc.rs"));
 
 #[inline]
 pub fn shutdown() -> ! {
@@ -1,9 +1,10 @@
 //! This module exposes SafaOS's syscalls and their rust counterparts
 
-#[cfg(not(feature = "rustc-dep-of-std"))]
+#[cfg(all(feature = "alloc", not(feature = "rustc-dep-of-std")))]
 extern crate alloc;
 
 pub(crate) mod call;
+pub(crate) mod syscall_backend;
 
 pub use safa_abi::syscalls::SyscallTable as SyscallNum;
 
@@ -12,28 +13,26 @@ pub use call::syscall;
 macro_rules! define_syscall {
     ($num:path => { $(#[$attrss:meta])* $name:ident ($($arg:ident : $ty:ty),*) unreachable }) => {
         $(#[$attrss])*
-        #[cfg_attr(
-            not(any(feature = "std", feature = "rustc-dep-of-std")),
-            unsafe(no_mangle)
-        )]
-        #[cfg_attr(any(feature = "std", feature = "rustc-dep-of-std"), inline(always))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std"), feature = "linkonce"), linkage = "weak")]
+        #[cfg_attr(not(any(all(feature = "c-exports", not(feature = "std")), feature = "small-syscalls")), inline(always))]
         pub extern "C" fn $name($($arg: $ty),*) -> ! {
             #[allow(unused_imports)]
             use $crate::syscalls::types::IntoSyscallArg;
+            const { $($crate::syscalls::types::assert_syscall_safe_arg::<$ty>();)* }
             let _: $crate::syscalls::types::SyscallResults<core::convert::Infallible> = $crate::syscalls::syscall!($num, $( $arg.into_syscall_arg() ),*);
             unreachable!()
         }
     };
     ($num:path => { $(#[$attrss:meta])* $name:ident ($($arg:ident : $ty:ty),*) $($return_ty:ty)? }) => {
         $(#[$attrss])*
-        #[cfg_attr(
-            not(any(feature = "std", feature = "rustc-dep-of-std")),
-            unsafe(no_mangle)
-        )]
-        #[cfg_attr(any(feature = "std", feature = "rustc-dep-of-std"), inline(always))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std")), unsafe(no_mangle))]
+        #[cfg_attr(all(feature = "c-exports", not(feature = "std"), feature = "linkonce"), linkage = "weak")]
+        #[cfg_attr(not(any(all(feature = "c-exports", not(feature = "std")), feature = "small-syscalls")), inline(always))]
         pub extern "C" fn $name($($arg: $ty),*) -> $crate::syscalls::types::SyscallResults$(<$return_ty>)? {
             #[allow(unused_imports)]
             use $crate::syscalls::types::IntoSyscallArg;
+            const { $($crate::syscalls::types::assert_syscall_safe_arg::<$ty>();)* }
             let result = $crate::syscalls::syscall!($num, $( $arg ),*);
             result
         }
@@ -20,39 +20,12 @@ use crate::{
 
 use super::{define_syscall, SyscallNum};
 
-#[cfg(not(feature = "rustc-dep-of-std"))]
+#[cfg(all(feature = "alloc", not(feature = "rustc-dep-of-std")))]
 extern crate alloc;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-define_syscall! {
-    SyscallNum::SysPExit => {
-        /// Exits the process with the exit code [`code`]
-        sysp_exit(code: usize) unreachable
-    },
-    SyscallNum::SysPWait => {
-        /// Waits for a child process with the pid `pid` to exit
-        ///
-        /// # Returns
-        /// - [`ErrorStatus::InvalidPid`] if the target process doesn't exist at the time of wait
-        ///
-        /// - [`ErrorStatus::MissingPermissions`] if the target process isn't a child of self
-        ///
-        /// - if `exit_code` is not null, it will be set to the exit code of the process if successful
-        sysp_wait(pid: Pid, exit_code: OptionalPtrMut<usize>)
-    },
-    SyscallNum::SysPTryCleanUp => {
-      /// Attempts to cleanup the process with pid `pid` and returns it's exit status on success
-      ///
-      /// # Returns
-      /// - [`ErrorStatus::InvalidPid`] if the target process doesn't exist at the time of attempted cleanup
-      ///
-      /// - [`ErrorStatus::Generic`] if the target process isn't dead and awaiting cleanup
-      sysp_try_cleanup(pid: Pid, dest_exit_code: OptionalPtrMut<usize>)
-    },
-    SyscallNum::SysPSpawn => {
-        sysp_spawn_inner(path: Str, raw_config: RequiredPtr<RawPSpawnConfig>) Pid
-    }
-}
+include!(concat!(env!("OUT_DIR"), "/syscalls/process.rs"));
 
 /// Exits the process with the exit code `code`
 #[inline]
@@ -105,6 +78,9 @@ exported_func! {
     ///   if they are None they will be inherited from the parent
     ///
     /// - the behavior isn't defined if `priority` is None, currently it will be set to a default
+    ///
+    /// - the new process inherits a duplicate of the environment, without the `alloc` feature the environment isn't kept
+    ///   so the new process starts with an empty one
     extern "C" fn sysp_spawn(
         name: OptZero<Str>,
         path: Str,
@@ -118,33 +94,72 @@ exported_func! {
         stderr: COption<Ri>,
         custom_stack_size: OptZero<ShouldNotBeZero<usize>>,
     ) -> SyscallResults<Pid> {
-        let (stdin, stdout, stderr): (Option<_>, Option<_>, Option<_>) =
-            (stdin.into(), stdout.into(), stderr.into());
-
-        let stdio = {
-            if stdin.is_none() && stdout.is_none() && stderr.is_none() {
-                None
-            } else {
-                let stdout = stdout.or(systry_get_stdout().into());
-                let stdin = stdin.or(systry_get_stdin().into());
-                let stderr = stderr.or(systry_get_stderr().into());
-
-                Some(ProcessStdio::new(stdout, stdin, stderr))
-            }
+        #[cfg(feature = "alloc")]
+        let mut duplicate = crate::process::env::duplicate_env();
+        #[cfg(feature = "alloc")]
+        let env = {
+            let duplicate_slices = duplicate.raw_slices_mut();
+            unsafe { OptZero::some(Slice::from_raw_parts(duplicate_slices.as_mut_ptr(), duplicate_slices.len())) }
         };
+        #[cfg(not(feature = "alloc"))]
+        let env = OptZero::none();
 
-        let stdio = stdio.as_ref();
-        let stdio_ptr = stdio.map(|m| unsafe {FFINonNull::new_unchecked(m as *const _ as *mut _)}).into();
-
-        let mut duplicate = crate::process::env::duplicate_env();
-        let duplicate_slices = duplicate.raw_slices_mut();
+        unsafe { spawn_with_env(name, path, args, env, flags, priority, stdin, stdout, stderr, custom_stack_size) }
+    }
+}
 
-        let env = unsafe { OptZero::some(Slice::from_raw_parts(duplicate_slices.as_mut_ptr(), duplicate_slices.len())) };
-        let config = RawPSpawnConfig::new_from_raw(name, args, env, flags, stdio_ptr, priority, custom_stack_size);
+/// Same as [`sysp_spawn`] but uses `env` as the environment of the new process instead of duplicating the current one,
+/// used to spawn multiple processes with the same environment without duplicating it each time.
+///
+/// # Safety
+/// `env` must be a valid slice of slices, each slice being a `key=value` pair followed by a null terminator,
+/// see [`crate::process::env::DuplicatedEnv`].
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn spawn_with_env(
+    name: OptZero<Str>,
+    path: Str,
+    args: OptZero<Slice<Str>>,
+    env: OptZero<Slice<Slice<u8>>>,
+    flags: SpawnFlags,
+    priority: RawContextPriority,
+    stdin: COption<Ri>,
+    stdout: COption<Ri>,
+    stderr: COption<Ri>,
+    custom_stack_size: OptZero<ShouldNotBeZero<usize>>,
+) -> SyscallResults<Pid> {
+    let (stdin, stdout, stderr): (Option<_>, Option<_>, Option<_>) =
+        (stdin.into(), stdout.into(), stderr.into());
+
+    let stdio = {
+        if stdin.is_none() && stdout.is_none() && stderr.is_none() {
+            None
+        } else {
+            let stdout = stdout.or(systry_get_stdout().into());
+            let stdin = stdin.or(systry_get_stdin().into());
+            let stderr = stderr.or(systry_get_stderr().into());
+
+            Some(ProcessStdio::new(stdout, stdin, stderr))
+        }
+    };
+
+    let stdio = stdio.as_ref();
+    let stdio_ptr = stdio
+        .map(|m| unsafe { FFINonNull::new_unchecked(m as *const _ as *mut _) })
+        .into();
+
+    let config = RawPSpawnConfig::new_from_raw(
+        name,
+        args,
+        env,
+        flags,
+        stdio_ptr,
+        priority,
+        custom_stack_size,
+    );
 
-        let raw_config_ptr = unsafe { RequiredPtr::new_unchecked(&config as *const _ as *mut _) };
-        sysp_spawn_inner(path, raw_config_ptr)
-    }
+    let raw_config_ptr = unsafe { RequiredPtr::new_unchecked(&config as *const _ as *mut _) };
+    sysp_spawn_inner(path, raw_config_ptr)
 }
 
 /// spawns a new process
@@ -168,7 +183,7 @@ pub unsafe fn unsafe_spawn(
     stderr: Option<Ri>,
     custom_stack_size: Option<NonZero<usize>>,
 ) -> Result<Pid, ErrorStatus> {
-    let name = name.map(|s| Str::from_str(s)).into();
+    let name = name.map(Str::from_str).into();
     let path = Str::from_str(path);
     let args = unsafe { OptZero::some(Slice::from_str_slices_mut(args as *mut [*mut str])) };
 
@@ -196,6 +211,7 @@ pub unsafe fn unsafe_spawn(
 // although this is fine because this method is only used in the rust standard library which gives args as an owned Vec anyways
 //
 /// same as [`unsafe_spawn`] but safe because it makes it clear that `argv`  are consumed
+#[cfg(feature = "alloc")]
 #[inline]
 pub fn spawn(
     name: Option<&str>,
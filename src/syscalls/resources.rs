@@ -1,21 +1,12 @@
-use safa_abi::errors::ErrorStatus;
+use safa_abi::{errors::ErrorStatus, fs::FSObjectType};
 
-use crate::syscalls::types::Ri;
+use crate::{exported_func, syscalls::types::Ri};
 
 use super::{define_syscall, SyscallNum};
-define_syscall! {
-    SyscallNum::SysRDestroy => {
-        /// Destroys "closes" a resource with the id `ri`, a resource can be a File, a Directory, a DirIter, etc...
-        ///
-        /// # Returns
-        /// - [`ErrorStatus::InvalidResource`] if the id `ri` is invalid
-        sysr_destroy(ri: Ri)
-    },
-    SyscallNum::SysRClone => {
-        /// Clones the resource referred to by the resource id `ri` and returns a new resource ID.
-        sysr_clone(ri: Ri) Ri
-    }
-}
+
+#[cfg(all(feature = "alloc", not(feature = "rustc-dep-of-std")))]
+extern crate alloc;
+include!(concat!(env!("OUT_DIR"), "/syscalls/resources.rs"));
 
 /// Destroys "closes" a resource with the id `ri`, a resource can be a File, Directory, DirIter, etc...
 ///
@@ -32,3 +23,140 @@ pub fn destroy(ri: Ri) -> Result<(), ErrorStatus> {
 pub fn dup(ri: Ri) -> Result<Ri, ErrorStatus> {
     sysr_clone(ri).get()
 }
+
+/// The kind of a resource, see [`kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum ResourceKind {
+    /// An opened file
+    File,
+    /// An opened directory
+    Directory,
+    /// An opened device file
+    Device,
+    /// A resource that isn't a filesystem object
+    ///
+    /// The kernel doesn't expose the kind of those yet, and every syscall that could tell them apart
+    /// changes the resource it is called on (advancing a DirIter, accepting a connection, ...),
+    /// so they aren't told apart until the kernel can report it.
+    Other,
+}
+
+impl ResourceKind {
+    /// Whether or not the resource is a filesystem object (a file, a directory or a device).
+    #[inline]
+    pub const fn is_fs_object(self) -> bool {
+        matches!(self, Self::File | Self::Directory | Self::Device)
+    }
+}
+
+impl From<FSObjectType> for ResourceKind {
+    #[inline]
+    fn from(value: FSObjectType) -> Self {
+        match value {
+            FSObjectType::File => Self::File,
+            FSObjectType::Directory => Self::Directory,
+            FSObjectType::Device => Self::Device,
+        }
+    }
+}
+
+/// Queries what kind of resource the resource id `ri` refers to.
+///
+/// Uses [`super::io::fattrs`] under the hood, so it has no side effects on the resource.
+///
+/// # Returns
+/// - [`ErrorStatus::UnknownResource`] if the id `ri` is invalid
+/// - [`ResourceKind::Other`] for any resource that isn't a filesystem object, see [`ResourceKind::Other`]
+pub fn kind(ri: Ri) -> Result<ResourceKind, ErrorStatus> {
+    match super::io::fattrs(ri) {
+        Ok(attrs) => Ok(attrs.kind.into()),
+        Err(ErrorStatus::UnknownResource) => Err(ErrorStatus::UnknownResource),
+        Err(_) => Ok(ResourceKind::Other),
+    }
+}
+
+/// Information about an open resource, see [`list`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct ResourceInfo {
+    pub ri: Ri,
+    pub kind: ResourceKind,
+    /// The size of the resource if it is a filesystem object, 0 otherwise
+    pub size: usize,
+}
+
+/// The amount of consecutive invalid resource ids after which [`list_with`] assumes there are no more open resources.
+///
+/// The kernel reuses freed resource ids starting from the lowest, so open resources are mostly packed at the start.
+const LIST_PROBE_GAP: Ri = 64;
+
+/// Calls `f` with the information of each open resource of the current process, in order of their resource ids.
+///
+/// The kernel has no way to enumerate resources yet, so this probes the resource ids one by one (using [`super::io::fattrs`])
+/// until [`LIST_PROBE_GAP`] consecutive ids are invalid, which means resources opened at much higher ids after a lot of
+/// resources were destroyed might be missed.
+pub fn list_with(mut f: impl FnMut(ResourceInfo)) {
+    let mut gap = 0;
+    let mut ri: Ri = 0;
+
+    while gap < LIST_PROBE_GAP {
+        let info = match super::io::fattrs(ri) {
+            Ok(attrs) => Some(ResourceInfo {
+                ri,
+                kind: attrs.kind.into(),
+                size: attrs.size,
+            }),
+            Err(ErrorStatus::UnknownResource) => None,
+            Err(_) => Some(ResourceInfo {
+                ri,
+                kind: ResourceKind::Other,
+                size: 0,
+            }),
+        };
+
+        match info {
+            Some(info) => {
+                gap = 0;
+                f(info);
+            }
+            None => gap += 1,
+        }
+
+        let Some(next) = ri.checked_add(1) else {
+            break;
+        };
+        ri = next;
+    }
+}
+
+/// Lists the open resources of the current process, see [`list_with`].
+#[cfg(feature = "alloc")]
+pub fn list() -> alloc::vec::Vec<ResourceInfo> {
+    let mut results = alloc::vec::Vec::new();
+    list_with(|info| results.push(info));
+    results
+}
+
+exported_func! {
+    /// Lists the open resources of the current process, see [`list_with`].
+    ///
+    /// Not a syscall, the resources are probed by this API.
+    ///
+    /// Puts up to `dest_len` entries in `dest` (which can be null if `dest_len` is 0),
+    /// and returns the total amount of open resources, which may be greater than `dest_len`.
+    ///
+    /// # Safety
+    /// `dest` must be valid for writes of `dest_len` [`ResourceInfo`]s.
+    pub unsafe extern "C" fn sysapi_resources_list(dest: *mut ResourceInfo, dest_len: usize) -> usize {
+        let mut count = 0;
+        list_with(|info| {
+            if count < dest_len {
+                unsafe { dest.add(count).write(info) };
+            }
+            count += 1;
+        });
+        count
+    }
+}
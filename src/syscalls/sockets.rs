@@ -7,7 +7,7 @@ use safa_abi::{
 };
 
 use crate::syscalls::types::{
-    IntoSyscallArg, OptionalPtr, OptionalPtrMut, RequiredPtr, RequiredPtrMut, Ri,
+    IntoSyscallArg, OptionalPtr, OptionalPtrMut, RequiredPtr, RequiredPtrMut, Ri, SyscallSafeArg,
 };
 
 use super::SyscallNum;
@@ -20,6 +20,8 @@ impl IntoSyscallArg for SockCreateKind {
     }
 }
 
+unsafe impl SyscallSafeArg for SockCreateKind {}
+
 impl IntoSyscallArg for SockMsgFlags {
     type RegResults = (usize,);
     fn into_syscall_arg(self) -> Self::RegResults {
@@ -28,74 +30,9 @@ impl IntoSyscallArg for SockMsgFlags {
     }
 }
 
-define_syscall! {
-    SyscallNum::SysSockCreate => {
-        /// Creates a new generic Unix Socket Descriptor with the given flags, domain, and protocol,
-        /// The generic Socket Descriptor can then be upgraded to a Server Socket using [`syssock_bind`]
-        /// # Arguments
-        /// - `domain` can only be 0 for now indicating Unix Local Sockets
-        /// - `kind` information about the Socket Type for example if it is a SEQPACKET or a STREAM socket, and whether or not it blocks
-        /// - `protocol` ignored for now
-        /// # Returns
-        /// Returns The resource ID of the Socket
-        syssock_create(domain: SockDomain, kind: SockCreateKind, protocol: u32) Ri
-    },
-    SyscallNum::SysSockBind => {
-        /// Binds a Server Socket to address pointed to by `addr` or upgrades a Generic Socket Descriptor to a Server Socket and then binds it to `addr`
-        /// you then have to [`syssock_listen`] to listen for connections
-        /// # Arguments
-        /// - `sock_resource` either a Server Socket or a Socket Descriptor Resource
-        /// - `addr` the address to bind to, the structure varies depending on the socket, an example is [`safa_abi::sockets::SockBindAbstractAddr`] for local sockets
-        /// - `addr_struct_size` the total size of `addr` in bytes minus the unused bytes
-        syssock_bind(sock_resource: Ri, addr: RequiredPtr<SocketAddr>, addr_struct_size: usize)
-    },
-    SyscallNum::SysSockListen => {
-        /// Configures a Server Socket listening queue to be able to hold `backlog` pending connections,
-        /// by default it can only hold 0, theoriticaly the limit is [`isize::MAX`], but you probably wouldn't have enough memory to hold all of that anyways,
-        ///
-        /// You can then accept a connection using [`syssock_accept`] :3
-        syssock_listen(sock_resource: Ri, backlog: usize)
-    },
-    SyscallNum::SysSockAccept => {
-        /// Accepts a pending connection from the listening queue that was configured using [`syssock_listen`]
-        ///
-        /// Doesn't block if the socket is non-blocking otherwise blocks until a connection is requested.
-        /// # Arguments
-        /// - `sock_resource`: a Server Socket
-        /// - `accepted_addr`: Gets filled with the address we accepted from on success TODO: docs.
-        /// # Returns
-        ///   The resource ID of the established Connection,
-        ///
-        /// You can then do reads and writes using [`super::io::sysread`] and [`super::io::syswrite`], on that connection (offsets are ignored),
-        /// They might block if the socket was set as blocking.
-        syssock_accept(sock_resource: Ri, accepted_addr: OptionalPtrMut<(NonNull<SocketAddr>, usize)>) Ri
-    },
-    SyscallNum::SysSockConnect => {
-        /// Given a Generic Socket Descriptor, Requests a pending connection in a Server Sockets'
-        /// (that was binded at `addr` using [`syssock_bind`]) listen queue (that was configured using [`syssock_listen`]),
-        ///
-        /// The given Socket Descriptor must match that of the Server's,
-        /// This will always block until the pending connection gets [`syssock_accept`]ed for now, even if the socket descriptor was non-blocking.
-        ///
-        /// # Arguments
-        /// - `sock_resource`: A Generic Socket Descriptor created using [`syssock_create`].
-        /// - `addr`, `addr_struct_size`: see [`syssock_bind`]
-        /// - `out_connection_resource`: (return value) the Client's end of the established connection if successful
-        ///
-        /// see [`syssock_accept`] for more information, the Client's connection works exactly like the Server's
-        syssock_connect(sock_resource: Ri, addr: RequiredPtr<SocketAddr>, addr_struct_size: usize)
-    },
-    SyscallNum::SysSockSendTo => {
-        /// Given a socket descriptor, use it to send the data `data` to address `addr`.
-        /// TODO: docs
-        syssock_sendto(sock_resource: Ri, data: Slice<u8>, flags: SockMsgFlags, addr: OptionalPtr<SocketAddr>, addr_struct_size: usize) usize
-    },
-    SyscallNum::SysSockRecvFrom => {
-        /// Given a socket descriptor, use it to receive data only if its connected, puts the address of the sender in `received_addr`.
-        /// TODO: docs
-        syssock_recvfrom(sock_resource: Ri, data: Slice<u8>, flags: SockMsgFlags, received_addr: OptionalPtrMut<(NonNull<SocketAddr>, usize)>) usize
-    }
-}
+unsafe impl SyscallSafeArg for SockMsgFlags {}
+
+include!(concat!(env!("OUT_DIR"), "/syscalls/sockets.rs"));
 
 /// Creates a new generic Unix Socket Descriptor with the given flags, domain, and protocol,
 /// The generic Socket Descriptor can then be upgraded to a Server Socket using [`bind`]
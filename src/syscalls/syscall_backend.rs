@@ -0,0 +1,251 @@
+//! Per-architecture trap conventions used to invoke syscalls.
+//!
+//! Each backend exposes `trap0`..`trap6` which take the syscall number as a const generic
+//! and the arguments as [`usize`]s, and return the raw result register.
+//!
+//! - x86_64: `int 0x80` with the number in `rax`, and the results in `rax`.
+//! - aarch64 (default): `svc #NUM` with the number encoded in the immediate, and the results in `x0`.
+//! - aarch64 (feature `aarch64-svc-register`): `svc #0` with the number in `x8`, and the results in `x0`,
+//!   this convention isn't limited by the size of the `svc` immediate.
+//!
+//! With the `small-syscalls` feature the `trapN` functions call a single outlined (non-generic) function per arity,
+//! which takes the number at runtime, so the trap sequence is emitted once instead of at every call site at the cost of a call,
+//! this needs the number to be passed in a register, so it has no effect with the default aarch64 convention
+//! (`build.rs` enables the `outlined_syscalls` cfg when it applies).
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    macro_rules! trap {
+        ($num:expr $(, $reg:tt = $arg:expr)*) => {{
+            let result: usize;
+            core::arch::asm!(
+                "int 0x80",
+                in("rax") $num as usize,
+                $(in($reg) $arg,)*
+                lateout("rax") result,
+            );
+            result
+        }};
+    }
+
+    pub(crate) use trap;
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "aarch64-svc-register")))]
+mod arch {
+    macro_rules! trap {
+        ($num:expr $(, $reg:tt = $arg:expr)*) => {{
+            let result: usize;
+            core::arch::asm!(
+                "svc #{num}",
+                num = const $num,
+                $(in($reg) $arg,)*
+                lateout("x0") result,
+            );
+            result
+        }};
+    }
+
+    pub(crate) use trap;
+}
+
+#[cfg(all(target_arch = "aarch64", feature = "aarch64-svc-register"))]
+mod arch {
+    macro_rules! trap {
+        ($num:expr $(, $reg:tt = $arg:expr)*) => {{
+            let result: usize;
+            core::arch::asm!(
+                "svc #0",
+                in("x8") $num as usize,
+                $(in($reg) $arg,)*
+                lateout("x0") result,
+            );
+            result
+        }};
+    }
+
+    pub(crate) use trap;
+}
+
+#[cfg(not(outlined_syscalls))]
+use arch::trap;
+
+/// Declares the outlined `trapN` functions taking the number at runtime, given the argument registers of the current architecture.
+#[cfg(outlined_syscalls)]
+macro_rules! define_outlined_traps {
+    ($r1:tt, $r2:tt, $r3:tt, $r4:tt, $r5:tt, $r6:tt) => {
+        mod outlined {
+            use super::arch::trap as trap_inline;
+
+            #[inline(never)]
+            pub(super) unsafe fn trap0(num: u16) -> usize {
+                unsafe { trap_inline!(num) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap1(num: u16, a1: usize) -> usize {
+                unsafe { trap_inline!(num, $r1 = a1) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap2(num: u16, a1: usize, a2: usize) -> usize {
+                unsafe { trap_inline!(num, $r1 = a1, $r2 = a2) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap3(num: u16, a1: usize, a2: usize, a3: usize) -> usize {
+                unsafe { trap_inline!(num, $r1 = a1, $r2 = a2, $r3 = a3) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap4(
+                num: u16,
+                a1: usize,
+                a2: usize,
+                a3: usize,
+                a4: usize,
+            ) -> usize {
+                unsafe { trap_inline!(num, $r1 = a1, $r2 = a2, $r3 = a3, $r4 = a4) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap5(
+                num: u16,
+                a1: usize,
+                a2: usize,
+                a3: usize,
+                a4: usize,
+                a5: usize,
+            ) -> usize {
+                unsafe { trap_inline!(num, $r1 = a1, $r2 = a2, $r3 = a3, $r4 = a4, $r5 = a5) }
+            }
+
+            #[inline(never)]
+            pub(super) unsafe fn trap6(
+                num: u16,
+                a1: usize,
+                a2: usize,
+                a3: usize,
+                a4: usize,
+                a5: usize,
+                a6: usize,
+            ) -> usize {
+                unsafe {
+                    trap_inline!(
+                        num,
+                        $r1 = a1,
+                        $r2 = a2,
+                        $r3 = a3,
+                        $r4 = a4,
+                        $r5 = a5,
+                        $r6 = a6
+                    )
+                }
+            }
+        }
+    };
+}
+
+/// Calls the outlined trap of the same arity, see [`define_outlined_traps`].
+#[cfg(outlined_syscalls)]
+macro_rules! trap {
+    ($num:expr) => {
+        outlined::trap0($num)
+    };
+    ($num:expr, $r1:tt = $a1:expr) => {
+        outlined::trap1($num, $a1)
+    };
+    ($num:expr, $r1:tt = $a1:expr, $r2:tt = $a2:expr) => {
+        outlined::trap2($num, $a1, $a2)
+    };
+    ($num:expr, $r1:tt = $a1:expr, $r2:tt = $a2:expr, $r3:tt = $a3:expr) => {
+        outlined::trap3($num, $a1, $a2, $a3)
+    };
+    ($num:expr, $r1:tt = $a1:expr, $r2:tt = $a2:expr, $r3:tt = $a3:expr, $r4:tt = $a4:expr) => {
+        outlined::trap4($num, $a1, $a2, $a3, $a4)
+    };
+    ($num:expr, $r1:tt = $a1:expr, $r2:tt = $a2:expr, $r3:tt = $a3:expr, $r4:tt = $a4:expr, $r5:tt = $a5:expr) => {
+        outlined::trap5($num, $a1, $a2, $a3, $a4, $a5)
+    };
+    ($num:expr, $r1:tt = $a1:expr, $r2:tt = $a2:expr, $r3:tt = $a3:expr, $r4:tt = $a4:expr, $r5:tt = $a5:expr, $r6:tt = $a6:expr) => {
+        outlined::trap6($num, $a1, $a2, $a3, $a4, $a5, $a6)
+    };
+}
+
+/// Declares the `trapN` functions, given the argument registers of the current architecture.
+macro_rules! define_traps {
+    ($r1:tt, $r2:tt, $r3:tt, $r4:tt, $r5:tt, $r6:tt) => {
+        #[inline(always)]
+        pub(crate) unsafe fn trap0<const NUM: u16>() -> usize {
+            unsafe { trap!(NUM) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap1<const NUM: u16>(a1: usize) -> usize {
+            unsafe { trap!(NUM, $r1 = a1) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap2<const NUM: u16>(a1: usize, a2: usize) -> usize {
+            unsafe { trap!(NUM, $r1 = a1, $r2 = a2) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap3<const NUM: u16>(a1: usize, a2: usize, a3: usize) -> usize {
+            unsafe { trap!(NUM, $r1 = a1, $r2 = a2, $r3 = a3) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap4<const NUM: u16>(
+            a1: usize,
+            a2: usize,
+            a3: usize,
+            a4: usize,
+        ) -> usize {
+            unsafe { trap!(NUM, $r1 = a1, $r2 = a2, $r3 = a3, $r4 = a4) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap5<const NUM: u16>(
+            a1: usize,
+            a2: usize,
+            a3: usize,
+            a4: usize,
+            a5: usize,
+        ) -> usize {
+            unsafe { trap!(NUM, $r1 = a1, $r2 = a2, $r3 = a3, $r4 = a4, $r5 = a5) }
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn trap6<const NUM: u16>(
+            a1: usize,
+            a2: usize,
+            a3: usize,
+            a4: usize,
+            a5: usize,
+            a6: usize,
+        ) -> usize {
+            unsafe {
+                trap!(
+                    NUM,
+                    $r1 = a1,
+                    $r2 = a2,
+                    $r3 = a3,
+                    $r4 = a4,
+                    $r5 = a5,
+                    $r6 = a6
+                )
+            }
+        }
+    };
+}
+
+#[cfg(all(target_arch = "x86_64", outlined_syscalls))]
+define_outlined_traps!("rdi", "rsi", "rdx", "rcx", "r8", "r9");
+#[cfg(all(target_arch = "aarch64", outlined_syscalls))]
+define_outlined_traps!("x0", "x1", "x2", "x3", "x4", "x5");
+
+#[cfg(target_arch = "x86_64")]
+define_traps!("rdi", "rsi", "rdx", "rcx", "r8", "r9");
+#[cfg(target_arch = "aarch64")]
+define_traps!("x0", "x1", "x2", "x3", "x4", "x5");
@@ -12,31 +12,7 @@ use crate::{
 
 use super::{define_syscall, SyscallNum};
 
-define_syscall! {
-    SyscallNum::SysTExit => {
-        /// Exits the current thread, threads don't have an exit code
-        /// however if the thread was the last thread in the process,
-        /// then the process will exit with code [`code`]
-        syst_exit(code: usize) unreachable
-    },
-    SyscallNum::SysTWait => {
-        /// Waits for a child thread with the cid `cid` to exit
-        ///
-        /// # Returns
-        /// - [`ErrorStatus::InvalidTid`] if thread doesn't exist at the time of wait
-        syst_wait(cid: Tid)
-    },
-    SyscallNum::SysTSleep => {
-      /// Sleeps for N ms
-      ///
-      /// should always succeed
-      syst_sleep(n: usize)
-    },
-    SyscallNum::SysTYield => {
-        /// Switches to the next thread in the thread queue of the current CPU
-        sysyield()
-    },
-}
+include!(concat!(env!("OUT_DIR"), "/syscalls/thread.rs"));
 
 /// Exits the current thread, threads don't have an exit code
 /// however if the thread was the last thread in the process,
@@ -73,15 +49,6 @@ pub fn sleep(duration: Duration) -> Result<(), ()> {
     Ok(assert!(syst_sleep(ms).get().is_ok()))
 }
 
-define_syscall! {
-    SyscallNum::SysTSpawn => {
-        /// Spawns a thread at the entry point `entry_point` with the config `config`
-        ///
-        /// - Returns the spawned thread's ID (TID)
-        syst_spawn_raw(entry_point: usize, config: RequiredPtr<RawTSpawnConfig>) Tid
-    },
-}
-
 exported_func! {
     /// Spawns a thread as a child of self
     /// # Arguments
@@ -4,6 +4,20 @@ pub(crate) trait IntoSyscallArg {
     fn into_syscall_arg(self) -> Self::RegResults;
 }
 
+/// Marks a type as safe to be passed as an argument to a syscall declared with `define_syscall!`,
+/// every argument type of a declared syscall must implement this.
+///
+/// # Safety
+/// Only implement this for types whose [`IntoSyscallArg`] conversion exactly matches what the kernel expects,
+/// that is integers that fit in a register, FFI pointers, [`Slice`]s, [`Str`]s and `#[repr(transparent)]` flag newtypes,
+/// never for structs passed by value.
+pub(crate) unsafe trait SyscallSafeArg: IntoSyscallArg {}
+
+/// Fails to compile if `T` isn't a [`SyscallSafeArg`], used by `define_syscall!`.
+#[doc(hidden)]
+#[inline(always)]
+pub(crate) const fn assert_syscall_safe_arg<T: SyscallSafeArg>() {}
+
 macro_rules! impl_into_syscall_as {
     ($ty:ty) => {
         impl IntoSyscallArg for $ty {
@@ -30,6 +44,23 @@ impl_into_syscall_as!(u128);
 impl_into_syscall_as!(i128);
 impl_into_syscall_as!(bool);
 
+macro_rules! impl_syscall_safe {
+    ($($ty:ty),*) => {
+        $(unsafe impl SyscallSafeArg for $ty {})*
+    };
+}
+
+// u128 and i128 are left out on purpose, they don't fit in a single register
+impl_syscall_safe!(usize, isize, u8, i8, u16, i16, u32, i32, u64, i64, bool);
+unsafe impl<T> SyscallSafeArg for *const T {}
+unsafe impl<T> SyscallSafeArg for *mut T {}
+unsafe impl SyscallSafeArg for safa_abi::fs::OpenOptions {}
+unsafe impl<T> SyscallSafeArg for Slice<T> {}
+unsafe impl SyscallSafeArg for Str {}
+unsafe impl<T> SyscallSafeArg for FFINonNull<T> {}
+unsafe impl<T: NotZeroable + SyscallSafeArg> SyscallSafeArg for OptZero<T> {}
+unsafe impl SyscallSafeArg for SockDomain {}
+
 impl IntoSyscallArg for (usize,) {
     type RegResults = (usize,);
 
@@ -149,6 +180,11 @@ use core::marker::PhantomData;
 use core::ptr::NonNull;
 use safa_abi::errors::SysResult;
 
+/// The raw error code [`SyscallResults::get_raw`] returns for results that don't fit in an error code,
+/// no error uses it, so it decodes as unknown
+pub const UNKNOWN_ERROR_CODE: u16 = u16::MAX;
+const _: () = assert!(errors::from_raw(UNKNOWN_ERROR_CODE).is_err());
+
 /// An opaque type that represents a syscall result
 /// the underlying type is a usize signed integer, in which 0..=isize::MAX is success and any negative value is in error
 /// represented by the -[`ErrorStatus`] enum
@@ -160,18 +196,66 @@ pub struct SyscallResults<T: OkSyscallResult = ()> {
 }
 
 impl<T: OkSyscallResult> SyscallResults<T> {
+    /// Decodes the results, error codes that are unknown to this crate are mapped to [`ErrorStatus::Unknown`],
+    /// use [`Self::get_raw`] to access them.
     pub fn get(self) -> Result<T, ErrorStatus> {
-        self.inner.into_result().map(|ok| T::from_usize(ok))
+        self.get_raw().map_err(decode_error)
+    }
+
+    /// Encodes `err` the same way the kernel does,
+    /// for functions implemented in userspace that are exported with a syscall-like interface.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub(crate) const fn from_err(err: ErrorStatus) -> Self {
+        let raw = -(err as u16 as isize);
+        Self {
+            inner: unsafe { core::mem::transmute::<isize, SysResult>(raw) },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Encodes the successful results `value` the same way the kernel does, see [`Self::from_err`].
+    ///
+    /// `value` must fit in an [`isize`].
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    pub(crate) const fn from_ok(value: usize) -> Self {
+        assert!(value <= isize::MAX as usize);
+        Self {
+            inner: unsafe { core::mem::transmute::<isize, SysResult>(value as isize) },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Like [`Self::get`] but returns the raw error code on failure instead of decoding it,
+    /// decode it with [`errors::from_raw`].
+    ///
+    /// Errors below `-(u16::MAX)` have no error code, they are returned as [`UNKNOWN_ERROR_CODE`] instead of being truncated.
+    pub fn get_raw(self) -> Result<T, u16> {
+        let raw = unsafe { core::mem::transmute::<SysResult, isize>(self.inner) };
+        if raw >= 0 {
+            Ok(T::from_usize(raw as usize))
+        } else if raw >= -(u16::MAX as isize) {
+            Err(raw.unsigned_abs() as u16)
+        } else {
+            Err(UNKNOWN_ERROR_CODE)
+        }
     }
 }
 
+/// Decodes the raw error code `raw`, see [`SyscallResults::get`].
+///
+/// Kept out of line with the `small-syscalls` feature so that the decoding isn't duplicated in every syscall wrapper.
+#[cfg_attr(feature = "small-syscalls", cold, inline(never))]
+fn decode_error(raw: u16) -> ErrorStatus {
+    errors::from_raw(raw).unwrap_or(ErrorStatus::Unknown)
+}
+
 use safa_abi::ffi::option::OptZero;
 use safa_abi::ffi::{ptr::FFINonNull, slice::Slice, str::Str};
 
 use safa_abi::ffi::NotZeroable;
 use safa_abi::sockets::SockDomain;
 
-use crate::errors::ErrorStatus;
+use crate::errors::{self, ErrorStatus};
 
 /// A nullable muttable pointer to `T`
 ///
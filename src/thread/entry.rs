@@ -0,0 +1,153 @@
+//! Spawning threads running functions that return, see [`spawn_fn`]
+//!
+//! The entry points taken by the raw [`crate::syscalls::thread`] spawn functions must never return,
+//! they have to exit the thread themselves, skipping any cleanup of the thread.
+//! The functions here run a function that returns normally instead, through a trampoline which sets the thread up,
+//! calls it, runs the destructors of the thread's thread-local values (see [`super::local`]) and then exits the thread.
+//!
+//! They don't allocate, so the function can only be given a `'static` argument, and must not capture anything:
+//! functions and closures without captures are accepted, `fn` pointers and capturing closures fail to compile,
+//! use [`super::spawn()`] (with the `alloc` feature) to run any closure and get its result back.
+
+use core::{mem::size_of, num::NonZero, ptr::NonNull};
+
+use safa_abi::{errors::ErrorStatus, process::ContextPriority};
+
+use crate::syscalls::{self, types::Tid};
+
+/// Sets up the current thread, must be called first by every thread spawned by this crate.
+pub(crate) fn enter() {
+    #[cfg(all(
+        feature = "alloc",
+        not(any(
+            feature = "std",
+            feature = "rustc-dep-of-std",
+            feature = "shadow-stack"
+        ))
+    ))]
+    super::local::init_thread();
+}
+
+/// Drops the thread-local values of the current thread, once the thread is done running user code.
+pub(crate) fn run_destructors() {
+    #[cfg(all(
+        feature = "alloc",
+        not(any(
+            feature = "std",
+            feature = "rustc-dep-of-std",
+            feature = "shadow-stack"
+        ))
+    ))]
+    super::local::run_destructors();
+}
+
+/// Frees what [`enter`] set up and exits the current thread, after [`run_destructors`].
+pub(crate) fn exit() -> ! {
+    #[cfg(all(
+        feature = "alloc",
+        not(any(
+            feature = "std",
+            feature = "rustc-dep-of-std",
+            feature = "shadow-stack"
+        ))
+    ))]
+    super::local::free_thread();
+    syscalls::thread::exit(0)
+}
+
+/// Fails to compile if `F` isn't zero-sized, which the trampolines rely on to get `F` without it being passed to them.
+const fn assert_zero_sized<F>() {
+    assert!(
+        size_of::<F>() == 0,
+        "thread entry functions must not capture anything, see `thread::entry`"
+    );
+}
+
+/// Makes a copy of a value of the zero-sized type `F`.
+///
+/// # Safety
+/// A value of `F` must exist, which is the case for the entry given to the spawn functions.
+unsafe fn copy_zero_sized<F: Copy>() -> F {
+    const { assert_zero_sized::<F>() };
+    unsafe { NonNull::<F>::dangling().read() }
+}
+
+extern "C" fn fn_start<F>(_tid: Tid, _argument: usize) -> !
+where
+    F: Fn() + Copy,
+{
+    let entry = unsafe { copy_zero_sized::<F>() };
+    enter();
+    entry();
+    run_destructors();
+    exit()
+}
+
+extern "C" fn fn_with_start<F, T>(_tid: Tid, argument: usize) -> !
+where
+    F: Fn(&'static T) + Copy,
+    T: 'static,
+{
+    let entry = unsafe { copy_zero_sized::<F>() };
+    let argument = unsafe { &*(argument as *const T) };
+    enter();
+    entry(argument);
+    run_destructors();
+    exit()
+}
+
+/// Spawns a new thread running `entry`, which exits once `entry` returns, see the [module documentation](self).
+///
+/// The thread runs at `priority` (defaults to its parent's), on a stack of `stack_size` bytes (defaults to the default size).
+///
+/// ```ignore
+/// fn worker() {
+///     // ...
+/// }
+///
+/// let tid = thread::spawn_fn(worker, None, None)?;
+/// syscalls::thread::wait(tid)?;
+/// ```
+pub fn spawn_fn<F>(
+    entry: F,
+    priority: Option<ContextPriority>,
+    stack_size: Option<NonZero<usize>>,
+) -> Result<Tid, ErrorStatus>
+where
+    F: Fn() + Copy + Send + 'static,
+{
+    const { assert_zero_sized::<F>() };
+    _ = entry;
+    syscalls::thread::spawn2(fn_start::<F>, 0, priority.into(), stack_size)
+}
+
+/// Spawns a new thread running `entry` with `argument`, which exits once `entry` returns, see [`spawn_fn`].
+///
+/// ```ignore
+/// static CONFIG: Config = Config::new();
+///
+/// fn worker(config: &Config) {
+///     // ...
+/// }
+///
+/// let tid = thread::spawn_fn_with(worker, &CONFIG, None, None)?;
+/// ```
+pub fn spawn_fn_with<F, T>(
+    entry: F,
+    argument: &'static T,
+    priority: Option<ContextPriority>,
+    stack_size: Option<NonZero<usize>>,
+) -> Result<Tid, ErrorStatus>
+where
+    F: Fn(&'static T) + Copy + Send + 'static,
+    T: Sync + 'static,
+{
+    const { assert_zero_sized::<F>() };
+    _ = entry;
+    syscalls::thread::spawn2(
+        fn_with_start::<F, T>,
+        argument as *const T as usize,
+        priority.into(),
+        stack_size,
+    )
+}
@@ -0,0 +1,53 @@
+//! Hints for cooperating with the scheduler, see [`hint_spin`] and [`hint_batch_start`]
+//!
+//! A thread runs until it blocks (waiting on a [`Futex`](crate::sync::Futex), sleeping, reading...),
+//! gives up the rest of its turn with [`yield_now`](crate::syscalls::thread::yield_now), or is preempted,
+//! and it is queued according to its priority (see [`super::priority`]).
+//!
+//! The kernel doesn't take spin or background-work hints yet, so:
+//! - [`hint_spin`] only tells the CPU that the thread is spinning, the kernel doesn't see it
+//! - [`hint_batch_start`] lowers the priority of the thread, which isn't supported by the kernel yet either
+//!   (see [`super::priority`]), so it currently changes nothing
+//!
+//! Code waiting for another thread should spin with [`hint_spin`] for a short while, then block on a [`Futex`](crate::sync::Futex),
+//! instead of looping on [`yield_now`](crate::syscalls::thread::yield_now), which keeps the thread in the queue and wastes its turns.
+
+use safa_abi::process::ContextPriority;
+
+use super::priority::PriorityGuard;
+
+/// Tells the processor that the current thread is busy-waiting, call it on each iteration of a spin loop.
+///
+/// Lets the processor save power and give resources to its sibling hardware thread, see [`core::hint::spin_loop`],
+/// the thread isn't descheduled.
+#[inline]
+pub fn hint_spin() {
+    core::hint::spin_loop()
+}
+
+/// A batch of background work started with [`hint_batch_start`], ended with [`hint_batch_end`] or on drop
+///
+/// Must be ended on the thread it was started on.
+#[derive(Debug)]
+#[must_use = "the batch ends as soon as the hint is dropped"]
+pub struct BatchHint {
+    /// `None` if the priority couldn't be lowered
+    _priority: Option<PriorityGuard>,
+}
+
+/// Marks the start of background work on the current thread, such as indexing or compaction,
+/// so that the scheduler favors other threads until [`hint_batch_end`].
+///
+/// The thread runs at [`ContextPriority::Low`] during the batch if the kernel supports changing its priority,
+/// see the [module documentation](self).
+pub fn hint_batch_start() -> BatchHint {
+    BatchHint {
+        _priority: PriorityGuard::new(ContextPriority::Low).ok(),
+    }
+}
+
+/// Marks the end of the background work started with [`hint_batch_start`], restoring the priority of the thread.
+#[inline]
+pub fn hint_batch_end(hint: BatchHint) {
+    drop(hint)
+}
@@ -0,0 +1,221 @@
+//! Thread-local storage, see [`ThreadLocalKey`]
+//!
+//! The kernel can't tell a thread its own ID, so each thread's values are found through the architecture's thread pointer
+//! (the FS base on x86_64, `TPIDR_EL0` on aarch64) which points to a per-thread block of slots, one slot per key,
+//! allocated the first time the thread uses the key.
+//!
+//! The thread pointer is set up by the API initialization for the main thread,
+//! and by [`crate::thread::spawn`] and [`crate::thread::spawn_fn`] for the threads they spawn,
+//! which also run the destructors of their values when they exit,
+//! the main thread's values are dropped when `main` returns to [`crate::process::init::_c_api_init`].
+//! Threads spawned with the raw [`crate::syscalls::thread`] syscalls have no thread pointer and must not use thread locals,
+//! on x86_64 doing so faults.
+//!
+//! Not available with the `shadow-stack` feature (or `std`), where the native thread-local storage owns the thread pointer.
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+use core::{
+    any::Any,
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{boxed::Box, vec::Vec};
+
+/// The per-thread block the thread pointer points to
+#[repr(C)]
+struct ThreadData {
+    /// points to this block, read through the thread pointer on x86_64 (`fs:0`)
+    this: *mut ThreadData,
+    /// set while the destructors run, values that weren't dropped yet can still be accessed but no new ones are initialized
+    destroying: bool,
+    slots: Vec<Option<Box<dyn Any>>>,
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn set_thread_pointer(data: *mut ThreadData) {
+    use crate::{abi::arch::ArchOp, syscalls::misc::sysarch_ctrl};
+    sysarch_ctrl(ArchOp::SetFsBase, data as u64)
+        .get()
+        .expect("System error while setting the thread pointer");
+}
+
+#[cfg(target_arch = "x86_64")]
+fn thread_pointer() -> *mut ThreadData {
+    let data: *mut ThreadData;
+    unsafe {
+        core::arch::asm!("mov {}, qword ptr fs:[0]", out(reg) data, options(nostack, readonly, preserves_flags));
+    }
+    data
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn set_thread_pointer(data: *mut ThreadData) {
+    unsafe {
+        core::arch::asm!("msr tpidr_el0, {}", in(reg) data, options(nostack, preserves_flags));
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn thread_pointer() -> *mut ThreadData {
+    let data: *mut ThreadData;
+    unsafe {
+        core::arch::asm!("mrs {}, tpidr_el0", out(reg) data, options(nostack, nomem, preserves_flags));
+    }
+    data
+}
+
+/// Sets up the thread-local storage of the current thread, must be called once by each thread before using thread locals.
+pub(crate) fn init_thread() {
+    let data = Box::into_raw(Box::new(ThreadData {
+        this: core::ptr::null_mut(),
+        destroying: false,
+        slots: Vec::new(),
+    }));
+
+    unsafe {
+        (*data).this = data;
+        set_thread_pointer(data);
+    }
+}
+
+/// Drops the thread-local values of the current thread, accessing thread locals afterwards panics.
+///
+/// Destructors may use other thread locals that are still alive, but can't initialize new ones.
+pub(crate) fn run_destructors() {
+    let data = thread_pointer();
+    if data.is_null() {
+        return;
+    }
+
+    unsafe { (*data).destroying = true };
+    // a destructor may access the slots, so each value is taken out before being dropped
+    loop {
+        let slots = unsafe { &mut (*data).slots };
+        let value = slots.iter_mut().find_map(Option::take);
+        match value {
+            Some(value) => drop(value),
+            None => break,
+        }
+    }
+}
+
+/// Frees the thread-local storage of the current thread after [`run_destructors`], right before the thread exits.
+pub(crate) fn free_thread() {
+    let data = thread_pointer();
+    if !data.is_null() {
+        drop(unsafe { Box::from_raw(data) });
+    }
+}
+
+/// The amount of keys ever created, plus one as 0 marks unassigned keys
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(1);
+
+/// The error returned by [`ThreadLocalKey::try_with`] once the current thread's values were destroyed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessError;
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("thread-local value accessed after its destruction")
+    }
+}
+
+/// A key to a value that each thread has its own copy of, initialized on first access by each thread
+///
+/// Keys are meant to be declared as statics:
+/// ```ignore
+/// static COUNTER: ThreadLocalKey<Cell<usize>> = ThreadLocalKey::new(|| Cell::new(0));
+///
+/// COUNTER.with(|counter| counter.set(counter.get() + 1));
+/// ```
+///
+/// Each thread's value is dropped when the thread exits, see the [module documentation](self) for which threads can use them.
+pub struct ThreadLocalKey<T: 'static> {
+    /// the index of the key's slot plus one, assigned on first use
+    index: AtomicUsize,
+    init: fn() -> T,
+}
+
+impl<T: 'static> ThreadLocalKey<T> {
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            index: AtomicUsize::new(0),
+            init,
+        }
+    }
+
+    fn index(&self) -> usize {
+        let index = self.index.load(Ordering::Relaxed);
+        if index != 0 {
+            return index - 1;
+        }
+
+        // a racing thread may assign another index first, wasting the one taken here
+        let new = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        match self
+            .index
+            .compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => new - 1,
+            Err(index) => index - 1,
+        }
+    }
+
+    /// Calls `f` with the current thread's value, initializing it first if the thread never accessed it.
+    ///
+    /// While the thread's values are being destroyed, values that weren't dropped yet can still be accessed.
+    ///
+    /// # Returns
+    /// - [`AccessError`] if the thread's value was destroyed, or if it would have to be initialized while the values are being destroyed
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R, AccessError> {
+        let data = thread_pointer();
+        assert!(
+            !data.is_null(),
+            "thread locals used by a thread without thread-local storage"
+        );
+
+        let index = self.index();
+        let value: *const dyn Any = unsafe {
+            let slots = &(*data).slots;
+            match slots.get(index).and_then(Option::as_deref) {
+                Some(value) => value,
+                // the value was dropped, or would be initialized after the destructors ran
+                None if (*data).destroying => return Err(AccessError),
+                None => {
+                    // the initializer may use thread locals, so the slots aren't borrowed while it runs
+                    let value: Box<dyn Any> = Box::new((self.init)());
+                    let slots = &mut (*data).slots;
+                    if slots.len() <= index {
+                        slots.resize_with(index + 1, || None);
+                    }
+                    // the initializer may have accessed the key itself, the first value stored wins
+                    &**slots[index].get_or_insert(value)
+                }
+            }
+        };
+
+        // values are boxed, so they don't move when the slots grow during `f`
+        let value = unsafe { &*value }
+            .downcast_ref::<T>()
+            .expect("thread-local slot holds a value of another type");
+        Ok(f(value))
+    }
+
+    /// Calls `f` with the current thread's value, initializing it first if the thread never accessed it.
+    ///
+    /// # Panics
+    /// If the thread's value was destroyed or can't be initialized anymore, see [`Self::try_with`].
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        self.try_with(f)
+            .expect("thread-local value accessed after its destruction")
+    }
+}
+
+impl<T: 'static> fmt::Debug for ThreadLocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadLocalKey").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,42 @@
+//! Higher-level thread operations, see [`crate::syscalls::thread`] for the raw syscalls
+
+pub mod entry;
+pub mod hint;
+#[cfg(all(
+    feature = "alloc",
+    not(any(
+        feature = "std",
+        feature = "rustc-dep-of-std",
+        feature = "shadow-stack"
+    ))
+))]
+pub mod local;
+pub mod priority;
+#[cfg(all(
+    feature = "alloc",
+    not(any(feature = "std", feature = "rustc-dep-of-std"))
+))]
+pub(crate) mod recovery;
+#[cfg(feature = "alloc")]
+pub mod spawn;
+
+pub use entry::{spawn_fn, spawn_fn_with};
+pub use hint::{hint_batch_end, hint_batch_start, hint_spin, BatchHint};
+#[cfg(all(
+    feature = "alloc",
+    not(any(
+        feature = "std",
+        feature = "rustc-dep-of-std",
+        feature = "shadow-stack"
+    ))
+))]
+pub use local::{AccessError, ThreadLocalKey};
+pub use priority::{current_priority, set_priority, with_priority, PriorityGuard};
+pub use safa_abi::process::ContextPriority;
+#[cfg(all(
+    feature = "alloc",
+    any(feature = "std", not(feature = "rustc-dep-of-std"))
+))]
+pub use spawn::spawn_isolated;
+#[cfg(feature = "alloc")]
+pub use spawn::{spawn, Builder, JoinHandle, Panicked};
@@ -0,0 +1,69 @@
+//! Changing the scheduling priority of the current thread, see [`with_priority`]
+//!
+//! The kernel doesn't have syscalls to query or change the priority of a running thread yet,
+//! threads only get a priority when spawned (see [`crate::syscalls::thread::spawn`]),
+//! so [`current_priority`] and [`set_priority`] currently fail with [`ErrorStatus::NotSupported`]
+//! and [`with_priority`] runs its closure at the thread's current priority.
+
+use safa_abi::{errors::ErrorStatus, process::ContextPriority};
+
+/// Returns the priority of the current thread.
+///
+/// # Returns
+/// - [`ErrorStatus::NotSupported`] as the kernel can't report it yet, see the [module documentation](self)
+pub fn current_priority() -> Result<ContextPriority, ErrorStatus> {
+    Err(ErrorStatus::NotSupported)
+}
+
+/// Changes the priority of the current thread to `priority`.
+///
+/// # Returns
+/// - [`ErrorStatus::NotSupported`] as the kernel can't change it yet, see the [module documentation](self)
+pub fn set_priority(priority: ContextPriority) -> Result<(), ErrorStatus> {
+    _ = priority;
+    Err(ErrorStatus::NotSupported)
+}
+
+/// Raises (or lowers) the priority of the current thread for as long as the guard lives,
+/// on drop restores the priority the thread had before.
+///
+/// The guard must be dropped on the thread it was created on.
+#[derive(Debug)]
+#[must_use = "the previous priority is restored as soon as the guard is dropped"]
+pub struct PriorityGuard {
+    previous: ContextPriority,
+}
+
+impl PriorityGuard {
+    /// Changes the priority of the current thread to `priority`, see [`PriorityGuard`].
+    ///
+    /// # Returns
+    /// - the errors of [`current_priority`] and [`set_priority`]
+    pub fn new(priority: ContextPriority) -> Result<Self, ErrorStatus> {
+        let previous = current_priority()?;
+        set_priority(priority)?;
+        Ok(Self { previous })
+    }
+
+    /// The priority that is restored on drop
+    #[inline]
+    pub const fn previous(&self) -> ContextPriority {
+        self.previous
+    }
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        _ = set_priority(self.previous);
+    }
+}
+
+/// Runs `f` with the priority of the current thread changed to `priority`, restoring it afterwards (even if `f` panics),
+/// for deadline-sensitive critical sections such as audio or compositing work.
+///
+/// Changing the priority is best-effort, if it fails `f` runs at the current priority,
+/// use [`PriorityGuard::new`] directly to handle the failure.
+pub fn with_priority<R>(priority: ContextPriority, f: impl FnOnce() -> R) -> R {
+    let _guard = PriorityGuard::new(priority).ok();
+    f()
+}
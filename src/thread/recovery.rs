@@ -0,0 +1,82 @@
+//! Recovering from panics in the threads spawned with [`super::spawn_isolated`], without unwinding
+//!
+//! The kernel can't tell a thread its own ID, and threads spawned with the raw syscalls have no thread pointer,
+//! so each isolated thread registers the bounds of its stack instead (a conservative estimate of them, see [`register`]), and the panic handler finds the panicking thread
+//! using the address of its own stack frame.
+//!
+//! Stacks aren't unwound, the panicking thread stops where it panicked and its stack is dropped as is.
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+use core::{ops::Range, panic::PanicInfo};
+
+use alloc::{string::ToString, vec::Vec};
+
+use super::{entry, spawn::Panicked};
+use crate::sync::locks::Mutex;
+
+/// An isolated thread
+struct Recovery {
+    stack: Range<usize>,
+    /// a pointer to what `recover` needs to report the panic
+    data: usize,
+    /// reports the panic of the thread to whoever is waiting for it
+    recover: fn(data: usize, panicked: Panicked),
+}
+
+static ISOLATED: Mutex<Vec<Recovery>> = Mutex::new(Vec::new());
+
+/// Registers the current thread as isolated, its stack being the `stack_size` bytes below `stack_top`.
+///
+/// `stack_top` is only an address in the thread's top frame, not the real top of its stack,
+/// so `stack_size` bytes below it would reach into the memory below the stack, which may be another thread's stack.
+/// The registered range is shrunk by `margin` bytes from the bottom to stay inside the stack,
+/// `margin` must be at least the size of the frames above `stack_top`.
+/// A panic in the excluded bottom part of the stack isn't recovered from and takes the process down.
+///
+/// Returns the key to unregister the thread with.
+pub(crate) fn register(
+    stack_top: usize,
+    stack_size: usize,
+    margin: usize,
+    data: usize,
+    recover: fn(usize, Panicked),
+) -> usize {
+    let stack_bottom = stack_top
+        .saturating_sub(stack_size)
+        .saturating_add(margin)
+        .min(stack_top);
+    ISOLATED.lock().push(Recovery {
+        stack: stack_bottom..stack_top,
+        data,
+        recover,
+    });
+    stack_top
+}
+
+/// Unregisters the current thread once it is done running the isolated code, `key` is the key returned by [`register`].
+pub(crate) fn unregister(key: usize) {
+    ISOLATED.lock().retain(|recovery| recovery.stack.end != key);
+}
+
+/// Called by the panic handler, if the current thread is isolated reports the panic `info` and exits the thread,
+/// otherwise returns.
+pub(crate) fn recover(info: &PanicInfo) {
+    let marker = 0u8;
+    let frame = core::hint::black_box(&marker) as *const u8 as usize;
+
+    let recovery = {
+        let mut isolated = ISOLATED.lock();
+        let Some(index) = isolated.iter().position(|r| r.stack.contains(&frame)) else {
+            return;
+        };
+        // removed first, so that a panic while recovering takes the process down instead
+        isolated.swap_remove(index)
+    };
+
+    crate::alloc::release_oom_hook(&recovery.stack);
+    entry::run_destructors();
+    (recovery.recover)(recovery.data, Panicked::new(info.message().to_string()));
+    entry::exit()
+}
@@ -0,0 +1,370 @@
+//! Spawning threads running closures and getting their results back, see [`spawn`]
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std as alloc;
+
+use core::{cell::UnsafeCell, fmt, num::NonZero, sync::atomic::Ordering};
+
+use alloc::{boxed::Box, string::String, sync::Arc};
+use safa_abi::{errors::ErrorStatus, process::ContextPriority};
+
+use super::entry;
+use crate::{
+    sync::Futex,
+    syscalls::{self, types::Tid},
+};
+
+const RUNNING: u32 = 0;
+const FINISHED: u32 = 1;
+/// the result is being stored, set by the first [`Packet::publish`]
+const PUBLISHING: u32 = 2;
+
+/// Where a thread stores its result for its [`JoinHandle`]
+struct Packet<T> {
+    state: Futex,
+    result: UnsafeCell<Option<T>>,
+}
+
+// the result is only written once by the thread that moves the packet out of `RUNNING`, and only read after it is marked finished
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+impl<T> fmt::Debug for Packet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Packet")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Packet<T> {
+    /// Stores the result of the thread and wakes up the thread joining it.
+    ///
+    /// Only the first call stores its result, later calls drop theirs.
+    fn publish(&self, result: T) {
+        if self
+            .state
+            .compare_exchange(RUNNING, PUBLISHING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return;
+        }
+
+        unsafe {
+            *self.result.get() = Some(result);
+        }
+        self.state.store(FINISHED, Ordering::Release);
+        _ = self.state.wake_all();
+    }
+}
+
+/// What the spawned thread receives through its argument pointer
+struct Start<F, T> {
+    f: F,
+    packet: Arc<Packet<T>>,
+}
+
+extern "C" fn thread_start<F, T>(_tid: Tid, argument: usize) -> !
+where
+    F: FnOnce() -> T,
+{
+    let Start { f, packet } = *unsafe { Box::from_raw(argument as *mut Start<F, T>) };
+    entry::enter();
+
+    let result = f();
+    // the thread-local values are dropped before the result is published, so that joining waits for their destructors
+    entry::run_destructors();
+
+    packet.publish(result);
+    drop(packet);
+    entry::exit()
+}
+
+/// What an isolated thread receives through its argument pointer, see [`Builder::spawn_isolated`]
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+struct IsolatedStart<F, T> {
+    start: Start<F, Result<T, Panicked>>,
+    stack_size: usize,
+}
+
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+extern "C" fn isolated_start<F, T>(_tid: Tid, argument: usize) -> !
+where
+    F: FnOnce() -> T,
+{
+    let IsolatedStart {
+        start: Start { f, packet },
+        stack_size,
+    } = *unsafe { Box::from_raw(argument as *mut IsolatedStart<F, T>) };
+    entry::enter();
+
+    // everything the thread runs is below this frame
+    let marker = 0u8;
+    let stack_top = core::hint::black_box(&marker) as *const u8 as usize;
+    // the frames above this one, which aren't part of the registered stack, are at least this large
+    let margin = size_of::<IsolatedStart<F, T>>() + size_of::<T>() + crate::limits::PAGE_SIZE;
+    let key = super::recovery::register(
+        stack_top,
+        stack_size,
+        margin,
+        Arc::as_ptr(&packet) as usize,
+        recover::<T>,
+    );
+
+    let result = f();
+    super::recovery::unregister(key);
+    entry::run_destructors();
+
+    packet.publish(Ok(result));
+    drop(packet);
+    entry::exit()
+}
+
+/// Reports the panic of an isolated thread, `packet` points to its packet.
+#[cfg(not(any(feature = "std", feature = "rustc-dep-of-std")))]
+fn recover<T>(packet: usize, panicked: Panicked) {
+    // the thread's reference to the packet is never dropped, so the packet is alive
+    let packet = unsafe { &*(packet as *const Packet<Result<T, Panicked>>) };
+    packet.publish(Err(panicked));
+}
+
+/// The stack size of isolated threads spawned without an explicit stack size, see [`Builder::spawn_isolated`]
+pub const ISOLATED_STACK_SIZE: NonZero<usize> =
+    NonZero::new(64 * crate::limits::PAGE_SIZE).expect("the page size is not zero");
+
+/// The error returned by joining a thread spawned with [`Builder::spawn_isolated`] that panicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panicked {
+    message: String,
+}
+
+impl Panicked {
+    #[cfg_attr(
+        all(feature = "rustc-dep-of-std", not(feature = "std")),
+        allow(dead_code)
+    )]
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    /// The message the thread panicked with.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "thread panicked: {}", self.message)
+    }
+}
+
+/// Configures a thread before spawning it, see [`Builder::spawn`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Builder {
+    priority: Option<ContextPriority>,
+    stack_size: Option<NonZero<usize>>,
+}
+
+impl Builder {
+    /// Constructs a builder for a thread with its parent's priority and the default stack size.
+    pub const fn new() -> Self {
+        Self {
+            priority: None,
+            stack_size: None,
+        }
+    }
+
+    /// Sets the priority of the thread in the thread queue.
+    pub const fn priority(&mut self, priority: ContextPriority) -> &mut Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Sets the size of the stack of the thread in bytes.
+    pub const fn stack_size(&mut self, size: NonZero<usize>) -> &mut Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Spawns a new thread running `f`, returns a handle to wait for it and get the value `f` returned.
+    ///
+    /// The thread exits once `f` returns, a panic in `f` goes through the global panic handler and takes the whole process down,
+    /// see [`Builder::spawn_isolated`] to only stop the thread.
+    pub fn spawn<F, T>(&self, f: F) -> Result<JoinHandle<T>, ErrorStatus>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let packet = Arc::new(Packet {
+            state: Futex::new(RUNNING),
+            result: UnsafeCell::new(None),
+        });
+
+        let start = Box::into_raw(Box::new(Start {
+            f,
+            packet: packet.clone(),
+        }));
+
+        match syscalls::thread::spawn2(
+            thread_start::<F, T>,
+            start as usize,
+            self.priority.into(),
+            self.stack_size,
+        ) {
+            Ok(tid) => Ok(JoinHandle { tid, packet }),
+            Err(err) => {
+                // the thread never started, so the closure is still ours
+                drop(unsafe { Box::from_raw(start) });
+                Err(err)
+            }
+        }
+    }
+
+    /// Spawns a new thread running `f` like [`Builder::spawn`], but a panic in `f` only stops the thread
+    /// and makes joining it return [`Panicked`] instead of taking the whole process down,
+    /// so that for example a worker pool can report the failure of a job.
+    ///
+    /// With the `std` feature the panic unwinds the thread as usual (see [`std::panic::catch_unwind`]).
+    /// Otherwise there is no unwinding and the thread is stopped where it panicked: the panic is still printed,
+    /// then the thread-local values of the thread are dropped and the thread exits, but anything else it owned is leaked,
+    /// and the locks it held stay locked, so `f` shouldn't hold locks shared with other threads while it may panic.
+    ///
+    /// Without `std` the thread gets a stack of [`ISOLATED_STACK_SIZE`] bytes unless another size is set,
+    /// as its stack bounds are needed to recognize its panics.
+    #[cfg(any(feature = "std", not(feature = "rustc-dep-of-std")))]
+    pub fn spawn_isolated<F, T>(&self, f: F) -> Result<JoinHandle<Result<T, Panicked>>, ErrorStatus>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        #[cfg(feature = "std")]
+        {
+            self.spawn(move || {
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+                    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                        String::from(*message)
+                    } else if let Some(message) = payload.downcast_ref::<String>() {
+                        message.clone()
+                    } else {
+                        String::from("Box<dyn Any>")
+                    };
+                    Panicked::new(message)
+                })
+            })
+        }
+
+        #[cfg(not(feature = "std"))]
+        {
+            let stack_size = self.stack_size.unwrap_or(ISOLATED_STACK_SIZE);
+            let packet = Arc::new(Packet {
+                state: Futex::new(RUNNING),
+                result: UnsafeCell::new(None),
+            });
+
+            let start = Box::into_raw(Box::new(IsolatedStart {
+                start: Start {
+                    f,
+                    packet: packet.clone(),
+                },
+                stack_size: stack_size.get(),
+            }));
+
+            match syscalls::thread::spawn2(
+                isolated_start::<F, T>,
+                start as usize,
+                self.priority.into(),
+                Some(stack_size),
+            ) {
+                Ok(tid) => Ok(JoinHandle { tid, packet }),
+                Err(err) => {
+                    // the thread never started, so the closure is still ours
+                    drop(unsafe { Box::from_raw(start) });
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+/// A handle to a thread spawned with [`spawn`], used to wait for it and get its result
+///
+/// Dropping the handle detaches the thread, which keeps running.
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    tid: Tid,
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// The ID of the thread.
+    #[inline]
+    pub const fn tid(&self) -> Tid {
+        self.tid
+    }
+
+    /// Returns true if the thread returned, in which case [`Self::join`] doesn't block.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.packet.state.load(Ordering::Acquire) == FINISHED
+    }
+
+    /// Waits for the thread to exit and returns the value its closure returned.
+    pub fn join(self) -> Result<T, ErrorStatus> {
+        loop {
+            let state = self.packet.state.load(Ordering::Acquire);
+            if state == FINISHED {
+                break;
+            }
+
+            match self.packet.state.wait(state, None) {
+                Ok(()) | Err(ErrorStatus::Timeout) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        // the result is stored before the thread exits, waiting for the exit lets the kernel clean it up
+        match syscalls::thread::wait(self.tid) {
+            Ok(()) | Err(ErrorStatus::InvalidTid) => {}
+            Err(err) => return Err(err),
+        }
+
+        let result = unsafe { (*self.packet.result.get()).take() };
+        Ok(result.expect("thread marked finished without a result"))
+    }
+}
+
+/// Spawns a new thread running `f` with the default configuration, see [`Builder::spawn`].
+///
+/// ```ignore
+/// let handle = thread::spawn(|| 6 * 7)?;
+/// assert_eq!(handle.join()?, 42);
+/// ```
+#[inline]
+pub fn spawn<F, T>(f: F) -> Result<JoinHandle<T>, ErrorStatus>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn(f)
+}
+
+/// Spawns a new thread running `f` with the default configuration, isolating the process from its panics,
+/// see [`Builder::spawn_isolated`].
+///
+/// ```ignore
+/// let handle = thread::spawn_isolated(|| panic!("job failed"))?;
+/// assert!(handle.join()?.is_err());
+/// ```
+#[cfg(any(feature = "std", not(feature = "rustc-dep-of-std")))]
+#[inline]
+pub fn spawn_isolated<F, T>(f: F) -> Result<JoinHandle<Result<T, Panicked>>, ErrorStatus>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn_isolated(f)
+}
@@ -0,0 +1,153 @@
+//! Monotonic time, see [`uptime`] and [`Instant`]
+//!
+//! All the time-dependent utilities of this crate (timeouts, deadlines, caches) read the time through [`uptime`],
+//! with the `mock-time` feature the time can be overridden and advanced programmatically for tests, see [`mock`].
+
+use core::{
+    ops::{Add, Sub},
+    time::Duration,
+};
+
+use safa_abi::clock::Clock;
+
+use crate::syscalls;
+
+/// Returns the time passed since boot, using the [`Clock::Monotonic`] clock,
+/// or the mocked time if one is set, see [`mock`].
+#[inline]
+pub fn uptime() -> Duration {
+    #[cfg(feature = "mock-time")]
+    if let Some(now) = mock::now() {
+        return now;
+    }
+
+    syscalls::clock::clock_gettime(Clock::Monotonic)
+}
+
+/// A point in time of the monotonic clock, see [`uptime`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    /// Returns the current instant.
+    #[inline]
+    pub fn now() -> Self {
+        Self(uptime())
+    }
+
+    /// Constructs an instant from the time passed since boot.
+    #[inline]
+    pub const fn from_uptime(uptime: Duration) -> Self {
+        Self(uptime)
+    }
+
+    /// Returns the time passed since boot at this instant.
+    #[inline]
+    pub const fn as_uptime(&self) -> Duration {
+        self.0
+    }
+
+    /// Returns the time passed since `earlier`, or zero if `earlier` is later than this instant.
+    #[inline]
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Returns the time passed since this instant.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+
+    #[inline]
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(duration).map(Self)
+    }
+
+    #[inline]
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(duration).map(Self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    /// Panics if the result overflows, see [`Instant::checked_add`]
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    /// Panics if the result underflows, see [`Instant::checked_sub`]
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Self::Output {
+        self.duration_since(rhs)
+    }
+}
+
+/// Overriding the time returned by [`uptime`] for tests
+///
+/// The mocked time is process-wide and only changes when told to, so code waiting on a deadline
+/// only observes it passing once the test advances the time past it,
+/// actual sleeps and kernel timeouts still use real time.
+#[cfg(feature = "mock-time")]
+pub mod mock {
+    use core::{
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    static MOCKED_NANOS: AtomicU64 = AtomicU64::new(0);
+
+    /// Returns the mocked time if set.
+    #[inline]
+    pub fn now() -> Option<Duration> {
+        ENABLED
+            .load(Ordering::Acquire)
+            .then(|| Duration::from_nanos(MOCKED_NANOS.load(Ordering::Acquire)))
+    }
+
+    /// Sets the time returned by [`super::uptime`] to `now`, enabling the mocked time.
+    ///
+    /// # Panics
+    /// Panics if `now` is larger than [`u64::MAX`] nanoseconds
+    pub fn set(now: Duration) {
+        let nanos = u64::try_from(now.as_nanos()).expect("mocked time is too large");
+        MOCKED_NANOS.store(nanos, Ordering::Release);
+        ENABLED.store(true, Ordering::Release);
+    }
+
+    /// Advances the mocked time by `by`,
+    /// if the mocked time isn't set it starts from the current real time.
+    pub fn advance(by: Duration) {
+        if !ENABLED.load(Ordering::Acquire) {
+            set(super::uptime());
+        }
+
+        let by = u64::try_from(by.as_nanos()).unwrap_or(u64::MAX);
+        _ = MOCKED_NANOS.fetch_update(Ordering::AcqRel, Ordering::Acquire, |nanos| {
+            Some(nanos.saturating_add(by))
+        });
+    }
+
+    /// Goes back to using the real time.
+    pub fn reset() {
+        ENABLED.store(false, Ordering::Release);
+    }
+}
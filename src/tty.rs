@@ -0,0 +1,127 @@
+//! Terminal helpers built on top of SafaOS VTTYs, see [`VttyPair`] and [`Session`]
+
+use core::time::Duration;
+
+use safa_abi::poll::{PollEntry, PollEvents};
+
+use crate::{
+    errors::ErrorStatus,
+    process::command::{Child, Command},
+    resource::Resource,
+    syscalls::{self, types::Ri},
+    vtty::{ChildVTTY, MotherVTTY},
+};
+
+/// A freshly allocated VTTY, made of its two ends.
+///
+/// - `master` is the terminal side, it reads the output of the attached processes and writes their input
+/// - `slave` is the process side, it is meant to be used as the stdio of the attached processes, see [`Command::terminal`]
+///
+/// Both ends are destroyed on drop.
+#[derive(Debug)]
+pub struct VttyPair {
+    pub master: MotherVTTY,
+    pub slave: ChildVTTY,
+}
+
+impl VttyPair {
+    /// Allocates a new VTTY.
+    pub fn new() -> Result<Self, ErrorStatus> {
+        let (master, slave) = syscalls::io::vtty_alloc()?;
+        unsafe {
+            Ok(Self {
+                master: MotherVTTY::from_resource(Resource::from_raw(master)),
+                slave: ChildVTTY::from_resource(Resource::from_raw(slave)),
+            })
+        }
+    }
+
+    /// Splits the pair into its master and slave ends.
+    #[inline]
+    pub fn into_parts(self) -> (MotherVTTY, ChildVTTY) {
+        (self.master, self.slave)
+    }
+}
+
+/// A process attached to a freshly allocated VTTY, the core of a terminal emulator.
+///
+/// Reading from the session reads the output of the process, and writing to it writes to its input.
+#[derive(Debug)]
+pub struct Session {
+    tty: VttyPair,
+    child: Child,
+}
+
+impl Session {
+    /// Allocates a new VTTY and spawns `command` attached to it, see [`Command::terminal`].
+    pub fn spawn(command: &Command) -> Result<Self, ErrorStatus> {
+        let tty = VttyPair::new()?;
+        let child = command.clone().terminal(&tty).spawn()?;
+        Ok(Self { tty, child })
+    }
+
+    /// The spawned process.
+    #[inline]
+    pub const fn child(&self) -> &Child {
+        &self.child
+    }
+
+    /// The VTTY the process is attached to.
+    #[inline]
+    pub const fn tty(&self) -> &VttyPair {
+        &self.tty
+    }
+
+    /// The resource to poll for the output of the process, see [`Session::poll_entry`].
+    #[inline]
+    pub const fn ri(&self) -> Ri {
+        self.tty.master.ri()
+    }
+
+    /// A [`PollEntry`] that waits for output from the process,
+    /// for use with [`syscalls::io::poll_resources`] alongside other resources.
+    #[inline]
+    pub const fn poll_entry(&self) -> PollEntry {
+        PollEntry::new(self.ri(), PollEvents::DATA_AVAILABLE)
+    }
+
+    /// Waits up to `timeout` for output from the process, waits forever if `timeout` is [`None`].
+    ///
+    /// Returns the events that occurred, [`PollEvents::NONE`] if the timeout was reached, which isn't an error.
+    pub fn poll(&self, timeout: Option<Duration>) -> Result<PollEvents, ErrorStatus> {
+        let mut entries = [self.poll_entry()];
+        syscalls::io::poll_resources(&mut entries, timeout)?;
+        Ok(entries[0].returned_events())
+    }
+
+    /// Reads the output of the process into `buf` without blocking,
+    /// returns [`None`] if there is no output available.
+    pub fn try_read(&self, buf: &mut [u8]) -> Result<Option<usize>, ErrorStatus> {
+        if !self
+            .poll(Some(Duration::ZERO))?
+            .contains(PollEvents::DATA_AVAILABLE)
+        {
+            return Ok(None);
+        }
+
+        self.read(buf).map(Some)
+    }
+
+    /// Reads the output of the process into `buf`, blocks until there is output available.
+    #[inline]
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, ErrorStatus> {
+        self.tty.master.read(-1, buf)
+    }
+
+    /// Writes `buf` to the input of the process.
+    #[inline]
+    pub fn write(&self, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        self.tty.master.write(-1, buf)
+    }
+
+    /// Returns the exit code of the process if it has exited, or [`None`] if it is still running.
+    #[inline]
+    pub fn try_wait(&self) -> Result<Option<usize>, ErrorStatus> {
+        self.child.try_wait()
+    }
+}
@@ -0,0 +1,9 @@
+//! Miscellaneous utilities built on top of the other modules of this crate
+
+pub mod rate_limit;
+pub mod retry;
+pub mod unique_id;
+
+pub use rate_limit::RateLimiter;
+pub use retry::{retry, retry_cancellable, RetryPolicy};
+pub use unique_id::{unique_id, UniqueId};
@@ -0,0 +1,203 @@
+//! Throttling using a token bucket, see [`RateLimiter`]
+
+use core::{sync::atomic::Ordering, time::Duration};
+
+use safa_abi::errors::ErrorStatus;
+
+use crate::{
+    sync::{locks::Mutex, Futex},
+    time::Instant,
+};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: u32,
+    /// the point in time tokens were last added, [`None`] until first used, at which point the bucket is full
+    last_refill: Option<Instant>,
+}
+
+/// A token bucket rate limiter, holding up to `capacity` tokens and gaining `refill_amount` tokens every `refill_period`.
+///
+/// Each operation takes tokens from the bucket, either failing when there aren't enough ([`RateLimiter::try_acquire`])
+/// or blocking until enough are refilled ([`RateLimiter::acquire`]), the bucket starts full so bursts of up to `capacity` are allowed.
+///
+/// The time is read using [`crate::time::uptime`], blocked threads wait on a [`Futex`]
+/// so they are woken up early when tokens are given back using [`RateLimiter::refund`].
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: u32,
+    refill_amount: u32,
+    refill_period: Duration,
+    bucket: Mutex<Bucket>,
+    /// incremented each time tokens are given back, to wake up the blocked threads
+    refunds: Futex,
+}
+
+impl RateLimiter {
+    /// Constructs a new full RateLimiter holding up to `capacity` tokens and gaining `refill_amount` tokens every `refill_period`.
+    ///
+    /// # Panics
+    /// Panics if `refill_amount` or `refill_period` is zero.
+    pub const fn new(capacity: u32, refill_amount: u32, refill_period: Duration) -> Self {
+        assert!(refill_amount != 0, "a RateLimiter must refill");
+        assert!(!refill_period.is_zero(), "a RateLimiter must refill");
+
+        Self {
+            capacity,
+            refill_amount,
+            refill_period,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: None,
+            }),
+            refunds: Futex::new(0),
+        }
+    }
+
+    /// Constructs a new full RateLimiter allowing `rate` operations per second, with bursts of up to `rate` operations.
+    ///
+    /// # Panics
+    /// Panics if `rate` is zero.
+    pub const fn per_second(rate: u32) -> Self {
+        Self::new(rate, rate, Duration::from_secs(1))
+    }
+
+    /// The maximum amount of tokens the bucket holds.
+    #[inline]
+    pub const fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Adds the tokens refilled since the last refill to the bucket.
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let Some(last_refill) = bucket.last_refill else {
+            bucket.last_refill = Some(now);
+            return;
+        };
+
+        let period = self.refill_period.as_nanos();
+        let elapsed = now.duration_since(last_refill).as_nanos();
+        let added = elapsed * self.refill_amount as u128 / period;
+        if added == 0 {
+            return;
+        }
+
+        let tokens = (bucket.tokens as u128 + added).min(self.capacity as u128);
+        bucket.tokens = tokens as u32;
+        bucket.last_refill = if bucket.tokens == self.capacity {
+            Some(now)
+        } else {
+            // keeps the time that didn't add up to a whole token
+            let used = added * period / self.refill_amount as u128;
+            Some(last_refill + Duration::from_nanos(used as u64))
+        };
+    }
+
+    /// Takes `n` tokens if available (only checks for them if `consume` is false), otherwise returns how long until they are.
+    fn take(&self, n: u32, consume: bool) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut bucket = self.bucket.lock();
+        self.refill(&mut bucket, now);
+
+        if bucket.tokens >= n {
+            if consume {
+                bucket.tokens -= n;
+            }
+            return Ok(());
+        }
+
+        let needed = (n - bucket.tokens) as u128;
+        let period = self.refill_period.as_nanos();
+        let refill_amount = self.refill_amount as u128;
+        let wait = (needed * period).div_ceil(refill_amount);
+
+        let since_refill = bucket
+            .last_refill
+            .map_or(0, |last| now.duration_since(last).as_nanos());
+        let wait = wait.saturating_sub(since_refill).max(1);
+        Err(Duration::from_nanos(wait.min(u64::MAX as u128) as u64))
+    }
+
+    /// Returns the amount of tokens currently available.
+    pub fn available(&self) -> u32 {
+        let mut bucket = self.bucket.lock();
+        self.refill(&mut bucket, Instant::now());
+        bucket.tokens
+    }
+
+    /// Takes `n` tokens without blocking, returns whether or not they were available.
+    pub fn try_acquire(&self, n: u32) -> bool {
+        self.take(n, true).is_ok()
+    }
+
+    /// Returns how long until `n` tokens are available, zero if they already are, without taking them.
+    ///
+    /// Returns [`None`] if `n` is larger than the capacity.
+    pub fn time_until(&self, n: u32) -> Option<Duration> {
+        if n > self.capacity {
+            return None;
+        }
+
+        Some(self.take(n, false).err().unwrap_or(Duration::ZERO))
+    }
+
+    fn acquire_until(&self, n: u32, deadline: Option<Instant>) -> Result<(), ErrorStatus> {
+        if n > self.capacity {
+            return Err(ErrorStatus::InvalidArgument);
+        }
+
+        loop {
+            let refunds = self.refunds.load(Ordering::Acquire);
+            let wait = match self.take(n, true) {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+
+            let wait = match deadline {
+                None => wait,
+                Some(deadline) => {
+                    let remaining = deadline.duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(ErrorStatus::Timeout);
+                    }
+                    wait.min(remaining)
+                }
+            };
+
+            match self.refunds.wait(refunds, Some(wait)) {
+                Ok(()) | Err(ErrorStatus::Timeout) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Takes `n` tokens, blocking until they are available.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `n` is larger than the capacity, as it would block forever
+    pub fn acquire(&self, n: u32) -> Result<(), ErrorStatus> {
+        self.acquire_until(n, None)
+    }
+
+    /// Takes `n` tokens, blocking until they are available or until `timeout` passes.
+    ///
+    /// # Returns
+    /// - [`ErrorStatus::InvalidArgument`] if `n` is larger than the capacity
+    /// - [`ErrorStatus::Timeout`] if the tokens didn't become available in time, no tokens are taken
+    pub fn acquire_timeout(&self, n: u32, timeout: Duration) -> Result<(), ErrorStatus> {
+        let deadline = Instant::now().checked_add(timeout);
+        self.acquire_until(n, deadline)
+    }
+
+    /// Gives back `n` tokens, for example for an operation that was throttled but never happened,
+    /// waking up the blocked threads.
+    pub fn refund(&self, n: u32) {
+        {
+            let mut bucket = self.bucket.lock();
+            bucket.tokens = bucket.tokens.saturating_add(n).min(self.capacity);
+        }
+
+        self.refunds.fetch_add(1, Ordering::Release);
+        _ = self.refunds.wake_all();
+    }
+}
@@ -0,0 +1,210 @@
+//! Retrying fallible operations with exponential backoff, see [`retry`]
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use safa_abi::errors::ErrorStatus;
+
+use crate::{sync::CancellationToken, syscalls, time::Instant};
+
+/// The default predicate of [`RetryPolicy`], retries on errors that are usually temporary:
+/// [`ErrorStatus::WouldBlock`], [`ErrorStatus::Timeout`] and [`ErrorStatus::Busy`].
+pub const fn is_transient(err: ErrorStatus) -> bool {
+    matches!(
+        err,
+        ErrorStatus::WouldBlock | ErrorStatus::Timeout | ErrorStatus::Busy
+    )
+}
+
+/// Describes how [`retry`] retries an operation.
+///
+/// After each failed attempt the operation is retried after a backoff that starts at the initial backoff
+/// and is multiplied with each retry up to the maximum backoff,
+/// with jitter enabled the actual backoff is randomly picked between half of it and all of it,
+/// so that clients failing at the same time don't retry at the same time.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    jitter: bool,
+    timeout: Option<Duration>,
+    retry_on: fn(ErrorStatus) -> bool,
+}
+
+impl RetryPolicy {
+    /// Constructs a new policy making up to 3 attempts, with a backoff starting at 10ms,
+    /// doubling with each retry up to 1s, with jitter, no overall timeout, retrying on [`is_transient`] errors.
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2,
+            jitter: true,
+            timeout: None,
+            retry_on: is_transient,
+        }
+    }
+
+    /// Sets the maximum amount of attempts, including the first one, 0 is treated as 1.
+    pub const fn set_max_attempts(&mut self, max_attempts: usize) -> &mut Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the backoff before the first retry.
+    pub const fn set_initial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between retries.
+    pub const fn set_max_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the factor the backoff is multiplied by with each retry, 1 keeps it constant.
+    pub const fn set_multiplier(&mut self, multiplier: u32) -> &mut Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets whether or not the backoff is randomized.
+    pub const fn set_jitter(&mut self, jitter: bool) -> &mut Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the overall timeout, no attempt is started after `timeout` passes since the start of [`retry`],
+    /// [`None`] means no timeout.
+    pub const fn set_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the predicate deciding which errors are retried, other errors are returned immediately.
+    pub const fn set_retry_on(&mut self, retry_on: fn(ErrorStatus) -> bool) -> &mut Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Returns the backoff before the retry following the failed attempt `attempt` (starting from 0), without jitter.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let mut backoff = self.initial_backoff;
+        for _ in 0..attempt {
+            if backoff >= self.max_backoff {
+                break;
+            }
+            backoff = backoff.saturating_mul(self.multiplier);
+        }
+        backoff.min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A xorshift state used for jitter, seeded from the clock on first use.
+static JITTER_STATE: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a pseudo-random number for jitter, not suitable for anything else.
+fn jitter_random() -> u64 {
+    let mut state = JITTER_STATE.load(Ordering::Relaxed);
+    if state == 0 {
+        state = (syscalls::clock::clock_gettime(safa_abi::clock::Clock::Monotonic).as_nanos()
+            as u64)
+            | 1;
+    }
+
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    JITTER_STATE.store(state, Ordering::Relaxed);
+    state
+}
+
+fn jittered(backoff: Duration) -> Duration {
+    let half = backoff / 2;
+    let range = half.as_nanos().min(u64::MAX as u128) as u64;
+    if range == 0 {
+        return backoff;
+    }
+
+    half + Duration::from_nanos(jitter_random() % (range + 1))
+}
+
+/// Calls `op` until it succeeds, fails with an error not accepted by the policy's predicate, or the policy gives up,
+/// sleeping between attempts as described by `policy`, see [`RetryPolicy`].
+///
+/// `op` is given the index of the current attempt, starting from 0.
+/// When the policy gives up the error of the last attempt is returned.
+pub fn retry<T>(
+    policy: &RetryPolicy,
+    op: impl FnMut(usize) -> Result<T, ErrorStatus>,
+) -> Result<T, ErrorStatus> {
+    retry_inner(policy, None, op)
+}
+
+/// Same as [`retry`] but gives up with [`crate::sync::cancel::CANCELLED`] once `cancel` is cancelled,
+/// which is checked before each attempt and interrupts the backoff between attempts.
+pub fn retry_cancellable<T>(
+    policy: &RetryPolicy,
+    cancel: &CancellationToken,
+    op: impl FnMut(usize) -> Result<T, ErrorStatus>,
+) -> Result<T, ErrorStatus> {
+    retry_inner(policy, Some(cancel), op)
+}
+
+fn retry_inner<T>(
+    policy: &RetryPolicy,
+    cancel: Option<&CancellationToken>,
+    mut op: impl FnMut(usize) -> Result<T, ErrorStatus>,
+) -> Result<T, ErrorStatus> {
+    let deadline = policy
+        .timeout
+        .and_then(|timeout| Instant::now().checked_add(timeout));
+    let max_attempts = policy.max_attempts.max(1);
+
+    let mut attempt = 0;
+    loop {
+        if let Some(cancel) = cancel {
+            cancel.check()?;
+        }
+
+        let err = match op(attempt) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        if !(policy.retry_on)(err) || attempt + 1 >= max_attempts {
+            return Err(err);
+        }
+
+        let mut backoff = policy.backoff(attempt);
+        if policy.jitter {
+            backoff = jittered(backoff);
+        }
+
+        if let Some(deadline) = deadline {
+            let remaining = deadline.duration_since(Instant::now());
+            if remaining <= backoff {
+                return Err(err);
+            }
+        }
+
+        match cancel {
+            _ if backoff.is_zero() => {}
+            Some(cancel) => cancel.sleep(backoff)?,
+            None => _ = syscalls::thread::sleep(backoff),
+        }
+        attempt += 1;
+    }
+}
@@ -0,0 +1,85 @@
+//! Process-wide unique identifiers, see [`unique_id`]
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use safa_abi::clock::Clock;
+
+use crate::syscalls;
+
+/// An identifier returned by [`unique_id`]
+///
+/// Formats as 32 lowercase hexadecimal digits, which is safe to use in file names and abstract socket addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UniqueId(u128);
+
+impl UniqueId {
+    /// The raw value of the identifier.
+    #[inline]
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+
+    /// The per-process part of the identifier, shared by all the identifiers generated by the same process.
+    #[inline]
+    pub const fn process_part(self) -> u32 {
+        (self.0 >> 96) as u32
+    }
+}
+
+impl fmt::Display for UniqueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// A random value picked once per process, 0 until the first call to [`unique_id`].
+static PROCESS_SEED: AtomicU32 = AtomicU32::new(0);
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// The splitmix64 finalizer, spreads the few changing bits of its input over the whole output.
+const fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn process_seed() -> u32 {
+    let seed = PROCESS_SEED.load(Ordering::Relaxed);
+    if seed != 0 {
+        return seed;
+    }
+
+    // there is no way to get the pid of the current process yet,
+    // so the time of the first call and the address of the stack (which differs when it is randomized) are used instead
+    let local = 0u8;
+    let stack = &local as *const u8 as u64;
+    let now = syscalls::clock::clock_gettime(Clock::Monotonic).as_nanos() as u64;
+    let candidate = (mix(now ^ stack.rotate_left(32)) as u32) | 1;
+
+    // if another thread raced us use the seed it picked
+    match PROCESS_SEED.compare_exchange(0, candidate, Ordering::Relaxed, Ordering::Relaxed) {
+        Ok(_) => candidate,
+        Err(seed) => seed,
+    }
+}
+
+/// Returns an identifier that is unique within the process and very unlikely to collide with the identifiers of other processes,
+/// identifiers returned to the same thread are increasing,
+/// intended for naming temporary files, abstract sockets and the like, see [`crate::fs::create_temp_file`].
+///
+/// The identifier is made of (from the most significant bits to the least):
+/// - 32 bits identifying the process, randomly picked on the first call as the kernel doesn't expose the pid of a process
+/// - 64 bits of the time passed since boot in nanoseconds
+/// - a 32 bits counter incremented on each call, making identifiers generated within the same nanosecond unique
+///
+/// The identifier isn't secret and is predictable, it must not be used for anything security-sensitive.
+pub fn unique_id() -> UniqueId {
+    let seed = process_seed() as u128;
+    let now = syscalls::clock::clock_gettime(Clock::Monotonic).as_nanos() as u64 as u128;
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) as u128;
+
+    UniqueId((seed << 96) | (now << 32) | count)
+}
@@ -20,6 +20,12 @@ pub struct ChildVTTY {
 impl MotherVTTY {
     pub const SET_FLAGS: u16 = 1;
 
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    #[inline(always)]
+    pub(crate) const fn from_resource(resource: Resource) -> Self {
+        Self { resource }
+    }
+
     #[inline(always)]
     pub const fn ri(&self) -> Ri {
         self.resource.ri()
@@ -35,6 +41,11 @@ impl MotherVTTY {
         unsafe { self.resource.read(offset, buf) }
     }
 
+    /// Writes data to the VTTY at the specified offset from the provided buffer, as input to the child side.
+    pub fn write(&self, offset: isize, buf: &[u8]) -> Result<usize, ErrorStatus> {
+        unsafe { self.resource.write(offset, buf) }
+    }
+
     /// Sends a command to the VTTY with the specified command and argument.
     pub fn send_command(&self, command: u16, argument: u64) -> Result<(), ErrorStatus> {
         unsafe { self.resource.io_command(command, argument) }
@@ -47,6 +58,17 @@ impl MotherVTTY {
 }
 
 impl ChildVTTY {
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    #[inline(always)]
+    pub(crate) const fn from_resource(resource: Resource) -> Self {
+        Self { resource }
+    }
+
+    #[inline(always)]
+    pub const fn ri(&self) -> Ri {
+        self.resource.ri()
+    }
+
     #[inline(always)]
     pub const fn resource(&self) -> &Resource {
         &self.resource